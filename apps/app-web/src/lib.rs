@@ -12,6 +12,10 @@ use momentum_ui_leptos::mount_app;
 #[cfg(target_arch = "wasm32")]
 use momentum_core::model::Shape;
 #[cfg(target_arch = "wasm32")]
+use momentum_core::usecases::Document;
+#[cfg(target_arch = "wasm32")]
+use momentum_core::svg::{self, SvgExportOptions};
+#[cfg(target_arch = "wasm32")]
 use serde::Serialize;
 #[cfg(target_arch = "wasm32")]
 use momentum_core::ports::RenderPort;
@@ -22,6 +26,8 @@ use web_sys::HtmlCanvasElement;
 #[cfg(all(target_arch = "wasm32", feature = "webgpu"))]
 mod renderer_webgpu;
 #[cfg(all(target_arch = "wasm32", feature = "webgpu"))]
+mod tessellation;
+#[cfg(all(target_arch = "wasm32", feature = "webgpu"))]
 use renderer_webgpu::WebGpuRenderer;
 #[cfg(target_arch = "wasm32")]
 mod renderer_canvas2d;
@@ -273,66 +279,40 @@ pub fn ecs_detect_handle_hover(x: f32, y: f32) -> JsValue {
 #[wasm_bindgen(js_namespace = window)]
 pub fn ecs_detect_shape_hover(x: f32, y: f32) -> JsValue {
     ECS.with(|ecs| {
-        if let Some(app) = &mut *ecs.borrow_mut() {
-            // Usar la misma lógica que en pointer_down pero solo para detección
-            let dpr = 1.0; // Simplificado para hover, podríamos mejorarlo después
-            let click_x = x * dpr;
-            let click_y = y * dpr;
-            
-            // Buscar si hay alguna entidad en esa posición
-            for (id, transform, _style, shape) in &app.document().entities {
-                // Crear transform escalado por DPR
-                let transform_physical = momentum_core::model::Transform {
-                    x: transform.x * dpr,
-                    y: transform.y * dpr,
-                    scale_x: transform.scale_x * dpr,
-                    scale_y: transform.scale_y * dpr,
-                    rotation: transform.rotation,
-                };
-                
-                // Escalar shape por DPR
-                let mut shape_physical = shape.clone();
-                match shape_physical {
-                    momentum_core::model::Shape::Rect { ref mut w, ref mut h } => {
-                        *w *= dpr;
-                        *h *= dpr;
-                    }
-                    momentum_core::model::Shape::Ellipse { ref mut rx, ref mut ry } => {
-                        *rx *= dpr;
-                        *ry *= dpr;
-                    }
-                    momentum_core::model::Shape::Line { ref mut x2, ref mut y2 } => {
-                        *x2 *= dpr;
-                        *y2 *= dpr;
-                    }
-                    momentum_core::model::Shape::Polygon { ref mut points } => {
-                        for (px, py) in points {
-                            *px *= dpr;
-                            *py *= dpr;
-                        }
-                    }
-                }
-                
-                // Usar sistema hitbox o fallback al shape
-                if let Some(hitbox) = app.document().get_hitbox(*id) {
-                    if hitbox.hit_test(click_x, click_y, &transform_physical, &shape_physical) {
-                        return JsValue::from_bool(true);
-                    }
-                } else {
-                    // Fallback: usar shape con tolerancia por defecto
-                    let default_hitbox = momentum_core::model::Hitbox::from_shape(&shape_physical);
-                    if default_hitbox.hit_test(click_x, click_y, &transform_physical, &shape_physical) {
-                        return JsValue::from_bool(true);
-                    }
-                }
-            }
-            JsValue::from_bool(false)
+        if let Some(app) = &*ecs.borrow() {
+            JsValue::from_bool(app.pick(x, y).entity.is_some())
         } else {
             JsValue::from_bool(false)
         }
     })
 }
 
+/// Convierte un PickResult en `{ handle: number|null, entity: number|null, hits: number[] }`
+#[cfg(target_arch = "wasm32")]
+fn pick_result_to_js(result: &momentum_ecs::PickResult) -> JsValue {
+    use js_sys::{Array, Object, Reflect};
+    let obj = Object::new();
+    let handle = result.handle.map(|h| JsValue::from_f64(h.to_u8() as f64)).unwrap_or(JsValue::NULL);
+    let entity = result.entity.map(|id| JsValue::from_f64(id.0 as f64)).unwrap_or(JsValue::NULL);
+    let hits: Array = result.hits.iter().map(|id| JsValue::from_f64(id.0 as f64)).collect();
+    let _ = Reflect::set(&obj, &JsValue::from_str("handle"), &handle);
+    let _ = Reflect::set(&obj, &JsValue::from_str("entity"), &entity);
+    let _ = Reflect::set(&obj, &JsValue::from_str("hits"), &hits);
+    obj.into()
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_pick(x: f32, y: f32) -> JsValue {
+    ECS.with(|ecs| {
+        if let Some(app) = &*ecs.borrow() {
+            pick_result_to_js(&app.pick(x, y))
+        } else {
+            JsValue::NULL
+        }
+    })
+}
+
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen(js_namespace = window)]
 pub fn ecs_create_rect(x: f32, y: f32, w: f32, h: f32) {
@@ -366,6 +346,125 @@ pub fn ecs_create_line(x1: f32, y1: f32, x2: f32, y2: f32) {
     });
 }
 
+/// Flecha por sus puntos de mundo en plano `[x1, y1, ..., xn, yn]`: inicio, quiebres y fin.
+/// Los extremos que caen sobre una forma quedan enganchados a ella.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_create_arrow(points: Vec<f32>) {
+    console::log_1(&format!("ecs_create_arrow({:?})", points).into());
+    let points: Vec<(f32, f32)> = points.chunks_exact(2).map(|p| (p[0], p[1])).collect();
+    ECS.with(|ecs| {
+        if let Some(app) = &mut *ecs.borrow_mut() {
+            app.send_create_arrow(points);
+        }
+    });
+}
+
+/// Conector en codo de (x1, y1) a (x2, y2) en mundo: tramos ortogonales que rodean las demás formas.
+/// Se engancha como `ecs_create_arrow`.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_create_connector(x1: f32, y1: f32, x2: f32, y2: f32) {
+    console::log_1(&format!("ecs_create_connector({}, {}, {}, {})", x1, y1, x2, y2).into());
+    ECS.with(|ecs| {
+        if let Some(app) = &mut *ecs.borrow_mut() {
+            app.send_create_connector((x1, y1), (x2, y2));
+        }
+    });
+}
+
+/// Trazado de las flechas seleccionadas por nombre: "straight" o "elbow".
+/// Devuelve false si ninguna cambia.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_set_arrow_routing(routing: &str) -> Result<bool, JsValue> {
+    use momentum_core::model::ArrowRouting;
+    let routing = match routing {
+        "straight" => ArrowRouting::Straight,
+        "elbow" => ArrowRouting::Elbow,
+        _ => return Err(js_error(&format!("Unknown arrow routing: {}", routing))),
+    };
+    ECS.with(|ecs| {
+        let mut ecs_mut = ecs.try_borrow_mut().map_err(|_| js_error("ECS is busy"))?;
+        let app = ecs_mut.as_mut().ok_or_else(|| js_error("ECS not initialized"))?;
+        Ok(app.set_arrow_routing(routing))
+    })
+}
+
+/// Dibujo a mano alzada de la selección: rugosidad (0 = geometría exacta, 1 = la habitual) y
+/// relleno por nombre: "hachure", "cross-hatch" o "solid". Devuelve false si nada cambia.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_set_sketch(roughness: f32, fill: &str) -> Result<bool, JsValue> {
+    use momentum_core::model::SketchFill;
+    let fill = match fill {
+        "hachure" => SketchFill::Hachure,
+        "cross-hatch" => SketchFill::CrossHatch,
+        "solid" => SketchFill::Solid,
+        _ => return Err(js_error(&format!("Unknown sketch fill: {}", fill))),
+    };
+    ECS.with(|ecs| {
+        let mut ecs_mut = ecs.try_borrow_mut().map_err(|_| js_error("ECS is busy"))?;
+        let app = ecs_mut.as_mut().ok_or_else(|| js_error("ECS not initialized"))?;
+        Ok(app.set_sketch(roughness, fill))
+    })
+}
+
+/// Pintura de relleno (`target` = "fill") o de trazo ("stroke") de la selección, en el JSON del
+/// documento: un color `[r, g, b, a]`, `{"LinearGradient": {...}}`, `{"Hatch": {...}}`, etc.;
+/// `null` la quita. Devuelve false si nada cambia.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_set_paint(target: &str, paint_json: &str) -> Result<bool, JsValue> {
+    use momentum_core::model::Paint;
+    let paint: Option<Paint> = serde_json::from_str(paint_json).map_err(|e| js_error(&format!("Invalid paint: {}", e)))?;
+    ECS.with(|ecs| {
+        let mut ecs_mut = ecs.try_borrow_mut().map_err(|_| js_error("ECS is busy"))?;
+        let app = ecs_mut.as_mut().ok_or_else(|| js_error("ECS not initialized"))?;
+        match target {
+            "fill" => Ok(app.set_fill_paint(paint)),
+            "stroke" => Ok(app.set_stroke_paint(paint)),
+            _ => Err(js_error(&format!("Unknown paint target: {}", target))),
+        }
+    })
+}
+
+/// Radios de las esquinas de los rectángulos seleccionados, en el JSON del documento: un número
+/// para las cuatro o `[tl, tr, br, bl]`. Devuelve false si nada cambia.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_set_corner_radii(radii_json: &str) -> Result<bool, JsValue> {
+    use momentum_core::model::CornerRadii;
+    let radii: CornerRadii = serde_json::from_str(radii_json).map_err(|e| js_error(&format!("Invalid corner radii: {}", e)))?;
+    ECS.with(|ecs| {
+        let mut ecs_mut = ecs.try_borrow_mut().map_err(|_| js_error("ECS is busy"))?;
+        let app = ecs_mut.as_mut().ok_or_else(|| js_error("ECS not initialized"))?;
+        Ok(app.set_corner_radii(radii))
+    })
+}
+
+/// Puntas de las flechas seleccionadas por nombre: "none", "triangle", "open", "dot" o "bar".
+/// Devuelve false si no hay ninguna flecha seleccionada.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_set_arrow_heads(start: &str, end: &str) -> Result<bool, JsValue> {
+    use momentum_core::model::ArrowHead;
+    let head = |name: &str| match name {
+        "none" => Ok(ArrowHead::None),
+        "triangle" => Ok(ArrowHead::Triangle),
+        "open" => Ok(ArrowHead::Open),
+        "dot" => Ok(ArrowHead::Dot),
+        "bar" => Ok(ArrowHead::Bar),
+        _ => Err(js_error(&format!("Unknown arrow head: {}", name))),
+    };
+    let (start, end) = (head(start)?, head(end)?);
+    ECS.with(|ecs| {
+        let mut ecs_mut = ecs.try_borrow_mut().map_err(|_| js_error("ECS is busy"))?;
+        let app = ecs_mut.as_mut().ok_or_else(|| js_error("ECS not initialized"))?;
+        Ok(app.set_arrow_heads(start, end))
+    })
+}
+
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen(js_namespace = window)]
 pub fn ecs_move_start(x: f32, y: f32) {
@@ -432,6 +531,17 @@ pub fn ecs_scale_update(dx: f32, dy: f32) {
     });
 }
 
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_scale_update_with_modifiers(dx: f32, dy: f32, shift_key: bool, alt_key: bool) {
+    console::log_1(&format!("ecs_scale_update_with_modifiers({}, {}, shift={}, alt={})", dx, dy, shift_key, alt_key).into());
+    ECS.with(|ecs| {
+        if let Some(app) = &mut *ecs.borrow_mut() {
+            app.send_scale_update_with_modifiers(dx, dy, shift_key, alt_key);
+        }
+    });
+}
+
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen(js_namespace = window)]
 pub fn ecs_scale_end() {
@@ -444,8 +554,520 @@ pub fn ecs_scale_end() {
 }
 
 #[cfg(target_arch = "wasm32")]
-#[derive(Serialize)]
-struct RectDto { x: f32, y: f32, w: f32, h: f32 }
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_rotate_start(x: f32, y: f32) {
+    console::log_1(&format!("ecs_rotate_start({}, {})", x, y).into());
+    ECS.with(|ecs| {
+        if let Some(app) = &mut *ecs.borrow_mut() {
+            app.send_rotate_start(x, y);
+        }
+    });
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_rotate_update(dx: f32, dy: f32, shift_key: bool) {
+    console::log_1(&format!("ecs_rotate_update({}, {}, shift={})", dx, dy, shift_key).into());
+    ECS.with(|ecs| {
+        if let Some(app) = &mut *ecs.borrow_mut() {
+            app.send_rotate_update(dx, dy, shift_key);
+        }
+    });
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_rotate_end() {
+    console::log_1(&"ecs_rotate_end()".into());
+    ECS.with(|ecs| {
+        if let Some(app) = &mut *ecs.borrow_mut() {
+            app.send_rotate_end();
+        }
+    });
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_undo() -> bool {
+    console::log_1(&"ecs_undo()".into());
+    ECS.with(|ecs| {
+        if let Some(app) = &mut *ecs.borrow_mut() {
+            app.undo()
+        } else {
+            false
+        }
+    })
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_redo() -> bool {
+    console::log_1(&"ecs_redo()".into());
+    ECS.with(|ecs| {
+        if let Some(app) = &mut *ecs.borrow_mut() {
+            app.redo()
+        } else {
+            false
+        }
+    })
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_can_undo() -> bool {
+    ECS.with(|ecs| ecs.borrow().as_ref().map(|app| app.can_undo()).unwrap_or(false))
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_can_redo() -> bool {
+    ECS.with(|ecs| ecs.borrow().as_ref().map(|app| app.can_redo()).unwrap_or(false))
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_set_history_limit(limit: u32) {
+    ECS.with(|ecs| {
+        if let Some(app) = &mut *ecs.borrow_mut() {
+            app.set_history_limit(limit as usize);
+        }
+    });
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_delete_selection() {
+    console::log_1(&"ecs_delete_selection()".into());
+    ECS.with(|ecs| {
+        if let Some(app) = &mut *ecs.borrow_mut() {
+            app.send_delete_selection();
+        }
+    });
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_duplicate_selection(dx: f32, dy: f32) {
+    console::log_1(&format!("ecs_duplicate_selection({}, {})", dx, dy).into());
+    ECS.with(|ecs| {
+        if let Some(app) = &mut *ecs.borrow_mut() {
+            app.send_duplicate_selection(dx, dy);
+        }
+    });
+}
+
+/// Operación de orden z en el bridge: 0 = subir, 1 = bajar, 2 = al frente, 3 = al fondo
+#[cfg(target_arch = "wasm32")]
+fn z_order_op_from_u8(op: u8) -> Option<momentum_ecs::ZOrderOp> {
+    match op {
+        0 => Some(momentum_ecs::ZOrderOp::BringForward),
+        1 => Some(momentum_ecs::ZOrderOp::SendBackward),
+        2 => Some(momentum_ecs::ZOrderOp::BringToFront),
+        3 => Some(momentum_ecs::ZOrderOp::SendToBack),
+        _ => None,
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_z_order(op: u8) {
+    console::log_1(&format!("ecs_z_order({})", op).into());
+    let Some(op) = z_order_op_from_u8(op) else { return; };
+    ECS.with(|ecs| {
+        if let Some(app) = &mut *ecs.borrow_mut() {
+            app.send_z_order(op);
+        }
+    });
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_get_entity_order() -> Vec<u32> {
+    ECS.with(|ecs| {
+        ecs.borrow()
+            .as_ref()
+            .map(|app| app.document().ids().map(|id| id.0 as u32).collect())
+            .unwrap_or_default()
+    })
+}
+
+/// Convierte la cámara en `{ x, y, zoom }`
+#[cfg(target_arch = "wasm32")]
+fn camera_to_js(camera: &momentum_ecs::camera::Camera) -> JsValue {
+    use js_sys::{Object, Reflect};
+    let obj = Object::new();
+    let _ = Reflect::set(&obj, &JsValue::from_str("x"), &JsValue::from_f64(camera.x as f64));
+    let _ = Reflect::set(&obj, &JsValue::from_str("y"), &JsValue::from_f64(camera.y as f64));
+    let _ = Reflect::set(&obj, &JsValue::from_str("zoom"), &JsValue::from_f64(camera.zoom as f64));
+    obj.into()
+}
+
+/// Convierte un punto en `[x, y]`
+#[cfg(target_arch = "wasm32")]
+fn point_to_js((x, y): (f32, f32)) -> JsValue {
+    let arr = js_sys::Array::new();
+    arr.push(&JsValue::from_f64(x as f64));
+    arr.push(&JsValue::from_f64(y as f64));
+    arr.into()
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_pan_by(dx: f32, dy: f32) {
+    ECS.with(|ecs| {
+        if let Some(app) = &mut *ecs.borrow_mut() {
+            app.pan_by(dx, dy);
+        }
+    });
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_zoom_at(x: f32, y: f32, factor: f32) {
+    ECS.with(|ecs| {
+        if let Some(app) = &mut *ecs.borrow_mut() {
+            app.zoom_at(x, y, factor);
+        }
+    });
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_zoom_to_fit() -> bool {
+    console::log_1(&"ecs_zoom_to_fit()".into());
+    ECS.with(|ecs| ecs.borrow_mut().as_mut().map(|app| app.zoom_to_fit()).unwrap_or(false))
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_zoom_to_selection() -> bool {
+    console::log_1(&"ecs_zoom_to_selection()".into());
+    ECS.with(|ecs| ecs.borrow_mut().as_mut().map(|app| app.zoom_to_selection()).unwrap_or(false))
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_reset_camera() {
+    ECS.with(|ecs| {
+        if let Some(app) = &mut *ecs.borrow_mut() {
+            app.set_camera(Default::default());
+        }
+    });
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_get_camera() -> JsValue {
+    ECS.with(|ecs| ecs.borrow().as_ref().map(|app| camera_to_js(&app.camera())).unwrap_or(JsValue::NULL))
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_screen_to_world(x: f32, y: f32) -> JsValue {
+    ECS.with(|ecs| point_to_js(ecs.borrow().as_ref().map(|app| app.screen_to_world(x, y)).unwrap_or((x, y))))
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_world_to_screen(x: f32, y: f32) -> JsValue {
+    ECS.with(|ecs| point_to_js(ecs.borrow().as_ref().map(|app| app.world_to_screen(x, y)).unwrap_or((x, y))))
+}
+
+/// Modo del marquee en el bridge: 0 = contener, 1 = intersectar
+#[cfg(target_arch = "wasm32")]
+fn marquee_mode_from_u8(mode: u8) -> momentum_ecs::MarqueeMode {
+    match mode {
+        1 => momentum_ecs::MarqueeMode::Intersect,
+        _ => momentum_ecs::MarqueeMode::Contain,
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_marquee_start(x: f32, y: f32, shift_key: bool, mode: u8) {
+    console::log_1(&format!("ecs_marquee_start({}, {}, shift={}, mode={})", x, y, shift_key, mode).into());
+    ECS.with(|ecs| {
+        if let Some(app) = &mut *ecs.borrow_mut() {
+            app.send_marquee_start(x, y, shift_key, marquee_mode_from_u8(mode));
+        }
+    });
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_marquee_update(dx: f32, dy: f32) {
+    ECS.with(|ecs| {
+        if let Some(app) = &mut *ecs.borrow_mut() {
+            app.send_marquee_update(dx, dy);
+        }
+    });
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_marquee_end() {
+    console::log_1(&"ecs_marquee_end()".into());
+    ECS.with(|ecs| {
+        if let Some(app) = &mut *ecs.borrow_mut() {
+            app.send_marquee_end();
+        }
+    });
+}
+
+/// Lápiz: muestras en CSS px con la presión del PointerEvent (0..=1)
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_pen_start(x: f32, y: f32, pressure: f32) {
+    console::log_1(&format!("ecs_pen_start({}, {}, pressure={})", x, y, pressure).into());
+    ECS.with(|ecs| {
+        if let Some(app) = &mut *ecs.borrow_mut() {
+            app.send_pen_start(x, y, pressure);
+        }
+    });
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_pen_move(x: f32, y: f32, pressure: f32) {
+    ECS.with(|ecs| {
+        if let Some(app) = &mut *ecs.borrow_mut() {
+            app.send_pen_move(x, y, pressure);
+        }
+    });
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_pen_end() {
+    console::log_1(&"ecs_pen_end()".into());
+    ECS.with(|ecs| {
+        if let Some(app) = &mut *ecs.borrow_mut() {
+            app.send_pen_end();
+        }
+    });
+}
+
+/// Texto: (x, y) en CSS px. `create` = herramienta de texto (crea uno si no hay texto debajo);
+/// sin él es el doble clic, que edita el texto o la etiqueta de la forma bajo el punto
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_text_begin(x: f32, y: f32, create: bool) {
+    console::log_1(&format!("ecs_text_begin({}, {}, create={})", x, y, create).into());
+    ECS.with(|ecs| {
+        if let Some(app) = &mut *ecs.borrow_mut() {
+            app.send_text_begin(x, y, create);
+        }
+    });
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_text_pointer(x: f32, y: f32, extend: bool) {
+    ECS.with(|ecs| {
+        if let Some(app) = &mut *ecs.borrow_mut() {
+            app.send_text_pointer(x, y, extend);
+        }
+    });
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_text_insert(text: String) {
+    ECS.with(|ecs| {
+        if let Some(app) = &mut *ecs.borrow_mut() {
+            app.send_text_insert(&text);
+        }
+    });
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_text_preedit(text: String) {
+    ECS.with(|ecs| {
+        if let Some(app) = &mut *ecs.borrow_mut() {
+            app.send_text_preedit(&text);
+        }
+    });
+}
+
+/// Tecla de edición por su nombre DOM (`KeyboardEvent.key`); "selectAll" para Ctrl/Cmd+A.
+/// Devuelve false si la tecla no es de edición.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_text_key(key: String, shift: bool) -> bool {
+    let Some(key) = text_key_from_str(&key) else { return false; };
+    ECS.with(|ecs| {
+        if let Some(app) = &mut *ecs.borrow_mut() {
+            app.send_text_key(key, shift);
+        }
+    });
+    true
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_text_end() {
+    console::log_1(&"ecs_text_end()".into());
+    ECS.with(|ecs| {
+        if let Some(app) = &mut *ecs.borrow_mut() {
+            app.send_text_end();
+        }
+    });
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_is_text_editing() -> bool {
+    ECS.with(|ecs| ecs.borrow().as_ref().map(|app| app.is_text_editing()).unwrap_or(false))
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_text_edit_hit(x: f32, y: f32) -> bool {
+    ECS.with(|ecs| ecs.borrow().as_ref().map(|app| app.text_edit_hit(x, y)).unwrap_or(false))
+}
+
+/// Cursor del texto en edición en CSS px: { x, y, h } o null
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn ecs_text_caret_rect() -> JsValue {
+    ECS.with(|ecs| ecs.borrow_mut().as_mut().and_then(|app| app.text_caret_rect()).map(caret_to_js).unwrap_or(JsValue::NULL))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn text_key_from_str(key: &str) -> Option<momentum_ecs::text_edit::TextKey> {
+    use momentum_ecs::text_edit::TextKey;
+    Some(match key {
+        "ArrowLeft" => TextKey::Left,
+        "ArrowRight" => TextKey::Right,
+        "ArrowUp" => TextKey::Up,
+        "ArrowDown" => TextKey::Down,
+        "Home" => TextKey::Home,
+        "End" => TextKey::End,
+        "Backspace" => TextKey::Backspace,
+        "Delete" => TextKey::Delete,
+        "selectAll" => TextKey::SelectAll,
+        _ => return None,
+    })
+}
+
+#[cfg(target_arch = "wasm32")]
+fn caret_to_js((x, y, h): (f32, f32, f32)) -> JsValue {
+    use js_sys::{Object, Reflect};
+    let obj = Object::new();
+    let _ = Reflect::set(&obj, &JsValue::from_str("x"), &JsValue::from_f64(x as f64));
+    let _ = Reflect::set(&obj, &JsValue::from_str("y"), &JsValue::from_f64(y as f64));
+    let _ = Reflect::set(&obj, &JsValue::from_str("h"), &JsValue::from_f64(h as f64));
+    obj.into()
+}
+
+#[cfg(target_arch = "wasm32")]
+#[derive(Serialize)]
+struct RectDto { x: f32, y: f32, w: f32, h: f32 }
+
+/// Documento completo en el formato JSON abierto (con cabecera de esquema y versión y los bytes
+/// de las imágenes)
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn export_document_json() -> Result<String, JsValue> {
+    ECS.with(|ecs| {
+        // Mutable para leer del almacenamiento los blobs de las imágenes
+        let mut ecs_mut = ecs.try_borrow_mut().map_err(|_| js_error("ECS is busy"))?;
+        let app = ecs_mut.as_mut().ok_or_else(|| js_error("ECS not initialized"))?;
+        app.document_json().map_err(|e| js_error(&e.to_string()))
+    })
+}
+
+/// Sustituye el documento actual por el JSON dado. Lanza un Error de JS si el JSON no es válido.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn import_document_json(json: &str) -> Result<(), JsValue> {
+    ECS.with(|ecs| {
+        let mut ecs_mut = ecs.try_borrow_mut().map_err(|_| js_error("ECS is busy"))?;
+        let app = ecs_mut.as_mut().ok_or_else(|| js_error("ECS not initialized"))?;
+        app.load_document_json(json).map_err(|e| js_error(&e.to_string()))?;
+        console::log_1(&format!("import_document_json -> {} entities", app.document().count()).into());
+        Ok(())
+    })
+}
+
+/// Documento (o solo la selección) como SVG autónomo, con el viewBox ajustado al contenido
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn export_svg(selection_only: bool, padding: f32) -> Result<String, JsValue> {
+    ECS.with(|ecs| {
+        // Mutable para leer del almacenamiento los blobs de las imágenes
+        let mut ecs_mut = ecs.try_borrow_mut().map_err(|_| js_error("ECS is busy"))?;
+        let app = ecs_mut.as_mut().ok_or_else(|| js_error("ECS not initialized"))?;
+        let options = SvgExportOptions {
+            padding,
+            fonts: app.fonts().clone(),
+            images: app.image_blobs(),
+            ..SvgExportOptions::default()
+        };
+        Ok(if selection_only {
+            svg::selection_to_svg(app.document(), &app.get_selected_entities(), &options)
+        } else {
+            svg::to_svg(app.document(), &options)
+        })
+    })
+}
+
+/// Importa un SVG encima del documento actual. Devuelve `{ entities: [ids], warnings: [texto] }`;
+/// solo lanza un Error de JS si el XML no es válido.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn import_svg(svg_text: &str) -> Result<JsValue, JsValue> {
+    use js_sys::{Array, Object, Reflect};
+    let imported = svg::from_svg(svg_text).map_err(|e| js_error(&e.to_string()))?;
+    let warnings: Vec<String> = imported.warnings.iter().map(|w| w.to_string()).collect();
+    let ids = ECS.with(|ecs| {
+        let mut ecs_mut = ecs.try_borrow_mut().map_err(|_| js_error("ECS is busy"))?;
+        let app = ecs_mut.as_mut().ok_or_else(|| js_error("ECS not initialized"))?;
+        Ok::<_, JsValue>(app.insert_entities(&imported.document))
+    })?;
+    console::log_1(&format!("import_svg -> {} entities, {} warnings", ids.len(), warnings.len()).into());
+
+    let result = Object::new();
+    let entities: Array = ids.iter().map(|id| JsValue::from_f64(id.0 as f64)).collect();
+    let warnings: Array = warnings.iter().map(|w| JsValue::from_str(w)).collect();
+    let _ = Reflect::set(&result, &JsValue::from_str("entities"), &entities);
+    let _ = Reflect::set(&result, &JsValue::from_str("warnings"), &warnings);
+    Ok(result.into())
+}
+
+/// Importa una imagen PNG, JPEG o WebP centrada en (x, y) CSS px. Devuelve el id de la entidad;
+/// lanza un Error de JS si los bytes no son una imagen admitida.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn import_image(data: Vec<u8>, x: f32, y: f32) -> Result<f64, JsValue> {
+    ECS.with(|ecs| {
+        let mut ecs_mut = ecs.try_borrow_mut().map_err(|_| js_error("ECS is busy"))?;
+        let app = ecs_mut.as_mut().ok_or_else(|| js_error("ECS not initialized"))?;
+        let id = app.import_image(&data, x, y).map_err(|e| js_error(&e.to_string()))?;
+        console::log_1(&format!("import_image -> {} ({} bytes)", id, data.len()).into());
+        Ok(id.0 as f64)
+    })
+}
+
+/// Carga una fuente (.ttf/.otf) para el texto del lienzo; desde entonces todos los renderers
+/// y la exportación comparten la maquetación del núcleo
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = window)]
+pub fn load_font(data: Vec<u8>) -> Result<(), JsValue> {
+    ECS.with(|ecs| {
+        let mut ecs_mut = ecs.try_borrow_mut().map_err(|_| js_error("ECS is busy"))?;
+        let app = ecs_mut.as_mut().ok_or_else(|| js_error("ECS not initialized"))?;
+        let id = app.load_font(data).map_err(|e| js_error(&e.to_string()))?;
+        console::log_1(&format!("load_font -> {:?}", app.fonts().family(id)).into());
+        Ok(())
+    })
+}
+
+#[cfg(target_arch = "wasm32")]
+fn js_error(message: &str) -> JsValue {
+    js_sys::Error::new(message).into()
+}
 
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen(js_namespace = window)]
@@ -453,10 +1075,10 @@ pub fn get_document_json() -> String {
     ECS.with(|ecs| {
         if let Some(app) = &*ecs.borrow() {
             let doc = app.document();
-            let mut rects: Vec<RectDto> = Vec::with_capacity(doc.entities.len());
-            for (_id, t, _s, shape) in &doc.entities {
-                if let Shape::Rect { w, h } = shape {
-                    rects.push(RectDto { x: t.x, y: t.y, w: *w, h: *h });
+            let mut rects: Vec<RectDto> = Vec::with_capacity(doc.count());
+            for entity in doc.iter() {
+                if let Shape::Rect { w, h, .. } = entity.shape {
+                    rects.push(RectDto { x: entity.transform.x, y: entity.transform.y, w, h });
                 }
             }
             let s = serde_json::to_string(&rects).unwrap_or_else(|_| "[]".to_string());
@@ -729,15 +1351,120 @@ fn register_window_functions() -> Result<(), JsValue> {
     if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_create_line"), f_line.as_ref()); }
     f_line.forget();
 
+    // ecs_create_arrow([x1, y1, ..., xn, yn])
+    let f_arrow = Closure::wrap(Box::new(move |points: js_sys::Array| {
+        let points: Vec<f32> = points.iter().filter_map(|v| v.as_f64()).map(|v| v as f32).collect();
+        ecs_create_arrow(points);
+    }) as Box<dyn FnMut(js_sys::Array)>);
+    Reflect::set(&global, &JsValue::from_str("ecs_create_arrow"), f_arrow.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_create_arrow"), f_arrow.as_ref()); }
+    f_arrow.forget();
+
+    // ecs_set_arrow_heads(start, end) -> bool
+    let f_arrow_heads = Closure::wrap(Box::new(move |start: String, end: String| -> Result<bool, JsValue> {
+        ecs_set_arrow_heads(&start, &end)
+    }) as Box<dyn FnMut(String, String) -> Result<bool, JsValue>>);
+    Reflect::set(&global, &JsValue::from_str("ecs_set_arrow_heads"), f_arrow_heads.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_set_arrow_heads"), f_arrow_heads.as_ref()); }
+    f_arrow_heads.forget();
+
+    // ecs_create_connector(x1, y1, x2, y2)
+    let f_connector = Closure::wrap(Box::new(move |x1: f32, y1: f32, x2: f32, y2: f32| {
+        ecs_create_connector(x1, y1, x2, y2);
+    }) as Box<dyn FnMut(f32, f32, f32, f32)>);
+    Reflect::set(&global, &JsValue::from_str("ecs_create_connector"), f_connector.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_create_connector"), f_connector.as_ref()); }
+    f_connector.forget();
+
+    // ecs_set_arrow_routing(routing) -> bool
+    let f_arrow_routing = Closure::wrap(Box::new(move |routing: String| -> Result<bool, JsValue> {
+        ecs_set_arrow_routing(&routing)
+    }) as Box<dyn FnMut(String) -> Result<bool, JsValue>>);
+    Reflect::set(&global, &JsValue::from_str("ecs_set_arrow_routing"), f_arrow_routing.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_set_arrow_routing"), f_arrow_routing.as_ref()); }
+    f_arrow_routing.forget();
+
+    // ecs_set_sketch(roughness, fill) -> bool
+    let f_sketch = Closure::wrap(Box::new(move |roughness: f32, fill: String| -> Result<bool, JsValue> {
+        ecs_set_sketch(roughness, &fill)
+    }) as Box<dyn FnMut(f32, String) -> Result<bool, JsValue>>);
+    Reflect::set(&global, &JsValue::from_str("ecs_set_sketch"), f_sketch.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_set_sketch"), f_sketch.as_ref()); }
+    f_sketch.forget();
+
+    // ecs_set_paint(target, paint_json) -> bool
+    let f_paint = Closure::wrap(Box::new(move |target: String, paint_json: String| -> Result<bool, JsValue> {
+        ecs_set_paint(&target, &paint_json)
+    }) as Box<dyn FnMut(String, String) -> Result<bool, JsValue>>);
+    Reflect::set(&global, &JsValue::from_str("ecs_set_paint"), f_paint.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_set_paint"), f_paint.as_ref()); }
+    f_paint.forget();
+
+    // ecs_set_corner_radii(radii_json) -> bool
+    let f_radii = Closure::wrap(Box::new(move |radii_json: String| -> Result<bool, JsValue> {
+        ecs_set_corner_radii(&radii_json)
+    }) as Box<dyn FnMut(String) -> Result<bool, JsValue>>);
+    Reflect::set(&global, &JsValue::from_str("ecs_set_corner_radii"), f_radii.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_set_corner_radii"), f_radii.as_ref()); }
+    f_radii.forget();
+
+    // export_document_json() -> String, import_document_json(json): lanzan Error si falla
+    let f_export = Closure::wrap(Box::new(move || -> Result<JsValue, JsValue> {
+        export_document_json().map(|json| JsValue::from_str(&json))
+    }) as Box<dyn FnMut() -> Result<JsValue, JsValue>>);
+    Reflect::set(&global, &JsValue::from_str("export_document_json"), f_export.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("export_document_json"), f_export.as_ref()); }
+    f_export.forget();
+
+    let f_import = Closure::wrap(Box::new(move |json: String| -> Result<JsValue, JsValue> {
+        console::log_1(&format!("[global] import_document_json len={}", json.len()).into());
+        import_document_json(&json).map(|_| JsValue::UNDEFINED)
+    }) as Box<dyn FnMut(String) -> Result<JsValue, JsValue>>);
+    Reflect::set(&global, &JsValue::from_str("import_document_json"), f_import.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("import_document_json"), f_import.as_ref()); }
+    f_import.forget();
+
+    // export_svg(selection_only, padding) -> String
+    let f_svg = Closure::wrap(Box::new(move |selection_only: bool, padding: f32| -> Result<JsValue, JsValue> {
+        export_svg(selection_only, padding).map(|svg| JsValue::from_str(&svg))
+    }) as Box<dyn FnMut(bool, f32) -> Result<JsValue, JsValue>>);
+    Reflect::set(&global, &JsValue::from_str("export_svg"), f_svg.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("export_svg"), f_svg.as_ref()); }
+    f_svg.forget();
+
+    // import_svg(svg) -> { entities, warnings }
+    let f_import_svg = Closure::wrap(Box::new(move |svg_text: String| -> Result<JsValue, JsValue> {
+        import_svg(&svg_text)
+    }) as Box<dyn FnMut(String) -> Result<JsValue, JsValue>>);
+    Reflect::set(&global, &JsValue::from_str("import_svg"), f_import_svg.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("import_svg"), f_import_svg.as_ref()); }
+    f_import_svg.forget();
+
+    // import_image(Uint8Array, x, y) -> id
+    let f_import_image = Closure::wrap(Box::new(move |data: js_sys::Uint8Array, x: f32, y: f32| -> Result<JsValue, JsValue> {
+        import_image(data.to_vec(), x, y).map(JsValue::from_f64)
+    }) as Box<dyn FnMut(js_sys::Uint8Array, f32, f32) -> Result<JsValue, JsValue>>);
+    Reflect::set(&global, &JsValue::from_str("import_image"), f_import_image.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("import_image"), f_import_image.as_ref()); }
+    f_import_image.forget();
+
+    // load_font(Uint8Array)
+    let f_load_font = Closure::wrap(Box::new(move |data: js_sys::Uint8Array| -> Result<(), JsValue> {
+        load_font(data.to_vec())
+    }) as Box<dyn FnMut(js_sys::Uint8Array) -> Result<(), JsValue>>);
+    Reflect::set(&global, &JsValue::from_str("load_font"), f_load_font.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("load_font"), f_load_font.as_ref()); }
+    f_load_font.forget();
+
     // get_document_json() -> String
     let f_get = Closure::wrap(Box::new(move || -> JsValue {
         let s = ECS.with(|ecs| {
             if let Some(app) = &*ecs.borrow() {
                 let doc = app.document();
-                let mut rects: Vec<RectDto> = Vec::with_capacity(doc.entities.len());
-                for (_id, t, _s, shape) in &doc.entities {
-                    if let Shape::Rect { w, h } = shape {
-                        rects.push(RectDto { x: t.x, y: t.y, w: *w, h: *h });
+                let mut rects: Vec<RectDto> = Vec::with_capacity(doc.count());
+                for entity in doc.iter() {
+                    if let Shape::Rect { w, h, .. } = entity.shape {
+                        rects.push(RectDto { x: entity.transform.x, y: entity.transform.y, w, h });
                     }
                 }
                 serde_json::to_string(&rects).unwrap_or_else(|_| "[]".to_string())
@@ -872,6 +1599,20 @@ fn register_window_functions() -> Result<(), JsValue> {
     if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_scale_update"), f_scale_update.as_ref()); }
     f_scale_update.forget();
 
+    // ecs_scale_update_with_modifiers(dx, dy, shift_key, alt_key)
+    let f_scale_update_mod = Closure::wrap(Box::new(move |dx: f32, dy: f32, shift_key: bool, alt_key: bool| {
+        ECS.with(|ecs| {
+            if let Ok(mut ecs_mut) = ecs.try_borrow_mut() {
+                if let Some(app) = &mut *ecs_mut {
+                    app.send_scale_update_with_modifiers(dx, dy, shift_key, alt_key);
+                }
+            }
+        });
+    }) as Box<dyn FnMut(f32, f32, bool, bool)>);
+    Reflect::set(&global, &JsValue::from_str("ecs_scale_update_with_modifiers"), f_scale_update_mod.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_scale_update_with_modifiers"), f_scale_update_mod.as_ref()); }
+    f_scale_update_mod.forget();
+
     // ecs_scale_end()
     let f_scale_end = Closure::wrap(Box::new(move || {
         console::log_1(&"[global] ecs_scale_end()".into());
@@ -887,6 +1628,475 @@ fn register_window_functions() -> Result<(), JsValue> {
     if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_scale_end"), f_scale_end.as_ref()); }
     f_scale_end.forget();
 
+    // ecs_rotate_start(x, y)
+    let f_rotate_start = Closure::wrap(Box::new(move |x: f32, y: f32| {
+        console::log_1(&format!("[global] ecs_rotate_start({}, {})", x, y).into());
+        ECS.with(|ecs| {
+            if let Ok(mut ecs_mut) = ecs.try_borrow_mut() {
+                if let Some(app) = &mut *ecs_mut {
+                    app.send_rotate_start(x, y);
+                }
+            }
+        });
+    }) as Box<dyn FnMut(f32, f32)>);
+    Reflect::set(&global, &JsValue::from_str("ecs_rotate_start"), f_rotate_start.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_rotate_start"), f_rotate_start.as_ref()); }
+    f_rotate_start.forget();
+
+    // ecs_rotate_update(dx, dy, shift_key)
+    let f_rotate_update = Closure::wrap(Box::new(move |dx: f32, dy: f32, shift_key: bool| {
+        ECS.with(|ecs| {
+            if let Ok(mut ecs_mut) = ecs.try_borrow_mut() {
+                if let Some(app) = &mut *ecs_mut {
+                    app.send_rotate_update(dx, dy, shift_key);
+                }
+            }
+        });
+    }) as Box<dyn FnMut(f32, f32, bool)>);
+    Reflect::set(&global, &JsValue::from_str("ecs_rotate_update"), f_rotate_update.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_rotate_update"), f_rotate_update.as_ref()); }
+    f_rotate_update.forget();
+
+    // ecs_rotate_end()
+    let f_rotate_end = Closure::wrap(Box::new(move || {
+        console::log_1(&"[global] ecs_rotate_end()".into());
+        ECS.with(|ecs| {
+            if let Ok(mut ecs_mut) = ecs.try_borrow_mut() {
+                if let Some(app) = &mut *ecs_mut {
+                    app.send_rotate_end();
+                }
+            }
+        });
+    }) as Box<dyn FnMut()>);
+    Reflect::set(&global, &JsValue::from_str("ecs_rotate_end"), f_rotate_end.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_rotate_end"), f_rotate_end.as_ref()); }
+    f_rotate_end.forget();
+
+    // ecs_marquee_start(x, y, shift_key, mode)
+    let f_marquee_start = Closure::wrap(Box::new(move |x: f32, y: f32, shift_key: bool, mode: u8| {
+        console::log_1(&format!("[global] ecs_marquee_start({}, {}, shift={}, mode={})", x, y, shift_key, mode).into());
+        ECS.with(|ecs| {
+            if let Ok(mut ecs_mut) = ecs.try_borrow_mut() {
+                if let Some(app) = &mut *ecs_mut {
+                    app.send_marquee_start(x, y, shift_key, marquee_mode_from_u8(mode));
+                }
+            }
+        });
+    }) as Box<dyn FnMut(f32, f32, bool, u8)>);
+    Reflect::set(&global, &JsValue::from_str("ecs_marquee_start"), f_marquee_start.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_marquee_start"), f_marquee_start.as_ref()); }
+    f_marquee_start.forget();
+
+    // ecs_marquee_update(dx, dy)
+    let f_marquee_update = Closure::wrap(Box::new(move |dx: f32, dy: f32| {
+        ECS.with(|ecs| {
+            if let Ok(mut ecs_mut) = ecs.try_borrow_mut() {
+                if let Some(app) = &mut *ecs_mut {
+                    app.send_marquee_update(dx, dy);
+                }
+            }
+        });
+    }) as Box<dyn FnMut(f32, f32)>);
+    Reflect::set(&global, &JsValue::from_str("ecs_marquee_update"), f_marquee_update.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_marquee_update"), f_marquee_update.as_ref()); }
+    f_marquee_update.forget();
+
+    // ecs_marquee_end()
+    let f_marquee_end = Closure::wrap(Box::new(move || {
+        console::log_1(&"[global] ecs_marquee_end()".into());
+        ECS.with(|ecs| {
+            if let Ok(mut ecs_mut) = ecs.try_borrow_mut() {
+                if let Some(app) = &mut *ecs_mut {
+                    app.send_marquee_end();
+                }
+            }
+        });
+    }) as Box<dyn FnMut()>);
+    Reflect::set(&global, &JsValue::from_str("ecs_marquee_end"), f_marquee_end.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_marquee_end"), f_marquee_end.as_ref()); }
+    f_marquee_end.forget();
+
+    // ecs_pen_start(x, y, pressure)
+    let f_pen_start = Closure::wrap(Box::new(move |x: f32, y: f32, pressure: f32| {
+        console::log_1(&format!("[global] ecs_pen_start({}, {}, pressure={})", x, y, pressure).into());
+        ECS.with(|ecs| {
+            if let Ok(mut ecs_mut) = ecs.try_borrow_mut() {
+                if let Some(app) = &mut *ecs_mut {
+                    app.send_pen_start(x, y, pressure);
+                }
+            }
+        });
+    }) as Box<dyn FnMut(f32, f32, f32)>);
+    Reflect::set(&global, &JsValue::from_str("ecs_pen_start"), f_pen_start.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_pen_start"), f_pen_start.as_ref()); }
+    f_pen_start.forget();
+
+    // ecs_pen_move(x, y, pressure)
+    let f_pen_move = Closure::wrap(Box::new(move |x: f32, y: f32, pressure: f32| {
+        ECS.with(|ecs| {
+            if let Ok(mut ecs_mut) = ecs.try_borrow_mut() {
+                if let Some(app) = &mut *ecs_mut {
+                    app.send_pen_move(x, y, pressure);
+                }
+            }
+        });
+    }) as Box<dyn FnMut(f32, f32, f32)>);
+    Reflect::set(&global, &JsValue::from_str("ecs_pen_move"), f_pen_move.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_pen_move"), f_pen_move.as_ref()); }
+    f_pen_move.forget();
+
+    // ecs_pen_end()
+    let f_pen_end = Closure::wrap(Box::new(move || {
+        console::log_1(&"[global] ecs_pen_end()".into());
+        ECS.with(|ecs| {
+            if let Ok(mut ecs_mut) = ecs.try_borrow_mut() {
+                if let Some(app) = &mut *ecs_mut {
+                    app.send_pen_end();
+                }
+            }
+        });
+    }) as Box<dyn FnMut()>);
+    Reflect::set(&global, &JsValue::from_str("ecs_pen_end"), f_pen_end.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_pen_end"), f_pen_end.as_ref()); }
+    f_pen_end.forget();
+
+    // ecs_text_begin(x, y, create)
+    let f_text_begin = Closure::wrap(Box::new(move |x: f32, y: f32, create: bool| {
+        console::log_1(&format!("[global] ecs_text_begin({}, {}, create={})", x, y, create).into());
+        ECS.with(|ecs| {
+            if let Ok(mut ecs_mut) = ecs.try_borrow_mut() {
+                if let Some(app) = &mut *ecs_mut {
+                    app.send_text_begin(x, y, create);
+                }
+            }
+        });
+    }) as Box<dyn FnMut(f32, f32, bool)>);
+    Reflect::set(&global, &JsValue::from_str("ecs_text_begin"), f_text_begin.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_text_begin"), f_text_begin.as_ref()); }
+    f_text_begin.forget();
+
+    // ecs_text_pointer(x, y, extend)
+    let f_text_pointer = Closure::wrap(Box::new(move |x: f32, y: f32, extend: bool| {
+        ECS.with(|ecs| {
+            if let Ok(mut ecs_mut) = ecs.try_borrow_mut() {
+                if let Some(app) = &mut *ecs_mut {
+                    app.send_text_pointer(x, y, extend);
+                }
+            }
+        });
+    }) as Box<dyn FnMut(f32, f32, bool)>);
+    Reflect::set(&global, &JsValue::from_str("ecs_text_pointer"), f_text_pointer.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_text_pointer"), f_text_pointer.as_ref()); }
+    f_text_pointer.forget();
+
+    // ecs_text_insert(text) / ecs_text_preedit(text)
+    let f_text_insert = Closure::wrap(Box::new(move |text: String| {
+        ECS.with(|ecs| {
+            if let Ok(mut ecs_mut) = ecs.try_borrow_mut() {
+                if let Some(app) = &mut *ecs_mut {
+                    app.send_text_insert(&text);
+                }
+            }
+        });
+    }) as Box<dyn FnMut(String)>);
+    Reflect::set(&global, &JsValue::from_str("ecs_text_insert"), f_text_insert.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_text_insert"), f_text_insert.as_ref()); }
+    f_text_insert.forget();
+
+    let f_text_preedit = Closure::wrap(Box::new(move |text: String| {
+        ECS.with(|ecs| {
+            if let Ok(mut ecs_mut) = ecs.try_borrow_mut() {
+                if let Some(app) = &mut *ecs_mut {
+                    app.send_text_preedit(&text);
+                }
+            }
+        });
+    }) as Box<dyn FnMut(String)>);
+    Reflect::set(&global, &JsValue::from_str("ecs_text_preedit"), f_text_preedit.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_text_preedit"), f_text_preedit.as_ref()); }
+    f_text_preedit.forget();
+
+    // ecs_text_key(key, shift) -> bool
+    let f_text_key = Closure::wrap(Box::new(move |key: String, shift: bool| -> bool {
+        let Some(key) = text_key_from_str(&key) else { return false; };
+        ECS.with(|ecs| {
+            if let Ok(mut ecs_mut) = ecs.try_borrow_mut() {
+                if let Some(app) = &mut *ecs_mut {
+                    app.send_text_key(key, shift);
+                }
+            }
+        });
+        true
+    }) as Box<dyn FnMut(String, bool) -> bool>);
+    Reflect::set(&global, &JsValue::from_str("ecs_text_key"), f_text_key.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_text_key"), f_text_key.as_ref()); }
+    f_text_key.forget();
+
+    // ecs_text_end()
+    let f_text_end = Closure::wrap(Box::new(move || {
+        console::log_1(&"[global] ecs_text_end()".into());
+        ECS.with(|ecs| {
+            if let Ok(mut ecs_mut) = ecs.try_borrow_mut() {
+                if let Some(app) = &mut *ecs_mut {
+                    app.send_text_end();
+                }
+            }
+        });
+    }) as Box<dyn FnMut()>);
+    Reflect::set(&global, &JsValue::from_str("ecs_text_end"), f_text_end.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_text_end"), f_text_end.as_ref()); }
+    f_text_end.forget();
+
+    // ecs_is_text_editing() / ecs_text_edit_hit(x, y) -> bool
+    let f_is_text_editing = Closure::wrap(Box::new(move || -> bool {
+        ECS.with(|ecs| ecs.try_borrow().ok().and_then(|e| e.as_ref().map(|app| app.is_text_editing())).unwrap_or(false))
+    }) as Box<dyn FnMut() -> bool>);
+    Reflect::set(&global, &JsValue::from_str("ecs_is_text_editing"), f_is_text_editing.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_is_text_editing"), f_is_text_editing.as_ref()); }
+    f_is_text_editing.forget();
+
+    let f_text_edit_hit = Closure::wrap(Box::new(move |x: f32, y: f32| -> bool {
+        ECS.with(|ecs| ecs.try_borrow().ok().and_then(|e| e.as_ref().map(|app| app.text_edit_hit(x, y))).unwrap_or(false))
+    }) as Box<dyn FnMut(f32, f32) -> bool>);
+    Reflect::set(&global, &JsValue::from_str("ecs_text_edit_hit"), f_text_edit_hit.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_text_edit_hit"), f_text_edit_hit.as_ref()); }
+    f_text_edit_hit.forget();
+
+    // ecs_text_caret_rect() -> { x, y, h } | null
+    let f_text_caret = Closure::wrap(Box::new(move || -> JsValue {
+        ECS.with(|ecs| {
+            ecs.try_borrow_mut()
+                .ok()
+                .and_then(|mut e| e.as_mut().and_then(|app| app.text_caret_rect()))
+                .map(caret_to_js)
+                .unwrap_or(JsValue::NULL)
+        })
+    }) as Box<dyn FnMut() -> JsValue>);
+    Reflect::set(&global, &JsValue::from_str("ecs_text_caret_rect"), f_text_caret.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_text_caret_rect"), f_text_caret.as_ref()); }
+    f_text_caret.forget();
+
+    // ecs_undo() / ecs_redo() -> bool
+    let f_undo = Closure::wrap(Box::new(move || -> bool {
+        console::log_1(&"[global] ecs_undo()".into());
+        ECS.with(|ecs| {
+            match ecs.try_borrow_mut() {
+                Ok(mut ecs_mut) => ecs_mut.as_mut().map(|app| app.undo()).unwrap_or(false),
+                Err(_) => false,
+            }
+        })
+    }) as Box<dyn FnMut() -> bool>);
+    Reflect::set(&global, &JsValue::from_str("ecs_undo"), f_undo.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_undo"), f_undo.as_ref()); }
+    f_undo.forget();
+
+    let f_redo = Closure::wrap(Box::new(move || -> bool {
+        console::log_1(&"[global] ecs_redo()".into());
+        ECS.with(|ecs| {
+            match ecs.try_borrow_mut() {
+                Ok(mut ecs_mut) => ecs_mut.as_mut().map(|app| app.redo()).unwrap_or(false),
+                Err(_) => false,
+            }
+        })
+    }) as Box<dyn FnMut() -> bool>);
+    Reflect::set(&global, &JsValue::from_str("ecs_redo"), f_redo.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_redo"), f_redo.as_ref()); }
+    f_redo.forget();
+
+    // ecs_can_undo() / ecs_can_redo() -> bool
+    let f_can_undo = Closure::wrap(Box::new(move || -> bool {
+        ECS.with(|ecs| ecs.try_borrow().ok().and_then(|e| e.as_ref().map(|app| app.can_undo())).unwrap_or(false))
+    }) as Box<dyn FnMut() -> bool>);
+    Reflect::set(&global, &JsValue::from_str("ecs_can_undo"), f_can_undo.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_can_undo"), f_can_undo.as_ref()); }
+    f_can_undo.forget();
+
+    let f_can_redo = Closure::wrap(Box::new(move || -> bool {
+        ECS.with(|ecs| ecs.try_borrow().ok().and_then(|e| e.as_ref().map(|app| app.can_redo())).unwrap_or(false))
+    }) as Box<dyn FnMut() -> bool>);
+    Reflect::set(&global, &JsValue::from_str("ecs_can_redo"), f_can_redo.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_can_redo"), f_can_redo.as_ref()); }
+    f_can_redo.forget();
+
+    // ecs_set_history_limit(limit)
+    let f_history_limit = Closure::wrap(Box::new(move |limit: u32| {
+        ECS.with(|ecs| {
+            if let Ok(mut ecs_mut) = ecs.try_borrow_mut() {
+                if let Some(app) = &mut *ecs_mut {
+                    app.set_history_limit(limit as usize);
+                }
+            }
+        });
+    }) as Box<dyn FnMut(u32)>);
+    Reflect::set(&global, &JsValue::from_str("ecs_set_history_limit"), f_history_limit.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_set_history_limit"), f_history_limit.as_ref()); }
+    f_history_limit.forget();
+
+    // ecs_delete_selection()
+    let f_delete = Closure::wrap(Box::new(move || {
+        console::log_1(&"[global] ecs_delete_selection()".into());
+        ECS.with(|ecs| {
+            if let Ok(mut ecs_mut) = ecs.try_borrow_mut() {
+                if let Some(app) = &mut *ecs_mut {
+                    app.send_delete_selection();
+                }
+            }
+        });
+    }) as Box<dyn FnMut()>);
+    Reflect::set(&global, &JsValue::from_str("ecs_delete_selection"), f_delete.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_delete_selection"), f_delete.as_ref()); }
+    f_delete.forget();
+
+    // ecs_duplicate_selection(dx, dy)
+    let f_duplicate = Closure::wrap(Box::new(move |dx: f32, dy: f32| {
+        console::log_1(&format!("[global] ecs_duplicate_selection({}, {})", dx, dy).into());
+        ECS.with(|ecs| {
+            if let Ok(mut ecs_mut) = ecs.try_borrow_mut() {
+                if let Some(app) = &mut *ecs_mut {
+                    app.send_duplicate_selection(dx, dy);
+                }
+            }
+        });
+    }) as Box<dyn FnMut(f32, f32)>);
+    Reflect::set(&global, &JsValue::from_str("ecs_duplicate_selection"), f_duplicate.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_duplicate_selection"), f_duplicate.as_ref()); }
+    f_duplicate.forget();
+
+    // ecs_z_order(op)
+    let f_z_order = Closure::wrap(Box::new(move |op: u8| {
+        console::log_1(&format!("[global] ecs_z_order({})", op).into());
+        let Some(op) = z_order_op_from_u8(op) else { return; };
+        ECS.with(|ecs| {
+            if let Ok(mut ecs_mut) = ecs.try_borrow_mut() {
+                if let Some(app) = &mut *ecs_mut {
+                    app.send_z_order(op);
+                }
+            }
+        });
+    }) as Box<dyn FnMut(u8)>);
+    Reflect::set(&global, &JsValue::from_str("ecs_z_order"), f_z_order.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_z_order"), f_z_order.as_ref()); }
+    f_z_order.forget();
+
+    // ecs_get_entity_order() -> ids en orden de pintado
+    let f_order = Closure::wrap(Box::new(move || -> JsValue {
+        let order = js_sys::Array::new();
+        ECS.with(|ecs| {
+            if let Ok(ecs_ref) = ecs.try_borrow() {
+                if let Some(app) = &*ecs_ref {
+                    for id in app.document().ids() {
+                        order.push(&JsValue::from_f64(id.0 as f64));
+                    }
+                }
+            }
+        });
+        order.into()
+    }) as Box<dyn FnMut() -> JsValue>);
+    Reflect::set(&global, &JsValue::from_str("ecs_get_entity_order"), f_order.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_get_entity_order"), f_order.as_ref()); }
+    f_order.forget();
+
+    // ecs_pan_by(dx, dy)
+    let f_pan = Closure::wrap(Box::new(move |dx: f32, dy: f32| {
+        ECS.with(|ecs| {
+            if let Ok(mut ecs_mut) = ecs.try_borrow_mut() {
+                if let Some(app) = &mut *ecs_mut {
+                    app.pan_by(dx, dy);
+                }
+            }
+        });
+    }) as Box<dyn FnMut(f32, f32)>);
+    Reflect::set(&global, &JsValue::from_str("ecs_pan_by"), f_pan.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_pan_by"), f_pan.as_ref()); }
+    f_pan.forget();
+
+    // ecs_zoom_at(x, y, factor)
+    let f_zoom_at = Closure::wrap(Box::new(move |x: f32, y: f32, factor: f32| {
+        ECS.with(|ecs| {
+            if let Ok(mut ecs_mut) = ecs.try_borrow_mut() {
+                if let Some(app) = &mut *ecs_mut {
+                    app.zoom_at(x, y, factor);
+                }
+            }
+        });
+    }) as Box<dyn FnMut(f32, f32, f32)>);
+    Reflect::set(&global, &JsValue::from_str("ecs_zoom_at"), f_zoom_at.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_zoom_at"), f_zoom_at.as_ref()); }
+    f_zoom_at.forget();
+
+    // ecs_zoom_to_fit() / ecs_zoom_to_selection() -> bool
+    let f_zoom_fit = Closure::wrap(Box::new(move || -> bool {
+        console::log_1(&"[global] ecs_zoom_to_fit()".into());
+        ECS.with(|ecs| {
+            match ecs.try_borrow_mut() {
+                Ok(mut ecs_mut) => ecs_mut.as_mut().map(|app| app.zoom_to_fit()).unwrap_or(false),
+                Err(_) => false,
+            }
+        })
+    }) as Box<dyn FnMut() -> bool>);
+    Reflect::set(&global, &JsValue::from_str("ecs_zoom_to_fit"), f_zoom_fit.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_zoom_to_fit"), f_zoom_fit.as_ref()); }
+    f_zoom_fit.forget();
+
+    let f_zoom_selection = Closure::wrap(Box::new(move || -> bool {
+        console::log_1(&"[global] ecs_zoom_to_selection()".into());
+        ECS.with(|ecs| {
+            match ecs.try_borrow_mut() {
+                Ok(mut ecs_mut) => ecs_mut.as_mut().map(|app| app.zoom_to_selection()).unwrap_or(false),
+                Err(_) => false,
+            }
+        })
+    }) as Box<dyn FnMut() -> bool>);
+    Reflect::set(&global, &JsValue::from_str("ecs_zoom_to_selection"), f_zoom_selection.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_zoom_to_selection"), f_zoom_selection.as_ref()); }
+    f_zoom_selection.forget();
+
+    // ecs_reset_camera()
+    let f_reset_camera = Closure::wrap(Box::new(move || {
+        ECS.with(|ecs| {
+            if let Ok(mut ecs_mut) = ecs.try_borrow_mut() {
+                if let Some(app) = &mut *ecs_mut {
+                    app.set_camera(Default::default());
+                }
+            }
+        });
+    }) as Box<dyn FnMut()>);
+    Reflect::set(&global, &JsValue::from_str("ecs_reset_camera"), f_reset_camera.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_reset_camera"), f_reset_camera.as_ref()); }
+    f_reset_camera.forget();
+
+    // ecs_get_camera() -> { x, y, zoom }
+    let f_get_camera = Closure::wrap(Box::new(move || -> JsValue {
+        ECS.with(|ecs| {
+            ecs.try_borrow()
+                .ok()
+                .and_then(|e| e.as_ref().map(|app| camera_to_js(&app.camera())))
+                .unwrap_or(JsValue::NULL)
+        })
+    }) as Box<dyn FnMut() -> JsValue>);
+    Reflect::set(&global, &JsValue::from_str("ecs_get_camera"), f_get_camera.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_get_camera"), f_get_camera.as_ref()); }
+    f_get_camera.forget();
+
+    // ecs_screen_to_world(x, y) / ecs_world_to_screen(x, y) -> [x, y]
+    let f_screen_to_world = Closure::wrap(Box::new(move |x: f32, y: f32| -> JsValue {
+        ECS.with(|ecs| {
+            point_to_js(ecs.try_borrow().ok().and_then(|e| e.as_ref().map(|app| app.screen_to_world(x, y))).unwrap_or((x, y)))
+        })
+    }) as Box<dyn FnMut(f32, f32) -> JsValue>);
+    Reflect::set(&global, &JsValue::from_str("ecs_screen_to_world"), f_screen_to_world.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_screen_to_world"), f_screen_to_world.as_ref()); }
+    f_screen_to_world.forget();
+
+    let f_world_to_screen = Closure::wrap(Box::new(move |x: f32, y: f32| -> JsValue {
+        ECS.with(|ecs| {
+            point_to_js(ecs.try_borrow().ok().and_then(|e| e.as_ref().map(|app| app.world_to_screen(x, y))).unwrap_or((x, y)))
+        })
+    }) as Box<dyn FnMut(f32, f32) -> JsValue>);
+    Reflect::set(&global, &JsValue::from_str("ecs_world_to_screen"), f_world_to_screen.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_world_to_screen"), f_world_to_screen.as_ref()); }
+    f_world_to_screen.forget();
+
     // ecs_detect_handle_hover
     let f_detect_handle_hover = Closure::wrap(Box::new(move |x: f32, y: f32| -> JsValue {
         ECS.with(|ecs| {
@@ -908,62 +2118,12 @@ fn register_window_functions() -> Result<(), JsValue> {
     // ecs_detect_shape_hover
     let f_detect_shape_hover = Closure::wrap(Box::new(move |x: f32, y: f32| -> JsValue {
         ECS.with(|ecs| {
-            if let Some(app) = &mut *ecs.borrow_mut() {
-                // Usar la misma lógica que en pointer_down pero solo para detección
-                let dpr = 1.0; // Simplificado para hover
-                let click_x = x * dpr;
-                let click_y = y * dpr;
-                
-                // Buscar si hay alguna entidad en esa posición
-                for (id, transform, _style, shape) in &app.document().entities {
-                    // Crear transform escalado por DPR
-                    let transform_physical = momentum_core::model::Transform {
-                        x: transform.x * dpr,
-                        y: transform.y * dpr,
-                        scale_x: transform.scale_x * dpr,
-                        scale_y: transform.scale_y * dpr,
-                        rotation: transform.rotation,
-                    };
-                    
-                    // Escalar shape por DPR
-                    let mut shape_physical = shape.clone();
-                    match shape_physical {
-                        momentum_core::model::Shape::Rect { ref mut w, ref mut h } => {
-                            *w *= dpr;
-                            *h *= dpr;
-                        }
-                        momentum_core::model::Shape::Ellipse { ref mut rx, ref mut ry } => {
-                            *rx *= dpr;
-                            *ry *= dpr;
-                        }
-                        momentum_core::model::Shape::Line { ref mut x2, ref mut y2 } => {
-                            *x2 *= dpr;
-                            *y2 *= dpr;
-                        }
-                        momentum_core::model::Shape::Polygon { ref mut points } => {
-                            for (px, py) in points {
-                                *px *= dpr;
-                                *py *= dpr;
-                            }
-                        }
-                    }
-                    
-                    // Usar sistema hitbox o fallback al shape
-                    if let Some(hitbox) = app.document().get_hitbox(*id) {
-                        if hitbox.hit_test(click_x, click_y, &transform_physical, &shape_physical) {
-                            return JsValue::from_bool(true);
-                        }
-                    } else {
-                        // Fallback: usar shape con tolerancia por defecto
-                        let default_hitbox = momentum_core::model::Hitbox::from_shape(&shape_physical);
-                        if default_hitbox.hit_test(click_x, click_y, &transform_physical, &shape_physical) {
-                            return JsValue::from_bool(true);
-                        }
-                    }
-                }
-                JsValue::from_bool(false)
-            } else {
-                JsValue::from_bool(false)
+            match ecs.try_borrow() {
+                Ok(ecs_ref) => match &*ecs_ref {
+                    Some(app) => JsValue::from_bool(app.pick(x, y).entity.is_some()),
+                    None => JsValue::from_bool(false),
+                },
+                Err(_) => JsValue::from_bool(false),
             }
         })
     }) as Box<dyn FnMut(f32, f32) -> JsValue>);
@@ -971,6 +2131,22 @@ fn register_window_functions() -> Result<(), JsValue> {
     if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_detect_shape_hover"), f_detect_shape_hover.as_ref()); }
     f_detect_shape_hover.forget();
 
+    // ecs_pick(x, y) -> { handle, entity, hits }
+    let f_pick = Closure::wrap(Box::new(move |x: f32, y: f32| -> JsValue {
+        ECS.with(|ecs| {
+            match ecs.try_borrow() {
+                Ok(ecs_ref) => match &*ecs_ref {
+                    Some(app) => pick_result_to_js(&app.pick(x, y)),
+                    None => JsValue::NULL,
+                },
+                Err(_) => JsValue::NULL,
+            }
+        })
+    }) as Box<dyn FnMut(f32, f32) -> JsValue>);
+    Reflect::set(&global, &JsValue::from_str("ecs_pick"), f_pick.as_ref())?;
+    if let Some(win) = &win_opt { let _ = Reflect::set(win, &JsValue::from_str("ecs_pick"), f_pick.as_ref()); }
+    f_pick.forget();
+
     console::log_1(&"Funciones globales registradas en globalThis/window".into());
     Ok(())
 }
@@ -1,4 +1,4 @@
-use momentum_core::{usecases::Document, model::{Style, Transform, Shape}};
+use momentum_core::{usecases::Document, model::{CornerRadii, Style, Transform, Shape}};
 
 fn main() {
     println!("Hodei Momentum — app-web");
@@ -8,7 +8,7 @@ fn main() {
     let id = doc.create_shape(
         Transform { x: 10.0, y: 20.0, ..Default::default() },
         Style { stroke_width: 1.0, opacity: 1.0, ..Default::default() },
-        Shape::Rect { w: 100.0, h: 80.0 },
+        Shape::Rect { w: 100.0, h: 80.0, radii: CornerRadii::default() },
     );
 
     println!("Created entity {}. Count={}", id, doc.count());
@@ -1,9 +1,14 @@
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::{JsCast, JsValue};
 #[cfg(target_arch = "wasm32")]
-use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement};
+use wasm_bindgen::closure::Closure;
+#[cfg(target_arch = "wasm32")]
+use web_sys::{Blob, BlobPropertyBag, CanvasGradient, CanvasRenderingContext2d, DomMatrix2dInit, HtmlCanvasElement, HtmlImageElement, Url};
+
+use std::collections::HashMap;
 
-use momentum_core::model::{Color, Shape, Transform};
+use momentum_core::model::{Color, FreehandStroke, GradientStop, ImageId, Paint, Shape, Transform, PATH_FLATTEN_TOLERANCE};
+use momentum_core::paint;
 use momentum_core::ports::{RenderError, RenderPort};
 
 pub struct Canvas2DRenderer {
@@ -12,6 +17,8 @@ pub struct Canvas2DRenderer {
     // Store camera transform matrix [a, b, c, d, e, f] for 2D affine transformation
     // Represents: [scaleX, skewY, skewX, scaleY, translateX, translateY]
     camera_transform: [f32; 6],
+    /// Caché de texturas: imágenes que el navegador decodifica a partir de los bytes subidos
+    images: HashMap<ImageId, HtmlImageElement>,
 }
 
 impl Canvas2DRenderer {
@@ -25,7 +32,75 @@ impl Canvas2DRenderer {
         // Initialize with identity matrix (no transformation)
         let camera_transform = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
         
-        Ok(Self { canvas, ctx, camera_transform })
+        Ok(Self { canvas, ctx, camera_transform, images: HashMap::new() })
+    }
+
+    /// Pone `paint` como estilo de relleno (o de trazo, con `stroke`) en las coordenadas locales del
+    /// contexto, que se mueven con la entidad. Los sombreados llegan ya como líneas, así que aquí son
+    /// su color; una imagen aún sin decodificar se pinta entretanto con `Paint::color`.
+    fn set_paint(&self, paint: &Paint, opacity: f32, stroke: bool) {
+        // Un patrón no lleva alfa: la opacidad del estilo va en la del contexto
+        let mut alpha = 1.0;
+        let value: JsValue = match paint {
+            Paint::LinearGradient { start, end, stops } => {
+                let gradient = self.ctx.create_linear_gradient(start.0 as f64, start.1 as f64, end.0 as f64, end.1 as f64);
+                add_color_stops(&gradient, stops, opacity);
+                gradient.into()
+            }
+            Paint::RadialGradient { center, radius, stops } if *radius > 0.0 => {
+                let (x, y) = (center.0 as f64, center.1 as f64);
+                match self.ctx.create_radial_gradient(x, y, 0.0, x, y, *radius as f64) {
+                    Ok(gradient) => {
+                        add_color_stops(&gradient, stops, opacity);
+                        gradient.into()
+                    }
+                    Err(_) => css_color(paint::color_at(paint, (0.0, 0.0)), opacity).into(),
+                }
+            }
+            // Sin radio, el color de la última parada (como en el resto de renderers)
+            Paint::RadialGradient { .. } => css_color(paint::color_at(paint, (0.0, 0.0)), opacity).into(),
+            Paint::Image { blob, w, h } => match self.image_pattern(ImageId::from(*blob), *w, *h) {
+                Some(pattern) => {
+                    alpha = opacity.clamp(0.0, 1.0);
+                    pattern
+                }
+                None => css_color(paint.color(), opacity).into(),
+            },
+            Paint::Solid(color) | Paint::Hatch { color, .. } => css_color(*color, opacity).into(),
+        };
+        self.ctx.set_global_alpha(alpha as f64);
+        if stroke {
+            self.ctx.set_stroke_style(&value);
+        } else {
+            self.ctx.set_fill_style(&value);
+        }
+    }
+
+    /// Mosaico repetido de la imagen, escalado para que cada copia mida `w` x `h` desde el origen
+    /// local; None mientras la imagen se decodifica o si no se pudo
+    fn image_pattern(&self, id: ImageId, w: f32, h: f32) -> Option<JsValue> {
+        let image = self.images.get(&id).filter(|image| image.complete() && image.natural_width() > 0)?;
+        if w == 0.0 || h == 0.0 {
+            return None;
+        }
+        let pattern = self.ctx.create_pattern_with_html_image_element(image, "repeat").ok().flatten()?;
+        let matrix = DomMatrix2dInit::new();
+        matrix.set_a(w as f64 / image.natural_width() as f64);
+        matrix.set_d(h as f64 / image.natural_height() as f64);
+        pattern.set_transform(&matrix);
+        Some(pattern.into())
+    }
+}
+
+/// Color CSS con el alfa multiplicado por la opacidad del estilo
+fn css_color(Color(r, g, b, a): Color, opacity: f32) -> String {
+    format!("rgba({},{},{},{})", (r * 255.0) as u32, (g * 255.0) as u32, (b * 255.0) as u32, a * opacity)
+}
+
+/// Canvas2D exige posiciones en [0, 1]; las paradas fuera de rango se recortan
+fn add_color_stops(gradient: &CanvasGradient, stops: &[GradientStop], opacity: f32) {
+    for stop in stops {
+        let _ = gradient.add_color_stop(stop.offset.clamp(0.0, 1.0), &css_color(stop.color, opacity));
     }
 }
 
@@ -68,10 +143,42 @@ impl RenderPort for Canvas2DRenderer {
         // Store the camera transformation matrix
         // The matrix represents [scaleX, skewY, skewX, scaleY, translateX, translateY]
         self.camera_transform = transform_2d;
+        // Apply it right away so that a camera change mid-frame affects the following draws
+        let [a, b, c, d, e, f] = transform_2d;
+        self.ctx.set_transform(
+            a as f64, b as f64, c as f64, d as f64, e as f64, f as f64
+        ).map_err(|_| RenderError::Other("Failed to set camera transform".into()))?;
         Ok(())
     }
 
     fn draw_shape(&mut self, transform: &Transform, shape: &Shape, style: &momentum_core::model::Style) -> Result<(), RenderError> {
+        // A mano alzada: los mismos trazados que en el resto de renderers y en el SVG
+        if let Some(geometry) = momentum_core::sketch::geometry(shape, style, transform) {
+            return momentum_core::sketch::draw_sketch(self, transform, &geometry, style);
+        }
+        // Los rellenos sombreados se pintan como líneas sobre el contorno exacto
+        if let Some(outline) = paint::hatched_outline(shape, style, transform) {
+            return self.draw_path(transform, &outline, style);
+        }
+        // Las esquinas redondeadas, como trazado
+        if let Some(outline) = paint::rounded_outline(shape, transform) {
+            return self.draw_path(transform, &outline, style);
+        }
+        match shape {
+            Shape::Path { path } => return self.draw_path(transform, path, style),
+            Shape::Freehand { stroke } => {
+                return self.draw_path(transform, &stroke.outline(PATH_FLATTEN_TOLERANCE), &FreehandStroke::ink_style(style));
+            }
+            Shape::Text { text } => return momentum_core::text::draw_text_box(self, &momentum_core::text::FontBook::default(), transform, text),
+            Shape::Arrow { arrow } => return momentum_core::arrow::draw_arrow(self, transform, arrow, style),
+            Shape::Image { blob, w, h } => {
+                let tint = (style.opacity < 1.0).then_some(Color(1.0, 1.0, 1.0, style.opacity));
+                let dest = momentum_core::model::Rect { x: 0.0, y: 0.0, w: *w, h: *h };
+                return self.draw_image(ImageId::from(*blob), dest, transform, tint);
+            }
+            _ => {}
+        }
+        
         // Save context for transformations
         self.ctx.save();
         
@@ -130,23 +237,19 @@ impl RenderPort for Canvas2DRenderer {
         }
         
         match shape {
-            Shape::Rect { w, h } => {
+            Shape::Rect { w, h, .. } => {
                 let width = *w as f64;
                 let height = *h as f64;
                 
                 // Fill if specified
-                if let Some(Color(r, g, b, a)) = style.fill {
-                    self.ctx.set_fill_style(&format!("rgba({},{},{},{})", 
-                        (r * 255.0) as u32, (g * 255.0) as u32, (b * 255.0) as u32, 
-                        a * style.opacity).into());
+                if let Some(paint) = &style.fill {
+                    self.set_paint(paint, style.opacity, false);
                     self.ctx.fill_rect(0.0, 0.0, width, height);
                 }
                 
                 // Stroke if specified
-                if let Some(Color(r, g, b, a)) = style.stroke {
-                    self.ctx.set_stroke_style(&format!("rgba({},{},{},{})", 
-                        (r * 255.0) as u32, (g * 255.0) as u32, (b * 255.0) as u32, 
-                        a * style.opacity).into());
+                if let Some(paint) = &style.stroke {
+                    self.set_paint(paint, style.opacity, true);
                     self.ctx.stroke_rect(0.0, 0.0, width, height);
                 }
             }
@@ -168,18 +271,14 @@ impl RenderPort for Canvas2DRenderer {
                 }
                 
                 // Fill if specified
-                if let Some(Color(r, g, b, a)) = style.fill {
-                    self.ctx.set_fill_style(&format!("rgba({},{},{},{})", 
-                        (r * 255.0) as u32, (g * 255.0) as u32, (b * 255.0) as u32, 
-                        a * style.opacity).into());
+                if let Some(paint) = &style.fill {
+                    self.set_paint(paint, style.opacity, false);
                     self.ctx.fill();
                 }
                 
                 // Stroke if specified
-                if let Some(Color(r, g, b, a)) = style.stroke {
-                    self.ctx.set_stroke_style(&format!("rgba({},{},{},{})", 
-                        (r * 255.0) as u32, (g * 255.0) as u32, (b * 255.0) as u32, 
-                        a * style.opacity).into());
+                if let Some(paint) = &style.stroke {
+                    self.set_paint(paint, style.opacity, true);
                     self.ctx.stroke();
                 }
             }
@@ -190,10 +289,8 @@ impl RenderPort for Canvas2DRenderer {
                 self.ctx.line_to(*x2 as f64, *y2 as f64);
                 
                 // Lines only support stroke
-                if let Some(Color(r, g, b, a)) = style.stroke {
-                    self.ctx.set_stroke_style(&format!("rgba({},{},{},{})", 
-                        (r * 255.0) as u32, (g * 255.0) as u32, (b * 255.0) as u32, 
-                        a * style.opacity).into());
+                if let Some(paint) = &style.stroke {
+                    self.set_paint(paint, style.opacity, true);
                     self.ctx.stroke();
                 }
             }
@@ -217,22 +314,21 @@ impl RenderPort for Canvas2DRenderer {
                     self.ctx.close_path();
                     
                     // Fill if specified
-                    if let Some(Color(r, g, b, a)) = style.fill {
-                        self.ctx.set_fill_style(&format!("rgba({},{},{},{})", 
-                            (r * 255.0) as u32, (g * 255.0) as u32, (b * 255.0) as u32, 
-                            a * style.opacity).into());
+                    if let Some(paint) = &style.fill {
+                        self.set_paint(paint, style.opacity, false);
                         self.ctx.fill();
                     }
                     
                     // Stroke if specified
-                    if let Some(Color(r, g, b, a)) = style.stroke {
-                        self.ctx.set_stroke_style(&format!("rgba({},{},{},{})", 
-                            (r * 255.0) as u32, (g * 255.0) as u32, (b * 255.0) as u32, 
-                            a * style.opacity).into());
+                    if let Some(paint) = &style.stroke {
+                        self.set_paint(paint, style.opacity, true);
                         self.ctx.stroke();
                     }
                 }
             }
+            
+            // Trazados, lápiz, texto, imágenes y flechas ya se han delegado
+            Shape::Path { .. } | Shape::Freehand { .. } | Shape::Text { .. } | Shape::Image { .. } | Shape::Arrow { .. } => {}
         }
         
         // Restore context
@@ -245,6 +341,9 @@ impl RenderPort for Canvas2DRenderer {
         if path.commands.is_empty() {
             return Ok(());
         }
+        if let Some(result) = paint::draw_hatched(self, transform, path, style) {
+            return result;
+        }
         
         // Save context for transformations
         self.ctx.save();
@@ -331,18 +430,14 @@ impl RenderPort for Canvas2DRenderer {
         }
         
         // Fill if specified
-        if let Some(Color(r, g, b, a)) = style.fill {
-            self.ctx.set_fill_style(&format!("rgba({},{},{},{})", 
-                (r * 255.0) as u32, (g * 255.0) as u32, (b * 255.0) as u32, 
-                a * style.opacity).into());
+        if let Some(paint) = &style.fill {
+            self.set_paint(paint, style.opacity, false);
             self.ctx.fill();
         }
         
         // Stroke if specified
-        if let Some(Color(r, g, b, a)) = style.stroke {
-            self.ctx.set_stroke_style(&format!("rgba({},{},{},{})", 
-                (r * 255.0) as u32, (g * 255.0) as u32, (b * 255.0) as u32, 
-                a * style.opacity).into());
+        if let Some(paint) = &style.stroke {
+            self.set_paint(paint, style.opacity, true);
             self.ctx.stroke();
         }
         
@@ -421,10 +516,11 @@ impl RenderPort for Canvas2DRenderer {
         let font_string = format!("{} {}px {}", weight, span.size, font_family);
         self.ctx.set_font(&font_string);
         
-        // Simple text width estimation since Canvas2D measure_text might not be available
-        // This is a rough approximation based on average character width
-        let avg_char_width = span.size * 0.6; // Rough approximation for typical fonts
-        let width = span.text.len() as f32 * avg_char_width;
+        // Ancho real del navegador (con kerning); si falla, estimación por carácter
+        let width = match self.ctx.measure_text(&span.text) {
+            Ok(metrics) => metrics.width() as f32,
+            Err(_) => span.text.chars().count() as f32 * span.size * 0.6,
+        };
         
         // Estimate ascent and descent based on font size
         // These are approximations since Canvas2D doesn't provide exact font metrics
@@ -440,9 +536,48 @@ impl RenderPort for Canvas2DRenderer {
         })
     }
 
-    fn upload_image(&mut self, _id: momentum_core::model::ImageId, _data: &[u8]) -> Result<(), RenderError> { Ok(()) }
+    /// `data` es una imagen PNG, JPEG o WebP codificada; el navegador la decodifica en segundo plano
+    fn upload_image(&mut self, id: ImageId, data: &[u8]) -> Result<(), RenderError> {
+        let info = momentum_core::image::probe(data).map_err(|_| RenderError::InvalidInput)?;
+        let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(data));
+        let options = BlobPropertyBag::new();
+        options.set_type(info.format.mime_type());
+        let blob = Blob::new_with_u8_array_sequence_and_options(&parts, &options).map_err(|_| RenderError::UploadFailed)?;
+        let url = Url::create_object_url_with_blob(&blob).map_err(|_| RenderError::UploadFailed)?;
+        let image = HtmlImageElement::new().map_err(|_| RenderError::UploadFailed)?;
 
-    fn draw_image(&mut self, _id: momentum_core::model::ImageId, _dest: momentum_core::model::Rect, _transform: &Transform, _tint: Option<momentum_core::model::Color>) -> Result<(), RenderError> { Ok(()) }
+        // La URL solo hace falta hasta que termina la decodificación, bien o mal
+        let revoke = |url: String| Closure::once_into_js(move || { let _ = Url::revoke_object_url(&url); });
+        image.set_onload(Some(revoke(url.clone()).unchecked_ref()));
+        image.set_onerror(Some(revoke(url.clone()).unchecked_ref()));
+        image.set_src(&url);
+        self.images.insert(id, image);
+        Ok(())
+    }
+
+    /// Mientras la imagen se decodifica (o si no se pudo) devuelve `UploadFailed` y no pinta nada.
+    /// Del tinte solo se aplica su alfa, como opacidad.
+    fn draw_image(&mut self, id: ImageId, dest: momentum_core::model::Rect, transform: &Transform, tint: Option<momentum_core::model::Color>) -> Result<(), RenderError> {
+        let image = self.images.get(&id).ok_or(RenderError::InvalidInput)?;
+        if !image.complete() || image.natural_width() == 0 {
+            return Err(RenderError::UploadFailed);
+        }
+
+        self.ctx.save();
+        self.ctx.translate(transform.x as f64, transform.y as f64).map_err(|_| RenderError::Other("Translation failed".into()))?;
+        if transform.rotation != 0.0 {
+            self.ctx.rotate(transform.rotation as f64).map_err(|_| RenderError::Other("Rotation failed".into()))?;
+        }
+        if transform.scale_x != 1.0 || transform.scale_y != 1.0 {
+            self.ctx.scale(transform.scale_x as f64, transform.scale_y as f64).map_err(|_| RenderError::Other("Scale failed".into()))?;
+        }
+        self.ctx.set_global_alpha(tint.map_or(1.0, |Color(_, _, _, a)| a.clamp(0.0, 1.0) as f64));
+        let drawn = self.ctx.draw_image_with_html_image_element_and_dw_and_dh(
+            image, dest.x as f64, dest.y as f64, dest.w as f64, dest.h as f64,
+        );
+        self.ctx.restore();
+        drawn.map_err(|_| RenderError::Other("Image draw failed".into()))
+    }
 
     fn draw_scale_handle(&mut self, handle: &momentum_core::model::ScaleHandle) -> Result<(), RenderError> {
         self.ctx.save();
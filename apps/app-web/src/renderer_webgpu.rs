@@ -3,23 +3,79 @@
 use std::{cell::RefCell, rc::Rc};
 use web_sys::HtmlCanvasElement;
 use wgpu::{InstanceDescriptor, PowerPreference, RequestAdapterOptions, SurfaceConfiguration, TextureUsages, PresentMode};
-use wgpu::util::DeviceExt;
 
+use lyon::path::Path as LyonPath;
 use momentum_core::ports::{RenderError, RenderPort};
-use momentum_core::model::{Path, Rect, Shape, Style, TextMetrics, TextSpan, Transform, ImageId, ScaleHandle};
+use momentum_core::model::{FreehandStroke, Path, PATH_FLATTEN_TOLERANCE, Rect, Shape, Style, TextMetrics, TextSpan, Transform, ImageId, ScaleHandle};
+
+use crate::tessellation::{self, Batch, Placement, Shading, Tessellator, Vertex};
+
+/// Frames que la GPU puede tener en vuelo: cada uno sube su lote a su propio hueco del anillo
+const FRAMES_IN_FLIGHT: usize = 3;
+/// Capacidad inicial del lote (vértices e índices); crece solo si un frame no cabe
+const INITIAL_VERTICES: usize = 16 * 1024;
+const INITIAL_INDICES: usize = 48 * 1024;
 
 struct WgpuState {
-    instance: wgpu::Instance,
-    adapter: wgpu::Adapter,
     device: wgpu::Device,
     queue: wgpu::Queue,
-    surface_format: wgpu::TextureFormat,
+    // Superficie creada una vez; solo se reconfigura al cambiar de tamaño o si se pierde
+    surface: wgpu::Surface<'static>,
     config: SurfaceConfiguration,
-    canvas: HtmlCanvasElement,
     pipeline: wgpu::RenderPipeline,
-    color_bgl: wgpu::BindGroupLayout,
-    // Frame state for current begin_frame..end_frame
-    cur_frame: Option<CurrentFrame>,
+    // Tamaño del viewport en píxeles físicos, para pasar de píxeles a NDC en el shader
+    viewport: wgpu::Buffer,
+    viewport_bg: wgpu::BindGroup,
+    vertex_ring: RingBuffer,
+    index_ring: RingBuffer,
+    tessellator: Tessellator,
+    // Triángulos del frame en curso, en orden de pintado, con el color en cada vértice
+    batch: Batch,
+    // Camera affine [a, b, c, d, e, f] from world to physical pixels
+    camera: [f32; 6],
+    // Textura del frame en curso entre begin_frame y end_frame
+    frame: Option<wgpu::SurfaceTexture>,
+}
+
+impl WgpuState {
+    fn configure(&self) {
+        self.surface.configure(&self.device, &self.config);
+        let size = [self.config.width as f32, self.config.height as f32, 0.0, 0.0];
+        self.queue.write_buffer(&self.viewport, 0, bytemuck::cast_slice(&size));
+    }
+}
+
+/// Buffers de GPU persistentes, uno por frame en vuelo, que se reutilizan en rueda. Un hueco solo
+/// se recrea (al doble de lo necesario) cuando el lote de un frame no cabe.
+struct RingBuffer {
+    label: &'static str,
+    usage: wgpu::BufferUsages,
+    slots: Vec<wgpu::Buffer>,
+    next: usize,
+}
+
+impl RingBuffer {
+    fn new(device: &wgpu::Device, label: &'static str, usage: wgpu::BufferUsages, size: u64) -> Self {
+        let usage = usage | wgpu::BufferUsages::COPY_DST;
+        let slots = (0..FRAMES_IN_FLIGHT).map(|_| Self::create(device, label, usage, size)).collect();
+        Self { label, usage, slots, next: 0 }
+    }
+
+    fn create(device: &wgpu::Device, label: &'static str, usage: wgpu::BufferUsages, size: u64) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor { label: Some(label), size, usage, mapped_at_creation: false })
+    }
+
+    /// Sube `data` al siguiente hueco y lo devuelve
+    fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, data: &[u8]) -> &wgpu::Buffer {
+        let slot = self.next;
+        self.next = (self.next + 1) % self.slots.len();
+        let needed = data.len() as u64;
+        if self.slots[slot].size() < needed {
+            self.slots[slot] = Self::create(device, self.label, self.usage, (needed * 2).next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT));
+        }
+        queue.write_buffer(&self.slots[slot], 0, data);
+        &self.slots[slot]
+    }
 }
 
 pub struct WebGpuRenderer {
@@ -43,21 +99,18 @@ impl WebGpuRenderer {
             .await
             .map_err(|e| RenderError::Other(format!("request_device error: {e}")))?;
 
-        // Create a temporary surface to determine capabilities/format
-        let tmp_surface = instance
-            .create_surface(wgpu::SurfaceTarget::Canvas(canvas.clone()))
-            .map_err(|e| RenderError::Other(format!("create_surface (caps) error: {e:?}")))?;
-        let surface_caps = tmp_surface.get_capabilities(&adapter);
+        let width = canvas.width().max(1);
+        let height = canvas.height().max(1);
+        let surface = instance
+            .create_surface(wgpu::SurfaceTarget::Canvas(canvas))
+            .map_err(|e| RenderError::Other(format!("create_surface error: {e:?}")))?;
+        let surface_caps = surface.get_capabilities(&adapter);
         let surface_format = surface_caps
             .formats
             .iter()
             .copied()
             .find(|f| f.is_srgb())
             .unwrap_or(surface_caps.formats[0]);
-        drop(tmp_surface);
-
-        let width = canvas.width().max(1);
-        let height = canvas.height().max(1);
         let config = SurfaceConfiguration {
             usage: TextureUsages::RENDER_ATTACHMENT,
             format: surface_format,
@@ -69,30 +122,44 @@ impl WebGpuRenderer {
             desired_maximum_frame_latency: 2,
         };
 
-        // Create a very simple pipeline: position-only, solid color fragment
+        // Un solo pipeline: triángulos en píxeles físicos con el color en cada vértice
         let shader_src = r#"
-@group(0) @binding(0) var<uniform> u_color: vec4<f32>;
+struct Viewport {
+    size: vec2<f32>,
+};
+@group(0) @binding(0) var<uniform> u_viewport: Viewport;
+
+struct VertexOut {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+};
+
 @vertex
-fn vs_main(@location(0) a_pos: vec2<f32>) -> @builtin(position) vec4<f32> {
-    return vec4<f32>(a_pos, 0.0, 1.0);
+fn vs_main(@location(0) a_pos: vec2<f32>, @location(1) a_color: vec4<f32>) -> VertexOut {
+    // Píxeles -> NDC (flip Y axis: pixel y down -> NDC up)
+    let ndc = vec2<f32>(a_pos.x / u_viewport.size.x * 2.0 - 1.0, 1.0 - a_pos.y / u_viewport.size.y * 2.0);
+    var out: VertexOut;
+    out.position = vec4<f32>(ndc, 0.0, 1.0);
+    out.color = a_color;
+    return out;
 }
 
 @fragment
-fn fs_main() -> @location(0) vec4<f32> {
-    return u_color;
+fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
+    return in.color;
 }
 "#;
         let device_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("basic-rect-shader"),
+            label: Some("batch-shader"),
             source: wgpu::ShaderSource::Wgsl(shader_src.into()),
         });
 
-        // Bind group layout for color uniform
-        let color_bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: Some("color-bgl"),
+        // Bind group layout for viewport uniform
+        let viewport_bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("viewport-bgl"),
             entries: &[wgpu::BindGroupLayoutEntry {
                 binding: 0,
-                visibility: wgpu::ShaderStages::FRAGMENT,
+                visibility: wgpu::ShaderStages::VERTEX,
                 ty: wgpu::BindingType::Buffer {
                     ty: wgpu::BufferBindingType::Uniform,
                     has_dynamic_offset: false,
@@ -101,27 +168,32 @@ fn fs_main() -> @location(0) vec4<f32> {
                 count: None,
             }],
         });
+        let viewport = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("viewport-ubuf"),
+            size: 16,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let viewport_bg = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("viewport-bg"),
+            layout: &viewport_bgl,
+            entries: &[wgpu::BindGroupEntry { binding: 0, resource: viewport.as_entire_binding() }],
+        });
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("pipeline-layout"),
-            bind_group_layouts: &[&color_bgl],
+            bind_group_layouts: &[&viewport_bgl],
             push_constant_ranges: &[],
         });
         let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("rect-pipeline"),
+            label: Some("batch-pipeline"),
             layout: Some(&pipeline_layout),
             vertex: wgpu::VertexState {
                 module: &device_shader,
                 entry_point: Some("vs_main"),
                 buffers: &[wgpu::VertexBufferLayout {
-                    array_stride: std::mem::size_of::<[f32; 2]>() as u64,
+                    array_stride: std::mem::size_of::<Vertex>() as u64,
                     step_mode: wgpu::VertexStepMode::Vertex,
-                    attributes: &[
-                        wgpu::VertexAttribute {
-                            format: wgpu::VertexFormat::Float32x2,
-                            offset: 0,
-                            shader_location: 0,
-                        },
-                    ],
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x4],
                 }],
                 compilation_options: wgpu::PipelineCompilationOptions::default(),
             },
@@ -142,39 +214,87 @@ fn fs_main() -> @location(0) vec4<f32> {
             cache: None,
         });
 
-        let state = Rc::new(RefCell::new(WgpuState { instance, adapter, device, queue, surface_format, config, canvas, pipeline, color_bgl, cur_frame: None }));
-        Ok(Self { state })
+        let vertex_ring = RingBuffer::new(&device, "batch-vbuf", wgpu::BufferUsages::VERTEX, (INITIAL_VERTICES * std::mem::size_of::<Vertex>()) as u64);
+        let index_ring = RingBuffer::new(&device, "batch-ibuf", wgpu::BufferUsages::INDEX, (INITIAL_INDICES * std::mem::size_of::<u32>()) as u64);
+        let state = WgpuState {
+            device,
+            queue,
+            surface,
+            config,
+            pipeline,
+            viewport,
+            viewport_bg,
+            vertex_ring,
+            index_ring,
+            tessellator: Tessellator::default(),
+            batch: Batch::with_capacity(INITIAL_VERTICES, INITIAL_INDICES),
+            camera: [1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+            frame: None,
+        };
+        state.configure();
+        Ok(Self { state: Rc::new(RefCell::new(state)) })
     }
 }
 
-struct CurrentFrame {
-    frame: wgpu::SurfaceTexture,
-    view: wgpu::TextureView,
-    encoder: wgpu::CommandEncoder,
+impl WebGpuRenderer {
+    /// Añade al lote del frame el relleno (si `fillable`) y el trazo de `path`, en coordenadas
+    /// locales de la entidad, teselados con lyon
+    fn draw_tessellated(&mut self, transform: &Transform, path: &LyonPath, style: &Style, fillable: bool) {
+        let mut s = self.state.borrow_mut();
+        if s.frame.is_none() {
+            return;
+        }
+        let s = &mut *s;
+        let placement = Placement { transform, camera: s.camera };
+        let opacity = style.opacity.clamp(0.0, 1.0);
+        if let Some(shading) = style.fill.as_ref().filter(|_| fillable).and_then(|fill| Shading::of(fill, opacity)) {
+            s.tessellator.fill(path, placement, shading, &mut s.batch);
+        }
+        if let Some(shading) = style.stroke.as_ref().and_then(|stroke| Shading::of(stroke, opacity)) {
+            s.tessellator.stroke(path, style, placement, shading, &mut s.batch);
+        }
+    }
 }
 
 impl RenderPort for WebGpuRenderer {
     fn begin_frame(&mut self, width: u32, height: u32) -> Result<(), RenderError> {
         let mut s = self.state.borrow_mut();
+        let (width, height) = (width.max(1), height.max(1));
         if width != s.config.width || height != s.config.height {
-            s.config.width = width.max(1);
-            s.config.height = height.max(1);
+            s.config.width = width;
+            s.config.height = height;
+            s.configure();
         }
-        let surface = s
-            .instance
-            .create_surface(wgpu::SurfaceTarget::Canvas(s.canvas.clone()))
-            .map_err(|e| RenderError::Other(format!("create_surface (frame) error: {e:?}")))?;
-        s.config.format = s.surface_format;
-        surface.configure(&s.device, &s.config);
-        let frame = surface
-            .get_current_texture()
-            .map_err(|e| RenderError::Other(format!("get_current_texture error: {e:?}")))?;
+        // Se vacía el lote conservando su memoria
+        s.batch.vertices.clear();
+        s.batch.indices.clear();
+        let frame = match s.surface.get_current_texture() {
+            Ok(frame) => frame,
+            // Superficie perdida o desfasada: se reconfigura y se reintenta una vez
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                s.configure();
+                s.surface
+                    .get_current_texture()
+                    .map_err(|e| RenderError::Other(format!("get_current_texture error: {e:?}")))?
+            }
+            Err(e) => return Err(RenderError::Other(format!("get_current_texture error: {e:?}"))),
+        };
+        s.frame = Some(frame);
+        Ok(())
+    }
+
+    fn end_frame(&mut self) -> Result<(), RenderError> {
+        let mut s = self.state.borrow_mut();
+        let Some(frame) = s.frame.take() else {
+            return Ok(());
+        };
+        let s = &mut *s;
         let view = frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
         let mut encoder = s.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("frame-encoder") });
-        // First clear pass
+        // Un único pase: limpia y pinta todo el lote con una sola llamada en orden de pintado
         {
-            let _rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("clear-pass"),
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("frame-pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
                     view: &view,
                     resolve_target: None,
@@ -188,479 +308,77 @@ impl RenderPort for WebGpuRenderer {
                 timestamp_writes: None,
                 occlusion_query_set: None,
             });
+            if !s.batch.indices.is_empty() {
+                let vertices = s.vertex_ring.upload(&s.device, &s.queue, bytemuck::cast_slice(&s.batch.vertices));
+                let indices = s.index_ring.upload(&s.device, &s.queue, bytemuck::cast_slice(&s.batch.indices));
+                rpass.set_pipeline(&s.pipeline);
+                rpass.set_bind_group(0, &s.viewport_bg, &[]);
+                rpass.set_vertex_buffer(0, vertices.slice(..));
+                rpass.set_index_buffer(indices.slice(..), wgpu::IndexFormat::Uint32);
+                rpass.draw_indexed(0..s.batch.indices.len() as u32, 0, 0..1);
+            }
         }
-        s.cur_frame = Some(CurrentFrame { frame, view, encoder });
+        s.queue.submit(std::iter::once(encoder.finish()));
+        frame.present();
         Ok(())
     }
 
-    fn end_frame(&mut self) -> Result<(), RenderError> {
-        let mut s = self.state.borrow_mut();
-        if let Some(mut cur) = s.cur_frame.take() {
-            s.queue.submit(std::iter::once(cur.encoder.finish()));
-            cur.frame.present();
-        }
+    fn set_camera(&mut self, transform_2d: [f32; 6]) -> Result<(), RenderError> {
+        self.state.borrow_mut().camera = transform_2d;
         Ok(())
     }
 
-    fn set_camera(&mut self, _transform_2d: [f32; 6]) -> Result<(), RenderError> { Ok(()) }
-
     fn draw_shape(&mut self, transform: &Transform, shape: &Shape, style: &Style) -> Result<(), RenderError> {
-        // Only Rect supported for now
-        let (w, h) = match shape {
-            Shape::Rect { w, h } => (*w, *h),
-            _ => return Ok(()),
-        };
-        let mut s = self.state.borrow_mut();
-        // Gather immutable state before borrowing cur frame mutably
-        let width = s.config.width.max(1) as f32;
-        let height = s.config.height.max(1) as f32;
-        let device = s.device.clone();
-        let color_bgl = s.color_bgl.clone();
-        let pipeline = s.pipeline.clone();
-        let cur = match s.cur_frame.as_mut() {
-            Some(c) => c,
-            None => return Ok(()),
-        };
-        // top-left origin in pixels
-        let x = transform.x;
-        let y = transform.y;
-        let xl = (x / width) * 2.0 - 1.0;
-        let xr = ((x + w) / width) * 2.0 - 1.0;
-        // flip Y axis: pixel y down -> NDC up
-        let yt = 1.0 - (y / height) * 2.0;
-        let yb = 1.0 - ((y + h) / height) * 2.0;
-        let mut rpass = cur.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("rect-pass"),
-            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &cur.view,
-                resolve_target: None,
-                ops: wgpu::Operations { load: wgpu::LoadOp::Load, store: wgpu::StoreOp::Store },
-                depth_slice: None,
-            })],
-            depth_stencil_attachment: None,
-            timestamp_writes: None,
-            occlusion_query_set: None,
-        });
-        rpass.set_pipeline(&pipeline);
-        // Helper function to draw a quad given NDC bounds and RGBA color
-        fn draw_quad_fn<'a>(
-            rpass: &mut wgpu::RenderPass<'a>,
-            device: &wgpu::Device,
-            color_bgl: &wgpu::BindGroupLayout,
-            xl: f32,
-            yt: f32,
-            xr: f32,
-            yb: f32,
-            color: [f32; 4],
-        ) {
-            let verts: [[f32; 2]; 6] = [
-                [xl, yt], [xl, yb], [xr, yb],
-                [xl, yt], [xr, yb], [xr, yt],
-            ];
-            let vbuf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("rect-vbuf"),
-                contents: bytemuck::cast_slice(&verts),
-                usage: wgpu::BufferUsages::VERTEX,
-            });
-            let ubuf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("color-ubuf"),
-                contents: bytemuck::cast_slice(&color),
-                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            });
-            let bg = device.create_bind_group(&wgpu::BindGroupDescriptor {
-                label: Some("color-bg"),
-                layout: color_bgl,
-                entries: &[wgpu::BindGroupEntry { binding: 0, resource: ubuf.as_entire_binding() }],
-            });
-            rpass.set_bind_group(0, &bg, &[]);
-            rpass.set_vertex_buffer(0, vbuf.slice(..));
-            rpass.draw(0..6, 0..1);
+        // A mano alzada: los mismos trazados que en el resto de renderers y en el SVG
+        if let Some(geometry) = momentum_core::sketch::geometry(shape, style, transform) {
+            return momentum_core::sketch::draw_sketch(self, transform, &geometry, style);
         }
-
-        // Fill pass
-        if let Some(fill) = style.fill {
-            let c = [fill.0, fill.1, fill.2, fill.3 * style.opacity.max(0.0).min(1.0)];
-            draw_quad_fn(&mut rpass, &device, &color_bgl, xl, yt, xr, yb, c);
+        // Los rellenos sombreados se pintan como líneas sobre el contorno exacto
+        if let Some(outline) = momentum_core::paint::hatched_outline(shape, style, transform) {
+            return self.draw_path(transform, &outline, style);
         }
-        // Stroke pass: dashed support for rectangle edges. Caps: Butt / Square. Round treated as Butt for now.
-        if let Some(st) = style.stroke {
-            let a = st.3 * style.opacity.max(0.0).min(1.0);
-            let color = [st.0, st.1, st.2, a];
-            let sw = style.stroke_width.max(0.5);
-            // Helper to draw a horizontal dashed edge at y in px from [x0,x1) with stroke sw
-            fn draw_h_edge_fn(
-                rpass: &mut wgpu::RenderPass<'_>,
-                device: &wgpu::Device,
-                color_bgl: &wgpu::BindGroupLayout,
-                width: f32,
-                height: f32,
-                sw: f32,
-                color: [f32;4],
-                stroke_cap: momentum_core::model::StrokeCap,
-                dash: &[f32],
-                dash_offset: f32,
-                x0_px: f32,
-                x1_px: f32,
-                y_px: f32,
-            ) {
-                let mut start = x0_px;
-                // dash pattern handling
-                if dash.is_empty() {
-                    let xl_ = (start / width) * 2.0 - 1.0;
-                    let xr_ = (x1_px / width) * 2.0 - 1.0;
-                    let yt_ = 1.0 - (y_px / height) * 2.0;
-                    let yb_ = 1.0 - ((y_px + sw) / height) * 2.0;
-                    draw_quad_fn(rpass, device, color_bgl, xl_, yt_, xr_, yb_, color);
-                    return;
-                }
-                // Normalized dash pattern cycle
-                let mut pattern = dash.to_vec();
-                if pattern.len() == 1 { pattern.push(pattern[0]); }
-                let mut idx = 0usize;
-                let mut on = true; // first segment on
-                let mut rem = pattern[0];
-                // apply dash_offset
-                let mut offset = dash_offset.rem_euclid(pattern.iter().sum());
-                while offset > 0.0 {
-                    let step = rem.min(offset);
-                    rem -= step;
-                    offset -= step;
-                    if rem <= 0.0 {
-                        idx = (idx + 1) % pattern.len();
-                        on = !on;
-                        rem = pattern[idx];
-                    }
-                }
-                while start < x1_px {
-                    let seg = (start + rem).min(x1_px);
-                    if on {
-                        // cap adjustment for square caps: extend half sw at both ends
-                        let mut sx = start;
-                        let mut ex = seg;
-                        if matches!(stroke_cap, momentum_core::model::StrokeCap::Square) {
-                            sx -= sw * 0.5;
-                            ex += sw * 0.5;
-                        }
-                        let xl_ = (sx / width) * 2.0 - 1.0;
-                        let xr_ = (ex / width) * 2.0 - 1.0;
-                        let yt_ = 1.0 - (y_px / height) * 2.0;
-                        let yb_ = 1.0 - ((y_px + sw) / height) * 2.0;
-                        draw_quad_fn(rpass, device, color_bgl, xl_, yt_, xr_, yb_, color);
-                    }
-                    start = seg;
-                    rem -= seg - start; // zero
-                    if rem <= 0.0 {
-                        idx = (idx + 1) % pattern.len();
-                        on = !on;
-                        rem = pattern[idx];
-                    }
-                }
-            }
-            // Helper to draw a vertical dashed edge at x in px from [y0,y1) with stroke sw
-            fn draw_v_edge_fn(
-                rpass: &mut wgpu::RenderPass<'_>,
-                device: &wgpu::Device,
-                color_bgl: &wgpu::BindGroupLayout,
-                width: f32,
-                height: f32,
-                sw: f32,
-                color: [f32;4],
-                stroke_cap: momentum_core::model::StrokeCap,
-                dash: &[f32],
-                dash_offset: f32,
-                x_px: f32,
-                y0_px: f32,
-                y1_px: f32,
-            ) {
-                let mut start = y0_px;
-                if dash.is_empty() {
-                    let xl_ = (x_px / width) * 2.0 - 1.0;
-                    let xr_ = ((x_px + sw) / width) * 2.0 - 1.0;
-                    let yt_ = 1.0 - (start / height) * 2.0;
-                    let yb_ = 1.0 - (y1_px / height) * 2.0;
-                    draw_quad_fn(rpass, device, color_bgl, xl_, yt_, xr_, yb_, color);
-                    return;
-                }
-                let mut pattern = dash.to_vec();
-                if pattern.len() == 1 { pattern.push(pattern[0]); }
-                let mut idx = 0usize;
-                let mut on = true;
-                let mut rem = pattern[0];
-                let mut offset = dash_offset.rem_euclid(pattern.iter().sum());
-                while offset > 0.0 {
-                    let step = rem.min(offset);
-                    rem -= step;
-                    offset -= step;
-                    if rem <= 0.0 {
-                        idx = (idx + 1) % pattern.len();
-                        on = !on;
-                        rem = pattern[idx];
-                    }
-                }
-                while start < y1_px {
-                    let seg = (start + rem).min(y1_px);
-                    if on {
-                        let mut sy = start;
-                        let mut ey = seg;
-                        if matches!(stroke_cap, momentum_core::model::StrokeCap::Square) {
-                            sy -= sw * 0.5;
-                            ey += sw * 0.5;
-                        }
-                        let xl_ = (x_px / width) * 2.0 - 1.0;
-                        let xr_ = ((x_px + sw) / width) * 2.0 - 1.0;
-                        let yt_ = 1.0 - (sy / height) * 2.0;
-                        let yb_ = 1.0 - (ey / height) * 2.0;
-                        draw_quad_fn(rpass, device, color_bgl, xl_, yt_, xr_, yb_, color);
-                    }
-                    start = seg;
-                    rem -= seg - start;
-                    if rem <= 0.0 {
-                        idx = (idx + 1) % pattern.len();
-                        on = !on;
-                        rem = pattern[idx];
-                    }
-                }
+        // Las esquinas redondeadas, como trazado
+        if let Some(outline) = momentum_core::paint::rounded_outline(shape, transform) {
+            return self.draw_path(transform, &outline, style);
+        }
+        let path = match shape {
+            Shape::Path { path } => return self.draw_path(transform, path, style),
+            Shape::Freehand { stroke } => {
+                return self.draw_path(transform, &stroke.outline(PATH_FLATTEN_TOLERANCE), &FreehandStroke::ink_style(style))
             }
-
-            // Edges in pixel space
-            // top: from (x,y) to (x+w,y)
-            draw_h_edge_fn(&mut rpass, &device, &color_bgl, width, height, sw, color, style.stroke_cap, &style.dash, style.dash_offset, x, x + w, y);
-            // bottom: at y+h-sw to y+h (align like previous implementation which used [yb+swy, yb])
-            draw_h_edge_fn(&mut rpass, &device, &color_bgl, width, height, sw, color, style.stroke_cap, &style.dash, style.dash_offset, x, x + w, y + h - sw);
-            // left: from (x,y) to (x,y+h)
-            draw_v_edge_fn(&mut rpass, &device, &color_bgl, width, height, sw, color, style.stroke_cap, &style.dash, style.dash_offset, x, y, y + h);
-            // right: at x+w-sw to x+w
-            draw_v_edge_fn(&mut rpass, &device, &color_bgl, width, height, sw, color, style.stroke_cap, &style.dash, style.dash_offset, x + w - sw, y, y + h);
+            Shape::Text { text } => return momentum_core::text::draw_text_box(self, &momentum_core::text::FontBook::default(), transform, text),
+            Shape::Arrow { arrow } => return momentum_core::arrow::draw_arrow(self, transform, arrow, style),
+            // Sin texturas todavía: el ECS pinta el marco de sustitución
+            Shape::Image { .. } => return Ok(()),
+            Shape::Rect { .. } | Shape::Ellipse { .. } | Shape::Line { .. } | Shape::Polygon { .. } => tessellation::shape_path(shape),
+        };
+        if let Some(path) = path {
+            // Las líneas, como en Canvas2D, solo llevan trazo
+            self.draw_tessellated(transform, &path, style, !matches!(shape, Shape::Line { .. }));
         }
         Ok(())
     }
 
     fn draw_path(&mut self, transform: &Transform, path: &Path, style: &Style) -> Result<(), RenderError> {
-        let mut s = self.state.borrow_mut();
-        if style.stroke.is_none() { return Ok(()); }
-        let stroke = style.stroke.unwrap();
-        let alpha = stroke.3 * style.opacity.max(0.0).min(1.0);
-        let color = [stroke.0, stroke.1, stroke.2, alpha];
-        let sw = style.stroke_width.max(0.5);
-        // Gather immutable state before borrowing current frame mutably
-        let width = s.config.width.max(1) as f32;
-        let height = s.config.height.max(1) as f32;
-        let device = s.device.clone();
-        let color_bgl = s.color_bgl.clone();
-        let pipeline = s.pipeline.clone();
-        let cur = match s.cur_frame.as_mut() { Some(c) => c, None => return Ok(()) };
-
-        // Render pass begun once for all segments
-        let mut rpass = cur.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("path-stroke-pass"),
-            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &cur.view,
-                resolve_target: None,
-                ops: wgpu::Operations { load: wgpu::LoadOp::Load, store: wgpu::StoreOp::Store },
-                depth_slice: None,
-            })],
-            depth_stencil_attachment: None,
-            timestamp_writes: None,
-            occlusion_query_set: None,
-        });
-        rpass.set_pipeline(&pipeline);
-
-        fn draw_tris_fn<'a>(
-            rpass: &mut wgpu::RenderPass<'a>,
-            device: &wgpu::Device,
-            color_bgl: &wgpu::BindGroupLayout,
-            color: [f32; 4],
-            verts_ndc: &[[f32; 2]],
-        ) {
-            if verts_ndc.is_empty() { return; }
-            let vbuf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("tris-vbuf"),
-                contents: bytemuck::cast_slice(verts_ndc),
-                usage: wgpu::BufferUsages::VERTEX,
-            });
-            let ubuf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("color-ubuf"),
-                contents: bytemuck::cast_slice(&color),
-                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            });
-            let bg = device.create_bind_group(&wgpu::BindGroupDescriptor {
-                label: Some("color-bg"),
-                layout: color_bgl,
-                entries: &[wgpu::BindGroupEntry { binding: 0, resource: ubuf.as_entire_binding() }],
-            });
-            rpass.set_bind_group(0, &bg, &[]);
-            rpass.set_vertex_buffer(0, vbuf.slice(..));
-            rpass.draw(0..(verts_ndc.len() as u32), 0..1);
+        if path.commands.is_empty() {
+            return Ok(());
         }
-
-        let to_ndc = |px: f32, py: f32| -> [f32;2] {
-            let x = (px / width) * 2.0 - 1.0;
-            let y = 1.0 - (py / height) * 2.0;
-            [x, y]
-        };
-
-        // Build polylines from path commands. Only MoveTo, LineTo, Close supported for stroke.
-        let mut subpath: Vec<(f32,f32)> = Vec::new();
-        let mut start_pt: Option<(f32,f32)> = None;
-        let tx = transform.x; let ty = transform.y;
-        let mut flush_subpath = |poly: &mut Vec<(f32,f32)>, _start: Option<(f32,f32)>, closed: bool| {
-            if poly.len() < 2 { poly.clear(); return; }
-            // Continuous dash state across the subpath
-            let mut pattern = style.dash.clone();
-            if pattern.len() == 1 { pattern.push(pattern[0]); }
-            let use_dash = !pattern.is_empty();
-            let mut pat_idx = 0usize;
-            let mut on = true;
-            let mut rem = if use_dash { pattern[0] } else { f32::INFINITY };
-            // offset
-            if use_dash {
-                let mut offset = style.dash_offset.rem_euclid(pattern.iter().sum());
-                while offset > 0.0 {
-                    let step = rem.min(offset);
-                    rem -= step;
-                    offset -= step;
-                    if rem <= 0.0 { pat_idx = (pat_idx + 1) % pattern.len(); on = !on; rem = pattern[pat_idx]; }
-                }
-            }
-
-            let hw = sw * 0.5;
-            let miter_limit = 4.0; // fallback to bevel if exceeded
-
-            let pts = poly.clone();
-            let seg_count = if closed { pts.len() } else { pts.len() - 1 };
-            let get_pt = |i: usize| -> (f32,f32) { if i < pts.len() { pts[i] } else { pts[0] } };
-
-            // draw each segment with dash splitting
-            let mut prev_dir: Option<(f32,f32)> = None;
-            let mut prev_norm: Option<(f32,f32)> = None;
-            for si in 0..seg_count {
-                let (mut x0, mut y0) = get_pt(si);
-                let (mut x1, mut y1) = get_pt((si+1)%pts.len());
-                // dash splitting along this segment, keeping continuous state
-                let mut seg_len = ((x1-x0).hypot(y1-y0)).max(1e-6);
-                let dir = ((x1-x0)/seg_len, (y1-y0)/seg_len);
-                // square cap: extend ends for first/last visible piece
-                let mut t0 = 0.0f32;
-                while t0 < seg_len {
-                    let take = rem.min(seg_len - t0);
-                    if on {
-                        let t1 = t0 + take;
-                        // compute endpoints in px
-                        let sx = x0 + dir.0 * t0;
-                        let sy = y0 + dir.1 * t0;
-                        let ex = x0 + dir.0 * t1;
-                        let ey = y0 + dir.1 * t1;
-                        // normal
-                        let n = (-dir.1, dir.0);
-                        // cap extend
-                        let (mut csx, mut csy, mut cex, mut cey) = (sx, sy, ex, ey);
-                        if matches!(style.stroke_cap, momentum_core::model::StrokeCap::Square) {
-                            csx -= dir.0 * hw; csy -= dir.1 * hw;
-                            cex += dir.0 * hw; cey += dir.1 * hw;
-                        }
-                        // segment quad
-                        let v0 = to_ndc(csx + n.0*hw, csy + n.1*hw);
-                        let v1 = to_ndc(csx - n.0*hw, csy - n.1*hw);
-                        let v2 = to_ndc(cex - n.0*hw, cey - n.1*hw);
-                        let v3 = to_ndc(cex + n.0*hw, cey + n.1*hw);
-                        let tris = [v0, v1, v2, v0, v2, v3];
-                        draw_tris_fn(&mut rpass, &device, &color_bgl, color, &tris);
-
-                        // join with previous visible segment (bevel)
-                        if t0 == 0.0 && si > 0 || (closed && si == 0 && poly.len() > 2) {
-                            if let (Some(pd), Some(pn)) = (prev_dir, prev_norm) {
-                                // Determine outer side
-                                let cross = pd.0*dir.1 - pd.1*dir.0;
-                                let outer_prev = if cross > 0.0 { (pn.0*hw, pn.1*hw) } else { (-pn.0*hw, -pn.1*hw) };
-                                let outer_next = if cross > 0.0 { (n.0*hw, n.1*hw) } else { (-n.0*hw, -n.1*hw) };
-                                let px = x0; let py = y0;
-                                // miter candidate
-                                if matches!(style.stroke_join, momentum_core::model::StrokeJoin::Miter) {
-                                    // Compute intersection of offset lines
-                                    let a1 = (px + outer_prev.0, py + outer_prev.1);
-                                    let d1 = (pd.0, pd.1);
-                                    let a2 = (px + outer_next.0, py + outer_next.1);
-                                    let d2 = (dir.0, dir.1);
-                                    let denom = d1.0*d2.1 - d1.1*d2.0;
-                                    if denom.abs() > 1e-5 {
-                                        let t = ((a2.0 - a1.0)*d2.1 - (a2.1 - a1.1)*d2.0) / denom;
-                                        let mx = a1.0 + d1.0 * t;
-                                        let my = a1.1 + d1.1 * t;
-                                        let m_len = ((mx - px).hypot(my - py)) / hw;
-                                        if m_len <= miter_limit {
-                                            let p_outer_prev = to_ndc(px + outer_prev.0, py + outer_prev.1);
-                                            let p_outer_next = to_ndc(px + outer_next.0, py + outer_next.1);
-                                            let p_miter = to_ndc(mx, my);
-                                            let tris2 = [p_outer_prev, p_miter, p_outer_next];
-                                            draw_tris_fn(&mut rpass, &device, &color_bgl, color, &tris2);
-                                        } else {
-                                            // bevel fallback
-                                            let p_outer_prev = to_ndc(px + outer_prev.0, py + outer_prev.1);
-                                            let p_outer_next = to_ndc(px + outer_next.0, py + outer_next.1);
-                                            let p_center = to_ndc(px, py);
-                                            let tris2 = [p_outer_prev, p_center, p_outer_next];
-                                            draw_tris_fn(&mut rpass, &device, &color_bgl, color, &tris2);
-                                        }
-                                    }
-                                } else {
-                                    // bevel or round (treated as bevel for now)
-                                    let p_outer_prev = to_ndc(px + outer_prev.0, py + outer_prev.1);
-                                    let p_outer_next = to_ndc(px + outer_next.0, py + outer_next.1);
-                                    let p_center = to_ndc(px, py);
-                                    let tris2 = [p_outer_prev, p_center, p_outer_next];
-                                    draw_tris_fn(&mut rpass, &device, &color_bgl, color, &tris2);
-                                }
-                            }
-                        }
-                    }
-                    t0 += take;
-                    if use_dash {
-                        rem -= take;
-                        if rem <= 0.0 {
-                            pat_idx = (pat_idx + 1) % pattern.len();
-                            on = !on; rem = pattern[pat_idx];
-                        }
-                    }
-                }
-                prev_dir = Some(dir);
-                prev_norm = Some((-dir.1, dir.0));
-            }
-            poly.clear();
-        };
-
-        for cmd in &path.commands {
-            match *cmd {
-                momentum_core::model::PathCommand::MoveTo(px, py) => {
-                    // flush previous open subpath
-                    flush_subpath(&mut subpath, start_pt, false);
-                    subpath.clear();
-                    subpath.push((tx + px, ty + py));
-                    start_pt = Some((tx + px, ty + py));
-                }
-                momentum_core::model::PathCommand::LineTo(px, py) => {
-                    subpath.push((tx + px, ty + py));
-                }
-                momentum_core::model::PathCommand::Close => {
-                    // close current polyline
-                    flush_subpath(&mut subpath, start_pt, true);
-                    subpath.clear();
-                    start_pt = None;
-                }
-                _ => { /* TODO: curve flattening not implemented yet */ }
-            }
+        if let Some(result) = momentum_core::paint::draw_hatched(self, transform, path, style) {
+            return result;
         }
-        // Flush trailing open subpath
-        flush_subpath(&mut subpath, start_pt, false);
+        self.draw_tessellated(transform, &tessellation::lyon_path(path), style, true);
         Ok(())
     }
 
     fn draw_text(&mut self, _transform: &Transform, _span: &TextSpan) -> Result<(), RenderError> { Ok(()) }
 
-    fn measure_text(&mut self, _span: &TextSpan) -> Result<TextMetrics, RenderError> { Ok(TextMetrics::default()) }
+    // Sin rasterizado de glifos todavía: medidas estimadas para que las cajas de texto sigan siendo usables
+    fn measure_text(&mut self, span: &TextSpan) -> Result<TextMetrics, RenderError> { Ok(momentum_core::text::approximate_metrics(span)) }
 
-    fn upload_image(&mut self, _id: ImageId, _data: &[u8]) -> Result<(), RenderError> { Ok(()) }
+    // Sin texturas todavía: las imágenes se pintan con el marco de sustitución del ECS
+    fn upload_image(&mut self, _id: ImageId, _data: &[u8]) -> Result<(), RenderError> { Err(RenderError::Unsupported) }
 
-    fn draw_image(&mut self, _id: ImageId, _dest: Rect, _transform: &Transform, _tint: Option<momentum_core::model::Color>) -> Result<(), RenderError> { Ok(()) }
+    fn draw_image(&mut self, _id: ImageId, _dest: Rect, _transform: &Transform, _tint: Option<momentum_core::model::Color>) -> Result<(), RenderError> { Err(RenderError::Unsupported) }
 
     fn draw_scale_handle(&mut self, handle: &momentum_core::model::ScaleHandle) -> Result<(), RenderError> {
         // Por ahora, implementación básica que delega a Canvas2D para los handles
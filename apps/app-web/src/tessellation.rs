@@ -0,0 +1,382 @@
+#![cfg(all(target_arch = "wasm32", feature = "webgpu"))]
+
+//! Teselación con lyon para el renderer WebGPU.
+//!
+//! Cada forma se convierte en un trazado de lyon en sus coordenadas locales y se tesela ahí: así el
+//! grosor del trazo y la discontinuidad escalan con la entidad igual que en Canvas2D (que aplica la
+//! transform al contexto). Los vértices salen ya en píxeles físicos, tras la transform de la entidad
+//! y la cámara, con el color de la forma, y se añaden al lote del frame. Los degradados se pintan
+//! color a color en los vértices, subdividiendo los triángulos hasta que la interpolación lineal
+//! entre vértices no se nota.
+
+use lyon::math::{point, vector, Angle, Box2D, Point};
+use lyon::path::iterator::PathIterator;
+use lyon::path::path::Builder;
+use lyon::path::{Path as LyonPath, PathEvent, Polygon, Winding};
+use lyon::tessellation::{
+    BuffersBuilder, FillOptions, FillRule, FillTessellator, FillVertex, LineCap, LineJoin, StrokeOptions, StrokeTessellator,
+    StrokeVertex, VertexBuffers,
+};
+
+use momentum_core::model::{Color, Paint, Path, PathCommand, Shape, StrokeCap, StrokeJoin, Style, Transform};
+use momentum_core::paint;
+
+/// Error máximo al aproximar curvas, en píxeles físicos
+const TOLERANCE_PX: f32 = 0.25;
+/// Grosor mínimo de un trazo en pantalla, en píxeles físicos (los más finos desaparecerían)
+const MIN_STROKE_PX: f32 = 0.5;
+/// Límite de inglete por defecto de Canvas2D
+const MITER_LIMIT: f32 = 10.0;
+/// Lado máximo de los triángulos de un degradado, en píxeles físicos
+const GRADIENT_STEP_PX: f32 = 8.0;
+/// Veces como mucho que se parte cada triángulo de un degradado (hasta 2^12 trozos)
+const MAX_GRADIENT_DEPTH: u32 = 12;
+
+/// Vértice del lote: posición en píxeles físicos y color RGBA `[x, y, r, g, b, a]`
+pub type Vertex = [f32; 6];
+
+/// Triángulos de todo un frame. Se vacía en cada frame sin liberar la memoria.
+pub type Batch = VertexBuffers<Vertex, u32>;
+
+/// Transform de la entidad seguida de la cámara `[a, b, c, d, e, f]` (mundo -> píxeles físicos)
+#[derive(Clone, Copy)]
+pub struct Placement<'a> {
+    pub transform: &'a Transform,
+    pub camera: [f32; 6],
+}
+
+impl Placement<'_> {
+    fn to_pixels(self, p: Point) -> [f32; 2] {
+        let (x, y) = self.transform.apply(p.x, p.y);
+        let m = &self.camera;
+        [m[0] * x + m[2] * y + m[4], m[1] * x + m[3] * y + m[5]]
+    }
+
+    /// Píxeles físicos por unidad local (la mayor de las dos escalas)
+    fn scale(&self) -> f32 {
+        let camera = self.camera[0].hypot(self.camera[1]);
+        (camera * self.transform.scale_x.abs().max(self.transform.scale_y.abs())).max(1e-6)
+    }
+
+    /// Tolerancia de aproximación en unidades locales
+    fn tolerance(&self) -> f32 {
+        TOLERANCE_PX / self.scale()
+    }
+}
+
+/// Color de los vértices: uno fijo, o el de `paint::color_at` en cada punto (degradados)
+#[derive(Clone, Copy)]
+pub enum Shading<'a> {
+    Flat([f32; 4]),
+    Gradient { paint: &'a Paint, opacity: f32 },
+}
+
+impl<'a> Shading<'a> {
+    /// Cómo se pinta `paint` con la opacidad del estilo; None si no se vería. Los sombreados llegan
+    /// ya como líneas y aquí son su color; sin texturas todavía, un mosaico de imagen se pinta con
+    /// el color representativo de `Paint::color`.
+    pub fn of(paint: &'a Paint, opacity: f32) -> Option<Self> {
+        match paint {
+            Paint::LinearGradient { .. } | Paint::RadialGradient { .. } => Some(Shading::Gradient { paint, opacity }),
+            Paint::Solid(_) | Paint::Hatch { .. } | Paint::Image { .. } => {
+                let Color(r, g, b, a) = paint.color();
+                (a * opacity > 0.0).then_some(Shading::Flat([r, g, b, a * opacity]))
+            }
+        }
+    }
+}
+
+/// Trazado local de las formas geométricas; None en las que se pintan de otra forma
+/// (trazados, lápiz, texto, imágenes y flechas)
+pub fn shape_path(shape: &Shape) -> Option<LyonPath> {
+    let mut builder = LyonPath::builder();
+    match shape {
+        Shape::Rect { w, h, .. } => {
+            // Normalizada: el trazo de un rect con tamaño negativo es el mismo
+            let b = Box2D::from_points([point(0.0, 0.0), point(*w, *h)]);
+            builder.add_rectangle(&b, Winding::Positive);
+        }
+        // Centrada en el origen de la entidad
+        Shape::Ellipse { rx, ry } => builder.add_ellipse(point(0.0, 0.0), vector(rx.abs(), ry.abs()), Angle::radians(0.0), Winding::Positive),
+        Shape::Line { x2, y2 } => {
+            builder.begin(point(0.0, 0.0));
+            builder.line_to(point(*x2, *y2));
+            builder.end(false);
+        }
+        Shape::Polygon { points } => {
+            let points: Vec<Point> = points.iter().map(|(x, y)| point(*x, *y)).collect();
+            if points.is_empty() {
+                return None;
+            }
+            builder.add_polygon(Polygon { points: &points, closed: true });
+        }
+        Shape::Path { .. } | Shape::Freehand { .. } | Shape::Text { .. } | Shape::Image { .. } | Shape::Arrow { .. } => return None,
+    }
+    Some(builder.build())
+}
+
+/// Trazado de lyon equivalente a un `Path` del núcleo. Como en Canvas2D, un dibujo sin `MoveTo`
+/// previo empieza en su primer punto y tras `Close` se sigue desde el inicio del subtrazado.
+pub fn lyon_path(path: &Path) -> LyonPath {
+    let mut builder = LyonPath::builder();
+    // Subtrazado empezado y punto actual (None antes del primer punto)
+    let mut open = false;
+    let mut start = point(0.0, 0.0);
+    let mut current: Option<Point> = None;
+    let ensure_open = |builder: &mut Builder, open: &mut bool, current: Option<Point>, first: Point| {
+        if !*open {
+            builder.begin(current.unwrap_or(first));
+            *open = true;
+        }
+    };
+    for command in &path.commands {
+        match *command {
+            PathCommand::MoveTo(x, y) => {
+                if open {
+                    builder.end(false);
+                    open = false;
+                }
+                start = point(x, y);
+                current = Some(start);
+            }
+            PathCommand::LineTo(x, y) => {
+                ensure_open(&mut builder, &mut open, current, point(x, y));
+                builder.line_to(point(x, y));
+                current = Some(point(x, y));
+            }
+            PathCommand::QuadTo { cx, cy, x, y } => {
+                ensure_open(&mut builder, &mut open, current, point(cx, cy));
+                builder.quadratic_bezier_to(point(cx, cy), point(x, y));
+                current = Some(point(x, y));
+            }
+            PathCommand::CubicTo { c1x, c1y, c2x, c2y, x, y } => {
+                ensure_open(&mut builder, &mut open, current, point(c1x, c1y));
+                builder.cubic_bezier_to(point(c1x, c1y), point(c2x, c2y), point(x, y));
+                current = Some(point(x, y));
+            }
+            PathCommand::Close => {
+                if open {
+                    builder.end(true);
+                    open = false;
+                }
+                current = current.map(|_| start);
+            }
+        }
+    }
+    if open {
+        builder.end(false);
+    }
+    builder.build()
+}
+
+/// Teseladores reutilizados entre frames (guardan sus buffers internos)
+#[derive(Default)]
+pub struct Tessellator {
+    fill: FillTessellator,
+    stroke: StrokeTessellator,
+    /// Triángulos en coordenadas locales de la forma en curso, para colorearlos después
+    local: VertexBuffers<Point, u32>,
+}
+
+impl Tessellator {
+    /// Añade al lote el relleno (regla nonzero, como Canvas2D) de `path`; los subtrazados
+    /// abiertos se cierran. Una geometría degenerada no deja triángulos a medias.
+    pub fn fill(&mut self, path: &LyonPath, placement: Placement, shading: Shading, batch: &mut Batch) {
+        let options = FillOptions::tolerance(placement.tolerance()).with_fill_rule(FillRule::NonZero);
+        match shading {
+            Shading::Flat(color) => {
+                let _ = self.fill.tessellate_path(
+                    path,
+                    &options,
+                    &mut BuffersBuilder::new(batch, |v: FillVertex| vertex(placement.to_pixels(v.position()), color)),
+                );
+            }
+            Shading::Gradient { paint, opacity } => {
+                self.local.vertices.clear();
+                self.local.indices.clear();
+                let _ = self.fill.tessellate_path(path, &options, &mut BuffersBuilder::new(&mut self.local, |v: FillVertex| v.position()));
+                GradientMesh { placement, paint, opacity }.append(&self.local, batch);
+            }
+        }
+    }
+
+    /// Añade al lote el trazo de `path` con el grosor, extremos, uniones y discontinuidad del estilo
+    pub fn stroke(&mut self, path: &LyonPath, style: &Style, placement: Placement, shading: Shading, batch: &mut Batch) {
+        let tolerance = placement.tolerance();
+        let width = style.stroke_width.max(MIN_STROKE_PX / placement.scale());
+        let cap = match style.stroke_cap {
+            StrokeCap::Butt => LineCap::Butt,
+            StrokeCap::Square => LineCap::Square,
+            StrokeCap::Round => LineCap::Round,
+        };
+        let join = match style.stroke_join {
+            StrokeJoin::Miter => LineJoin::Miter,
+            StrokeJoin::Bevel => LineJoin::Bevel,
+            StrokeJoin::Round => LineJoin::Round,
+        };
+        let options = StrokeOptions::tolerance(tolerance)
+            .with_line_width(width)
+            .with_line_cap(cap)
+            .with_line_join(join)
+            .with_miter_limit(MITER_LIMIT);
+        let dashed = dash_pattern(&style.dash).map(|pattern| dashed(path, &pattern, style.dash_offset, tolerance));
+
+        let path = dashed.as_ref().unwrap_or(path);
+        match shading {
+            Shading::Flat(color) => {
+                let _ = self.stroke.tessellate_path(
+                    path,
+                    &options,
+                    &mut BuffersBuilder::new(batch, |v: StrokeVertex| vertex(placement.to_pixels(v.position()), color)),
+                );
+            }
+            Shading::Gradient { paint, opacity } => {
+                self.local.vertices.clear();
+                self.local.indices.clear();
+                let _ = self.stroke.tessellate_path(path, &options, &mut BuffersBuilder::new(&mut self.local, |v: StrokeVertex| v.position()));
+                GradientMesh { placement, paint, opacity }.append(&self.local, batch);
+            }
+        }
+    }
+}
+
+/// Colorea triángulos locales con un degradado
+struct GradientMesh<'a> {
+    placement: Placement<'a>,
+    paint: &'a Paint,
+    opacity: f32,
+}
+
+impl GradientMesh<'_> {
+    fn append(&self, mesh: &VertexBuffers<Point, u32>, batch: &mut Batch) {
+        let max_edge = GRADIENT_STEP_PX / self.placement.scale();
+        for triangle in mesh.indices.chunks_exact(3) {
+            let corners = [0, 1, 2].map(|i| mesh.vertices[triangle[i] as usize]);
+            self.triangle(corners, max_edge, MAX_GRADIENT_DEPTH, batch);
+        }
+    }
+
+    /// Parte el triángulo por la mitad de su lado más largo hasta que todos midan `max_edge`
+    fn triangle(&self, corners: [Point; 3], max_edge: f32, depth: u32, batch: &mut Batch) {
+        let (i, j) = [(0, 1), (1, 2), (2, 0)]
+            .into_iter()
+            .max_by(|a, b| (corners[a.0] - corners[a.1]).square_length().total_cmp(&(corners[b.0] - corners[b.1]).square_length()))
+            .unwrap_or((0, 1));
+        if depth == 0 || (corners[i] - corners[j]).length() <= max_edge {
+            let base = batch.vertices.len() as u32;
+            for p in corners {
+                let Color(r, g, b, a) = paint::color_at(self.paint, (p.x, p.y));
+                batch.vertices.push(vertex(self.placement.to_pixels(p), [r, g, b, a * self.opacity]));
+            }
+            batch.indices.extend_from_slice(&[base, base + 1, base + 2]);
+            return;
+        }
+        let k = 3 - i - j;
+        let middle = corners[i].lerp(corners[j], 0.5);
+        self.triangle([corners[i], middle, corners[k]], max_edge, depth - 1, batch);
+        self.triangle([middle, corners[j], corners[k]], max_edge, depth - 1, batch);
+    }
+}
+
+fn vertex([x, y]: [f32; 2], [r, g, b, a]: [f32; 4]) -> Vertex {
+    [x, y, r, g, b, a]
+}
+
+/// Patrón de discontinuidad como lo interpreta Canvas2D: una lista impar se repite dos veces, y
+/// una con valores negativos o no finitos, o que suma cero, equivale a trazo continuo
+fn dash_pattern(dash: &[f32]) -> Option<Vec<f32>> {
+    if dash.is_empty() || dash.iter().any(|d| !d.is_finite() || *d < 0.0) || dash.iter().sum::<f32>() <= 0.0 {
+        return None;
+    }
+    let mut pattern = dash.to_vec();
+    if pattern.len() % 2 == 1 {
+        pattern.extend_from_slice(dash);
+    }
+    Some(pattern)
+}
+
+/// Trozos visibles de `path` según `pattern` (longitudes alternas de trazo y hueco). El patrón
+/// empieza de nuevo, desplazado `offset`, en cada subtrazado; los trozos quedan como subtrazados
+/// abiertos para que lleven los extremos del estilo.
+fn dashed(path: &LyonPath, pattern: &[f32], offset: f32, tolerance: f32) -> LyonPath {
+    let cycle: f32 = pattern.iter().sum();
+    let mut builder = LyonPath::builder();
+    let mut state = DashState::new(pattern, offset.rem_euclid(cycle));
+    for event in path.iter().flattened(tolerance) {
+        match event {
+            PathEvent::Begin { .. } => {
+                state.finish(&mut builder);
+                state = DashState::new(pattern, offset.rem_euclid(cycle));
+            }
+            PathEvent::Line { from, to } => state.segment(&mut builder, from, to),
+            PathEvent::End { last, first, close } => {
+                if close {
+                    state.segment(&mut builder, last, first);
+                }
+                state.finish(&mut builder);
+            }
+            // `flattened` solo produce rectas
+            PathEvent::Quadratic { .. } | PathEvent::Cubic { .. } => {}
+        }
+    }
+    state.finish(&mut builder);
+    builder.build()
+}
+
+/// Recorrido del patrón de discontinuidad a lo largo de un subtrazado
+struct DashState<'a> {
+    pattern: &'a [f32],
+    index: usize,
+    /// Lo que queda del tramo actual del patrón
+    remaining: f32,
+    /// ¿Hay un trozo visible empezado?
+    drawing: bool,
+}
+
+impl<'a> DashState<'a> {
+    fn new(pattern: &'a [f32], mut offset: f32) -> Self {
+        let mut index = 0;
+        while offset >= pattern[index] {
+            offset -= pattern[index];
+            index = (index + 1) % pattern.len();
+        }
+        Self { pattern, index, remaining: pattern[index] - offset, drawing: false }
+    }
+
+    /// Los tramos pares del patrón son visibles
+    fn on(&self) -> bool {
+        self.index.is_multiple_of(2)
+    }
+
+    fn segment(&mut self, builder: &mut Builder, from: Point, to: Point) {
+        let length = (to - from).length();
+        let mut t = 0.0;
+        while t < length {
+            let step = self.remaining.min(length - t);
+            if self.on() {
+                if !self.drawing {
+                    builder.begin(from.lerp(to, t / length));
+                    self.drawing = true;
+                }
+                builder.line_to(from.lerp(to, (t + step) / length));
+            }
+            t += step;
+            self.remaining -= step;
+            if self.remaining <= 1e-6 {
+                if self.drawing {
+                    builder.end(false);
+                    self.drawing = false;
+                }
+                self.index = (self.index + 1) % self.pattern.len();
+                self.remaining = self.pattern[self.index];
+            }
+        }
+    }
+
+    fn finish(&mut self, builder: &mut Builder) {
+        if self.drawing {
+            builder.end(false);
+            self.drawing = false;
+        }
+    }
+}
@@ -0,0 +1,336 @@
+//! Flechas: geometría de las puntas y enganche de los extremos a otras entidades.
+//!
+//! La flecha es una polilínea en coordenadas locales. Sus puntas se construyen aquí como trazados,
+//! así todos los renderers y el exportador SVG pintan exactamente lo mismo. Un extremo enganchado
+//! apunta a un punto (normalizado) de la caja de su destino y se corta en el contorno del destino
+//! con un pequeño hueco; `update_bindings` lo recoloca cuando el destino se mueve o se escala.
+//! Los conectores en codo (`ArrowRouting::Elbow`) recalculan además todos sus tramos con `routing`.
+
+use crate::model::{
+    rotate_around, Arrow, ArrowBinding, ArrowHead, ArrowRouting, BoundingBox, EntityId, FreehandStroke, OrientedBoundingBox, Path,
+    PathCommand, Shape, Style, Transform,
+};
+use crate::ports::{RenderError, RenderPort};
+use crate::routing::{self, Endpoint, Side};
+use crate::sketch;
+use crate::usecases::{Document, Entity};
+use std::collections::HashSet;
+
+/// Hueco entre la punta de un extremo enganchado y el contorno de su destino, en unidades de mundo
+pub const BINDING_GAP: f32 = 4.0;
+
+/// Trazados de una flecha, en sus coordenadas locales
+#[derive(Clone, Debug, Default)]
+pub struct ArrowGeometry {
+    /// Cuerpo, recortado bajo las puntas rellenas; se pinta con el estilo de la flecha
+    pub shaft: Path,
+    /// Puntas rellenas (triángulo, punto) con el color de trazo
+    pub filled: Path,
+    /// Puntas de trazo (abierta, barra), sin discontinuidad
+    pub stroked: Path,
+}
+
+/// Largo de las puntas: crece con el grosor para que sigan viéndose con trazos gruesos
+pub fn head_size(style: &Style) -> f32 {
+    6.0 + 3.0 * style.stroke_width.max(0.0)
+}
+
+/// Cuerpo y puntas de la flecha con el tamaño que le da `style`
+pub fn geometry(arrow: &Arrow, style: &Style) -> ArrowGeometry {
+    let mut out = ArrowGeometry::default();
+    let mut points = arrow.points.clone();
+    if points.len() < 2 {
+        return out;
+    }
+    let size = head_size(style);
+    let n = points.len();
+    let start = end_direction(points.iter().copied());
+    let end = end_direction(points.iter().rev().copied());
+    if let Some(direction) = start {
+        let trim = add_head(&mut out, points[0], direction, arrow.start_head, size);
+        points[0] = trimmed(points[0], points[1], direction, trim);
+    }
+    if let Some(direction) = end {
+        let trim = add_head(&mut out, points[n - 1], direction, arrow.end_head, size);
+        points[n - 1] = trimmed(points[n - 1], points[n - 2], direction, trim);
+    }
+    match arrow.routing {
+        ArrowRouting::Straight => {
+            out.shaft.commands.push(PathCommand::MoveTo(points[0].0, points[0].1));
+            out.shaft.commands.extend(points[1..].iter().map(|(x, y)| PathCommand::LineTo(*x, *y)));
+        }
+        ArrowRouting::Elbow => out.shaft = routing::rounded_path(&points, routing::CORNER_RADIUS),
+    }
+    // A mano alzada solo tiembla el cuerpo; las puntas siguen siendo exactas
+    if let Some(sketch) = &style.sketch {
+        out.shaft = sketch::rough_path(&out.shaft, sketch);
+    }
+    out
+}
+
+/// Dirección hacia fuera en el primer punto de `points`, desde el primero distinto a él
+fn end_direction(mut points: impl Iterator<Item = (f32, f32)>) -> Option<(f32, f32)> {
+    let tip = points.next()?;
+    points.find_map(|p| {
+        let (dx, dy) = (tip.0 - p.0, tip.1 - p.1);
+        let len = dx.hypot(dy);
+        (len > 1e-4).then_some((dx / len, dy / len))
+    })
+}
+
+/// Añade la punta en `tip` y devuelve cuánto hay que recortar el cuerpo para que no asome por ella
+fn add_head(out: &mut ArrowGeometry, tip: (f32, f32), (dx, dy): (f32, f32), head: ArrowHead, size: f32) -> f32 {
+    let (px, py) = (-dy, dx);
+    let at = |back: f32, side: f32| (tip.0 - dx * back + px * side, tip.1 - dy * back + py * side);
+    match head {
+        ArrowHead::None => 0.0,
+        ArrowHead::Triangle => {
+            let (a, b) = (at(size, size / 2.0), at(size, -size / 2.0));
+            out.filled.commands.extend([
+                PathCommand::MoveTo(tip.0, tip.1),
+                PathCommand::LineTo(a.0, a.1),
+                PathCommand::LineTo(b.0, b.1),
+                PathCommand::Close,
+            ]);
+            size
+        }
+        ArrowHead::Open => {
+            let (a, b) = (at(size, size / 2.0), at(size, -size / 2.0));
+            out.stroked.commands.extend([PathCommand::MoveTo(a.0, a.1), PathCommand::LineTo(tip.0, tip.1), PathCommand::LineTo(b.0, b.1)]);
+            0.0
+        }
+        ArrowHead::Dot => {
+            let r = size * 0.35;
+            // Círculo con cuatro cúbicas
+            let k = 0.552_284_8 * r;
+            let (cx, cy) = tip;
+            out.filled.commands.extend([
+                PathCommand::MoveTo(cx + r, cy),
+                PathCommand::CubicTo { c1x: cx + r, c1y: cy + k, c2x: cx + k, c2y: cy + r, x: cx, y: cy + r },
+                PathCommand::CubicTo { c1x: cx - k, c1y: cy + r, c2x: cx - r, c2y: cy + k, x: cx - r, y: cy },
+                PathCommand::CubicTo { c1x: cx - r, c1y: cy - k, c2x: cx - k, c2y: cy - r, x: cx, y: cy - r },
+                PathCommand::CubicTo { c1x: cx + k, c1y: cy - r, c2x: cx + r, c2y: cy - k, x: cx + r, y: cy },
+                PathCommand::Close,
+            ]);
+            r
+        }
+        ArrowHead::Bar => {
+            let (a, b) = (at(0.0, size / 2.0), at(0.0, -size / 2.0));
+            out.stroked.commands.extend([PathCommand::MoveTo(a.0, a.1), PathCommand::LineTo(b.0, b.1)]);
+            0.0
+        }
+    }
+}
+
+/// Extremo `tip` retrasado `trim` hacia `next`, sin pasar de él
+fn trimmed(tip: (f32, f32), next: (f32, f32), (dx, dy): (f32, f32), trim: f32) -> (f32, f32) {
+    let trim = trim.min((tip.0 - next.0).hypot(tip.1 - next.1));
+    (tip.0 - dx * trim, tip.1 - dy * trim)
+}
+
+/// Pinta la flecha con `draw_path`: el cuerpo con el estilo (incluida la discontinuidad) y las puntas
+/// con el color de trazo. El relleno del estilo no se usa.
+pub fn draw_arrow(renderer: &mut dyn RenderPort, transform: &Transform, arrow: &Arrow, style: &Style) -> Result<(), RenderError> {
+    if style.stroke.is_none() {
+        return Ok(());
+    }
+    let geometry = geometry(arrow, style);
+    renderer.draw_path(transform, &geometry.shaft, &Style { fill: None, ..style.clone() })?;
+    if !geometry.filled.commands.is_empty() {
+        renderer.draw_path(transform, &geometry.filled, &FreehandStroke::ink_style(style))?;
+    }
+    if !geometry.stroked.commands.is_empty() {
+        renderer.draw_path(transform, &geometry.stroked, &head_stroke_style(style))?;
+    }
+    Ok(())
+}
+
+/// Estilo de las puntas de trazo: el de la flecha, sin relleno ni discontinuidad
+pub fn head_stroke_style(style: &Style) -> Style {
+    Style { fill: None, dash: Vec::new(), dash_offset: 0.0, ..style.clone() }
+}
+
+/// ¿Se puede enganchar una flecha a esta forma? No a otras flechas ni a líneas.
+pub fn is_bindable(shape: &Shape) -> bool {
+    !matches!(shape, Shape::Arrow { .. } | Shape::Line { .. })
+}
+
+/// Punto de mundo de un ancla normalizada sobre la caja local de la forma
+pub fn anchor_point(transform: &Transform, shape: &Shape, anchor: (f32, f32)) -> (f32, f32) {
+    OrientedBoundingBox::from_shape(transform, shape).point_at(anchor.0, anchor.1)
+}
+
+/// Ancla normalizada (dentro de 0..=1) de un punto de mundo sobre la caja local de la forma
+pub fn anchor_at(transform: &Transform, shape: &Shape, x: f32, y: f32) -> (f32, f32) {
+    let frame = OrientedBoundingBox::from_shape(transform, shape);
+    let (lx, ly) = frame.to_local(x, y);
+    let norm = |v: f32, start: f32, len: f32| if len > f32::EPSILON { ((v - start) / len).clamp(0.0, 1.0) } else { 0.5 };
+    (norm(lx, frame.local.x, frame.local.width), norm(ly, frame.local.y, frame.local.height))
+}
+
+/// Extremo enganchado: sobre la recta de `toward` al ancla, justo fuera del contorno del destino
+/// (la elipse en las elipses, la caja local en el resto). Si `toward` queda dentro, el propio ancla.
+pub fn attach_point(transform: &Transform, shape: &Shape, anchor: (f32, f32), toward: (f32, f32)) -> (f32, f32) {
+    let frame = OrientedBoundingBox::from_shape(transform, shape);
+    let (ax, ay) = frame.to_local(anchor.0, anchor.1);
+    let (bx, by) = frame.to_local(toward.0, toward.1);
+    let len = (bx - ax).hypot(by - ay);
+    if len < 1e-4 {
+        return anchor;
+    }
+    let (ux, uy) = ((bx - ax) / len, (by - ay) / len);
+    // Distancia desde el ancla hasta donde la recta sale del contorno
+    let exit = match shape {
+        Shape::Ellipse { rx, ry } => {
+            let (rx, ry) = ((rx * transform.scale_x).abs().max(1e-4), (ry * transform.scale_y).abs().max(1e-4));
+            let (px, py) = (ax - transform.x, ay - transform.y);
+            let a = (ux / rx).powi(2) + (uy / ry).powi(2);
+            let b = 2.0 * (px * ux / (rx * rx) + py * uy / (ry * ry));
+            let c = (px / rx).powi(2) + (py / ry).powi(2) - 1.0;
+            let disc = b * b - 4.0 * a * c;
+            if disc < 0.0 { 0.0 } else { ((-b + disc.sqrt()) / (2.0 * a)).max(0.0) }
+        }
+        _ => {
+            let b = frame.local;
+            let axis = |p: f32, u: f32, min: f32, max: f32| {
+                if u > 1e-6 { (max - p) / u } else if u < -1e-6 { (min - p) / u } else { f32::INFINITY }
+            };
+            axis(ax, ux, b.x, b.x + b.width).min(axis(ay, uy, b.y, b.y + b.height)).max(0.0)
+        }
+    };
+    let distance = exit + BINDING_GAP;
+    if distance >= len {
+        return anchor;
+    }
+    rotate_around(ax + ux * distance, ay + uy * distance, frame.pivot_x, frame.pivot_y, frame.rotation)
+}
+
+/// Puntos locales de la flecha `entity` con sus extremos enganchados recolocados según sus destinos
+/// (y, en un conector en codo, sus tramos recalculados). None si no es una flecha, no hay nada que
+/// recolocar o su escala no se puede invertir.
+pub fn route(doc: &Document, entity: &Entity) -> Option<Vec<(f32, f32)>> {
+    let Shape::Arrow { arrow } = &entity.shape else { return None };
+    let t = &entity.transform;
+    if arrow.points.len() < 2 || t.scale_x.abs() < f32::EPSILON || t.scale_y.abs() < f32::EPSILON {
+        return None;
+    }
+    // Un destino borrado deja el extremo donde estaba (el enganche vuelve a valer si se deshace el borrado)
+    let target = |binding: Option<ArrowBinding>| {
+        let binding = binding.filter(|b| b.target != entity.id)?;
+        let target = doc.get(binding.target).filter(|e| is_bindable(&e.shape))?;
+        Some((target, binding.anchor, anchor_point(&target.transform, &target.shape, binding.anchor)))
+    };
+    let (start, end) = (target(entity.bindings.start), target(entity.bindings.end));
+    if start.is_none() && end.is_none() && arrow.routing == ArrowRouting::Straight {
+        return None;
+    }
+
+    let world: Vec<(f32, f32)> = arrow.points.iter().map(|(x, y)| t.apply(*x, *y)).collect();
+    let n = world.len();
+    let routed = match arrow.routing {
+        ArrowRouting::Straight => straight_route(world, start.map(|(e, _, p)| (e, p)), end.map(|(e, _, p)| (e, p))),
+        ArrowRouting::Elbow => elbow_route(doc, entity.id, (world[0], start), (world[n - 1], end)),
+    };
+    Some(
+        routed
+            .into_iter()
+            .map(|(x, y)| {
+                let (x, y) = t.unrotate_point(x, y);
+                ((x - t.x) / t.scale_x, (y - t.y) / t.scale_y)
+            })
+            .collect(),
+    )
+}
+
+/// Puntos de mundo de una flecha recta con sus extremos enganchados sobre el contorno de su destino
+fn straight_route(world: Vec<(f32, f32)>, start: Option<(&Entity, (f32, f32))>, end: Option<(&Entity, (f32, f32))>) -> Vec<(f32, f32)> {
+    let n = world.len();
+    let mut routed = world.clone();
+    // Cada extremo se orienta hacia su vecino; en una flecha recta, hacia el ancla del otro extremo
+    if let Some((target, anchor)) = start {
+        let toward = if n > 2 { world[1] } else { end.map_or(world[n - 1], |(_, a)| a) };
+        routed[0] = attach_point(&target.transform, &target.shape, anchor, toward);
+    }
+    if let Some((target, anchor)) = end {
+        let toward = if n > 2 { world[n - 2] } else { start.map_or(world[0], |(_, a)| a) };
+        routed[n - 1] = attach_point(&target.transform, &target.shape, anchor, toward);
+    }
+    routed
+}
+
+/// Extremo de un conector con su destino (la entidad, el ancla normalizada y su punto de mundo)
+type ElbowEnd<'a> = ((f32, f32), Option<(&'a Entity, (f32, f32), (f32, f32))>);
+
+/// Puntos de mundo de un conector en codo. Cada extremo enganchado sale por el lado de la caja de su
+/// destino más cercano al ancla o, si el ancla está hacia el centro, por el que mira al otro extremo.
+/// Rodea todas las entidades enganchables salvo él mismo.
+fn elbow_route(doc: &Document, id: EntityId, start: ElbowEnd, end: ElbowEnd) -> Vec<(f32, f32)> {
+    let aim = |(point, target): ElbowEnd| target.map_or(point, |(_, _, anchor)| anchor);
+    let endpoint = |(point, target): ElbowEnd, toward: (f32, f32)| match target {
+        None => Endpoint::free(point),
+        Some((entity, anchor, _)) => {
+            let bounds = BoundingBox::from_shape(&entity.transform, &entity.shape);
+            let side = Side::nearest(anchor).unwrap_or_else(|| Side::facing(&bounds, toward));
+            Endpoint::on_box(&bounds, side, anchor, BINDING_GAP)
+        }
+    };
+    let (from, to) = (endpoint(start, aim(end)), endpoint(end, aim(start)));
+    let obstacles: Vec<BoundingBox> = doc
+        .iter()
+        .filter(|e| e.id != id && is_bindable(&e.shape))
+        .map(|e| BoundingBox::from_shape(&e.transform, &e.shape))
+        .collect();
+    routing::orthogonal_route(from, to, &obstacles)
+}
+
+/// Recoloca los extremos enganchados de todas las flechas del documento y recalcula los conectores.
+/// Devuelve las flechas que han cambiado.
+pub fn update_bindings(doc: &mut Document) -> Vec<EntityId> {
+    let ids: Vec<EntityId> = doc.ids().collect();
+    update_arrows(doc, &ids)
+}
+
+/// Como `update_bindings`, pero solo con las flechas `ids` (el resto de ids se ignora)
+pub fn update_arrows(doc: &mut Document, ids: &[EntityId]) -> Vec<EntityId> {
+    let changes: Vec<_> = ids
+        .iter()
+        .filter_map(|id| {
+            let e = doc.get(*id)?;
+            let Shape::Arrow { arrow } = &e.shape else { return None };
+            let points = route(doc, e)?;
+            let moved = points.len() != arrow.points.len()
+                || points.iter().zip(&arrow.points).any(|(a, b)| (a.0 - b.0).abs() > 1e-3 || (a.1 - b.1).abs() > 1e-3);
+            moved.then_some((e.id, points))
+        })
+        .collect();
+    let mut changed = Vec::with_capacity(changes.len());
+    for (id, points) in changes {
+        if let Some(Entity { shape: Shape::Arrow { arrow }, .. }) = doc.get_mut(id) {
+            arrow.points = points;
+            changed.push(id);
+        }
+    }
+    changed
+}
+
+/// Flechas que hay que recolocar cuando cambian (o aparecen, o desaparecen) las entidades `changed`,
+/// cuyas cajas de mundo antes y después del cambio son `areas`: las propias flechas cambiadas, las
+/// enganchadas a alguna de ellas y los conectores en codo cuyo recorrido pasa cerca de esas cajas.
+/// Cuesta O(flechas) más O(áreas) por cada conector que toca la caja que las engloba a todas.
+pub fn affected_arrows(doc: &Document, changed: &HashSet<EntityId>, areas: &[BoundingBox]) -> Vec<EntityId> {
+    let everything = areas.iter().copied().reduce(|a, b| a.union(&b));
+    doc.iter()
+        .filter(|e| {
+            let Shape::Arrow { arrow } = &e.shape else { return false };
+            let targets = [e.bindings.start, e.bindings.end].into_iter().flatten();
+            if changed.contains(&e.id) || targets.map(|b| b.target).any(|id| changed.contains(&id)) {
+                return true;
+            }
+            if arrow.routing != ArrowRouting::Elbow { return false; }
+            let reach = BoundingBox::from_shape(&e.transform, &e.shape).inflated(2.0 * routing::ROUTE_MARGIN);
+            // La caja común descarta de una vez los conectores lejos de todo lo que ha cambiado
+            everything.is_some_and(|all| all.intersects(&reach)) && areas.iter().any(|a| a.intersects(&reach))
+        })
+        .map(|e| e.id)
+        .collect()
+}
@@ -0,0 +1,98 @@
+//! Lápiz a mano alzada: de las muestras del puntero (posición y presión) a un `FreehandStroke`.
+//! Se descartan las muestras casi repetidas, se suaviza la presión, se simplifica la polilínea
+//! con Ramer–Douglas–Peucker y los puntos que quedan se unen con cúbicas Catmull-Rom, que pasan
+//! por todos ellos sin esquinas.
+
+use crate::model::{distance_to_segment, FreehandStroke, Path, PathCommand};
+
+/// Muestra del puntero. `pressure` en 0..=1, como en Pointer Events (el ratón pulsado da 0.5).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StrokeSample {
+    pub x: f32,
+    pub y: f32,
+    pub pressure: f32,
+}
+
+/// Grosor para una presión: `width` con la presión por defecto (0.5), de la mitad al 150 %
+pub fn pressure_width(width: f32, pressure: f32) -> f32 {
+    width * (0.5 + pressure.clamp(0.0, 1.0))
+}
+
+/// Ajusta un trazo a las muestras, en sus mismas coordenadas. `tolerance` es la desviación
+/// máxima de la simplificación; None si no hay muestras.
+pub fn fit_stroke(samples: &[StrokeSample], width: f32, tolerance: f32) -> Option<FreehandStroke> {
+    let first = *samples.first()?;
+
+    // Muestras casi coincidentes no aportan forma, solo ruido en las direcciones
+    let min_step = tolerance * 0.5;
+    let mut points = vec![first];
+    for s in &samples[1..] {
+        let last = points[points.len() - 1];
+        if (s.x - last.x).hypot(s.y - last.y) > min_step {
+            points.push(*s);
+        } else {
+            // Se conserva la mayor presión del grupo para no adelgazar el trazo al ir despacio
+            let n = points.len();
+            points[n - 1].pressure = points[n - 1].pressure.max(s.pressure);
+        }
+    }
+
+    // La presión de los digitalizadores salta de muestra a muestra: media ponderada con los vecinos
+    let pressures: Vec<f32> = (0..points.len())
+        .map(|i| {
+            let prev = points[i.saturating_sub(1)].pressure;
+            let next = points[(i + 1).min(points.len() - 1)].pressure;
+            (prev + 2.0 * points[i].pressure + next) / 4.0
+        })
+        .collect();
+
+    let kept = simplify(&points, tolerance);
+    let nodes: Vec<(f32, f32, f32)> = kept
+        .iter()
+        .map(|&i| (points[i].x, points[i].y, pressure_width(width, pressures[i])))
+        .collect();
+
+    let mut commands = vec![PathCommand::MoveTo(nodes[0].0, nodes[0].1)];
+    let mut widths = vec![nodes[0].2];
+    for i in 0..nodes.len().saturating_sub(1) {
+        // Catmull-Rom uniforme como Bézier: tangente en cada nodo = (siguiente - anterior) / 2
+        let p0 = nodes[i.saturating_sub(1)];
+        let (p1, p2) = (nodes[i], nodes[i + 1]);
+        let p3 = nodes[(i + 2).min(nodes.len() - 1)];
+        commands.push(PathCommand::CubicTo {
+            c1x: p1.0 + (p2.0 - p0.0) / 6.0,
+            c1y: p1.1 + (p2.1 - p0.1) / 6.0,
+            c2x: p2.0 - (p3.0 - p1.0) / 6.0,
+            c2y: p2.1 - (p3.1 - p1.1) / 6.0,
+            x: p2.0,
+            y: p2.1,
+        });
+        widths.push(p2.2);
+    }
+    Some(FreehandStroke { path: Path { commands }, widths })
+}
+
+/// Índices que sobreviven a Ramer–Douglas–Peucker (siempre el primero y el último).
+/// Iterativo para que los trazos largos no agoten la pila.
+fn simplify(points: &[StrokeSample], tolerance: f32) -> Vec<usize> {
+    let n = points.len();
+    if n < 3 {
+        return (0..n).collect();
+    }
+    let mut keep = vec![false; n];
+    keep[0] = true;
+    keep[n - 1] = true;
+    let mut stack = vec![(0, n - 1)];
+    while let Some((start, end)) = stack.pop() {
+        let (a, b) = ((points[start].x, points[start].y), (points[end].x, points[end].y));
+        let farthest = (start + 1..end)
+            .map(|i| (i, distance_to_segment(points[i].x, points[i].y, a, b)))
+            .fold((start, 0.0f32), |best, candidate| if candidate.1 > best.1 { candidate } else { best });
+        if farthest.1 > tolerance {
+            keep[farthest.0] = true;
+            stack.push((start, farthest.0));
+            stack.push((farthest.0, end));
+        }
+    }
+    (0..n).filter(|i| keep[*i]).collect()
+}
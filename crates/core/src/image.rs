@@ -0,0 +1,84 @@
+//! Imágenes raster importadas (PNG, JPEG y WebP).
+//!
+//! El núcleo solo lee la cabecera para reconocer el formato y el tamaño en píxeles: los píxeles
+//! los decodifica cada renderer al subir la textura (`RenderPort::upload_image`). Los bytes
+//! originales se guardan como blob y su id sale del contenido, así la misma imagen importada
+//! dos veces comparte blob y textura.
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use thiserror::Error;
+
+use crate::model::BlobId;
+use crate::ports::StorageError;
+
+/// Lado mayor, en unidades de mundo, con el que se coloca una imagen importada
+pub const MAX_IMPORT_SIZE: f32 = 1024.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    Webp,
+}
+
+impl ImageFormat {
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "image/png",
+            ImageFormat::Jpeg => "image/jpeg",
+            ImageFormat::Webp => "image/webp",
+        }
+    }
+}
+
+/// Formato y tamaño en píxeles de una imagen codificada
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ImageInfo {
+    pub format: ImageFormat,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ImageInfo {
+    /// Tamaño en mundo al importarla: el de sus píxeles, reducido si pasa de `MAX_IMPORT_SIZE`
+    pub fn import_size(&self) -> (f32, f32) {
+        let (w, h) = (self.width as f32, self.height as f32);
+        let scale = (MAX_IMPORT_SIZE / w.max(h)).min(1.0);
+        (w * scale, h * scale)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ImageError {
+    #[error("Unsupported image format (expected PNG, JPEG or WebP)")] UnsupportedFormat,
+    #[error("Corrupt image")] Corrupt,
+    #[error("Storage: {0}")] Storage(#[from] StorageError),
+}
+
+/// Reconoce una imagen PNG, JPEG o WebP por su cabecera
+pub fn probe(data: &[u8]) -> Result<ImageInfo, ImageError> {
+    let format = match imagesize::image_type(data) {
+        Ok(imagesize::ImageType::Png) => ImageFormat::Png,
+        Ok(imagesize::ImageType::Jpeg) => ImageFormat::Jpeg,
+        Ok(imagesize::ImageType::Webp) => ImageFormat::Webp,
+        // Cabecera desconocida (o demasiado corta para reconocerla)
+        _ => return Err(ImageError::UnsupportedFormat),
+    };
+    let size = imagesize::blob_size(data).map_err(|_| ImageError::Corrupt)?;
+    match (u32::try_from(size.width), u32::try_from(size.height)) {
+        (Ok(width), Ok(height)) if width > 0 && height > 0 => Ok(ImageInfo { format, width, height }),
+        _ => Err(ImageError::Corrupt),
+    }
+}
+
+/// Id de blob derivado del contenido (FNV-1a de 64 bits): estable entre sesiones y plataformas
+pub fn blob_id(data: &[u8]) -> BlobId {
+    let hash = data.iter().fold(0xcbf2_9ce4_8422_2325u64, |h, b| (h ^ *b as u64).wrapping_mul(0x0100_0000_01b3));
+    BlobId(hash)
+}
+
+/// URI `data:` con la imagen en base64, p.ej. para incrustarla en un SVG
+pub fn data_uri(data: &[u8]) -> Result<String, ImageError> {
+    let info = probe(data)?;
+    Ok(format!("data:{};base64,{}", info.format.mime_type(), STANDARD.encode(data)))
+}
@@ -29,13 +29,44 @@ pub mod model {
         }
     }
 
-    #[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+    impl Transform {
+        /// Lleva un punto del espacio de la forma a mundo: escala, rota y traslada
+        /// (mismo orden que aplica Canvas2D al dibujar).
+        pub fn apply(&self, px: f32, py: f32) -> (f32, f32) {
+            self.rotate_point(self.x + px * self.scale_x, self.y + py * self.scale_y)
+        }
+
+        /// Rota un punto de mundo alrededor del origen de la entidad según `rotation`
+        pub fn rotate_point(&self, x: f32, y: f32) -> (f32, f32) {
+            rotate_around(x, y, self.x, self.y, self.rotation)
+        }
+
+        /// Deshace la rotación de un punto de mundo alrededor del origen de la entidad.
+        /// El resultado queda en el marco local rotado (traslación y escala siguen aplicadas),
+        /// que es el espacio en el que trabajan los hit tests y las cajas sin rotar.
+        pub fn unrotate_point(&self, x: f32, y: f32) -> (f32, f32) {
+            rotate_around(x, y, self.x, self.y, -self.rotation)
+        }
+    }
+
+    /// Rota (x, y) un ángulo en radianes alrededor de (cx, cy)
+    pub fn rotate_around(x: f32, y: f32, cx: f32, cy: f32, angle: f32) -> (f32, f32) {
+        if angle == 0.0 {
+            return (x, y);
+        }
+        let (sin, cos) = angle.sin_cos();
+        let dx = x - cx;
+        let dy = y - cy;
+        (cx + dx * cos - dy * sin, cy + dx * sin + dy * cos)
+    }
+
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
     pub struct Color(pub f32, pub f32, pub f32, pub f32);
 
     #[derive(Clone, Debug, Default, Serialize, Deserialize)]
     pub struct Style {
-        pub fill: Option<Color>,
-        pub stroke: Option<Color>,
+        pub fill: Option<Paint>,
+        pub stroke: Option<Paint>,
         pub stroke_width: f32,
         pub opacity: f32,
         #[serde(default)]
@@ -46,6 +77,93 @@ pub mod model {
         pub dash: Vec<f32>,
         #[serde(default)]
         pub dash_offset: f32,
+        /// Estilo a mano alzada; None = geometría exacta
+        #[serde(default)]
+        pub sketch: Option<Sketch>,
+    }
+
+    /// Pintura de un relleno o de un trazo. Las coordenadas son locales de la forma, así que la
+    /// pintura se mueve, gira y escala con la entidad. En JSON un color suelto es pintura sólida,
+    /// como cuando `fill` y `stroke` solo admitían colores.
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    pub enum Paint {
+        /// Degradado lineal de `start` a `end`; fuera de ese tramo se extienden los colores extremos
+        LinearGradient { start: (f32, f32), end: (f32, f32), stops: Vec<GradientStop> },
+        /// Degradado radial desde `center` hasta la distancia `radius`
+        RadialGradient { center: (f32, f32), radius: f32, stops: Vec<GradientStop> },
+        /// Líneas rectas paralelas de `color` y grosor `width`, separadas `gap` e inclinadas `angle`
+        /// radianes; con `cross` se añaden las perpendiculares. Como trazo equivale a `color`.
+        Hatch {
+            color: Color,
+            gap: f32,
+            angle: f32,
+            width: f32,
+            #[serde(default)]
+            cross: bool,
+        },
+        /// Imagen del almacén de blobs repetida en mosaicos de `w` x `h` desde el origen local
+        Image { blob: BlobId, w: f32, h: f32 },
+        /// Sin etiqueta: un `Color` de los documentos anteriores se lee como pintura sólida
+        #[serde(untagged)]
+        Solid(Color),
+    }
+
+    /// Parada de un degradado: color en la posición `offset` (0 = inicio, 1 = final)
+    #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+    pub struct GradientStop {
+        pub offset: f32,
+        pub color: Color,
+    }
+
+    impl From<Color> for Paint {
+        fn from(color: Color) -> Self {
+            Paint::Solid(color)
+        }
+    }
+
+    impl Paint {
+        /// Color único que mejor representa la pintura, para quien no sabe pintar la variante:
+        /// el propio color, el del sombreado, la media de las paradas o un gris para las imágenes
+        pub fn color(&self) -> Color {
+            match self {
+                Paint::Solid(color) | Paint::Hatch { color, .. } => *color,
+                Paint::LinearGradient { stops, .. } | Paint::RadialGradient { stops, .. } => {
+                    if stops.is_empty() {
+                        return Color::default();
+                    }
+                    let n = stops.len() as f32;
+                    let sum = stops.iter().fold([0.0; 4], |acc, s| {
+                        let Color(r, g, b, a) = s.color;
+                        [acc[0] + r, acc[1] + g, acc[2] + b, acc[3] + a]
+                    });
+                    Color(sum[0] / n, sum[1] / n, sum[2] / n, sum[3] / n)
+                }
+                Paint::Image { .. } => Color(0.5, 0.5, 0.5, 1.0),
+            }
+        }
+    }
+
+    /// Dibujo a mano alzada (ver `sketch`): contornos temblorosos en dos pasadas y relleno sombreado
+    #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+    pub struct Sketch {
+        /// Cuánto se apartan los trazos de la geometría exacta (1 = el valor por defecto de rough.js)
+        pub roughness: f32,
+        /// Semilla de la entidad (`sketch::entity_seed`): la misma semilla da siempre los mismos trazos
+        pub seed: u32,
+        #[serde(default)]
+        pub fill: SketchFill,
+    }
+
+    /// Cómo se rellena una forma a mano alzada (con el color de relleno del estilo)
+    #[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum SketchFill {
+        /// Líneas paralelas inclinadas
+        #[default]
+        Hachure,
+        /// Dos sombreados cruzados
+        CrossHatch,
+        /// Relleno sólido de un contorno algo desplazado
+        Solid,
     }
 
     #[derive(Default, Clone, Copy, Debug, Serialize, Deserialize)]
@@ -56,10 +174,161 @@ pub mod model {
 
     #[derive(Clone, Debug, Serialize, Deserialize)]
     pub enum Shape {
-        Rect { w: f32, h: f32 },
+        /// Rectángulo con su esquina superior izquierda en el origen de la entidad
+        Rect {
+            w: f32,
+            h: f32,
+            /// Esquinas redondeadas; las de los documentos anteriores son vivas
+            #[serde(default)]
+            radii: CornerRadii,
+        },
         Ellipse { rx: f32, ry: f32 },
         Line { x2: f32, y2: f32 },
         Polygon { points: Vec<(f32, f32)> },
+        /// Trazado libre (curvas Bézier) en coordenadas locales de la entidad
+        Path { path: Path },
+        /// Trazo a mano alzada de grosor variable (lápiz); se pinta con el color de trazo
+        Freehand { stroke: FreehandStroke },
+        /// Bloque de texto con su esquina superior izquierda en el origen de la entidad
+        Text { text: TextBox },
+        /// Imagen raster de (w, h) con su esquina superior izquierda en el origen de la entidad;
+        /// los bytes codificados (PNG/JPEG/WebP) se guardan aparte como blob
+        Image { blob: BlobId, w: f32, h: f32 },
+        /// Flecha: polilínea con puntas en sus extremos (ver `arrow`); se pinta con el color de trazo
+        Arrow { arrow: Arrow },
+    }
+
+    /// Radios de las esquinas de un rectángulo, en unidades locales. En JSON, un número para el
+    /// radio común o un array con los cuatro.
+    #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(untagged)]
+    pub enum CornerRadii {
+        /// El mismo radio en las cuatro esquinas
+        Uniform(f32),
+        /// Superior izquierda, superior derecha, inferior derecha e inferior izquierda (como en CSS)
+        PerCorner([f32; 4]),
+    }
+
+    impl Default for CornerRadii {
+        fn default() -> Self {
+            CornerRadii::Uniform(0.0)
+        }
+    }
+
+    impl CornerRadii {
+        /// Radios en el orden de `PerCorner`; los negativos o no finitos cuentan como 0
+        pub fn corners(&self) -> [f32; 4] {
+            let radii = match *self {
+                CornerRadii::Uniform(r) => [r; 4],
+                CornerRadii::PerCorner(radii) => radii,
+            };
+            radii.map(|r| if r.is_finite() { r.max(0.0) } else { 0.0 })
+        }
+
+        /// ¿Esquinas vivas?
+        pub fn is_sharp(&self) -> bool {
+            self.corners().iter().all(|r| *r == 0.0)
+        }
+
+        /// Radios locales (horizontal, vertical) de cada esquina de un rect de `w` x `h` dibujado con
+        /// `transform`, en el orden de `PerCorner` sobre la caja normalizada (la esquina superior
+        /// izquierda es la de menor x e y). El radio se mide con la menor de las dos escalas, así que
+        /// en mundo las esquinas siguen siendo circulares aunque `scale_x != scale_y`; y, como en CSS,
+        /// si dos esquinas vecinas no caben en un lado se reducen todas en la misma proporción.
+        pub fn resolve(&self, w: f32, h: f32, transform: &Transform) -> [(f32, f32); 4] {
+            let (sx, sy) = (transform.scale_x.abs(), transform.scale_y.abs());
+            if sx == 0.0 || sy == 0.0 {
+                return [(0.0, 0.0); 4];
+            }
+            // En mundo
+            let radii = self.corners().map(|r| r * sx.min(sy));
+            let (width, height) = ((w * sx).abs(), (h * sy).abs());
+            let [tl, tr, br, bl] = radii;
+            let fit = |side: f32, a: f32, b: f32| if a + b > side { side / (a + b) } else { 1.0 };
+            let factor = fit(width, tl, tr).min(fit(width, bl, br)).min(fit(height, tl, bl)).min(fit(height, tr, br));
+            radii.map(|r| (r * factor / sx, r * factor / sy))
+        }
+    }
+
+    /// Punta de flecha en un extremo
+    #[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum ArrowHead {
+        #[default]
+        None,
+        /// Triángulo relleno
+        Triangle,
+        /// Dos trazos en V
+        Open,
+        /// Círculo relleno centrado en el extremo
+        Dot,
+        /// Trazo perpendicular
+        Bar,
+    }
+
+    fn default_end_head() -> ArrowHead { ArrowHead::Triangle }
+
+    /// Cómo se trazan los tramos de una flecha entre sus extremos
+    #[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum ArrowRouting {
+        /// Los puntos tal como se dibujaron (recta o con los quiebres del usuario)
+        #[default]
+        Straight,
+        /// Conector ortogonal en codo que rodea las demás entidades (ver `routing`);
+        /// sus puntos intermedios se recalculan y las esquinas se pintan redondeadas
+        Elbow,
+    }
+
+    /// Flecha en coordenadas locales de la entidad
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct Arrow {
+        /// Inicio, puntos de quiebre (si los hay) y fin
+        pub points: Vec<(f32, f32)>,
+        #[serde(default)]
+        pub start_head: ArrowHead,
+        #[serde(default = "default_end_head")]
+        pub end_head: ArrowHead,
+        #[serde(default)]
+        pub routing: ArrowRouting,
+    }
+
+    impl Arrow {
+        /// Flecha recta de `start` a `end` con punta triangular al final
+        pub fn new(start: (f32, f32), end: (f32, f32)) -> Self {
+            Self { points: vec![start, end], start_head: ArrowHead::None, end_head: default_end_head(), routing: ArrowRouting::Straight }
+        }
+
+        /// Conector en codo de `start` a `end`; sus tramos los calcula `routing::orthogonal_route`
+        pub fn elbow(start: (f32, f32), end: (f32, f32)) -> Self {
+            Self { routing: ArrowRouting::Elbow, ..Self::new(start, end) }
+        }
+    }
+
+    /// Extremo de una flecha enganchado a otra entidad
+    #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+    pub struct ArrowBinding {
+        pub target: EntityId,
+        /// Punto al que apunta, normalizado sobre la caja local del destino: (0.5, 0.5) es el centro
+        pub anchor: (f32, f32),
+    }
+
+    /// Enganches de los dos extremos de una flecha
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+    pub struct ArrowBindings {
+        #[serde(default)]
+        pub start: Option<ArrowBinding>,
+        #[serde(default)]
+        pub end: Option<ArrowBinding>,
+    }
+
+    impl ArrowBindings {
+        pub fn is_empty(&self) -> bool {
+            self.start.is_none() && self.end.is_none()
+        }
+
+        /// ¿Algún extremo apunta a `id`?
+        pub fn targets(&self, id: EntityId) -> bool {
+            self.start.is_some_and(|b| b.target == id) || self.end.is_some_and(|b| b.target == id)
+        }
     }
 
     // Tipos base adicionales (contratos de puertos)
@@ -74,7 +343,7 @@ pub mod model {
         pub h: f32,
     }
 
-    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
     pub enum PathCommand {
         MoveTo(f32, f32),
         LineTo(f32, f32),
@@ -83,11 +352,346 @@ pub mod model {
         Close,
     }
 
-    #[derive(Clone, Debug, Default, Serialize, Deserialize)]
+    #[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
     pub struct Path {
         pub commands: Vec<PathCommand>,
     }
 
+    /// Desviación máxima al aplanar curvas, en unidades locales del trazado
+    pub const PATH_FLATTEN_TOLERANCE: f32 = 0.25;
+
+    /// Subtrazado aplanado a polilínea
+    #[derive(Clone, Debug, Default)]
+    pub struct Polyline {
+        pub points: Vec<(f32, f32)>,
+        /// Terminaba en `Close`
+        pub closed: bool,
+    }
+
+    impl Path {
+        /// Rectángulo de `w` x `h` desde el origen con las esquinas redondeadas con los radios
+        /// (horizontal, vertical) de `CornerRadii::resolve`, en cuartos de elipse
+        pub fn rounded_rect(w: f32, h: f32, radii: [(f32, f32); 4]) -> Path {
+            const K: f32 = 1.0 - 0.552_284_8;
+            let (x0, x1, y0, y1) = (w.min(0.0), w.max(0.0), h.min(0.0), h.max(0.0));
+            let [tl, tr, br, bl] = radii;
+            let mut commands = vec![PathCommand::MoveTo(x0 + tl.0, y0), PathCommand::LineTo(x1 - tr.0, y0)];
+            // Cuarto de elipse desde el punto actual hasta (x, y) con la esquina en (cx, cy)
+            let corner = |commands: &mut Vec<PathCommand>, from: (f32, f32), (cx, cy): (f32, f32), (x, y): (f32, f32)| {
+                if from != (x, y) {
+                    commands.push(PathCommand::CubicTo {
+                        c1x: from.0 + (cx - from.0) * (1.0 - K),
+                        c1y: from.1 + (cy - from.1) * (1.0 - K),
+                        c2x: x + (cx - x) * (1.0 - K),
+                        c2y: y + (cy - y) * (1.0 - K),
+                        x,
+                        y,
+                    });
+                }
+            };
+            corner(&mut commands, (x1 - tr.0, y0), (x1, y0), (x1, y0 + tr.1));
+            commands.push(PathCommand::LineTo(x1, y1 - br.1));
+            corner(&mut commands, (x1, y1 - br.1), (x1, y1), (x1 - br.0, y1));
+            commands.push(PathCommand::LineTo(x0 + bl.0, y1));
+            corner(&mut commands, (x0 + bl.0, y1), (x0, y1), (x0, y1 - bl.1));
+            commands.push(PathCommand::LineTo(x0, y0 + tl.1));
+            corner(&mut commands, (x0, y0 + tl.1), (x0, y0), (x0 + tl.0, y0));
+            commands.push(PathCommand::Close);
+            Path { commands }
+        }
+
+        /// Subtrazados como polilíneas. Las curvas se subdividen hasta desviarse menos de `tolerance`.
+        pub fn flatten(&self, tolerance: f32) -> Vec<Polyline> {
+            let tolerance = tolerance.max(1e-3);
+            let mut out: Vec<Polyline> = Vec::new();
+            let mut current = Polyline::default();
+            let mut start = (0.0, 0.0);
+            let mut last = (0.0, 0.0);
+            for command in &self.commands {
+                // Un dibujo tras Close (sin MoveTo) empieza un subtrazado nuevo en el inicio del anterior
+                if current.points.is_empty() && !matches!(command, PathCommand::MoveTo(..) | PathCommand::Close) {
+                    current.points.push(last);
+                }
+                match *command {
+                    PathCommand::MoveTo(x, y) => {
+                        if current.points.len() > 1 {
+                            out.push(std::mem::take(&mut current));
+                        }
+                        current.points = vec![(x, y)];
+                        start = (x, y);
+                        last = start;
+                    }
+                    PathCommand::LineTo(x, y) => {
+                        current.points.push((x, y));
+                        last = (x, y);
+                    }
+                    PathCommand::QuadTo { cx, cy, x, y } => {
+                        let (p0, p1, p2) = (last, (cx, cy), (x, y));
+                        let dd = ((p0.0 - 2.0 * p1.0 + p2.0).powi(2) + (p0.1 - 2.0 * p1.1 + p2.1).powi(2)).sqrt();
+                        let n = segments_for(0.25 * dd, tolerance);
+                        for i in 1..=n {
+                            let t = i as f32 / n as f32;
+                            let mt = 1.0 - t;
+                            current.points.push((
+                                mt * mt * p0.0 + 2.0 * mt * t * p1.0 + t * t * p2.0,
+                                mt * mt * p0.1 + 2.0 * mt * t * p1.1 + t * t * p2.1,
+                            ));
+                        }
+                        last = p2;
+                    }
+                    PathCommand::CubicTo { c1x, c1y, c2x, c2y, x, y } => {
+                        let (p0, p1, p2, p3) = (last, (c1x, c1y), (c2x, c2y), (x, y));
+                        let d1 = ((p0.0 - 2.0 * p1.0 + p2.0).powi(2) + (p0.1 - 2.0 * p1.1 + p2.1).powi(2)).sqrt();
+                        let d2 = ((p1.0 - 2.0 * p2.0 + p3.0).powi(2) + (p1.1 - 2.0 * p2.1 + p3.1).powi(2)).sqrt();
+                        let n = segments_for(0.75 * d1.max(d2), tolerance);
+                        for i in 1..=n {
+                            current.points.push(cubic_point(p0, p1, p2, p3, i as f32 / n as f32));
+                        }
+                        last = p3;
+                    }
+                    PathCommand::Close => {
+                        if !current.points.is_empty() {
+                            current.closed = true;
+                            out.push(std::mem::take(&mut current));
+                        }
+                        last = start;
+                    }
+                }
+            }
+            if current.points.len() > 1 {
+                out.push(current);
+            }
+            out
+        }
+
+        /// Caja exacta del trazado (incluye los extremos de las curvas, no sus puntos de control)
+        pub fn bounds(&self) -> Option<BoundingBox> {
+            let mut points = Vec::new();
+            let mut start = (0.0, 0.0);
+            let mut last = (0.0, 0.0);
+            for command in &self.commands {
+                match *command {
+                    PathCommand::MoveTo(x, y) => {
+                        start = (x, y);
+                        last = start;
+                        points.push(last);
+                    }
+                    PathCommand::LineTo(x, y) => {
+                        last = (x, y);
+                        points.push(last);
+                    }
+                    PathCommand::QuadTo { cx, cy, x, y } => {
+                        // Como cúbica equivalente, para reutilizar el cálculo de extremos
+                        let c1 = (last.0 + 2.0 / 3.0 * (cx - last.0), last.1 + 2.0 / 3.0 * (cy - last.1));
+                        let c2 = (x + 2.0 / 3.0 * (cx - x), y + 2.0 / 3.0 * (cy - y));
+                        points.extend(cubic_extrema(last, c1, c2, (x, y)));
+                        last = (x, y);
+                        points.push(last);
+                    }
+                    PathCommand::CubicTo { c1x, c1y, c2x, c2y, x, y } => {
+                        points.extend(cubic_extrema(last, (c1x, c1y), (c2x, c2y), (x, y)));
+                        last = (x, y);
+                        points.push(last);
+                    }
+                    PathCommand::Close => last = start,
+                }
+            }
+            BoundingBox::from_points(points)
+        }
+    }
+
+    /// Segmentos para que una curva con segunda diferencia `deviation` quede por debajo de `tolerance`
+    fn segments_for(deviation: f32, tolerance: f32) -> usize {
+        ((deviation / tolerance).sqrt().ceil() as usize).clamp(1, 256)
+    }
+
+    fn cubic_point(p0: (f32, f32), p1: (f32, f32), p2: (f32, f32), p3: (f32, f32), t: f32) -> (f32, f32) {
+        let mt = 1.0 - t;
+        let (a, b, c, d) = (mt * mt * mt, 3.0 * mt * mt * t, 3.0 * mt * t * t, t * t * t);
+        (a * p0.0 + b * p1.0 + c * p2.0 + d * p3.0, a * p0.1 + b * p1.1 + c * p2.1 + d * p3.1)
+    }
+
+    /// Puntos de la cúbica donde la derivada se anula en x o en y (dentro de 0 < t < 1)
+    fn cubic_extrema(p0: (f32, f32), p1: (f32, f32), p2: (f32, f32), p3: (f32, f32)) -> Vec<(f32, f32)> {
+        let mut roots = Vec::new();
+        for (a0, a1, a2, a3) in [(p0.0, p1.0, p2.0, p3.0), (p0.1, p1.1, p2.1, p3.1)] {
+            // B'(t)/3 = a t² + b t + c
+            let a = -a0 + 3.0 * a1 - 3.0 * a2 + a3;
+            let b = 2.0 * (a0 - 2.0 * a1 + a2);
+            let c = a1 - a0;
+            if a.abs() < 1e-6 {
+                if b.abs() > 1e-6 {
+                    roots.push(-c / b);
+                }
+            } else {
+                let disc = b * b - 4.0 * a * c;
+                if disc >= 0.0 {
+                    let sq = disc.sqrt();
+                    roots.push((-b + sq) / (2.0 * a));
+                    roots.push((-b - sq) / (2.0 * a));
+                }
+            }
+        }
+        roots.into_iter().filter(|t| *t > 0.0 && *t < 1.0).map(|t| cubic_point(p0, p1, p2, p3, t)).collect()
+    }
+
+    /// Distancia de (px, py) al segmento a-b
+    pub fn distance_to_segment(px: f32, py: f32, a: (f32, f32), b: (f32, f32)) -> f32 {
+        let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+        let len_sq = dx * dx + dy * dy;
+        let t = if len_sq == 0.0 { 0.0 } else { (((px - a.0) * dx + (py - a.1) * dy) / len_sq).clamp(0.0, 1.0) };
+        let (qx, qy) = (a.0 + t * dx, a.1 + t * dy);
+        ((px - qx).powi(2) + (py - qy).powi(2)).sqrt()
+    }
+
+    /// Trazo de lápiz: línea central ya suavizada (un `MoveTo` seguido de cúbicas) y el grosor
+    /// en cada uno de sus nodos. El contorno que se rellena se calcula a partir de ambos.
+    #[derive(Clone, Debug, Default, Serialize, Deserialize)]
+    pub struct FreehandStroke {
+        pub path: Path,
+        /// Grosor en unidades locales, uno por comando de `path`
+        pub widths: Vec<f32>,
+    }
+
+    impl FreehandStroke {
+        /// Línea central aplanada con el grosor interpolado: (x, y, grosor) por punto
+        pub fn samples(&self, tolerance: f32) -> Vec<(f32, f32, f32)> {
+            let tolerance = tolerance.max(1e-3);
+            let mut out = Vec::new();
+            let mut last = (0.0, 0.0);
+            let mut last_width = self.widths.first().copied().unwrap_or(0.0);
+            for (i, command) in self.path.commands.iter().enumerate() {
+                let width = self.widths.get(i).copied().unwrap_or(last_width);
+                let lerp = |t: f32| last_width + (width - last_width) * t;
+                match *command {
+                    PathCommand::MoveTo(x, y) | PathCommand::LineTo(x, y) => {
+                        last = (x, y);
+                        out.push((x, y, width));
+                    }
+                    PathCommand::QuadTo { cx, cy, x, y } => {
+                        let (p0, p3) = (last, (x, y));
+                        let p1 = (p0.0 + 2.0 / 3.0 * (cx - p0.0), p0.1 + 2.0 / 3.0 * (cy - p0.1));
+                        let p2 = (x + 2.0 / 3.0 * (cx - x), y + 2.0 / 3.0 * (cy - y));
+                        let n = segments_for(((p0.0 - 2.0 * cx + x).powi(2) + (p0.1 - 2.0 * cy + y).powi(2)).sqrt() * 0.25, tolerance);
+                        for i in 1..=n {
+                            let t = i as f32 / n as f32;
+                            let (px, py) = cubic_point(p0, p1, p2, p3, t);
+                            out.push((px, py, lerp(t)));
+                        }
+                        last = p3;
+                    }
+                    PathCommand::CubicTo { c1x, c1y, c2x, c2y, x, y } => {
+                        let (p0, p1, p2, p3) = (last, (c1x, c1y), (c2x, c2y), (x, y));
+                        let d1 = ((p0.0 - 2.0 * p1.0 + p2.0).powi(2) + (p0.1 - 2.0 * p1.1 + p2.1).powi(2)).sqrt();
+                        let d2 = ((p1.0 - 2.0 * p2.0 + p3.0).powi(2) + (p1.1 - 2.0 * p2.1 + p3.1).powi(2)).sqrt();
+                        let n = segments_for(0.75 * d1.max(d2), tolerance);
+                        for i in 1..=n {
+                            let t = i as f32 / n as f32;
+                            let (px, py) = cubic_point(p0, p1, p2, p3, t);
+                            out.push((px, py, lerp(t)));
+                        }
+                        last = p3;
+                    }
+                    // Un trazo de lápiz no se cierra
+                    PathCommand::Close => {}
+                }
+                last_width = width;
+            }
+            out
+        }
+
+        /// Contorno a rellenar: los dos lados desplazados medio grosor y extremos redondeados.
+        /// Un trazo de un solo punto es un círculo del grosor de ese punto.
+        pub fn outline(&self, tolerance: f32) -> Path {
+            let mut points = self.samples(tolerance);
+            points.dedup_by(|b, a| (b.0 - a.0).abs() < 1e-4 && (b.1 - a.1).abs() < 1e-4);
+            let mut commands = Vec::new();
+            match points.len() {
+                0 => {}
+                1 => {
+                    let (x, y, w) = points[0];
+                    commands.push(PathCommand::MoveTo(x + w / 2.0, y));
+                    arc_to(&mut commands, (x, y), w / 2.0, 0.0, std::f32::consts::TAU);
+                    commands.push(PathCommand::Close);
+                }
+                n => {
+                    // Dirección en cada punto: la de sus vecinos, para que los lados no se quiebren
+                    let directions: Vec<(f32, f32)> = (0..n)
+                        .map(|i| {
+                            let (a, b) = (points[i.saturating_sub(1)], points[(i + 1).min(n - 1)]);
+                            let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+                            let len = (dx * dx + dy * dy).sqrt().max(f32::EPSILON);
+                            (dx / len, dy / len)
+                        })
+                        .collect();
+                    let side = |i: usize, sign: f32| {
+                        let (x, y, w) = points[i];
+                        let (dx, dy) = directions[i];
+                        (x - dy * w / 2.0 * sign, y + dx * w / 2.0 * sign)
+                    };
+                    let angle = |i: usize, sign: f32| {
+                        let (dx, dy) = directions[i];
+                        (dx * sign).atan2(-dy * sign)
+                    };
+                    let start = side(0, 1.0);
+                    commands.push(PathCommand::MoveTo(start.0, start.1));
+                    for i in 1..n {
+                        let (x, y) = side(i, 1.0);
+                        commands.push(PathCommand::LineTo(x, y));
+                    }
+                    let (x, y, w) = points[n - 1];
+                    arc_to(&mut commands, (x, y), w / 2.0, angle(n - 1, 1.0), -std::f32::consts::PI);
+                    for i in (0..n - 1).rev() {
+                        let (x, y) = side(i, -1.0);
+                        commands.push(PathCommand::LineTo(x, y));
+                    }
+                    let (x, y, w) = points[0];
+                    arc_to(&mut commands, (x, y), w / 2.0, angle(0, -1.0), -std::f32::consts::PI);
+                    commands.push(PathCommand::Close);
+                }
+            }
+            Path { commands }
+        }
+
+        /// Caja de la tinta: la de la línea central ampliada con el mayor semigrosor
+        pub fn bounds(&self) -> Option<BoundingBox> {
+            let b = self.path.bounds()?;
+            let half = self.widths.iter().fold(0.0f32, |m, w| m.max(*w)) / 2.0;
+            Some(BoundingBox { x: b.x - half, y: b.y - half, width: b.width + 2.0 * half, height: b.height + 2.0 * half })
+        }
+
+        /// Estilo con el que se rellena el contorno: la tinta es el color de trazo
+        pub fn ink_style(style: &Style) -> Style {
+            // Como trazo, un sombreado es su color
+            let ink = style.stroke.clone().map(|paint| match paint {
+                Paint::Hatch { color, .. } => Paint::Solid(color),
+                paint => paint,
+            });
+            Style { fill: ink, stroke: None, opacity: style.opacity, ..Style::default() }
+        }
+    }
+
+    /// Arco de circunferencia desde el ángulo `from` recorriendo `sweep` radianes, en cúbicas de hasta 90°
+    fn arc_to(commands: &mut Vec<PathCommand>, center: (f32, f32), radius: f32, from: f32, sweep: f32) {
+        let segments = (sweep.abs() / std::f32::consts::FRAC_PI_2).ceil().max(1.0) as usize;
+        let step = sweep / segments as f32;
+        let k = 4.0 / 3.0 * (step / 4.0).tan() * radius;
+        let mut t = from;
+        for _ in 0..segments {
+            let (s0, c0) = t.sin_cos();
+            let (s1, c1) = (t + step).sin_cos();
+            commands.push(PathCommand::CubicTo {
+                c1x: center.0 + radius * c0 - k * s0,
+                c1y: center.1 + radius * s0 + k * c0,
+                c2x: center.0 + radius * c1 + k * s1,
+                c2y: center.1 + radius * s1 - k * c1,
+                x: center.0 + radius * c1,
+                y: center.1 + radius * s1,
+            });
+            t += step;
+        }
+    }
+
     #[derive(Clone, Debug, Serialize, Deserialize)]
     pub struct TextSpan {
         pub text: String,
@@ -105,12 +709,109 @@ pub mod model {
         pub line_gap: f32,
     }
 
+    /// Alineación horizontal de las líneas dentro del bloque
+    #[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum TextAlign { #[default] Left, Center, Right }
+
+    fn default_line_height() -> f32 { 1.25 }
+
+    /// Bloque de texto: tramos con su propio estilo de letra, alineación, interlineado y ancho de ajuste.
+    /// Las posiciones (cursor, selección) son offsets en caracteres sobre el texto de todos los tramos seguidos.
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct TextBox {
+        pub spans: Vec<TextSpan>,
+        #[serde(default)]
+        pub align: TextAlign,
+        /// Alto de línea como múltiplo del mayor tamaño de letra de la línea
+        #[serde(default = "default_line_height")]
+        pub line_height: f32,
+        /// Ancho máximo de línea en unidades locales; None = solo saltos de línea explícitos
+        #[serde(default)]
+        pub wrap_width: Option<f32>,
+        /// Tamaño (ancho, alto) de la última maquetación, medida con las fuentes cargadas
+        /// (`text::FontBook`) o, sin ellas, con `RenderPort::measure_text`. Lo usan las cajas y el hit test, que no tienen acceso al renderer.
+        #[serde(default)]
+        pub extent: Vec2,
+    }
+
+    impl TextBox {
+        /// Bloque de un solo tramo, alineado a la izquierda y sin ajuste
+        pub fn new(span: TextSpan) -> Self {
+            Self { spans: vec![span], align: TextAlign::Left, line_height: default_line_height(), wrap_width: None, extent: Vec2::default() }
+        }
+
+        /// Texto completo, sin formato
+        pub fn text(&self) -> String {
+            self.spans.iter().map(|s| s.text.as_str()).collect()
+        }
+
+        /// Longitud en caracteres
+        pub fn len(&self) -> usize {
+            self.spans.iter().map(|s| s.text.chars().count()).sum()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.spans.iter().all(|s| s.text.is_empty())
+        }
+
+        /// Inserta `text` en el offset `at` con el estilo del tramo donde cae (el anterior en una frontera).
+        /// Sin tramos no hay estilo que heredar y no se inserta nada.
+        pub fn insert(&mut self, at: usize, text: &str) {
+            let mut start = 0;
+            let count = self.spans.len();
+            for (i, span) in self.spans.iter_mut().enumerate() {
+                let len = span.text.chars().count();
+                if at <= start + len || i + 1 == count {
+                    let byte = byte_offset(&span.text, at.saturating_sub(start));
+                    span.text.insert_str(byte, text);
+                    return;
+                }
+                start += len;
+            }
+        }
+
+        /// Borra los caracteres en `start..end`. Los tramos que quedan vacíos desaparecen,
+        /// salvo el primero si se vacía todo (guarda el estilo para seguir escribiendo).
+        pub fn delete(&mut self, start: usize, end: usize) {
+            if start >= end {
+                return;
+            }
+            let mut offset = 0;
+            for span in &mut self.spans {
+                let len = span.text.chars().count();
+                let (from, to) = (start.clamp(offset, offset + len) - offset, end.clamp(offset, offset + len) - offset);
+                if from < to {
+                    let range = byte_offset(&span.text, from)..byte_offset(&span.text, to);
+                    span.text.replace_range(range, "");
+                }
+                offset += len;
+            }
+            if self.is_empty() {
+                self.spans.truncate(1);
+            } else {
+                self.spans.retain(|s| !s.text.is_empty());
+            }
+        }
+    }
+
+    /// Byte donde empieza el carácter `chars` de `text` (o el final)
+    fn byte_offset(text: &str, chars: usize) -> usize {
+        text.char_indices().nth(chars).map_or(text.len(), |(i, _)| i)
+    }
+
     #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
     pub struct ImageId(pub u64);
 
     #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
     pub struct BlobId(pub u64);
 
+    /// La textura de una imagen en el renderer se identifica por su blob
+    impl From<BlobId> for ImageId {
+        fn from(blob: BlobId) -> Self {
+            ImageId(blob.0)
+        }
+    }
+
     // Hitbox: zona invisible para detección de eventos/colisiones, separada de la representación visual
     #[derive(Clone, Debug, Serialize, Deserialize)]
     pub enum Hitbox {
@@ -138,7 +839,8 @@ pub mod model {
         /// Crear hitbox desde una forma geométrica con tolerancia por defecto
         pub fn from_shape(shape: &Shape) -> Self {
             match shape {
-                Shape::Line { .. } => Hitbox::FromShape { tolerance: 8.0 }, // Más tolerancia para líneas
+                // Más tolerancia para líneas y trazos finos
+                Shape::Line { .. } | Shape::Path { .. } | Shape::Freehand { .. } | Shape::Arrow { .. } => Hitbox::FromShape { tolerance: 8.0 },
                 _ => Hitbox::FromShape { tolerance: 2.0 }, // Tolerancia mínima para otras formas
             }
         }
@@ -164,8 +866,15 @@ pub mod model {
             }
         }
 
-        /// Test de hit contra un punto, considerando transform de la entidad
+        /// Test de hit contra un punto, considerando transform de la entidad.
+        /// El punto se lleva al marco local rotado, así todas las variantes giran con la entidad.
         pub fn hit_test(&self, click_x: f32, click_y: f32, transform: &Transform, shape: &Shape) -> bool {
+            let (click_x, click_y) = transform.unrotate_point(click_x, click_y);
+            self.hit_test_local(click_x, click_y, transform, shape)
+        }
+
+        /// Hit test con el punto ya expresado en el marco local rotado de la entidad
+        fn hit_test_local(&self, click_x: f32, click_y: f32, transform: &Transform, shape: &Shape) -> bool {
             match self {
                 Hitbox::FromShape { tolerance } => {
                     Self::hit_test_shape(click_x, click_y, transform, shape, *tolerance)
@@ -176,13 +885,14 @@ pub mod model {
                     let world_w = w * transform.scale_x;
                     let world_h = h * transform.scale_y;
                     
-                    click_x >= world_x && click_x <= world_x + world_w &&
-                    click_y >= world_y && click_y <= world_y + world_h
+                    // Normalizar: con escala negativa (forma volteada) la caja crece hacia el otro lado
+                    BoundingBox::from_corners(world_x, world_y, world_x + world_w, world_y + world_h)
+                        .contains_point(click_x, click_y)
                 },
                 Hitbox::Circle { x, y, radius } => {
                     let world_x = transform.x + x * transform.scale_x;
                     let world_y = transform.y + y * transform.scale_y;
-                    let world_radius = radius * transform.scale_x.max(transform.scale_y);
+                    let world_radius = radius * transform.scale_x.abs().max(transform.scale_y.abs());
                     
                     let dx = click_x - world_x;
                     let dy = click_y - world_y;
@@ -209,20 +919,46 @@ pub mod model {
                     inside
                 },
                 Hitbox::Multiple(hitboxes) => {
-                    hitboxes.iter().any(|h| h.hit_test(click_x, click_y, transform, shape))
+                    hitboxes.iter().any(|h| h.hit_test_local(click_x, click_y, transform, shape))
                 },
                 Hitbox::None => false,
             }
         }
 
-        /// Hit test directo contra una forma geométrica (método original)
+        /// Hit test directo contra una forma geométrica, con el punto en el marco local rotado
         fn hit_test_shape(click_x: f32, click_y: f32, transform: &Transform, shape: &Shape, tolerance: f32) -> bool {
             match shape {
-                Shape::Rect { w, h } => {
+                Shape::Rect { w, h, radii } if !radii.is_sharp() => {
+                    // Al marco local sin escalar, donde están los centros de las esquinas
+                    let (sx, sy) = (transform.scale_x, transform.scale_y);
+                    if sx == 0.0 || sy == 0.0 {
+                        return false;
+                    }
+                    let (lx, ly) = ((click_x - transform.x) / sx, (click_y - transform.y) / sy);
+                    let (x0, x1, y0, y1) = (w.min(0.0), w.max(0.0), h.min(0.0), h.max(0.0));
+                    if lx < x0 || lx > x1 || ly < y0 || ly > y1 {
+                        return false;
+                    }
+                    let [tl, tr, br, bl] = radii.resolve(*w, *h, transform);
+                    // Cada esquina: centro del arco, radios y hacia dónde queda el vértice. Solo se
+                    // descartan los puntos entre el arco y el vértice.
+                    let corners = [
+                        ((x0 + tl.0, y0 + tl.1), tl, (-1.0, -1.0)),
+                        ((x1 - tr.0, y0 + tr.1), tr, (1.0, -1.0)),
+                        ((x1 - br.0, y1 - br.1), br, (1.0, 1.0)),
+                        ((x0 + bl.0, y1 - bl.1), bl, (-1.0, 1.0)),
+                    ];
+                    corners.iter().all(|((cx, cy), (rx, ry), (ox, oy))| {
+                        let (dx, dy) = (lx - cx, ly - cy);
+                        let in_corner = dx * ox > 0.0 && dy * oy > 0.0;
+                        !in_corner || (dx / rx).powi(2) + (dy / ry).powi(2) <= 1.0
+                    })
+                }
+                Shape::Rect { w, h, .. } | Shape::Image { w, h, .. } => {
                     let world_w = w * transform.scale_x;
                     let world_h = h * transform.scale_y;
-                    click_x >= transform.x && click_x <= transform.x + world_w &&
-                    click_y >= transform.y && click_y <= transform.y + world_h
+                    BoundingBox::from_corners(transform.x, transform.y, transform.x + world_w, transform.y + world_h)
+                        .contains_point(click_x, click_y)
                 }
                 Shape::Ellipse { rx, ry } => {
                     let world_rx = rx * transform.scale_x;
@@ -262,10 +998,78 @@ pub mod model {
                     }
                     inside
                 }
+                Shape::Path { path } => {
+                    // Aplanar con precisión de pantalla aunque la forma esté escalada
+                    let scale = transform.scale_x.abs().max(transform.scale_y.abs()).max(f32::EPSILON);
+                    let mut polylines = path.flatten(PATH_FLATTEN_TOLERANCE / scale);
+                    for polyline in &mut polylines {
+                        for (px, py) in &mut polyline.points {
+                            *px = transform.x + *px * transform.scale_x;
+                            *py = transform.y + *py * transform.scale_y;
+                        }
+                        if polyline.closed {
+                            polyline.points.push(polyline.points[0]);
+                        }
+                    }
+                    // Trazo: cerca de cualquier segmento, abierto o cerrado
+                    let near_stroke = polylines.iter().any(|p| {
+                        p.points.windows(2).any(|w| distance_to_segment(click_x, click_y, w[0], w[1]) <= tolerance)
+                    });
+                    // Relleno: regla nonzero (la de Canvas2D) solo sobre subtrazados cerrados,
+                    // así un trazo a mano alzada abierto no se selecciona por su interior
+                    near_stroke || winding_number(polylines.iter().filter(|p| p.closed), click_x, click_y) != 0
+                }
+                Shape::Text { text } => {
+                    let (w, h) = (text.extent.0 * transform.scale_x, text.extent.1 * transform.scale_y);
+                    BoundingBox::from_corners(transform.x, transform.y, transform.x + w, transform.y + h)
+                        .contains_point(click_x, click_y)
+                }
+                Shape::Arrow { arrow } => {
+                    // Cerca de algún tramo; las puntas caben en la tolerancia
+                    let points: Vec<(f32, f32)> = arrow
+                        .points
+                        .iter()
+                        .map(|(px, py)| (transform.x + px * transform.scale_x, transform.y + py * transform.scale_y))
+                        .collect();
+                    points.windows(2).any(|w| distance_to_segment(click_x, click_y, w[0], w[1]) <= tolerance)
+                }
+                Shape::Freehand { stroke } => {
+                    // Dentro de la tinta (semigrosor local en cada tramo) más la tolerancia
+                    let scale = transform.scale_x.abs().max(transform.scale_y.abs()).max(f32::EPSILON);
+                    let points: Vec<((f32, f32), f32)> = stroke
+                        .samples(PATH_FLATTEN_TOLERANCE / scale)
+                        .into_iter()
+                        .map(|(px, py, w)| ((transform.x + px * transform.scale_x, transform.y + py * transform.scale_y), w * scale / 2.0))
+                        .collect();
+                    match points.as_slice() {
+                        [] => false,
+                        [(p, half)] => distance_to_segment(click_x, click_y, *p, *p) <= half + tolerance,
+                        _ => points.windows(2).any(|w| {
+                            distance_to_segment(click_x, click_y, w[0].0, w[1].0) <= w[0].1.max(w[1].1) + tolerance
+                        }),
+                    }
+                }
             }
         }
     }
 
+    /// Número de giro alrededor de (x, y) de polilíneas cerradas (con el primer punto repetido al final)
+    fn winding_number<'a>(polylines: impl Iterator<Item = &'a Polyline>, x: f32, y: f32) -> i32 {
+        let mut winding = 0;
+        for polyline in polylines {
+            for w in polyline.points.windows(2) {
+                let ((x0, y0), (x1, y1)) = (w[0], w[1]);
+                let side = (x1 - x0) * (y - y0) - (x - x0) * (y1 - y0);
+                if y0 <= y {
+                    if y1 > y && side > 0.0 { winding += 1; }
+                } else if y1 <= y && side < 0.0 {
+                    winding -= 1;
+                }
+            }
+        }
+        winding
+    }
+
     // Scale handles para manipulación de formas
     #[derive(Clone, Copy, Debug, PartialEq)]
     pub enum HandleType {
@@ -279,8 +1083,13 @@ pub mod model {
         Right,
         Bottom,
         Left,
+        // Rotación (flota por encima del borde superior)
+        Rotate,
     }
 
+    /// Distancia del handle de rotación al borde superior, en múltiplos del tamaño de handle
+    pub const ROTATE_HANDLE_OFFSET: f32 = 2.5;
+
     impl HandleType {
         pub fn to_u8(self) -> u8 {
             match self {
@@ -292,6 +1101,22 @@ pub mod model {
                 HandleType::Right => 5,
                 HandleType::Bottom => 6,
                 HandleType::Left => 7,
+                HandleType::Rotate => 8,
+            }
+        }
+
+        /// Posición normalizada (u, v) del handle sobre la caja: (0,0) esquina superior izquierda
+        pub fn box_position(self) -> (f32, f32) {
+            match self {
+                HandleType::TopLeft => (0.0, 0.0),
+                HandleType::TopRight => (1.0, 0.0),
+                HandleType::BottomLeft => (0.0, 1.0),
+                HandleType::BottomRight => (1.0, 1.0),
+                HandleType::Top => (0.5, 0.0),
+                HandleType::Right => (1.0, 0.5),
+                HandleType::Bottom => (0.5, 1.0),
+                HandleType::Left => (0.0, 0.5),
+                HandleType::Rotate => (0.5, 0.0),
             }
         }
     }
@@ -305,7 +1130,7 @@ pub mod model {
     }
 
     // Información de bounding box de una forma
-    #[derive(Clone, Copy, Debug, Default)]
+    #[derive(Clone, Copy, Debug, Default, PartialEq)]
     pub struct BoundingBox {
         pub x: f32,
         pub y: f32,
@@ -346,72 +1171,297 @@ pub mod model {
 
     // Utilidades para calcular bounding boxes
     impl BoundingBox {
+        /// AABB en mundo de la forma, teniendo en cuenta la rotación de la entidad
         pub fn from_shape(transform: &Transform, shape: &Shape) -> Self {
+            if transform.rotation == 0.0 {
+                return Self::local_from_shape(transform, shape);
+            }
             match shape {
-                Shape::Rect { w, h } => BoundingBox {
-                    x: transform.x,
-                    y: transform.y,
-                    width: *w * transform.scale_x,
-                    height: *h * transform.scale_y,
-                },
-                Shape::Ellipse { rx, ry } => BoundingBox {
-                    x: transform.x - rx * transform.scale_x,
-                    y: transform.y - ry * transform.scale_y,
-                    width: 2.0 * rx * transform.scale_x,
-                    height: 2.0 * ry * transform.scale_y,
-                },
-                Shape::Line { x2, y2 } => {
-                    let end_x = transform.x + x2 * transform.scale_x;
-                    let end_y = transform.y + y2 * transform.scale_y;
-                    BoundingBox {
-                        x: transform.x.min(end_x),
-                        y: transform.y.min(end_y),
-                        width: (end_x - transform.x).abs(),
-                        height: (end_y - transform.y).abs(),
-                    }
-                },
-                Shape::Polygon { points } => {
-                    if points.is_empty() {
-                        return BoundingBox::default();
-                    }
-                    let mut min_x = f32::INFINITY;
-                    let mut max_x = f32::NEG_INFINITY;
-                    let mut min_y = f32::INFINITY;
-                    let mut max_y = f32::NEG_INFINITY;
-                    
-                    for (px, py) in points {
-                        let world_x = transform.x + px * transform.scale_x;
-                        let world_y = transform.y + py * transform.scale_y;
-                        min_x = min_x.min(world_x);
-                        max_x = max_x.max(world_x);
-                        min_y = min_y.min(world_y);
-                        max_y = max_y.max(world_y);
-                    }
-                    
+                Shape::Ellipse { rx, ry } => {
+                    // Extensión exacta de una elipse rotada alrededor de su centro
+                    let a = rx * transform.scale_x;
+                    let b = ry * transform.scale_y;
+                    let (sin, cos) = transform.rotation.sin_cos();
+                    let half_w = ((a * cos).powi(2) + (b * sin).powi(2)).sqrt();
+                    let half_h = ((a * sin).powi(2) + (b * cos).powi(2)).sqrt();
                     BoundingBox {
-                        x: min_x,
-                        y: min_y,
-                        width: max_x - min_x,
-                        height: max_y - min_y,
+                        x: transform.x - half_w,
+                        y: transform.y - half_h,
+                        width: 2.0 * half_w,
+                        height: 2.0 * half_h,
                     }
                 }
+                Shape::Polygon { points } | Shape::Arrow { arrow: Arrow { points, .. } } => {
+                    Self::from_points(points.iter().map(|(px, py)| transform.apply(*px, *py)))
+                        .unwrap_or_default()
+                }
+                Shape::Path { path } => {
+                    // Los extremos de una curva rotada no son los rotados: se usa el trazado aplanado
+                    let scale = transform.scale_x.abs().max(transform.scale_y.abs()).max(f32::EPSILON);
+                    let polylines = path.flatten(PATH_FLATTEN_TOLERANCE / scale);
+                    Self::from_points(polylines.iter().flat_map(|p| p.points.iter()).map(|(px, py)| transform.apply(*px, *py)))
+                        .unwrap_or_default()
+                }
+                Shape::Freehand { stroke } => {
+                    let scale = transform.scale_x.abs().max(transform.scale_y.abs()).max(f32::EPSILON);
+                    let polylines = stroke.outline(PATH_FLATTEN_TOLERANCE / scale).flatten(PATH_FLATTEN_TOLERANCE / scale);
+                    Self::from_points(polylines.iter().flat_map(|p| p.points.iter()).map(|(px, py)| transform.apply(*px, *py)))
+                        .unwrap_or_default()
+                }
+                _ => OrientedBoundingBox::from_shape(transform, shape).aabb(),
+            }
+        }
+
+        /// Caja de la forma en el marco local rotado de la entidad (es decir, ignorando `rotation`).
+        /// Siempre normalizada: width/height no negativos aunque la escala lo sea.
+        pub fn local_from_shape(transform: &Transform, shape: &Shape) -> Self {
+            let (x, y) = (transform.x, transform.y);
+            let (sx, sy) = (transform.scale_x, transform.scale_y);
+            match shape {
+                Shape::Rect { w, h, .. } | Shape::Image { w, h, .. } => Self::from_corners(x, y, x + w * sx, y + h * sy),
+                Shape::Ellipse { rx, ry } => Self::from_corners(x - rx * sx, y - ry * sy, x + rx * sx, y + ry * sy),
+                Shape::Line { x2, y2 } => Self::from_corners(x, y, x + x2 * sx, y + y2 * sy),
+                Shape::Polygon { points } | Shape::Arrow { arrow: Arrow { points, .. } } => {
+                    Self::from_points(points.iter().map(|(px, py)| (x + px * sx, y + py * sy)))
+                        .unwrap_or_default()
+                }
+                Shape::Path { path } => match path.bounds() {
+                    Some(b) => Self::from_corners(x + b.x * sx, y + b.y * sy, x + (b.x + b.width) * sx, y + (b.y + b.height) * sy),
+                    None => Self::from_corners(x, y, x, y),
+                },
+                Shape::Freehand { stroke } => match stroke.bounds() {
+                    Some(b) => Self::from_corners(x + b.x * sx, y + b.y * sy, x + (b.x + b.width) * sx, y + (b.y + b.height) * sy),
+                    None => Self::from_corners(x, y, x, y),
+                },
+                Shape::Text { text } => Self::from_corners(x, y, x + text.extent.0 * sx, y + text.extent.1 * sy),
+            }
+        }
+
+        /// Caja normalizada a partir de dos esquinas opuestas cualesquiera
+        pub fn from_corners(x0: f32, y0: f32, x1: f32, y1: f32) -> Self {
+            BoundingBox {
+                x: x0.min(x1),
+                y: y0.min(y1),
+                width: (x1 - x0).abs(),
+                height: (y1 - y0).abs(),
+            }
+        }
+
+        /// Caja mínima que contiene todos los puntos (None si no hay puntos)
+        pub fn from_points(points: impl IntoIterator<Item = (f32, f32)>) -> Option<Self> {
+            let mut min_x = f32::INFINITY;
+            let mut max_x = f32::NEG_INFINITY;
+            let mut min_y = f32::INFINITY;
+            let mut max_y = f32::NEG_INFINITY;
+
+            for (px, py) in points {
+                min_x = min_x.min(px);
+                max_x = max_x.max(px);
+                min_y = min_y.min(py);
+                max_y = max_y.max(py);
+            }
+
+            if min_x > max_x {
+                return None;
+            }
+            Some(BoundingBox {
+                x: min_x,
+                y: min_y,
+                width: max_x - min_x,
+                height: max_y - min_y,
+            })
+        }
+
+        /// Caja que contiene a ambas
+        pub fn union(&self, other: &BoundingBox) -> Self {
+            Self::from_points([
+                (self.x, self.y),
+                (self.x + self.width, self.y + self.height),
+                (other.x, other.y),
+                (other.x + other.width, other.y + other.height),
+            ])
+            .unwrap_or(*self)
+        }
+
+        pub fn contains_point(&self, x: f32, y: f32) -> bool {
+            x >= self.x && x <= self.x + self.width && y >= self.y && y <= self.y + self.height
+        }
+
+        /// Las cajas se solapan (tocarse en el borde cuenta)
+        pub fn intersects(&self, other: &BoundingBox) -> bool {
+            self.x <= other.x + other.width
+                && other.x <= self.x + self.width
+                && self.y <= other.y + other.height
+                && other.y <= self.y + self.height
+        }
+
+        /// Caja agrandada `margin` por cada lado
+        pub fn inflated(&self, margin: f32) -> Self {
+            Self { x: self.x - margin, y: self.y - margin, width: self.width + 2.0 * margin, height: self.height + 2.0 * margin }
+        }
+
+        /// `other` queda completamente dentro de esta caja
+        pub fn contains_box(&self, other: &BoundingBox) -> bool {
+            other.x >= self.x
+                && other.y >= self.y
+                && other.x + other.width <= self.x + self.width
+                && other.y + other.height <= self.y + self.height
+        }
+
+        pub fn center(&self) -> (f32, f32) {
+            (self.x + self.width / 2.0, self.y + self.height / 2.0)
+        }
+
+        pub fn generate_handles(&self, handle_size: f32) -> Vec<ScaleHandle> {
+            OrientedBoundingBox::from(*self).generate_handles(handle_size)
+        }
+    }
+
+    /// Caja orientada: la caja local de una entidad más la rotación alrededor de su origen.
+    /// Es lo que usa el chrome de selección (contorno y handles) para seguir a formas rotadas.
+    #[derive(Clone, Copy, Debug, Default)]
+    pub struct OrientedBoundingBox {
+        /// Caja en el marco local rotado (coordenadas de mundo si la rotación fuese 0)
+        pub local: BoundingBox,
+        /// Pivote de la rotación (origen de la entidad)
+        pub pivot_x: f32,
+        pub pivot_y: f32,
+        pub rotation: f32,
+    }
+
+    impl From<BoundingBox> for OrientedBoundingBox {
+        fn from(local: BoundingBox) -> Self {
+            Self { local, pivot_x: local.x, pivot_y: local.y, rotation: 0.0 }
+        }
+    }
+
+    impl OrientedBoundingBox {
+        pub fn from_shape(transform: &Transform, shape: &Shape) -> Self {
+            Self {
+                local: BoundingBox::local_from_shape(transform, shape),
+                pivot_x: transform.x,
+                pivot_y: transform.y,
+                rotation: transform.rotation,
+            }
+        }
+
+        /// Punto de mundo para coordenadas normalizadas (u, v) de la caja local: (0,0) es TopLeft, (1,1) BottomRight
+        pub fn point_at(&self, u: f32, v: f32) -> (f32, f32) {
+            let x = self.local.x + u * self.local.width;
+            let y = self.local.y + v * self.local.height;
+            rotate_around(x, y, self.pivot_x, self.pivot_y, self.rotation)
+        }
+
+        pub fn center(&self) -> (f32, f32) {
+            self.point_at(0.5, 0.5)
+        }
+
+        /// Esquinas en mundo en orden TopLeft, TopRight, BottomRight, BottomLeft
+        pub fn corners(&self) -> [(f32, f32); 4] {
+            [self.point_at(0.0, 0.0), self.point_at(1.0, 0.0), self.point_at(1.0, 1.0), self.point_at(0.0, 1.0)]
+        }
+
+        /// AABB en mundo que contiene la caja orientada
+        pub fn aabb(&self) -> BoundingBox {
+            BoundingBox::from_points(self.corners()).unwrap_or_default()
+        }
+
+        /// Lleva un punto de mundo al marco local de la caja
+        pub fn to_local(&self, x: f32, y: f32) -> (f32, f32) {
+            rotate_around(x, y, self.pivot_x, self.pivot_y, -self.rotation)
+        }
+
+        pub fn contains_point(&self, x: f32, y: f32) -> bool {
+            let (lx, ly) = self.to_local(x, y);
+            self.local.contains_point(lx, ly)
+        }
+
+        /// Intersección con una caja alineada a los ejes (teorema del eje separador)
+        pub fn intersects_box(&self, other: &BoundingBox) -> bool {
+            let corners = self.corners();
+            let other_corners = [
+                (other.x, other.y),
+                (other.x + other.width, other.y),
+                (other.x + other.width, other.y + other.height),
+                (other.x, other.y + other.height),
+            ];
+            let (sin, cos) = self.rotation.sin_cos();
+            // Ejes candidatos: los de la caja alineada y los de la caja orientada
+            let axes = [(1.0, 0.0), (0.0, 1.0), (cos, sin), (-sin, cos)];
+            axes.iter().all(|&(ax, ay)| {
+                let project = |points: &[(f32, f32)]| {
+                    points.iter().fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), (px, py)| {
+                        let d = px * ax + py * ay;
+                        (min.min(d), max.max(d))
+                    })
+                };
+                let (min_a, max_a) = project(&corners);
+                let (min_b, max_b) = project(&other_corners);
+                max_a >= min_b && max_b >= min_a
+            })
+        }
+
+        /// Misma caja con todas sus coordenadas multiplicadas (p.ej. CSS px -> píxeles físicos)
+        pub fn scaled(&self, factor: f32) -> Self {
+            Self {
+                local: BoundingBox {
+                    x: self.local.x * factor,
+                    y: self.local.y * factor,
+                    width: self.local.width * factor,
+                    height: self.local.height * factor,
+                },
+                pivot_x: self.pivot_x * factor,
+                pivot_y: self.pivot_y * factor,
+                rotation: self.rotation,
+            }
+        }
+
+        /// Misma caja desplazada (dx, dy)
+        pub fn translated(&self, dx: f32, dy: f32) -> Self {
+            Self {
+                local: BoundingBox { x: self.local.x + dx, y: self.local.y + dy, ..self.local },
+                pivot_x: self.pivot_x + dx,
+                pivot_y: self.pivot_y + dy,
+                rotation: self.rotation,
             }
         }
 
+        /// Handles de escala (más el de rotación) colocados sobre la caja rotada
         pub fn generate_handles(&self, handle_size: f32) -> Vec<ScaleHandle> {
             let half_size = handle_size / 2.0;
-            vec![
+            [
                 // Esquinas
-                ScaleHandle { handle_type: HandleType::TopLeft, x: self.x - half_size, y: self.y - half_size, size: handle_size },
-                ScaleHandle { handle_type: HandleType::TopRight, x: self.x + self.width - half_size, y: self.y - half_size, size: handle_size },
-                ScaleHandle { handle_type: HandleType::BottomLeft, x: self.x - half_size, y: self.y + self.height - half_size, size: handle_size },
-                ScaleHandle { handle_type: HandleType::BottomRight, x: self.x + self.width - half_size, y: self.y + self.height - half_size, size: handle_size },
+                HandleType::TopLeft,
+                HandleType::TopRight,
+                HandleType::BottomLeft,
+                HandleType::BottomRight,
                 // Bordes
-                ScaleHandle { handle_type: HandleType::Top, x: self.x + self.width / 2.0 - half_size, y: self.y - half_size, size: handle_size },
-                ScaleHandle { handle_type: HandleType::Right, x: self.x + self.width - half_size, y: self.y + self.height / 2.0 - half_size, size: handle_size },
-                ScaleHandle { handle_type: HandleType::Bottom, x: self.x + self.width / 2.0 - half_size, y: self.y + self.height - half_size, size: handle_size },
-                ScaleHandle { handle_type: HandleType::Left, x: self.x - half_size, y: self.y + self.height / 2.0 - half_size, size: handle_size },
+                HandleType::Top,
+                HandleType::Right,
+                HandleType::Bottom,
+                HandleType::Left,
             ]
+            .into_iter()
+            .map(|handle_type| {
+                let (u, v) = handle_type.box_position();
+                let (cx, cy) = self.point_at(u, v);
+                ScaleHandle { handle_type, x: cx - half_size, y: cy - half_size, size: handle_size }
+            })
+            .chain(std::iter::once(self.rotate_handle(handle_size)))
+            .collect()
+        }
+
+        /// Handle de rotación: por encima del centro del borde superior, en la dirección "arriba" de la caja
+        pub fn rotate_handle(&self, handle_size: f32) -> ScaleHandle {
+            let half_size = handle_size / 2.0;
+            let (cx, cy) = rotate_around(
+                self.local.x + self.local.width / 2.0,
+                self.local.y - handle_size * ROTATE_HANDLE_OFFSET,
+                self.pivot_x,
+                self.pivot_y,
+                self.rotation,
+            );
+            ScaleHandle { handle_type: HandleType::Rotate, x: cx - half_size, y: cy - half_size, size: handle_size }
         }
     }
 }
@@ -436,7 +1486,9 @@ pub mod ports {
     pub trait RenderPort {
         fn begin_frame(&mut self, width: u32, height: u32) -> Result<(), RenderError>;
         fn end_frame(&mut self) -> Result<(), RenderError>;
-        fn set_camera(&mut self, transform_2d: [f32; 6]) -> Result<(), RenderError>; // 2D affine
+        /// Afín 2D [a, b, c, d, e, f] de mundo a píxeles físicos. Se aplica a los dibujos siguientes,
+        /// también dentro de un frame ya empezado (p.ej. overlays en coordenadas de pantalla).
+        fn set_camera(&mut self, transform_2d: [f32; 6]) -> Result<(), RenderError>;
 
         fn draw_shape(
             &mut self,
@@ -498,58 +1550,411 @@ pub mod ports {
     }
 }
 
+pub mod arrow;
+pub mod freehand;
+pub mod image;
+pub mod paint;
+pub mod routing;
+pub mod sketch;
+pub mod storage;
+pub mod svg;
+pub mod text;
+
 pub mod usecases {
-    use super::model::{EntityId, Shape, Style, Transform, Hitbox};
+    use super::model::{ArrowBinding, ArrowBindings, BlobId, EntityId, Paint, Shape, Style, TextBox, Transform, Hitbox};
+    use super::ports::StorageError;
+    use base64::{engine::general_purpose::STANDARD, Engine as _};
     use serde::{Deserialize, Serialize};
+    use std::collections::{BTreeMap, HashMap};
+    use thiserror::Error;
+
+    /// Identificador del formato JSON abierto de documentos
+    pub const DOCUMENT_SCHEMA: &str = "hodei-momentum/document";
+    /// Versión actual del esquema; los documentos sin cabecera se tratan como versión 0. Sube con
+    /// cada cambio del formato y las anteriores se siguen cargando (los campos nuevos tienen valor
+    /// por defecto y un color suelto sigue siendo pintura sólida):
+    /// - 1: rectángulos, elipses, líneas y polígonos con estilo de color y hitboxes.
+    /// - 2: trazados, lápiz, texto, imágenes y flechas; etiquetas y enganches de flechas; estilo a
+    ///   mano alzada; `Paint` en rellenos y trazos; radios de las esquinas de los rectángulos.
+    /// - 3: bytes de las imágenes en `blobs` (base64 por id de blob), junto al documento.
+    pub const DOCUMENT_SCHEMA_VERSION: u32 = 3;
 
+    #[derive(Debug, Error)]
+    pub enum DocumentFormatError {
+        #[error("Invalid document JSON: {0}")] Json(#[from] serde_json::Error),
+        #[error("Unknown document schema: {0}")] UnknownSchema(String),
+        #[error("Unsupported document version {found} (newest supported: {supported})")]
+        UnsupportedVersion { found: u32, supported: u32 },
+        #[error("Duplicate entity id {0}")] DuplicateEntity(u64),
+        #[error("Arrow binding refers to unknown entity {0}")] UnknownEntity(u64),
+        #[error("Invalid image blob {0}")] InvalidBlob(u64),
+        #[error("Storage: {0}")] Storage(#[from] StorageError),
+    }
+
+    /// Entidad del documento: geometría, estilo, hitbox, etiqueta y enganches opcionales
+    #[derive(Clone, Debug)]
+    pub struct Entity {
+        pub id: EntityId,
+        pub transform: Transform,
+        pub style: Style,
+        pub shape: Shape,
+        /// None significa usar shape como hitbox
+        pub hitbox: Option<Hitbox>,
+        /// Texto centrado dentro de la forma (ver `text::label_layout`)
+        pub label: Option<TextBox>,
+        /// Entidades a las que van enganchados los extremos de una flecha (ver `arrow::update_bindings`)
+        pub bindings: ArrowBindings,
+    }
+
+    impl Entity {
+        pub fn new(id: EntityId, transform: Transform, style: Style, shape: Shape) -> Self {
+            Self { id, transform, style, shape, hitbox: None, label: None, bindings: ArrowBindings::default() }
+        }
+    }
+
+    /// Documento indexado por id. Las entidades viven en slots ordenados por z (orden de pintado);
+    /// borrar deja un hueco O(1) y los huecos se compactan de forma amortizada.
     #[derive(Clone, Debug, Default, Serialize, Deserialize)]
+    #[serde(try_from = "DocumentRepr", into = "DocumentRepr")]
     pub struct Document {
-        pub entities: Vec<(EntityId, Transform, Style, Shape)>,
-        /// Hitboxes opcionales para cada entidad - None significa usar shape como hitbox
+        slots: Vec<Option<Entity>>,
+        /// id -> posición en `slots`
+        index: HashMap<EntityId, usize>,
+        next_id: u64,
+    }
+
+    /// Formato serializado (compatible con el JSON existente: listas de tuplas)
+    #[derive(Serialize, Deserialize)]
+    struct DocumentRepr {
+        entities: Vec<(EntityId, Transform, Style, Shape)>,
+        #[serde(default)]
+        hitboxes: Vec<(EntityId, Hitbox)>,
+        #[serde(default)]
+        labels: Vec<(EntityId, TextBox)>,
+        #[serde(default)]
+        bindings: Vec<(EntityId, ArrowBindings)>,
         #[serde(default)]
-        pub hitboxes: Vec<(EntityId, Hitbox)>,
         next_id: u64,
     }
 
+    impl TryFrom<DocumentRepr> for Document {
+        type Error = DocumentFormatError;
+
+        fn try_from(repr: DocumentRepr) -> Result<Self, Self::Error> {
+            let mut doc = Document::new();
+            for (id, transform, style, shape) in repr.entities {
+                if doc.contains(id) {
+                    return Err(DocumentFormatError::DuplicateEntity(id.0));
+                }
+                doc.insert(Entity::new(id, transform, style, shape));
+            }
+            // Hitboxes y etiquetas de entidades que no están se ignoran, como antes de guardarlas en la entidad
+            for (id, hitbox) in repr.hitboxes {
+                doc.set_hitbox(id, hitbox);
+            }
+            for (id, label) in repr.labels {
+                if let Some(entity) = doc.get_mut(id) {
+                    entity.label = Some(label);
+                }
+            }
+            for (id, bindings) in repr.bindings {
+                if let Some(target) = [bindings.start, bindings.end].into_iter().flatten().find(|b| !doc.contains(b.target)) {
+                    return Err(DocumentFormatError::UnknownEntity(target.target.0));
+                }
+                let entity = doc.get_mut(id).ok_or(DocumentFormatError::UnknownEntity(id.0))?;
+                entity.bindings = bindings;
+            }
+            doc.next_id = doc.next_id.max(repr.next_id);
+            Ok(doc)
+        }
+    }
+
+    /// Cabecera del formato: se lee antes que el documento para rechazar versiones futuras con un error claro
+    #[derive(Deserialize)]
+    struct EnvelopeHeader {
+        schema: Option<String>,
+        version: Option<u32>,
+    }
+
+    #[derive(Serialize)]
+    struct EnvelopeOut<'a> {
+        schema: &'static str,
+        version: u32,
+        document: &'a Document,
+        #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+        blobs: BTreeMap<u64, String>,
+    }
+
+    #[derive(Deserialize)]
+    struct EnvelopeIn {
+        document: Document,
+        #[serde(default)]
+        blobs: BTreeMap<u64, String>,
+    }
+
+    impl From<Document> for DocumentRepr {
+        fn from(doc: Document) -> Self {
+            let mut entities = Vec::with_capacity(doc.count());
+            let mut hitboxes = Vec::new();
+            let mut labels = Vec::new();
+            let mut bindings = Vec::new();
+            // Un enganche a una entidad borrada (p.ej. hasta deshacer el borrado) no se guarda
+            let exists = |binding: &Option<ArrowBinding>| binding.filter(|b| doc.contains(b.target));
+            for entity in doc.slots.iter().flatten() {
+                let kept = ArrowBindings { start: exists(&entity.bindings.start), end: exists(&entity.bindings.end) };
+                if !kept.is_empty() {
+                    bindings.push((entity.id, kept));
+                }
+            }
+            for entity in doc.slots.into_iter().flatten() {
+                if let Some(hitbox) = entity.hitbox {
+                    hitboxes.push((entity.id, hitbox));
+                }
+                if let Some(label) = entity.label {
+                    labels.push((entity.id, label));
+                }
+                entities.push((entity.id, entity.transform, entity.style, entity.shape));
+            }
+            DocumentRepr { entities, hitboxes, labels, bindings, next_id: doc.next_id }
+        }
+    }
+
     impl Document {
         pub fn new() -> Self { 
             Self { 
-                entities: Vec::new(), 
-                hitboxes: Vec::new(),
+                slots: Vec::new(), 
+                index: HashMap::new(),
                 next_id: 1 
             } 
         }
         
         pub fn create_shape(&mut self, transform: Transform, style: Style, shape: Shape) -> EntityId {
             let id = EntityId(self.next_id);
-            self.next_id += 1;
-            self.entities.push((id, transform, style, shape));
+            self.insert(Entity::new(id, transform, style, shape));
             id
         }
         
         pub fn create_shape_with_hitbox(&mut self, transform: Transform, style: Style, shape: Shape, hitbox: Hitbox) -> EntityId {
             let id = self.create_shape(transform, style, shape);
-            self.hitboxes.push((id, hitbox));
+            self.set_hitbox(id, hitbox);
             id
         }
+
+        /// Añade una entidad (con su id) encima de todas. Si el id ya existe, la reemplaza en su sitio.
+        pub fn insert(&mut self, entity: Entity) {
+            self.next_id = self.next_id.max(entity.id.0 + 1);
+            if let Some(&slot) = self.index.get(&entity.id) {
+                self.slots[slot] = Some(entity);
+                return;
+            }
+            self.index.insert(entity.id, self.slots.len());
+            self.slots.push(Some(entity));
+        }
+
+        /// Inserta una entidad en la posición `z` del orden de pintado (0 = la de más abajo)
+        pub fn insert_at(&mut self, z: usize, entity: Entity) {
+            self.remove(entity.id);
+            self.compact();
+            self.next_id = self.next_id.max(entity.id.0 + 1);
+            let z = z.min(self.slots.len());
+            self.slots.insert(z, Some(entity));
+            self.reindex_from(z);
+        }
+
+        /// Como `insert_at` con varias entidades a la vez (p.ej. al deshacer un borrado), pero
+        /// compactando, mezclando y reindexando una sola vez. Cada entidad queda en su posición `z`
+        /// del orden resultante, como si se insertaran una a una de menor a mayor `z`.
+        pub fn insert_many(&mut self, mut entities: Vec<(usize, Entity)>) {
+            if entities.is_empty() { return; }
+            for (_, entity) in &entities {
+                self.remove(entity.id);
+            }
+            self.compact();
+            entities.sort_by_key(|(z, _)| *z);
+            let start = entities[0].0.min(self.slots.len());
+            let mut rest = self.slots.split_off(start).into_iter();
+            for (z, entity) in entities {
+                if self.index.contains_key(&entity.id) { continue; }
+                while self.slots.len() < z {
+                    let Some(below) = rest.next() else { break; };
+                    self.slots.push(below);
+                }
+                self.next_id = self.next_id.max(entity.id.0 + 1);
+                self.index.insert(entity.id, self.slots.len());
+                self.slots.push(Some(entity));
+            }
+            self.slots.extend(rest);
+            self.reindex_from(start);
+        }
+
+        pub fn get(&self, id: EntityId) -> Option<&Entity> {
+            self.index.get(&id).and_then(|&slot| self.slots[slot].as_ref())
+        }
+
+        pub fn get_mut(&mut self, id: EntityId) -> Option<&mut Entity> {
+            self.index.get(&id).and_then(|&slot| self.slots[slot].as_mut())
+        }
+
+        pub fn contains(&self, id: EntityId) -> bool {
+            self.index.contains_key(&id)
+        }
+
+        /// Elimina la entidad en O(1) (amortizado) y la devuelve
+        pub fn remove(&mut self, id: EntityId) -> Option<Entity> {
+            let slot = self.index.remove(&id)?;
+            let entity = self.slots[slot].take();
+            // Compactar cuando los huecos dominan, para que iterar siga siendo proporcional a las entidades vivas
+            let holes = self.slots.len() - self.index.len();
+            if holes > 32 && holes > self.index.len() {
+                self.compact();
+            }
+            entity
+        }
+
+        /// Posición de la entidad en el orden de pintado (0 = la de más abajo). Es O(1) sin huecos
+        /// (ver `compact`); con huecos cuenta las entidades de debajo.
+        pub fn z_index(&self, id: EntityId) -> Option<usize> {
+            let slot = *self.index.get(&id)?;
+            if self.slots.len() == self.index.len() {
+                return Some(slot);
+            }
+            Some(self.slots[..slot].iter().filter(|e| e.is_some()).count())
+        }
+
+        /// Reordena el pintado según `order` (de abajo a arriba). Los ids desconocidos se ignoran
+        /// y las entidades que no aparecen quedan encima, en su orden actual.
+        pub fn reorder(&mut self, order: &[EntityId]) {
+            let mut slots = Vec::with_capacity(self.index.len());
+            for id in order {
+                if let Some(slot) = self.index.get(id) {
+                    if let Some(entity) = self.slots[*slot].take() {
+                        slots.push(Some(entity));
+                    }
+                }
+            }
+            slots.extend(self.slots.drain(..).filter(|e| e.is_some()));
+            self.slots = slots;
+            self.reindex_from(0);
+        }
+
+        /// Entidades en orden de pintado (de abajo a arriba)
+        pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Entity> + '_ {
+            self.slots.iter().flatten()
+        }
+
+        pub fn iter_mut(&mut self) -> impl DoubleEndedIterator<Item = &mut Entity> + '_ {
+            self.slots.iter_mut().flatten()
+        }
+
+        /// Ids en orden de pintado
+        pub fn ids(&self) -> impl DoubleEndedIterator<Item = EntityId> + '_ {
+            self.iter().map(|e| e.id)
+        }
         
         pub fn set_hitbox(&mut self, entity_id: EntityId, hitbox: Hitbox) {
-            // Remover hitbox existente si lo hay
-            self.hitboxes.retain(|(id, _)| *id != entity_id);
-            self.hitboxes.push((entity_id, hitbox));
+            if let Some(entity) = self.get_mut(entity_id) {
+                entity.hitbox = Some(hitbox);
+            }
         }
         
         pub fn get_hitbox(&self, entity_id: EntityId) -> Option<&Hitbox> {
-            self.hitboxes.iter()
-                .find(|(id, _)| *id == entity_id)
-                .map(|(_, hitbox)| hitbox)
+            self.get(entity_id).and_then(|e| e.hitbox.as_ref())
         }
         
         pub fn remove_hitbox(&mut self, entity_id: EntityId) {
-            self.hitboxes.retain(|(id, _)| *id != entity_id);
+            if let Some(entity) = self.get_mut(entity_id) {
+                entity.hitbox = None;
+            }
         }
         
-        pub fn count(&self) -> usize { self.entities.len() }
+        pub fn count(&self) -> usize { self.index.len() }
+
+        /// Blobs de las imágenes a los que hace referencia el documento (formas imagen y pinturas de
+        /// mosaico), sin repetir y en orden de pintado
+        pub fn image_blobs(&self) -> Vec<BlobId> {
+            let mut blobs = Vec::new();
+            for entity in self.iter() {
+                let paints = [&entity.style.fill, &entity.style.stroke];
+                let painted = paints.into_iter().flatten().filter_map(|paint| match paint {
+                    Paint::Image { blob, .. } => Some(*blob),
+                    _ => None,
+                });
+                let shape = match entity.shape {
+                    Shape::Image { blob, .. } => Some(blob),
+                    _ => None,
+                };
+                for blob in shape.into_iter().chain(painted) {
+                    if !blobs.contains(&blob) {
+                        blobs.push(blob);
+                    }
+                }
+            }
+            blobs
+        }
+
+        /// Serializa el documento completo con la cabecera de esquema y versión, sin imágenes
+        /// (ver `to_json_with_images`)
+        pub fn to_json(&self) -> Result<String, DocumentFormatError> {
+            self.to_json_with_images(&HashMap::new())
+        }
+
+        /// Como `to_json`, con los bytes de las imágenes del documento incrustados en `blobs`, para
+        /// que el JSON se pueda abrir en otra sesión. Solo se guardan los blobs que se usan.
+        pub fn to_json_with_images(&self, images: &HashMap<BlobId, Vec<u8>>) -> Result<String, DocumentFormatError> {
+            let blobs = self
+                .image_blobs()
+                .into_iter()
+                .filter_map(|blob| Some((blob.0, STANDARD.encode(images.get(&blob)?))))
+                .collect();
+            let envelope = EnvelopeOut { schema: DOCUMENT_SCHEMA, version: DOCUMENT_SCHEMA_VERSION, document: self, blobs };
+            Ok(serde_json::to_string(&envelope)?)
+        }
+
+        /// Carga un documento exportado con `to_json`. También acepta el JSON sin cabecera (versión 0).
+        /// Las imágenes incrustadas se descartan; ver `from_json_with_images`.
+        pub fn from_json(json: &str) -> Result<Self, DocumentFormatError> {
+            Self::from_json_with_images(json).map(|(doc, _)| doc)
+        }
+
+        /// Carga un documento y los bytes de sus imágenes incrustadas, por blob. Cada blob debe
+        /// coincidir con el id que sale de su contenido (`image::blob_id`).
+        pub fn from_json_with_images(json: &str) -> Result<(Self, HashMap<BlobId, Vec<u8>>), DocumentFormatError> {
+            let header: EnvelopeHeader = serde_json::from_str(json)?;
+            let envelope = match (header.schema, header.version) {
+                // Documento serializado directamente, anterior a la cabecera
+                (None, None) => return Ok((serde_json::from_str(json)?, HashMap::new())),
+                (Some(schema), _) if schema != DOCUMENT_SCHEMA => return Err(DocumentFormatError::UnknownSchema(schema)),
+                (_, Some(version)) if version > DOCUMENT_SCHEMA_VERSION => return Err(DocumentFormatError::UnsupportedVersion {
+                    found: version,
+                    supported: DOCUMENT_SCHEMA_VERSION,
+                }),
+                _ => serde_json::from_str::<EnvelopeIn>(json)?,
+            };
+            let mut images = HashMap::with_capacity(envelope.blobs.len());
+            for (id, data) in envelope.blobs {
+                let data = STANDARD.decode(data).map_err(|_| DocumentFormatError::InvalidBlob(id))?;
+                if crate::image::blob_id(&data) != BlobId(id) {
+                    return Err(DocumentFormatError::InvalidBlob(id));
+                }
+                images.insert(BlobId(id), data);
+            }
+            Ok((envelope.document, images))
+        }
+
+        /// Elimina los huecos dejados por `remove`, conservando el orden
+        pub fn compact(&mut self) {
+            if self.slots.len() == self.index.len() { return; }
+            self.slots.retain(|e| e.is_some());
+            self.reindex_from(0);
+        }
+
+        fn reindex_from(&mut self, start: usize) {
+            for (slot, entity) in self.slots.iter().enumerate().skip(start) {
+                if let Some(entity) = entity {
+                    self.index.insert(entity.id, slot);
+                }
+            }
+        }
     }
 }
 
@@ -0,0 +1,195 @@
+//! Pinturas de relleno y de trazo (`Paint`) comunes a todos los renderers y al exportador SVG.
+//!
+//! Los degradados y los mosaicos de imagen los pinta cada backend con sus propias primitivas, o
+//! color a color con `color_at` donde no las hay. El sombreado, en cambio, son líneas rectas que
+//! se generan aquí y se trazan con `draw_path`, así que sale idéntico en todas partes.
+
+use std::f32::consts::FRAC_PI_2;
+
+use crate::model::{Color, GradientStop, Paint, Path, PathCommand, Polyline, Shape, Style, Transform, PATH_FLATTEN_TOLERANCE};
+use crate::ports::{RenderError, RenderPort};
+
+/// Líneas de sombreado como mucho por dirección; en formas enormes la separación crece
+const MAX_HATCH_LINES: f32 = 2000.0;
+/// Separación mínima del sombreado, en unidades locales
+const MIN_HATCH_GAP: f32 = 0.5;
+
+/// Contorno exacto de las formas geométricas (rectángulo, elipse, línea, polígono y trazado), en
+/// sus coordenadas locales; None en las que no tienen uno (lápiz, texto, imágenes y flechas).
+/// `transform` solo cuenta para los radios de las esquinas, ver `CornerRadii::resolve`.
+pub fn shape_outline(shape: &Shape, transform: &Transform) -> Option<Path> {
+    if let Some(rounded) = rounded_outline(shape, transform) {
+        return Some(rounded);
+    }
+    let polygon = |points: &[(f32, f32)]| {
+        let mut commands: Vec<PathCommand> = points.iter().map(|(x, y)| PathCommand::LineTo(*x, *y)).collect();
+        let (x, y) = points.first()?;
+        commands[0] = PathCommand::MoveTo(*x, *y);
+        commands.push(PathCommand::Close);
+        Some(Path { commands })
+    };
+    match shape {
+        Shape::Rect { w, h, .. } => polygon(&[(0.0, 0.0), (*w, 0.0), (*w, *h), (0.0, *h)]),
+        Shape::Polygon { points } => polygon(points),
+        Shape::Line { x2, y2 } => Some(Path { commands: vec![PathCommand::MoveTo(0.0, 0.0), PathCommand::LineTo(*x2, *y2)] }),
+        Shape::Ellipse { rx, ry } => {
+            // Cuatro cúbicas centradas en el origen de la entidad
+            let (rx, ry) = (rx.abs(), ry.abs());
+            let (kx, ky) = (0.552_284_8 * rx, 0.552_284_8 * ry);
+            Some(Path {
+                commands: vec![
+                    PathCommand::MoveTo(rx, 0.0),
+                    PathCommand::CubicTo { c1x: rx, c1y: ky, c2x: kx, c2y: ry, x: 0.0, y: ry },
+                    PathCommand::CubicTo { c1x: -kx, c1y: ry, c2x: -rx, c2y: ky, x: -rx, y: 0.0 },
+                    PathCommand::CubicTo { c1x: -rx, c1y: -ky, c2x: -kx, c2y: -ry, x: 0.0, y: -ry },
+                    PathCommand::CubicTo { c1x: kx, c1y: -ry, c2x: rx, c2y: -ky, x: rx, y: 0.0 },
+                    PathCommand::Close,
+                ],
+            })
+        }
+        Shape::Path { path } => (!path.commands.is_empty()).then(|| path.clone()),
+        Shape::Freehand { .. } | Shape::Text { .. } | Shape::Image { .. } | Shape::Arrow { .. } => None,
+    }
+}
+
+/// Contorno de un rectángulo con las esquinas redondeadas tal como se dibuja con `transform`; None
+/// en las demás formas y en los rectángulos de esquinas vivas. Los renderers lo pintan con `draw_path`.
+pub fn rounded_outline(shape: &Shape, transform: &Transform) -> Option<Path> {
+    match shape {
+        Shape::Rect { w, h, radii } if !radii.is_sharp() => Some(Path::rounded_rect(*w, *h, radii.resolve(*w, *h, transform))),
+        _ => None,
+    }
+}
+
+/// Contorno de `shape` si su relleno es un sombreado: los renderers lo pintan entonces con
+/// `draw_path` (que pasa por `draw_hatched`). Las líneas no se rellenan.
+pub fn hatched_outline(shape: &Shape, style: &Style, transform: &Transform) -> Option<Path> {
+    if !matches!(style.fill, Some(Paint::Hatch { .. })) || matches!(shape, Shape::Line { .. }) {
+        return None;
+    }
+    shape_outline(shape, transform)
+}
+
+/// Líneas del sombreado de relleno de `style` dentro de `path`, con el estilo con el que se trazan;
+/// None si el relleno no es un sombreado
+pub fn hatch_fill(path: &Path, style: &Style) -> Option<(Path, Style)> {
+    let Some(Paint::Hatch { color, gap, angle, width, cross }) = style.fill else { return None };
+    let polygons = path.flatten(PATH_FLATTEN_TOLERANCE);
+    let mut lines = Path::default();
+    let mut push = |angle: f32| {
+        for (a, b) in hatch_segments(&polygons, angle, gap) {
+            lines.commands.push(PathCommand::MoveTo(a.0, a.1));
+            lines.commands.push(PathCommand::LineTo(b.0, b.1));
+        }
+    };
+    push(angle);
+    if cross {
+        push(angle + FRAC_PI_2);
+    }
+    let line_style = Style { stroke: Some(Paint::Solid(color)), stroke_width: width, opacity: style.opacity, ..Style::default() };
+    Some((lines, line_style))
+}
+
+/// Pinta con `draw_path` un relleno de sombreado (sus líneas) y, encima, el trazo del estilo.
+/// None si el relleno no es un sombreado y el renderer debe pintar `path` por su cuenta.
+pub fn draw_hatched(renderer: &mut dyn RenderPort, transform: &Transform, path: &Path, style: &Style) -> Option<Result<(), RenderError>> {
+    let (lines, line_style) = hatch_fill(path, style)?;
+    let mut draw = || {
+        if !lines.commands.is_empty() {
+            renderer.draw_path(transform, &lines, &line_style)?;
+        }
+        if style.stroke.is_some() {
+            renderer.draw_path(transform, path, &Style { fill: None, ..style.clone() })?;
+        }
+        Ok(())
+    };
+    Some(draw())
+}
+
+/// Tramos del interior de `polygons` (regla nonzero, como el relleno de Canvas2D; los subtrazados
+/// abiertos se cierran) sobre rectas inclinadas `angle` radianes y separadas `gap`
+pub fn hatch_segments(polygons: &[Polyline], angle: f32, gap: f32) -> Vec<((f32, f32), (f32, f32))> {
+    let (sin, cos) = angle.sin_cos();
+    // Se gira el contorno para que las rectas queden horizontales y se deshace el giro al final
+    let edges: Vec<((f32, f32), (f32, f32))> = polygons
+        .iter()
+        .filter(|p| p.points.len() > 2)
+        .flat_map(|p| {
+            let points: Vec<(f32, f32)> = p.points.iter().map(|(x, y)| (x * cos + y * sin, y * cos - x * sin)).collect();
+            let n = points.len();
+            (0..n).map(move |i| (points[i], points[(i + 1) % n]))
+        })
+        .collect();
+    let mut segments = Vec::new();
+    let Some((top, bottom)) = edges.iter().map(|(a, _)| a.1).fold(None, |acc: Option<(f32, f32)>, y| {
+        Some(acc.map_or((y, y), |(min, max)| (min.min(y), max.max(y))))
+    }) else {
+        return segments;
+    };
+    let gap = if gap.is_finite() { gap.max(MIN_HATCH_GAP) } else { MIN_HATCH_GAP };
+    let gap = gap.max((bottom - top) / MAX_HATCH_LINES);
+    let unrotate = |x: f32, y: f32| (x * cos - y * sin, x * sin + y * cos);
+    let mut crossings: Vec<(f32, i32)> = Vec::new();
+    let mut y = top + gap / 2.0;
+    while y < bottom {
+        crossings.clear();
+        for (a, b) in &edges {
+            if (a.1 <= y) != (b.1 <= y) {
+                let x = a.0 + (y - a.1) / (b.1 - a.1) * (b.0 - a.0);
+                crossings.push((x, if b.1 > a.1 { 1 } else { -1 }));
+            }
+        }
+        crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut winding = 0;
+        let mut from = 0.0;
+        for (x, direction) in &crossings {
+            let before = winding;
+            winding += direction;
+            if before == 0 {
+                from = *x;
+            } else if winding == 0 {
+                segments.push((unrotate(from, y), unrotate(*x, y)));
+            }
+        }
+        y += gap;
+    }
+    segments
+}
+
+/// Color de un degradado en la posición `t` (0 = inicio, 1 = final), con las paradas en orden
+/// creciente; fuera de [0, 1] se extienden los colores extremos
+pub fn gradient_color(stops: &[GradientStop], t: f32) -> Color {
+    let t = if t.is_finite() { t.clamp(0.0, 1.0) } else { 0.0 };
+    let Some(first) = stops.first() else { return Color::default() };
+    if t <= first.offset {
+        return first.color;
+    }
+    for pair in stops.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        if t <= b.offset {
+            let span = b.offset - a.offset;
+            let k = if span > 0.0 { (t - a.offset) / span } else { 1.0 };
+            let (Color(r0, g0, b0, a0), Color(r1, g1, b1, a1)) = (a.color, b.color);
+            return Color(r0 + (r1 - r0) * k, g0 + (g1 - g0) * k, b0 + (b1 - b0) * k, a0 + (a1 - a0) * k);
+        }
+    }
+    stops[stops.len() - 1].color
+}
+
+/// Color de `paint` en el punto local `(x, y)`. Un degradado sin longitud (o sin radio) toma el
+/// color de su última parada; las pinturas que no varían con la posición dan `Paint::color`.
+pub fn color_at(paint: &Paint, (x, y): (f32, f32)) -> Color {
+    match paint {
+        Paint::LinearGradient { start, end, stops } => {
+            let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+            let length2 = dx * dx + dy * dy;
+            let t = if length2 > 0.0 { ((x - start.0) * dx + (y - start.1) * dy) / length2 } else { 1.0 };
+            gradient_color(stops, t)
+        }
+        Paint::RadialGradient { center, radius, stops } => {
+            let t = if *radius > 0.0 { (x - center.0).hypot(y - center.1) / radius } else { 1.0 };
+            gradient_color(stops, t)
+        }
+        Paint::Solid(_) | Paint::Hatch { .. } | Paint::Image { .. } => paint.color(),
+    }
+}
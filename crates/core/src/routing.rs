@@ -0,0 +1,394 @@
+//! Enrutado ortogonal de conectores: tramos solo horizontales y verticales que rodean obstáculos.
+//!
+//! Los obstáculos son cajas de mundo agrandadas `ROUTE_MARGIN`. Sus bordes, junto con los extremos
+//! del conector, forman una rejilla dispersa (un grafo de visibilidad ortogonal) que se recorre con
+//! A*: el coste es la longitud más una penalización por giro, así salen rutas cortas y con pocos
+//! codos. Cada extremo enganchado sale perpendicular a un lado de su caja.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::model::{BoundingBox, Path, PathCommand};
+
+/// Separación mínima entre un conector y las cajas que rodea, en unidades de mundo
+pub const ROUTE_MARGIN: f32 = 20.0;
+/// Radio con el que se redondean los codos al pintar
+pub const CORNER_RADIUS: f32 = 8.0;
+/// Coste de un giro, en unidades de longitud
+const BEND_PENALTY: f32 = 24.0;
+
+/// Lado de una caja por el que sale un extremo
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+impl Side {
+    /// Dirección hacia fuera de la caja
+    pub fn normal(self) -> (f32, f32) {
+        match self {
+            Side::Left => (-1.0, 0.0),
+            Side::Right => (1.0, 0.0),
+            Side::Top => (0.0, -1.0),
+            Side::Bottom => (0.0, 1.0),
+        }
+    }
+
+    /// Lado de `bounds` que mira hacia `toward` (relativo a su tamaño, así una caja alargada
+    /// prefiere sus lados largos)
+    pub fn facing(bounds: &BoundingBox, toward: (f32, f32)) -> Self {
+        let (cx, cy) = (bounds.x + bounds.width / 2.0, bounds.y + bounds.height / 2.0);
+        let dx = (toward.0 - cx) / bounds.width.max(1e-4);
+        let dy = (toward.1 - cy) / bounds.height.max(1e-4);
+        if dx.abs() >= dy.abs() {
+            if dx >= 0.0 { Side::Right } else { Side::Left }
+        } else if dy >= 0.0 {
+            Side::Bottom
+        } else {
+            Side::Top
+        }
+    }
+
+    /// Lado más cercano a un punto normalizado (0..=1) de la caja, si está cerca de alguno
+    pub fn nearest(anchor: (f32, f32)) -> Option<Self> {
+        let sides = [(anchor.0, Side::Left), (1.0 - anchor.0, Side::Right), (anchor.1, Side::Top), (1.0 - anchor.1, Side::Bottom)];
+        let (distance, side) = sides.into_iter().min_by(|a, b| a.0.total_cmp(&b.0))?;
+        (distance < 0.25).then_some(side)
+    }
+
+    fn direction(self) -> usize {
+        match self {
+            Side::Right => 0,
+            Side::Left => 1,
+            Side::Bottom => 2,
+            Side::Top => 3,
+        }
+    }
+}
+
+/// Extremo de un conector, en mundo
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Endpoint {
+    pub point: (f32, f32),
+    /// Lado por el que sale de su caja; None en un extremo suelto, que puede salir en cualquier dirección
+    pub side: Option<Side>,
+}
+
+impl Endpoint {
+    pub fn free(point: (f32, f32)) -> Self {
+        Self { point, side: None }
+    }
+
+    /// Sobre el lado `side` de `bounds`, a la altura del punto normalizado `anchor` y separado `gap`
+    pub fn on_box(bounds: &BoundingBox, side: Side, anchor: (f32, f32), gap: f32) -> Self {
+        let (ax, ay) = (anchor.0.clamp(0.0, 1.0), anchor.1.clamp(0.0, 1.0));
+        let (x, y) = match side {
+            Side::Left => (bounds.x - gap, bounds.y + ay * bounds.height),
+            Side::Right => (bounds.x + bounds.width + gap, bounds.y + ay * bounds.height),
+            Side::Top => (bounds.x + ax * bounds.width, bounds.y - gap),
+            Side::Bottom => (bounds.x + ax * bounds.width, bounds.y + bounds.height + gap),
+        };
+        Self { point: (x, y), side: Some(side) }
+    }
+
+    /// Primer punto del recorrido: fuera del margen de su caja, en la dirección de salida
+    fn stub(&self) -> (f32, f32) {
+        match self.side {
+            Some(side) => {
+                let (nx, ny) = side.normal();
+                (self.point.0 + nx * ROUTE_MARGIN, self.point.1 + ny * ROUTE_MARGIN)
+            }
+            None => self.point,
+        }
+    }
+}
+
+/// Polilínea ortogonal de `start` a `end` que rodea `obstacles` (cajas de mundo, incluidas las de
+/// los propios destinos). Si no hay camino libre devuelve un codo simple que ignora los obstáculos.
+pub fn orthogonal_route(start: Endpoint, end: Endpoint, obstacles: &[BoundingBox]) -> Vec<(f32, f32)> {
+    let (from, to) = (start.stub(), end.stub());
+    let inside = |b: &BoundingBox, (x, y): (f32, f32)| x > b.x && x < b.x + b.width && y > b.y && y < b.y + b.height;
+    // Un obstáculo que tapa un extremo no se puede rodear: se ignora
+    let mut inflated: Vec<BoundingBox> =
+        obstacles.iter().map(|b| b.inflated(ROUTE_MARGIN)).filter(|b| !inside(b, from) && !inside(b, to)).collect();
+    let region = nearby(&mut inflated, from, to);
+
+    let mut xs = vec![from.0, to.0, (from.0 + to.0) / 2.0, region.x, region.x + region.width];
+    let mut ys = vec![from.1, to.1, (from.1 + to.1) / 2.0, region.y, region.y + region.height];
+    for b in &inflated {
+        xs.extend([b.x, b.x + b.width]);
+        ys.extend([b.y, b.y + b.height]);
+    }
+    let grid = Grid::new(xs, ys, &inflated);
+
+    let middle = grid
+        .index_of(from)
+        .zip(grid.index_of(to))
+        .and_then(|(a, b)| grid.search(a, start.side.map(Side::direction), b, end.side.map(Side::direction)));
+    let mut points = vec![start.point];
+    match middle {
+        Some(middle) => points.extend(middle),
+        None => points.extend(fallback(start, from, to)),
+    }
+    points.push(end.point);
+    simplified(points)
+}
+
+/// Quita de `obstacles` los que no influyen en la ruta y devuelve la zona en la que se busca:
+/// la caja de los extremos, ampliada con los obstáculos que la tocan (y los que tocan a estos)
+fn nearby(obstacles: &mut Vec<BoundingBox>, from: (f32, f32), to: (f32, f32)) -> BoundingBox {
+    let mut region = BoundingBox::from_points([from, to]).unwrap_or_default();
+    let mut kept = vec![false; obstacles.len()];
+    loop {
+        let mut grown = false;
+        for (b, kept) in obstacles.iter().zip(kept.iter_mut()) {
+            if !*kept && b.intersects(&region) {
+                *kept = true;
+                region = region.union(b);
+                grown = true;
+            }
+        }
+        if !grown {
+            break;
+        }
+    }
+    let mut kept = kept.into_iter();
+    obstacles.retain(|_| kept.next().unwrap_or(false));
+    // Un carril libre alrededor de todo para poder rodear por fuera
+    region.inflated(ROUTE_MARGIN)
+}
+
+/// Codo en dos giros por el punto medio, en la dirección de salida del inicio
+fn fallback(start: Endpoint, from: (f32, f32), to: (f32, f32)) -> [(f32, f32); 4] {
+    let horizontal = start.side.map_or((to.0 - from.0).abs() >= (to.1 - from.1).abs(), |s| matches!(s, Side::Left | Side::Right));
+    if horizontal {
+        let mx = (from.0 + to.0) / 2.0;
+        [from, (mx, from.1), (mx, to.1), to]
+    } else {
+        let my = (from.1 + to.1) / 2.0;
+        [from, (from.0, my), (to.0, my), to]
+    }
+}
+
+/// Sin puntos repetidos ni intermedios alineados con sus vecinos
+fn simplified(points: Vec<(f32, f32)>) -> Vec<(f32, f32)> {
+    let mut out: Vec<(f32, f32)> = Vec::with_capacity(points.len());
+    for p in points {
+        if out.last().is_some_and(|q| (q.0 - p.0).abs() < 1e-3 && (q.1 - p.1).abs() < 1e-3) {
+            continue;
+        }
+        if let [.., a, b] = out[..] {
+            let aligned = ((a.0 - b.0).abs() < 1e-3 && (b.0 - p.0).abs() < 1e-3) || ((a.1 - b.1).abs() < 1e-3 && (b.1 - p.1).abs() < 1e-3);
+            if aligned {
+                out.pop();
+            }
+        }
+        out.push(p);
+    }
+    out
+}
+
+/// Rejilla de líneas candidatas con los nodos y tramos libres de obstáculos
+struct Grid {
+    xs: Vec<f32>,
+    ys: Vec<f32>,
+    /// Nodo libre (fuera de todos los obstáculos), por índice `i * ys.len() + j`
+    free: Vec<bool>,
+    /// Tramo libre hacia el nodo siguiente en x y en y
+    open_x: Vec<bool>,
+    open_y: Vec<bool>,
+}
+
+/// Pasos de la búsqueda: +x, -x, +y, -y
+const STEPS: [(isize, isize); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+impl Grid {
+    fn new(mut xs: Vec<f32>, mut ys: Vec<f32>, obstacles: &[BoundingBox]) -> Self {
+        for v in [&mut xs, &mut ys] {
+            v.sort_by(f32::total_cmp);
+            v.dedup_by(|a, b| (*a - *b).abs() < 1e-3);
+        }
+        let (nx, ny) = (xs.len(), ys.len());
+        let mut free = vec![true; nx * ny];
+        let mut open_x: Vec<bool> = (0..nx * ny).map(|k| k / ny + 1 < nx).collect();
+        let mut open_y: Vec<bool> = (0..nx * ny).map(|k| k % ny + 1 < ny).collect();
+        // Cada obstáculo tapa las líneas estrictamente dentro de él y los tramos entre sus bordes
+        // (que son líneas de la rejilla): se marcan por rangos en vez de probar cada nodo
+        let inside = |v: &[f32], min: f32, max: f32| v.partition_point(|c| *c <= min + 1e-3)..v.partition_point(|c| *c < max - 1e-3);
+        let spans = |v: &[f32], min: f32, max: f32| {
+            let start = v.partition_point(|c| *c < min - 1e-3);
+            start..v.partition_point(|c| *c <= max + 1e-3).saturating_sub(1).max(start)
+        };
+        for b in obstacles {
+            let (x_in, y_in) = (inside(&xs, b.x, b.x + b.width), inside(&ys, b.y, b.y + b.height));
+            for i in x_in.clone() {
+                for j in y_in.clone() {
+                    free[i * ny + j] = false;
+                }
+            }
+            for i in spans(&xs, b.x, b.x + b.width) {
+                for j in y_in.clone() {
+                    open_x[i * ny + j] = false;
+                }
+            }
+            for i in x_in {
+                for j in spans(&ys, b.y, b.y + b.height) {
+                    open_y[i * ny + j] = false;
+                }
+            }
+        }
+        Self { xs, ys, free, open_x, open_y }
+    }
+
+    fn index_of(&self, (x, y): (f32, f32)) -> Option<usize> {
+        let i = self.xs.iter().position(|v| (v - x).abs() < 1e-3)?;
+        let j = self.ys.iter().position(|v| (v - y).abs() < 1e-3)?;
+        Some(i * self.ys.len() + j)
+    }
+
+    fn point(&self, k: usize) -> (f32, f32) {
+        let ny = self.ys.len();
+        (self.xs[k / ny], self.ys[k % ny])
+    }
+
+    /// Vecino de `k` en la dirección `d`, si el tramo hasta él está libre
+    fn step(&self, k: usize, d: usize) -> Option<usize> {
+        let ny = self.ys.len() as isize;
+        let (i, j) = ((k as isize) / ny, (k as isize) % ny);
+        let (di, dj) = STEPS[d];
+        let (ni, nj) = (i + di, j + dj);
+        if ni < 0 || nj < 0 || ni >= self.xs.len() as isize || nj >= ny {
+            return None;
+        }
+        let next = (ni * ny + nj) as usize;
+        let open = match d {
+            0 => self.open_x[k],
+            1 => self.open_x[next],
+            2 => self.open_y[k],
+            _ => self.open_y[next],
+        };
+        (open && self.free[next]).then_some(next)
+    }
+
+    /// A* de `from` a `to` con el estado (nodo, dirección de llegada). `start_dir` es la dirección
+    /// con la que se sale y `end_dir` la de salida del destino: se llega en la contraria.
+    fn search(&self, from: usize, start_dir: Option<usize>, to: usize, end_dir: Option<usize>) -> Option<Vec<(f32, f32)>> {
+        // Estados: 4 direcciones por nodo, una más para "sin dirección" y un estado final virtual
+        let goal = self.free.len() * 5;
+        let state = |k: usize, d: Option<usize>| k * 5 + d.unwrap_or(4);
+        let target = self.point(to);
+        let heuristic = |k: usize| {
+            let p = self.point(k);
+            (p.0 - target.0).abs() + (p.1 - target.1).abs()
+        };
+        let turn = |a: Option<usize>, b: usize| match a {
+            None => 0.0,
+            Some(a) if a == b => 0.0,
+            // Media vuelta: dos giros
+            Some(a) if a ^ 1 == b => 2.0 * BEND_PENALTY,
+            Some(_) => BEND_PENALTY,
+        };
+
+        let mut cost = vec![f32::INFINITY; goal + 1];
+        let mut came_from = vec![usize::MAX; goal + 1];
+        let mut open = BinaryHeap::new();
+        let first = state(from, start_dir);
+        cost[first] = 0.0;
+        open.push(Candidate { priority: heuristic(from), state: first });
+        while let Some(Candidate { state: s, priority }) = open.pop() {
+            if s == goal {
+                break;
+            }
+            let (k, d) = (s / 5, (s % 5 < 4).then_some(s % 5));
+            let g = cost[s];
+            if priority > g + heuristic(k) + 1e-3 {
+                continue;
+            }
+            if k == to {
+                // Llegar al destino en la dirección contraria a su salida cuesta lo que falte por girar
+                let arrive = end_dir.map_or(0.0, |e| d.map_or(0.0, |d| turn(Some(d), e ^ 1)));
+                if g + arrive < cost[goal] {
+                    cost[goal] = g + arrive;
+                    came_from[goal] = s;
+                    open.push(Candidate { priority: g + arrive, state: goal });
+                }
+                continue;
+            }
+            for dir in 0..4 {
+                // Nunca se vuelve por donde se ha venido
+                if d.is_some_and(|d| d ^ 1 == dir) {
+                    continue;
+                }
+                let Some(next) = self.step(k, dir) else { continue };
+                let (a, b) = (self.point(k), self.point(next));
+                let g2 = g + (a.0 - b.0).abs() + (a.1 - b.1).abs() + turn(d, dir);
+                let ns = state(next, Some(dir));
+                if g2 < cost[ns] {
+                    cost[ns] = g2;
+                    came_from[ns] = s;
+                    open.push(Candidate { priority: g2 + heuristic(next), state: ns });
+                }
+            }
+        }
+        if came_from[goal] == usize::MAX {
+            return None;
+        }
+        let mut path = Vec::new();
+        let mut s = came_from[goal];
+        while s != usize::MAX {
+            path.push(self.point(s / 5));
+            s = came_from[s];
+        }
+        path.reverse();
+        Some(path)
+    }
+}
+
+/// Entrada de la cola de A*: menor prioridad primero y, a igualdad, menor estado (determinista)
+#[derive(PartialEq)]
+struct Candidate {
+    priority: f32,
+    state: usize,
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.priority.total_cmp(&self.priority).then_with(|| other.state.cmp(&self.state))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Trazado de la polilínea con los codos redondeados: cada esquina se sustituye por una curva de
+/// radio `radius` (menos si los tramos que la forman son cortos)
+pub fn rounded_path(points: &[(f32, f32)], radius: f32) -> Path {
+    let mut path = Path::default();
+    let Some(&(x0, y0)) = points.first() else { return path };
+    path.commands.push(PathCommand::MoveTo(x0, y0));
+    for w in points.windows(3) {
+        let (a, corner, b) = (w[0], w[1], w[2]);
+        let (l_in, l_out) = ((corner.0 - a.0).hypot(corner.1 - a.1), (b.0 - corner.0).hypot(b.1 - corner.1));
+        let r = radius.min(l_in / 2.0).min(l_out / 2.0);
+        if r < 1e-3 {
+            path.commands.push(PathCommand::LineTo(corner.0, corner.1));
+            continue;
+        }
+        let before = (corner.0 - (corner.0 - a.0) / l_in * r, corner.1 - (corner.1 - a.1) / l_in * r);
+        let after = (corner.0 + (b.0 - corner.0) / l_out * r, corner.1 + (b.1 - corner.1) / l_out * r);
+        path.commands.push(PathCommand::LineTo(before.0, before.1));
+        path.commands.push(PathCommand::QuadTo { cx: corner.0, cy: corner.1, x: after.0, y: after.1 });
+    }
+    if let [.., _, (x, y)] = points[..] {
+        path.commands.push(PathCommand::LineTo(x, y));
+    }
+    path
+}
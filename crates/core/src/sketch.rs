@@ -0,0 +1,338 @@
+//! Estilo a mano alzada ("sketchy"), a la manera de rough.js.
+//!
+//! A partir de la forma exacta se generan trazados: el contorno en dos pasadas temblorosas y el
+//! relleno como sombreado de líneas paralelas (o un relleno sólido algo desplazado). El temblor sale
+//! de un generador pseudoaleatorio con la semilla de la entidad mezclada con `GLOBAL_SEED`, así que
+//! una entidad se dibuja igual en cada frame, sesión y exportación. Todos los renderers y el
+//! exportador SVG pintan estos mismos trazados.
+
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+use crate::model::{BoundingBox, EntityId, Path, PathCommand, Polyline, Shape, Sketch, SketchFill, StrokeCap, StrokeJoin, Style, Transform, PATH_FLATTEN_TOLERANCE};
+use crate::paint;
+use crate::ports::{RenderError, RenderPort};
+
+/// Semilla global con la que se mezcla la de cada entidad (ver "Determinismo" en PORTS_CONTRACTS)
+pub const GLOBAL_SEED: u32 = 0x4d6f_6d65;
+/// Desplazamiento máximo de los extremos con rugosidad 1, en unidades locales
+const MAX_OFFSET: f32 = 2.0;
+/// Curvatura de las líneas (el "bowing" de rough.js)
+const BOWING: f32 = 1.0;
+/// Inclinación del sombreado, en grados (la de rough.js)
+const HACHURE_ANGLE_DEG: f32 = -41.0;
+/// Separación del sombreado en múltiplos del grosor de trazo, y la mínima
+const HACHURE_GAP_PER_WIDTH: f32 = 4.0;
+const MIN_HACHURE_GAP: f32 = 2.0;
+/// Puntos mínimos con los que se recorre una elipse
+const ELLIPSE_STEPS: f32 = 9.0;
+
+/// Trazados a mano alzada de una forma, en sus coordenadas locales
+#[derive(Clone, Debug, Default)]
+pub struct SketchGeometry {
+    /// Relleno sólido (`SketchFill::Solid`) con el color de relleno
+    pub fill: Path,
+    /// Líneas del sombreado, trazadas con el color de relleno (`hachure_style`)
+    pub hachure: Path,
+    /// Contorno en dos pasadas, trazado con el estilo de la forma (`outline_style`)
+    pub outline: Path,
+}
+
+impl SketchGeometry {
+    /// AABB en mundo de todo lo que se pinta (sin el grosor del trazo)
+    pub fn bounds(&self, transform: &Transform) -> Option<BoundingBox> {
+        [&self.fill, &self.hachure, &self.outline]
+            .into_iter()
+            .filter(|path| !path.commands.is_empty())
+            .map(|path| BoundingBox::from_shape(transform, &Shape::Path { path: path.clone() }))
+            .reduce(|acc, b| acc.union(&b))
+    }
+}
+
+/// Semilla estable de una entidad: depende solo de su id
+pub fn entity_seed(id: EntityId) -> u32 {
+    mix(id.0)
+}
+
+/// Mezcla de splitmix64: valores cercanos dan resultados sin relación
+fn mix(value: u64) -> u32 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    (z ^ (z >> 31)) as u32
+}
+
+/// Trazados a mano alzada de `shape`, o None si el estilo no lo pide o la forma se pinta de otra
+/// manera (texto, imágenes y lápiz; en las flechas solo tiembla el cuerpo, ver `arrow::geometry`).
+/// `transform` es el de la entidad, para los radios de las esquinas redondeadas.
+pub fn geometry(shape: &Shape, style: &Style, transform: &Transform) -> Option<SketchGeometry> {
+    let sketch = style.sketch?;
+    let exact = paint::shape_outline(shape, transform)?;
+    let roughness = roughness(&sketch);
+    let mut out = SketchGeometry::default();
+    // Como en los renderers, las líneas solo llevan trazo
+    if style.fill.is_some() && !matches!(shape, Shape::Line { .. }) {
+        // Cada parte con su propia secuencia: cambiar el relleno no altera el contorno
+        let mut random = Random::new(sketch.seed, 1);
+        let polygons = exact.flatten(PATH_FLATTEN_TOLERANCE);
+        let gap = (style.stroke_width * HACHURE_GAP_PER_WIDTH).max(MIN_HACHURE_GAP);
+        let angle = HACHURE_ANGLE_DEG.to_radians();
+        match sketch.fill {
+            SketchFill::Solid => out.fill = displaced(&exact, roughness, &mut random),
+            SketchFill::Hachure => hachure(&mut out.hachure, &polygons, angle, gap, roughness, &mut random),
+            SketchFill::CrossHatch => {
+                hachure(&mut out.hachure, &polygons, angle, gap, roughness, &mut random);
+                hachure(&mut out.hachure, &polygons, angle + FRAC_PI_2, gap, roughness, &mut random);
+            }
+        }
+    }
+    if style.stroke.is_some() {
+        let mut random = Random::new(sketch.seed, 0);
+        out.outline = match shape {
+            Shape::Ellipse { rx, ry } => ellipse(rx.abs(), ry.abs(), roughness, &mut random),
+            _ => path_outline(&exact, roughness, &mut random),
+        };
+    }
+    Some(out)
+}
+
+/// Contorno a mano alzada de un trazado cualquiera: dos pasadas por tramo
+pub fn rough_path(path: &Path, sketch: &Sketch) -> Path {
+    path_outline(path, roughness(sketch), &mut Random::new(sketch.seed, 0))
+}
+
+/// Pinta los trazados con `draw_path`: relleno, sombreado y, encima, el contorno
+pub fn draw_sketch(renderer: &mut dyn RenderPort, transform: &Transform, geometry: &SketchGeometry, style: &Style) -> Result<(), RenderError> {
+    if !geometry.fill.commands.is_empty() {
+        renderer.draw_path(transform, &geometry.fill, &fill_style(style))?;
+    }
+    if !geometry.hachure.commands.is_empty() {
+        renderer.draw_path(transform, &geometry.hachure, &hachure_style(style))?;
+    }
+    if !geometry.outline.commands.is_empty() {
+        renderer.draw_path(transform, &geometry.outline, &outline_style(style))?;
+    }
+    Ok(())
+}
+
+/// Estilo del relleno sólido: solo el color de relleno
+pub fn fill_style(style: &Style) -> Style {
+    Style { stroke: None, sketch: None, ..style.clone() }
+}
+
+/// Estilo del sombreado: el color de relleno con la mitad del grosor de trazo
+pub fn hachure_style(style: &Style) -> Style {
+    Style {
+        fill: None,
+        stroke: style.fill.clone(),
+        stroke_width: (style.stroke_width / 2.0).max(0.5),
+        opacity: style.opacity,
+        stroke_cap: StrokeCap::Round,
+        stroke_join: StrokeJoin::Round,
+        ..Style::default()
+    }
+}
+
+/// Estilo del contorno: el de la forma sin relleno, con extremos y uniones redondeados como un lápiz
+pub fn outline_style(style: &Style) -> Style {
+    Style { fill: None, stroke_cap: StrokeCap::Round, stroke_join: StrokeJoin::Round, sketch: None, ..style.clone() }
+}
+
+fn roughness(sketch: &Sketch) -> f32 {
+    if sketch.roughness.is_finite() { sketch.roughness.max(0.0) } else { 0.0 }
+}
+
+/// Generador de Park–Miller (el de rough.js): barato, portable y reproducible
+struct Random(u32);
+
+impl Random {
+    /// Secuencia `stream` de la semilla `seed`
+    fn new(seed: u32, stream: u32) -> Self {
+        let mixed = mix(((seed ^ GLOBAL_SEED) as u64) << 32 | stream as u64);
+        Random((mixed % 0x7fff_ffff).max(1))
+    }
+
+    /// Siguiente valor en [0, 1)
+    fn next(&mut self) -> f32 {
+        self.0 = ((self.0 as u64 * 48_271) % 0x7fff_ffff) as u32;
+        self.0 as f32 / 0x7fff_ffff as f32
+    }
+
+    /// Desplazamiento en [-amount, amount) escalado por la rugosidad
+    fn offset(&mut self, amount: f32, roughness: f32) -> f32 {
+        roughness * (self.next() * 2.0 - 1.0) * amount
+    }
+
+    /// Valor en [min, max) escalado por la rugosidad
+    fn range(&mut self, min: f32, max: f32, roughness: f32) -> f32 {
+        roughness * (self.next() * (max - min) + min)
+    }
+}
+
+/// Recta de `a` a `b` en dos pasadas, la segunda con menos temblor
+fn double_line(out: &mut Path, a: (f32, f32), b: (f32, f32), roughness: f32, random: &mut Random) {
+    line(out, a, b, roughness, random, false);
+    line(out, a, b, roughness, random, true);
+}
+
+/// Una pasada de recta: una cúbica algo arqueada con los extremos y los puntos de control desplazados
+fn line(out: &mut Path, a: (f32, f32), b: (f32, f32), roughness: f32, random: &mut Random, overlay: bool) {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length = dx.hypot(dy);
+    if length < 1e-4 {
+        return;
+    }
+    // Las líneas largas tiemblan proporcionalmente menos
+    let gain = if length < 200.0 { 1.0 } else if length > 500.0 { 0.4 } else { -0.001_666_8 * length + 1.233_334 };
+    let roughness = roughness * gain;
+    // Y las cortas no se desplazan más de una décima de su largo
+    let offset = if MAX_OFFSET * MAX_OFFSET * 100.0 > length * length { length / 10.0 } else { MAX_OFFSET };
+    let amount = if overlay { offset / 2.0 } else { offset };
+    let diverge = 0.2 + random.next() * 0.2;
+    let bow_x = random.offset(BOWING * MAX_OFFSET * dy / 200.0, roughness);
+    let bow_y = random.offset(BOWING * MAX_OFFSET * -dx / 200.0, roughness);
+    let mut jitter = || random.offset(amount, roughness);
+    out.commands.push(PathCommand::MoveTo(a.0 + jitter(), a.1 + jitter()));
+    out.commands.push(PathCommand::CubicTo {
+        c1x: bow_x + a.0 + dx * diverge + jitter(),
+        c1y: bow_y + a.1 + dy * diverge + jitter(),
+        c2x: bow_x + a.0 + 2.0 * dx * diverge + jitter(),
+        c2y: bow_y + a.1 + 2.0 * dy * diverge + jitter(),
+        x: b.0 + jitter(),
+        y: b.1 + jitter(),
+    });
+}
+
+/// Cúbica en dos pasadas con los puntos de control y el final desplazados
+fn bezier(out: &mut Path, from: (f32, f32), c1: (f32, f32), c2: (f32, f32), to: (f32, f32), roughness: f32, random: &mut Random) {
+    for (pass, amount) in [MAX_OFFSET, MAX_OFFSET + 0.3].into_iter().enumerate() {
+        let start = if pass == 0 { from } else { (from.0 + random.offset(MAX_OFFSET, roughness), from.1 + random.offset(MAX_OFFSET, roughness)) };
+        out.commands.push(PathCommand::MoveTo(start.0, start.1));
+        let mut jitter = || random.offset(amount, roughness);
+        out.commands.push(PathCommand::CubicTo {
+            c1x: c1.0 + jitter(),
+            c1y: c1.1 + jitter(),
+            c2x: c2.0 + jitter(),
+            c2y: c2.1 + jitter(),
+            x: to.0 + jitter(),
+            y: to.1 + jitter(),
+        });
+    }
+}
+
+/// Contorno a mano alzada de cada tramo de `path`; las cuadráticas pasan a cúbicas
+fn path_outline(path: &Path, roughness: f32, random: &mut Random) -> Path {
+    let mut out = Path::default();
+    let mut start = (0.0, 0.0);
+    let mut last = (0.0, 0.0);
+    for command in &path.commands {
+        match *command {
+            PathCommand::MoveTo(x, y) => {
+                start = (x, y);
+                last = start;
+            }
+            PathCommand::LineTo(x, y) => {
+                double_line(&mut out, last, (x, y), roughness, random);
+                last = (x, y);
+            }
+            PathCommand::QuadTo { cx, cy, x, y } => {
+                let c1 = (last.0 + 2.0 / 3.0 * (cx - last.0), last.1 + 2.0 / 3.0 * (cy - last.1));
+                let c2 = (x + 2.0 / 3.0 * (cx - x), y + 2.0 / 3.0 * (cy - y));
+                bezier(&mut out, last, c1, c2, (x, y), roughness, random);
+                last = (x, y);
+            }
+            PathCommand::CubicTo { c1x, c1y, c2x, c2y, x, y } => {
+                bezier(&mut out, last, (c1x, c1y), (c2x, c2y), (x, y), roughness, random);
+                last = (x, y);
+            }
+            PathCommand::Close => {
+                double_line(&mut out, last, start, roughness, random);
+                last = start;
+            }
+        }
+    }
+    out
+}
+
+/// Elipse centrada en el origen: dos vueltas por puntos desplazados unidas con una curva suave;
+/// la primera se pasa un poco de su inicio, como quien cierra un círculo a mano
+fn ellipse(rx: f32, ry: f32, roughness: f32, random: &mut Random) -> Path {
+    let size = (TAU * ((rx * rx + ry * ry) / 2.0).sqrt()).sqrt();
+    let steps = ELLIPSE_STEPS.max(ELLIPSE_STEPS / 200f32.sqrt() * size).ceil();
+    let increment = TAU / steps;
+    // Radios algo distintos de los exactos
+    let rx = rx + random.offset(rx * 0.05, roughness);
+    let ry = ry + random.offset(ry * 0.05, roughness);
+    let reach = random.range(0.4, 1.0, roughness);
+    let overlap = increment * random.range(0.1, reach, roughness);
+    let mut out = Path::default();
+    for (offset, overlap) in [(1.0, overlap), (1.5, 0.0)] {
+        let points = ellipse_points(rx, ry, increment, offset, overlap, roughness, random);
+        curve(&mut out, &points);
+    }
+    out
+}
+
+fn ellipse_points(rx: f32, ry: f32, increment: f32, offset: f32, overlap: f32, roughness: f32, random: &mut Random) -> Vec<(f32, f32)> {
+    let start = random.offset(0.5, roughness) - FRAC_PI_2;
+    let mut points = Vec::new();
+    let mut push = |angle: f32, k: f32, random: &mut Random| {
+        let (sin, cos) = angle.sin_cos();
+        points.push((random.offset(offset, roughness) + k * rx * cos, random.offset(offset, roughness) + k * ry * sin));
+    };
+    push(start - increment, 0.9, random);
+    let mut angle = start;
+    while angle < start + TAU - 0.01 {
+        push(angle, 1.0, random);
+        angle += increment;
+    }
+    push(start + TAU + overlap * 0.5, 1.0, random);
+    push(start + overlap, 0.98, random);
+    push(start + overlap * 0.5, 0.9, random);
+    points
+}
+
+/// Curva de Catmull-Rom (en cúbicas) por los puntos, del segundo al penúltimo
+fn curve(out: &mut Path, points: &[(f32, f32)]) {
+    if points.len() < 4 {
+        return;
+    }
+    out.commands.push(PathCommand::MoveTo(points[1].0, points[1].1));
+    for w in points.windows(4) {
+        let (p0, p1, p2, p3) = (w[0], w[1], w[2], w[3]);
+        out.commands.push(PathCommand::CubicTo {
+            c1x: p1.0 + (p2.0 - p0.0) / 6.0,
+            c1y: p1.1 + (p2.1 - p0.1) / 6.0,
+            c2x: p2.0 + (p1.0 - p3.0) / 6.0,
+            c2y: p2.1 + (p1.1 - p3.1) / 6.0,
+            x: p2.0,
+            y: p2.1,
+        });
+    }
+}
+
+/// Relleno sólido: el contorno exacto desplazado en bloque
+fn displaced(path: &Path, roughness: f32, random: &mut Random) -> Path {
+    let (dx, dy) = (random.offset(MAX_OFFSET, roughness), random.offset(MAX_OFFSET, roughness));
+    let commands = path
+        .commands
+        .iter()
+        .map(|command| match *command {
+            PathCommand::MoveTo(x, y) => PathCommand::MoveTo(x + dx, y + dy),
+            PathCommand::LineTo(x, y) => PathCommand::LineTo(x + dx, y + dy),
+            PathCommand::QuadTo { cx, cy, x, y } => PathCommand::QuadTo { cx: cx + dx, cy: cy + dy, x: x + dx, y: y + dy },
+            PathCommand::CubicTo { c1x, c1y, c2x, c2y, x, y } => {
+                PathCommand::CubicTo { c1x: c1x + dx, c1y: c1y + dy, c2x: c2x + dx, c2y: c2y + dy, x: x + dx, y: y + dy }
+            }
+            PathCommand::Close => PathCommand::Close,
+        })
+        .collect();
+    Path { commands }
+}
+
+/// Sombreado del interior de `polygons` (ver `paint::hatch_segments`): líneas inclinadas `angle`
+/// cada `gap`, a mano alzada
+fn hachure(out: &mut Path, polygons: &[Polyline], angle: f32, gap: f32, roughness: f32, random: &mut Random) {
+    for (a, b) in paint::hatch_segments(polygons, angle, gap) {
+        double_line(out, a, b, roughness, random);
+    }
+}
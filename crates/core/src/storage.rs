@@ -0,0 +1,70 @@
+//! Adaptador de `StoragePort` en memoria: lo que se guarda dura lo que la sesión.
+//! Es el almacenamiento por defecto (p.ej. de los blobs de las imágenes importadas) hasta
+//! conectar uno persistente, y sirve para tests.
+
+use std::collections::HashMap;
+
+use crate::model::{BlobId, Project, ProjectId, ProjectMeta};
+use crate::ports::{StorageError, StoragePort};
+
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStorage {
+    projects: HashMap<ProjectId, Project>,
+    blobs: HashMap<BlobId, Vec<u8>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn blob(&self, id: BlobId) -> Option<&[u8]> {
+        self.blobs.get(&id).map(Vec::as_slice)
+    }
+}
+
+impl StoragePort for MemoryStorage {
+    fn save_project(&mut self, project: Project) -> Result<ProjectId, StorageError> {
+        let id = project.id;
+        self.projects.insert(id, project);
+        Ok(id)
+    }
+
+    fn load_project(&mut self, id: ProjectId) -> Result<Project, StorageError> {
+        self.projects.get(&id).cloned().ok_or(StorageError::NotFound)
+    }
+
+    fn list_projects(&mut self) -> Result<Vec<ProjectMeta>, StorageError> {
+        let mut metas: Vec<ProjectMeta> = self
+            .projects
+            .values()
+            .map(|p| ProjectMeta {
+                id: p.id,
+                name: p.name.clone(),
+                updated_at: p.updated_at,
+                // Tamaño del documento serializado
+                size: p.document.to_json().map_or(0, |json| json.len() as u64),
+            })
+            .collect();
+        metas.sort_by_key(|m| m.id.0);
+        Ok(metas)
+    }
+
+    fn delete_project(&mut self, id: ProjectId) -> Result<(), StorageError> {
+        self.projects.remove(&id).map(|_| ()).ok_or(StorageError::NotFound)
+    }
+
+    fn put_blob(&mut self, id: BlobId, bytes: &[u8]) -> Result<(), StorageError> {
+        self.blobs.insert(id, bytes.to_vec());
+        Ok(())
+    }
+
+    fn get_blob(&mut self, id: BlobId) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self.blobs.get(&id).cloned())
+    }
+
+    fn migrate(&mut self, _from: u32, _to: u32) -> Result<(), StorageError> {
+        // Nada persiste entre versiones
+        Ok(())
+    }
+}
@@ -0,0 +1,413 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::model::{
+    BlobId, BoundingBox, Color, EntityId, FreehandStroke, GradientStop, Paint, Path, PathCommand, Shape, StrokeCap, StrokeJoin,
+    Style, Transform, PATH_FLATTEN_TOLERANCE,
+};
+use crate::{arrow, image, paint, sketch};
+use crate::text::{self, FontBook, TextLayout};
+use crate::usecases::{Document, Entity};
+
+#[derive(Clone, Debug)]
+pub struct SvgExportOptions {
+    /// Margen alrededor del contenido, en unidades de mundo
+    pub padding: f32,
+    /// Fondo opaco bajo el contenido; `None` = transparente
+    pub background: Option<Color>,
+    /// Región de mundo a exportar (p.ej. la vista actual). `None` ajusta el viewBox al contenido.
+    pub view_box: Option<BoundingBox>,
+    /// Fuentes con las que se maquetan los textos, las mismas que en el lienzo.
+    /// Sin fuentes las medidas son estimadas y el visor puede variar ligeramente los anchos.
+    pub fonts: FontBook,
+    /// Bytes codificados de las imágenes por blob; se incrustan como URI `data:`.
+    /// Las imágenes cuyo blob falte no se exportan.
+    pub images: HashMap<BlobId, Vec<u8>>,
+}
+
+impl Default for SvgExportOptions {
+    fn default() -> Self {
+        Self { padding: 10.0, background: None, view_box: None, fonts: FontBook::default(), images: HashMap::new() }
+    }
+}
+
+/// SVG autónomo con todas las entidades del documento, en orden de pintado
+pub fn to_svg(doc: &Document, options: &SvgExportOptions) -> String {
+    write_svg(&doc.iter().collect::<Vec<_>>(), options)
+}
+
+/// SVG autónomo solo con las entidades indicadas (se respeta el orden de pintado del documento)
+pub fn selection_to_svg(doc: &Document, ids: &[EntityId], options: &SvgExportOptions) -> String {
+    write_svg(&doc.iter().filter(|e| ids.contains(&e.id)).collect::<Vec<_>>(), options)
+}
+
+fn write_svg(entities: &[&Entity], options: &SvgExportOptions) -> String {
+    let view = options.view_box.unwrap_or_else(|| {
+        let content = entities
+            .iter()
+            .map(|e| painted_bounds(e))
+            .reduce(|acc, b| acc.union(&b))
+            .unwrap_or_default();
+        BoundingBox {
+            x: content.x - options.padding,
+            y: content.y - options.padding,
+            width: content.width + 2.0 * options.padding,
+            height: content.height + 2.0 * options.padding,
+        }
+    });
+    // Un viewBox vacío invalida el SVG
+    let (width, height) = (view.width.max(1.0), view.height.max(1.0));
+
+    let mut defs = Defs { images: &options.images, markup: String::new(), count: 0 };
+    let mut body = String::new();
+    if let Some(color) = options.background {
+        let _ = writeln!(
+            body,
+            r#"  <rect x="{}" y="{}" width="{}" height="{}"{}/>"#,
+            num(view.x), num(view.y), num(width), num(height), paint_attrs("fill", Some(&Paint::Solid(color)), 1.0, &mut defs)
+        );
+    }
+    for entity in entities {
+        write_entity(&mut body, entity, options, &mut defs);
+    }
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="{}" height="{}" viewBox="{} {} {} {}">"#,
+        num(width), num(height), num(view.x), num(view.y), num(width), num(height)
+    );
+    // Degradados y mosaicos a los que hacen referencia los `fill`/`stroke` con `url(#…)`
+    if !defs.markup.is_empty() {
+        let _ = write!(out, "  <defs>\n{}  </defs>\n", defs.markup);
+    }
+    out.push_str(&body);
+    out.push_str("</svg>\n");
+    out
+}
+
+/// Definiciones reutilizables del documento (degradados y mosaicos), con ids `paint-N`
+struct Defs<'a> {
+    images: &'a HashMap<BlobId, Vec<u8>>,
+    markup: String,
+    count: usize,
+}
+
+impl Defs<'_> {
+    fn next_id(&mut self) -> String {
+        self.count += 1;
+        format!("paint-{}", self.count)
+    }
+
+    /// Degradado en el espacio de usuario del elemento que lo usa, o sea, en coordenadas locales
+    fn gradient(&mut self, element: &str, geometry: String, stops: &[GradientStop], opacity: f32) -> String {
+        let id = self.next_id();
+        let _ = writeln!(self.markup, r#"    <{} id="{}" gradientUnits="userSpaceOnUse" {}>"#, element, id, geometry);
+        for stop in stops {
+            let _ = writeln!(self.markup, r#"      <stop offset="{}"{}/>"#, num(stop.offset.clamp(0.0, 1.0)), color_attrs("stop-color", "stop-opacity", stop.color, opacity));
+        }
+        let _ = writeln!(self.markup, "    </{}>", element);
+        id
+    }
+
+    /// Mosaico de `w` x `h` con la imagen del blob; None si faltan sus bytes
+    fn image_pattern(&mut self, blob: BlobId, w: f32, h: f32, opacity: f32) -> Option<String> {
+        let href = self.images.get(&blob).and_then(|data| image::data_uri(data).ok())?;
+        let id = self.next_id();
+        let (width, height) = (num(w.abs()), num(h.abs()));
+        let opacity = if opacity < 1.0 { format!(r#" opacity="{}""#, num(opacity.max(0.0))) } else { String::new() };
+        let _ = writeln!(
+            self.markup,
+            r#"    <pattern id="{}" patternUnits="userSpaceOnUse" width="{}" height="{}"><image width="{}" height="{}" preserveAspectRatio="none" xlink:href="{}"{}/></pattern>"#,
+            id, width, height, width, height, href, opacity
+        );
+        Some(id)
+    }
+}
+
+fn write_entity(out: &mut String, entity: &Entity, options: &SvgExportOptions, defs: &mut Defs) {
+    let fonts = &options.fonts;
+    write_shape(out, entity, options, defs);
+    if let Some(label) = &entity.label {
+        let (layout, origin) = text::label_layout(&entity.shape, label, &mut fonts.measurer(text::approximate_metrics));
+        write_text(out, fonts, &entity.transform, &layout, origin, defs);
+    }
+}
+
+fn write_shape(out: &mut String, entity: &Entity, options: &SvgExportOptions, defs: &mut Defs) {
+    let style = &entity.style;
+    let transform = transform_attr(&entity.transform);
+    // A mano alzada: los mismos trazados (y la misma semilla) que pintan los renderers
+    if let Some(geometry) = sketch::geometry(&entity.shape, style, &entity.transform) {
+        if !geometry.fill.commands.is_empty() {
+            let fill = sketch::fill_style(style);
+            if !write_hatched(out, &geometry.fill, &transform, &fill, defs) {
+                let _ = writeln!(out, r#"  <path d="{}"{}{}/>"#, path_data(&geometry.fill), transform, style_attrs(&fill, fill.fill.as_ref(), defs));
+            }
+        }
+        if !geometry.hachure.commands.is_empty() {
+            let _ = writeln!(out, r#"  <path d="{}"{}{}/>"#, path_data(&geometry.hachure), transform, style_attrs(&sketch::hachure_style(style), None, defs));
+        }
+        if !geometry.outline.commands.is_empty() {
+            let _ = writeln!(out, r#"  <path d="{}"{}{}/>"#, path_data(&geometry.outline), transform, style_attrs(&sketch::outline_style(style), None, defs));
+        }
+        return;
+    }
+    if let Some(outline) = paint::hatched_outline(&entity.shape, style, &entity.transform) {
+        write_hatched(out, &outline, &transform, style, defs);
+        return;
+    }
+    let element = match &entity.shape {
+        Shape::Rect { w, h, radii } => {
+            let rect = format!(
+                r#"<rect x="{}" y="{}" width="{}" height="{}""#,
+                num(w.min(0.0)), num(h.min(0.0)), num(w.abs()), num(h.abs())
+            );
+            let resolved = radii.resolve(*w, *h, &entity.transform);
+            match resolved {
+                _ if radii.is_sharp() => rect,
+                // Todas las esquinas iguales caben en un <rect>; si no, el contorno como trazado
+                [(rx, ry), ..] if resolved.iter().all(|r| *r == (rx, ry)) => format!(r#"{} rx="{}" ry="{}""#, rect, num(rx), num(ry)),
+                _ => format!(r#"<path d="{}""#, path_data(&Path::rounded_rect(*w, *h, resolved))),
+            }
+        }
+        Shape::Ellipse { rx, ry } => format!(r#"<ellipse cx="0" cy="0" rx="{}" ry="{}""#, num(rx.abs()), num(ry.abs())),
+        Shape::Line { x2, y2 } => format!(r#"<line x1="0" y1="0" x2="{}" y2="{}""#, num(*x2), num(*y2)),
+        Shape::Polygon { points } => {
+            if points.is_empty() {
+                return;
+            }
+            let points: Vec<String> = points.iter().map(|(x, y)| format!("{},{}", num(*x), num(*y))).collect();
+            format!(r#"<polygon points="{}""#, points.join(" "))
+        }
+        Shape::Path { path } => {
+            if path.commands.is_empty() {
+                return;
+            }
+            format!(r#"<path d="{}""#, path_data(path))
+        }
+        Shape::Freehand { stroke } => {
+            // El grosor variable no existe en SVG: se exporta el contorno relleno con la tinta
+            let outline = stroke.outline(PATH_FLATTEN_TOLERANCE);
+            if outline.commands.is_empty() {
+                return;
+            }
+            let ink = FreehandStroke::ink_style(style);
+            let _ = writeln!(out, r#"  <path d="{}"{}{}/>"#, path_data(&outline), transform, style_attrs(&ink, ink.fill.as_ref(), defs));
+            return;
+        }
+        Shape::Text { text: text_box } => {
+            let fonts = &options.fonts;
+            let layout = text::layout(text_box, &mut fonts.measurer(text::approximate_metrics));
+            write_text(out, fonts, &entity.transform, &layout, (0.0, 0.0), defs);
+            return;
+        }
+        Shape::Arrow { arrow } => {
+            // Cuerpo y puntas, la misma geometría que pintan los renderers
+            if style.stroke.is_none() {
+                return;
+            }
+            let geometry = arrow::geometry(arrow, style);
+            let _ = writeln!(out, r#"  <path d="{}"{}{}/>"#, path_data(&geometry.shaft), transform, style_attrs(style, None, defs));
+            if !geometry.filled.commands.is_empty() {
+                let ink = FreehandStroke::ink_style(style);
+                let _ = writeln!(out, r#"  <path d="{}"{}{}/>"#, path_data(&geometry.filled), transform, style_attrs(&ink, ink.fill.as_ref(), defs));
+            }
+            if !geometry.stroked.commands.is_empty() {
+                let heads = arrow::head_stroke_style(style);
+                let _ = writeln!(out, r#"  <path d="{}"{}{}/>"#, path_data(&geometry.stroked), transform, style_attrs(&heads, None, defs));
+            }
+            return;
+        }
+        Shape::Image { blob, w, h } => {
+            let Some(href) = options.images.get(blob).and_then(|data| image::data_uri(data).ok()) else { return };
+            let (x, y, width, height) = (num(w.min(0.0)), num(h.min(0.0)), num(w.abs()), num(h.abs()));
+            let opacity = if style.opacity < 1.0 { format!(r#" opacity="{}""#, num(style.opacity.max(0.0))) } else { String::new() };
+            let _ = writeln!(
+                out,
+                r#"  <image x="{}" y="{}" width="{}" height="{}" preserveAspectRatio="none" xlink:href="{}"{}{}/>"#,
+                x, y, width, height, href, transform, opacity
+            );
+            // El trazo, si lo hay, enmarca la imagen
+            if style.stroke.is_none() {
+                return;
+            }
+            format!(r#"<rect x="{}" y="{}" width="{}" height="{}""#, x, y, width, height)
+        }
+    };
+    // Las líneas solo admiten trazo, como en los renderers; la imagen hace de relleno de su marco
+    let fill = if matches!(entity.shape, Shape::Line { .. } | Shape::Image { .. }) { None } else { style.fill.as_ref() };
+    let _ = writeln!(out, "  {}{}{}/>", element, transform, style_attrs(style, fill, defs));
+}
+
+/// Relleno sombreado: las mismas líneas que trazan los renderers (`paint::draw_hatched`) y, encima,
+/// el trazo del contorno. false si el relleno no es un sombreado y no se ha escrito nada.
+fn write_hatched(out: &mut String, path: &Path, transform: &str, style: &Style, defs: &mut Defs) -> bool {
+    let Some((lines, line_style)) = paint::hatch_fill(path, style) else { return false };
+    if !lines.commands.is_empty() {
+        let _ = writeln!(out, r#"  <path d="{}"{}{}/>"#, path_data(&lines), transform, style_attrs(&line_style, None, defs));
+    }
+    if style.stroke.is_some() {
+        let _ = writeln!(out, r#"  <path d="{}"{}{}/>"#, path_data(path), transform, style_attrs(style, None, defs));
+    }
+    true
+}
+
+/// Un `<text>` por trozo maquetado, sobre su línea base, dentro de un grupo con el transform de la entidad.
+/// Los trozos conformados llevan la familia de su fuente para que el visor use la misma si la tiene.
+fn write_text(out: &mut String, fonts: &FontBook, transform: &Transform, layout: &TextLayout, origin: (f32, f32), defs: &mut Defs) {
+    if layout.lines.iter().all(|l| l.runs.is_empty()) {
+        return;
+    }
+    let _ = writeln!(out, "  <g{}>", transform_attr(transform));
+    for line in &layout.lines {
+        for run in &line.runs {
+            let span = &run.span;
+            let mut attrs = format!(r#" font-size="{}""#, num(span.size));
+            let family = span.font_family.as_deref().or_else(|| run.glyphs.first().and_then(|g| fonts.family(g.font)));
+            if let Some(family) = family.filter(|f| !f.is_empty()) {
+                let _ = write!(attrs, r#" font-family="{}""#, escape_xml(family));
+            }
+            if let Some(weight) = span.weight {
+                let _ = write!(attrs, r#" font-weight="{}""#, weight);
+            }
+            let _ = writeln!(
+                out,
+                r#"    <text x="{}" y="{}" xml:space="preserve"{}{}>{}</text>"#,
+                num(origin.0 + line.x + run.x), num(origin.1 + line.baseline), attrs, paint_attrs("fill", Some(&Paint::Solid(span.color)), 1.0, defs), escape_xml(&span.text)
+            );
+        }
+    }
+    out.push_str("  </g>\n");
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Datos `d` de un `<path>` a partir de los comandos del modelo
+pub fn path_data(path: &Path) -> String {
+    let mut d = String::new();
+    for command in &path.commands {
+        if !d.is_empty() {
+            d.push(' ');
+        }
+        let _ = match *command {
+            PathCommand::MoveTo(x, y) => write!(d, "M{} {}", num(x), num(y)),
+            PathCommand::LineTo(x, y) => write!(d, "L{} {}", num(x), num(y)),
+            PathCommand::QuadTo { cx, cy, x, y } => write!(d, "Q{} {} {} {}", num(cx), num(cy), num(x), num(y)),
+            PathCommand::CubicTo { c1x, c1y, c2x, c2y, x, y } => {
+                write!(d, "C{} {} {} {} {} {}", num(c1x), num(c1y), num(c2x), num(c2y), num(x), num(y))
+            }
+            PathCommand::Close => write!(d, "Z"),
+        };
+    }
+    d
+}
+
+/// Caja de la forma incluyendo medio trazo, para que el viewBox ajustado no recorte los bordes
+fn painted_bounds(entity: &Entity) -> BoundingBox {
+    // A mano alzada los trazos se salen algo de la forma exacta
+    let b = sketch::geometry(&entity.shape, &entity.style, &entity.transform)
+        .and_then(|geometry| geometry.bounds(&entity.transform))
+        .unwrap_or_else(|| BoundingBox::from_shape(&entity.transform, &entity.shape));
+    // La caja de un trazo de lápiz ya es la de su tinta
+    if entity.style.stroke.is_none() || matches!(entity.shape, Shape::Freehand { .. }) {
+        return b;
+    }
+    let scale = entity.transform.scale_x.abs().max(entity.transform.scale_y.abs());
+    // Las puntas de flecha sobresalen medio ancho de punta a cada lado de la línea
+    let reach = match entity.shape {
+        Shape::Arrow { .. } => arrow::head_size(&entity.style).max(entity.style.stroke_width),
+        _ => entity.style.stroke_width,
+    };
+    let half = reach * scale / 2.0;
+    BoundingBox { x: b.x - half, y: b.y - half, width: b.width + 2.0 * half, height: b.height + 2.0 * half }
+}
+
+/// Mismo orden que Canvas2D: traslación, rotación y escala
+fn transform_attr(t: &Transform) -> String {
+    let mut parts = Vec::new();
+    if t.x != 0.0 || t.y != 0.0 {
+        parts.push(format!("translate({} {})", num(t.x), num(t.y)));
+    }
+    if t.rotation != 0.0 {
+        parts.push(format!("rotate({})", num(t.rotation.to_degrees())));
+    }
+    if t.scale_x != 1.0 || t.scale_y != 1.0 {
+        parts.push(format!("scale({} {})", num(t.scale_x), num(t.scale_y)));
+    }
+    if parts.is_empty() {
+        String::new()
+    } else {
+        format!(r#" transform="{}""#, parts.join(" "))
+    }
+}
+
+fn style_attrs(style: &Style, fill: Option<&Paint>, defs: &mut Defs) -> String {
+    let mut attrs = paint_attrs("fill", fill, style.opacity, defs);
+    if style.stroke.is_none() {
+        return attrs;
+    }
+    attrs.push_str(&paint_attrs("stroke", style.stroke.as_ref(), style.opacity, defs));
+    let _ = write!(attrs, r#" stroke-width="{}""#, num(style.stroke_width));
+    match style.stroke_cap {
+        StrokeCap::Butt => {}
+        StrokeCap::Square => attrs.push_str(r#" stroke-linecap="square""#),
+        StrokeCap::Round => attrs.push_str(r#" stroke-linecap="round""#),
+    }
+    match style.stroke_join {
+        StrokeJoin::Miter => {}
+        StrokeJoin::Bevel => attrs.push_str(r#" stroke-linejoin="bevel""#),
+        StrokeJoin::Round => attrs.push_str(r#" stroke-linejoin="round""#),
+    }
+    if !style.dash.is_empty() {
+        let dash: Vec<String> = style.dash.iter().map(|d| num(*d)).collect();
+        let _ = write!(attrs, r#" stroke-dasharray="{}""#, dash.join(" "));
+        if style.dash_offset != 0.0 {
+            let _ = write!(attrs, r#" stroke-dashoffset="{}""#, num(style.dash_offset));
+        }
+    }
+    attrs
+}
+
+/// `fill`/`stroke` con su opacidad (alpha del color por la opacidad del estilo, como en los renderers).
+/// Los degradados y las imágenes se definen en `defs`; como `fill` o `stroke` de un elemento, un
+/// sombreado es su color (los rellenos sombreados se exportan antes como líneas).
+fn paint_attrs(name: &str, paint: Option<&Paint>, opacity: f32, defs: &mut Defs) -> String {
+    let url = |id: String| format!(r#" {}="url(#{})""#, name, id);
+    match paint {
+        None => format!(r#" {}="none""#, name),
+        Some(Paint::LinearGradient { start, end, stops }) => {
+            let geometry = format!(r#"x1="{}" y1="{}" x2="{}" y2="{}""#, num(start.0), num(start.1), num(end.0), num(end.1));
+            url(defs.gradient("linearGradient", geometry, stops, opacity))
+        }
+        Some(Paint::RadialGradient { center, radius, stops }) => {
+            let geometry = format!(r#"cx="{}" cy="{}" r="{}""#, num(center.0), num(center.1), num(radius.max(0.0)));
+            url(defs.gradient("radialGradient", geometry, stops, opacity))
+        }
+        Some(paint @ Paint::Image { blob, w, h }) => match defs.image_pattern(*blob, *w, *h, opacity) {
+            Some(id) => url(id),
+            None => color_attrs(name, &format!("{}-opacity", name), paint.color(), opacity),
+        },
+        Some(Paint::Solid(color) | Paint::Hatch { color, .. }) => color_attrs(name, &format!("{}-opacity", name), *color, opacity),
+    }
+}
+
+/// Color en hexadecimal y, si no es opaco, su opacidad en el atributo `opacity_name`
+fn color_attrs(name: &str, opacity_name: &str, Color(r, g, b, a): Color, opacity: f32) -> String {
+    let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+    let mut attrs = format!(r##" {}="#{:02x}{:02x}{:02x}""##, name, channel(r), channel(g), channel(b));
+    let alpha = (a * opacity).clamp(0.0, 1.0);
+    if alpha < 1.0 {
+        let _ = write!(attrs, r#" {}="{}""#, opacity_name, num(alpha));
+    }
+    attrs
+}
+
+/// Número compacto: como mucho 3 decimales y sin ceros sobrantes
+fn num(v: f32) -> String {
+    let s = format!("{:.3}", v);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" { "0".to_string() } else { s.to_string() }
+}
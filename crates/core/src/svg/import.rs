@@ -0,0 +1,729 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use roxmltree::Node;
+use thiserror::Error;
+
+use super::path_data::parse_path_data;
+use crate::model::{Color, CornerRadii, Paint, Path, PathCommand, Shape, StrokeCap, StrokeJoin, Style, Transform};
+use crate::usecases::Document;
+
+#[derive(Debug, Error)]
+pub enum SvgImportError {
+    #[error("Invalid SVG/XML: {0}")] Xml(String),
+    #[error("Root element is <{0}>, expected <svg>")] NotSvg(String),
+}
+
+/// Algo del SVG que no se pudo importar tal cual; la importación sigue con el resto
+#[derive(Clone, Debug)]
+pub struct SvgImportWarning {
+    pub element: String,
+    pub message: String,
+}
+
+impl fmt::Display for SvgImportWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<{}>: {}", self.element, self.message)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SvgImport {
+    /// Rectángulos, elipses, líneas, polígonos y trazados, en el orden de pintado del SVG
+    pub document: Document,
+    pub warnings: Vec<SvgImportWarning>,
+}
+
+/// Importa un SVG en coordenadas de usuario (las del viewBox raíz).
+/// Solo los errores de XML hacen fallar la importación; lo no soportado se avisa en `warnings`.
+pub fn from_svg(svg: &str) -> Result<SvgImport, SvgImportError> {
+    let xml = roxmltree::Document::parse(svg).map_err(|e| SvgImportError::Xml(e.to_string()))?;
+    let root = xml.root_element();
+    if root.tag_name().name() != "svg" {
+        return Err(SvgImportError::NotSvg(root.tag_name().name().to_string()));
+    }
+    let mut importer = Importer { out: SvgImport { document: Document::new(), ..SvgImport::default() } };
+    importer.children(root, Affine::IDENTITY, &Presentation::default());
+    Ok(importer.out)
+}
+
+struct Importer {
+    out: SvgImport,
+}
+
+impl Importer {
+    fn warn(&mut self, node: Node, message: impl Into<String>) {
+        self.out.warnings.push(SvgImportWarning { element: node.tag_name().name().to_string(), message: message.into() });
+    }
+
+    fn children(&mut self, node: Node, ctm: Affine, inherited: &Presentation) {
+        for child in node.children().filter(|n| n.is_element()) {
+            self.element(child, ctm, inherited);
+        }
+    }
+
+    fn element(&mut self, node: Node, parent_ctm: Affine, inherited: &Presentation) {
+        let name = node.tag_name().name();
+        // Elementos que no pintan nada por sí mismos
+        if matches!(name, "defs" | "title" | "desc" | "metadata" | "symbol" | "marker" | "clipPath" | "mask"
+            | "linearGradient" | "radialGradient" | "pattern" | "filter")
+        {
+            return;
+        }
+        let declarations = declarations(node);
+        if declarations.get("display") == Some(&"none") {
+            return;
+        }
+        let mut ctm = parent_ctm;
+        if let Some(list) = node.attribute("transform") {
+            match parse_transform(list) {
+                Ok(local) => ctm = parent_ctm.then(&local),
+                Err(e) => self.warn(node, format!("ignoring invalid transform: {}", e)),
+            }
+        }
+        let presentation = self.presentation(node, &declarations, inherited);
+        // visibility se hereda pero un hijo puede volver a mostrarse: solo se omiten las formas ocultas
+        if !presentation.visible && !matches!(name, "g" | "a" | "svg") {
+            return;
+        }
+
+        match name {
+            "g" | "a" => self.children(node, ctm, &presentation),
+            "svg" => {
+                // <svg> anidado: solo su posición; el viewBox propio no se reescala
+                if node.attribute("viewBox").is_some() {
+                    self.warn(node, "nested viewBox is ignored");
+                }
+                let x = self.length(node, "x", 0.0);
+                let y = self.length(node, "y", 0.0);
+                self.children(node, ctm.then(&Affine::translate(x, y)), &presentation);
+            }
+            "style" => self.warn(node, "CSS style sheets are not supported; only presentation attributes and style=\"\" are applied"),
+            "rect" => self.rect(node, ctm, &presentation),
+            "circle" => {
+                let r = self.length(node, "r", 0.0);
+                self.ellipse(node, ctm, &presentation, r, r);
+            }
+            "ellipse" => {
+                let rx = self.length(node, "rx", 0.0);
+                let ry = self.length(node, "ry", 0.0);
+                self.ellipse(node, ctm, &presentation, rx, ry);
+            }
+            "line" => {
+                let (x1, y1) = (self.length(node, "x1", 0.0), self.length(node, "y1", 0.0));
+                let (x2, y2) = (self.length(node, "x2", 0.0), self.length(node, "y2", 0.0));
+                let style = presentation.style(false);
+                match ctm.decompose() {
+                    Some(placed) => self.shape(placed.at(&ctm, x1, y1), style, Shape::Line { x2: x2 - x1, y2: y2 - y1 }),
+                    None => {
+                        let (ax, ay) = ctm.apply(x1, y1);
+                        let (bx, by) = ctm.apply(x2, y2);
+                        let style = baked_style(style, &ctm);
+                        self.shape(Transform { x: ax, y: ay, ..Transform::default() }, style, Shape::Line { x2: bx - ax, y2: by - ay });
+                    }
+                }
+            }
+            "polygon" | "polyline" => {
+                let (points, error) = parse_points(node.attribute("points").unwrap_or_default());
+                if let Some(e) = error {
+                    self.warn(node, format!("points truncated: {}", e));
+                }
+                if points.len() < 2 {
+                    return;
+                }
+                if name == "polygon" {
+                    self.polygon(&ctm, presentation.style(true), points);
+                } else {
+                    // Abierta: trazado sin Close (Shape::Polygon siempre cierra)
+                    let mut commands = vec![PathCommand::MoveTo(points[0].0, points[0].1)];
+                    commands.extend(points[1..].iter().map(|(x, y)| PathCommand::LineTo(*x, *y)));
+                    self.path(&ctm, presentation.style(true), Path { commands });
+                }
+            }
+            "path" => {
+                let parsed = parse_path_data(node.attribute("d").unwrap_or_default());
+                if let Some(e) = parsed.error {
+                    self.warn(node, format!("path data truncated: {}", e));
+                }
+                if !parsed.path.commands.is_empty() {
+                    self.path(&ctm, presentation.style(true), parsed.path);
+                }
+            }
+            other => self.warn(node, format!("unsupported element <{}> skipped", other)),
+        }
+    }
+
+    fn rect(&mut self, node: Node, ctm: Affine, presentation: &Presentation) {
+        let (x, y) = (self.length(node, "x", 0.0), self.length(node, "y", 0.0));
+        let (w, h) = (self.length(node, "width", 0.0), self.length(node, "height", 0.0));
+        if w <= 0.0 || h <= 0.0 {
+            return;
+        }
+        // Si solo hay uno de los dos radios, el otro vale lo mismo; ninguno pasa de medio lado
+        let (rx, ry) = match (node.attribute("rx").is_some(), node.attribute("ry").is_some()) {
+            (false, false) => (0.0, 0.0),
+            (true, false) => (self.length(node, "rx", 0.0), self.length(node, "rx", 0.0)),
+            (false, true) => (self.length(node, "ry", 0.0), self.length(node, "ry", 0.0)),
+            (true, true) => (self.length(node, "rx", 0.0), self.length(node, "ry", 0.0)),
+        };
+        let (rx, ry) = (rx.clamp(0.0, w / 2.0), ry.clamp(0.0, h / 2.0));
+        match ctm.decompose() {
+            Some(placed) if rx == 0.0 || ry == 0.0 => self.shape(placed.at(&ctm, x, y), presentation.style(true), Shape::Rect { w, h, radii: CornerRadii::default() }),
+            // Las esquinas de `Shape::Rect` son circulares en mundo: solo valen si siguen siéndolo
+            Some(placed) if rx == ry && placed.scale_x.abs() == placed.scale_y.abs() => {
+                let radii = CornerRadii::Uniform(rx);
+                self.shape(placed.at(&ctm, x, y), presentation.style(true), Shape::Rect { w, h, radii })
+            }
+            _ if rx > 0.0 && ry > 0.0 => {
+                let path = Affine::translate(x, y).apply_path(&Path::rounded_rect(w, h, [(rx, ry); 4]));
+                self.path(&ctm, presentation.style(true), path)
+            }
+            _ => self.polygon(&ctm, presentation.style(true), vec![(x, y), (x + w, y), (x + w, y + h), (x, y + h)]),
+        }
+    }
+
+    fn ellipse(&mut self, node: Node, ctm: Affine, presentation: &Presentation, rx: f32, ry: f32) {
+        if rx <= 0.0 || ry <= 0.0 {
+            return;
+        }
+        let (cx, cy) = (self.length(node, "cx", 0.0), self.length(node, "cy", 0.0));
+        match ctm.decompose() {
+            Some(placed) => self.shape(placed.at(&ctm, cx, cy), presentation.style(true), Shape::Ellipse { rx, ry }),
+            // Con sesgo ya no es una elipse alineada: cuatro cúbicas exactas a la transformación
+            None => self.path(&ctm, presentation.style(true), ellipse_path(cx, cy, rx, ry)),
+        }
+    }
+
+    fn polygon(&mut self, ctm: &Affine, style: Style, points: Vec<(f32, f32)>) {
+        match ctm.decompose() {
+            Some(placed) => self.shape(placed.at(ctm, 0.0, 0.0), style, Shape::Polygon { points }),
+            None => {
+                // Puntos horneados en mundo, relativos al primero para que la entidad tenga un origen razonable
+                let world: Vec<(f32, f32)> = points.iter().map(|(x, y)| ctm.apply(*x, *y)).collect();
+                let (ox, oy) = world[0];
+                let points = world.iter().map(|(x, y)| (x - ox, y - oy)).collect();
+                self.shape(Transform { x: ox, y: oy, ..Transform::default() }, baked_style(style, ctm), Shape::Polygon { points });
+            }
+        }
+    }
+
+    fn path(&mut self, ctm: &Affine, style: Style, path: Path) {
+        let (transform, path, style) = match ctm.decompose() {
+            Some(placed) => (placed.at(ctm, 0.0, 0.0), path, style),
+            None => (Transform::default(), ctm.apply_path(&path), baked_style(style, ctm)),
+        };
+        self.shape(transform, style, Shape::Path { path });
+    }
+
+    fn shape(&mut self, transform: Transform, style: Style, shape: Shape) {
+        self.out.document.create_shape(transform, style, shape);
+    }
+
+    /// Atributo de longitud (con unidades absolutas); si no se entiende, se avisa y se usa `default`
+    fn length(&mut self, node: Node, name: &str, default: f32) -> f32 {
+        match node.attribute(name).map(parse_length) {
+            None => default,
+            Some(Ok(v)) => v,
+            Some(Err(e)) => {
+                self.warn(node, format!("{}: {}", name, e));
+                default
+            }
+        }
+    }
+
+    /// Propiedades de presentación del elemento sobre las heredadas
+    fn presentation(&mut self, node: Node, declarations: &HashMap<&str, &str>, inherited: &Presentation) -> Presentation {
+        let mut p = inherited.clone();
+        // `opacity` no se hereda, pero la de los grupos se acumula sobre sus hijos
+        // (aproximación: no se compone el grupo como una capa aparte)
+        for (&name, &value) in declarations {
+            if value == "inherit" {
+                continue;
+            }
+            let result: Result<(), String> = match name {
+                "fill" => parse_paint(value).map(|v| p.fill = v),
+                "stroke" => parse_paint(value).map(|v| p.stroke = v),
+                "color" => parse_color(value).map(|v| p.color = v).ok_or_else(|| format!("unknown color '{}'", value)),
+                "stroke-width" => parse_length(value).map(|v| p.stroke_width = v),
+                "opacity" => parse_opacity(value).map(|v| p.opacity *= v),
+                "fill-opacity" => parse_opacity(value).map(|v| p.fill_opacity = v),
+                "stroke-opacity" => parse_opacity(value).map(|v| p.stroke_opacity = v),
+                "stroke-linecap" => match value {
+                    "butt" => { p.cap = StrokeCap::Butt; Ok(()) }
+                    "square" => { p.cap = StrokeCap::Square; Ok(()) }
+                    "round" => { p.cap = StrokeCap::Round; Ok(()) }
+                    _ => Err(format!("unknown linecap '{}'", value)),
+                },
+                "stroke-linejoin" => match value {
+                    "miter" | "miter-clip" | "arcs" => { p.join = StrokeJoin::Miter; Ok(()) }
+                    "bevel" => { p.join = StrokeJoin::Bevel; Ok(()) }
+                    "round" => { p.join = StrokeJoin::Round; Ok(()) }
+                    _ => Err(format!("unknown linejoin '{}'", value)),
+                },
+                "stroke-dasharray" => parse_dasharray(value).map(|v| p.dash = v),
+                "stroke-dashoffset" => parse_length(value).map(|v| p.dash_offset = v),
+                "visibility" => { p.visible = value == "visible"; Ok(()) }
+                _ => Ok(()),
+            };
+            if let Err(e) = result {
+                self.warn(node, format!("{}: {}", name, e));
+            }
+        }
+        p
+    }
+}
+
+/// Atributos de presentación y, por encima, las declaraciones de `style=""`
+fn declarations<'a>(node: Node<'a, 'a>) -> HashMap<&'a str, &'a str> {
+    let mut map: HashMap<&str, &str> = node
+        .attributes()
+        .filter(|a| a.namespace().is_none())
+        .map(|a| (a.name(), a.value().trim()))
+        .collect();
+    if let Some(style) = node.attribute("style") {
+        for declaration in style.split(';') {
+            if let Some((name, value)) = declaration.split_once(':') {
+                let value = value.trim().trim_end_matches("!important").trim();
+                map.insert(name.trim(), value);
+            }
+        }
+    }
+    map
+}
+
+/// Estado de estilo heredable mientras se recorre el árbol
+#[derive(Clone)]
+struct Presentation {
+    fill: PaintSpec,
+    stroke: PaintSpec,
+    color: Color,
+    stroke_width: f32,
+    opacity: f32,
+    fill_opacity: f32,
+    stroke_opacity: f32,
+    cap: StrokeCap,
+    join: StrokeJoin,
+    dash: Vec<f32>,
+    dash_offset: f32,
+    visible: bool,
+}
+
+impl Default for Presentation {
+    /// Valores iniciales de SVG: relleno negro, sin trazo, grosor 1
+    fn default() -> Self {
+        Self {
+            fill: PaintSpec::Color(Color(0.0, 0.0, 0.0, 1.0)),
+            stroke: PaintSpec::None,
+            color: Color(0.0, 0.0, 0.0, 1.0),
+            stroke_width: 1.0,
+            opacity: 1.0,
+            fill_opacity: 1.0,
+            stroke_opacity: 1.0,
+            cap: StrokeCap::Butt,
+            join: StrokeJoin::Miter,
+            dash: Vec::new(),
+            dash_offset: 0.0,
+            visible: true,
+        }
+    }
+}
+
+impl Presentation {
+    fn style(&self, fillable: bool) -> Style {
+        let resolve = |paint: &PaintSpec, alpha: f32| {
+            let Color(r, g, b, a) = match paint {
+                PaintSpec::None => return None,
+                PaintSpec::Color(c) => *c,
+                PaintSpec::CurrentColor => self.color,
+            };
+            Some(Paint::Solid(Color(r, g, b, a * alpha)))
+        };
+        Style {
+            fill: if fillable { resolve(&self.fill, self.fill_opacity) } else { None },
+            stroke: resolve(&self.stroke, self.stroke_opacity),
+            stroke_width: self.stroke_width,
+            opacity: self.opacity,
+            stroke_cap: self.cap,
+            stroke_join: self.join,
+            dash: self.dash.clone(),
+            dash_offset: self.dash_offset,
+            sketch: None,
+        }
+    }
+}
+
+#[derive(Clone)]
+enum PaintSpec {
+    None,
+    Color(Color),
+    CurrentColor,
+}
+
+fn parse_paint(value: &str) -> Result<PaintSpec, String> {
+    match value {
+        "none" => Ok(PaintSpec::None),
+        "currentColor" => Ok(PaintSpec::CurrentColor),
+        _ if value.starts_with("url(") => {
+            // Degradados y patrones: se usa el color de reserva si lo hay
+            let fallback = value.split_once(')').map(|(_, rest)| rest.trim()).unwrap_or_default();
+            match parse_color(fallback) {
+                Some(color) => Ok(PaintSpec::Color(color)),
+                None => Err(format!("paint server {} is not supported", value)),
+            }
+        }
+        _ => parse_color(value).map(PaintSpec::Color).ok_or_else(|| format!("unknown color '{}'", value)),
+    }
+}
+
+/// `#rgb`, `#rgba`, `#rrggbb`, `#rrggbbaa`, `rgb()/rgba()` y los nombres CSS más comunes
+fn parse_color(value: &str) -> Option<Color> {
+    let value = value.trim();
+    if let Some(hex) = value.strip_prefix('#') {
+        let digits: Vec<u8> = hex.chars().map(|c| c.to_digit(16).map(|d| d as u8)).collect::<Option<_>>()?;
+        let channel = |hi: u8, lo: u8| (hi * 16 + lo) as f32 / 255.0;
+        return match digits.as_slice() {
+            [r, g, b] => Some(Color(channel(*r, *r), channel(*g, *g), channel(*b, *b), 1.0)),
+            [r, g, b, a] => Some(Color(channel(*r, *r), channel(*g, *g), channel(*b, *b), channel(*a, *a))),
+            [r1, r2, g1, g2, b1, b2] => Some(Color(channel(*r1, *r2), channel(*g1, *g2), channel(*b1, *b2), 1.0)),
+            [r1, r2, g1, g2, b1, b2, a1, a2] => {
+                Some(Color(channel(*r1, *r2), channel(*g1, *g2), channel(*b1, *b2), channel(*a1, *a2)))
+            }
+            _ => None,
+        };
+    }
+    if let Some(args) = value.strip_prefix("rgba(").or_else(|| value.strip_prefix("rgb(")) {
+        let parts: Vec<&str> = args.trim_end_matches(')').split([',', ' ', '/']).filter(|s| !s.is_empty()).collect();
+        let channel = |s: &str| match s.strip_suffix('%') {
+            Some(pct) => pct.parse::<f32>().ok().map(|v| v / 100.0),
+            None => s.parse::<f32>().ok().map(|v| v / 255.0),
+        };
+        let alpha = match parts.get(3) {
+            Some(a) => parse_opacity(a).ok()?,
+            None => 1.0,
+        };
+        return match parts[..] {
+            [r, g, b, ..] => Some(Color(channel(r)?.clamp(0.0, 1.0), channel(g)?.clamp(0.0, 1.0), channel(b)?.clamp(0.0, 1.0), alpha)),
+            _ => None,
+        };
+    }
+    let rgb = |r: u8, g: u8, b: u8| Some(Color(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, 1.0));
+    match value.to_ascii_lowercase().as_str() {
+        "transparent" => Some(Color(0.0, 0.0, 0.0, 0.0)),
+        "black" => rgb(0, 0, 0),
+        "white" => rgb(255, 255, 255),
+        "red" => rgb(255, 0, 0),
+        "green" => rgb(0, 128, 0),
+        "lime" => rgb(0, 255, 0),
+        "blue" => rgb(0, 0, 255),
+        "yellow" => rgb(255, 255, 0),
+        "cyan" | "aqua" => rgb(0, 255, 255),
+        "magenta" | "fuchsia" => rgb(255, 0, 255),
+        "gray" | "grey" => rgb(128, 128, 128),
+        "darkgray" | "darkgrey" => rgb(169, 169, 169),
+        "lightgray" | "lightgrey" => rgb(211, 211, 211),
+        "silver" => rgb(192, 192, 192),
+        "maroon" => rgb(128, 0, 0),
+        "navy" => rgb(0, 0, 128),
+        "teal" => rgb(0, 128, 128),
+        "olive" => rgb(128, 128, 0),
+        "purple" => rgb(128, 0, 128),
+        "orange" => rgb(255, 165, 0),
+        "pink" => rgb(255, 192, 203),
+        "brown" => rgb(165, 42, 42),
+        "gold" => rgb(255, 215, 0),
+        "indigo" => rgb(75, 0, 130),
+        "violet" => rgb(238, 130, 238),
+        "coral" => rgb(255, 127, 80),
+        "salmon" => rgb(250, 128, 114),
+        "tomato" => rgb(255, 99, 71),
+        "crimson" => rgb(220, 20, 60),
+        "steelblue" => rgb(70, 130, 180),
+        "skyblue" => rgb(135, 206, 235),
+        "royalblue" => rgb(65, 105, 225),
+        "darkblue" => rgb(0, 0, 139),
+        "darkgreen" => rgb(0, 100, 0),
+        "darkred" => rgb(139, 0, 0),
+        "whitesmoke" => rgb(245, 245, 245),
+        _ => None,
+    }
+}
+
+fn parse_opacity(value: &str) -> Result<f32, String> {
+    let v = match value.strip_suffix('%') {
+        Some(pct) => pct.trim().parse::<f32>().map(|v| v / 100.0),
+        None => value.parse::<f32>(),
+    };
+    v.map(|v| v.clamp(0.0, 1.0)).map_err(|_| format!("invalid opacity '{}'", value))
+}
+
+/// Longitud en unidades de usuario. Se convierten las unidades absolutas (96 px por pulgada).
+fn parse_length(value: &str) -> Result<f32, String> {
+    let value = value.trim();
+    // La unidad son las letras finales ("1e3" es un número, "1em" no)
+    let unit_len = value.bytes().rev().take_while(|b| b.is_ascii_alphabetic() || *b == b'%').count();
+    let (number, unit) = value.split_at(value.len() - unit_len);
+    let number: f32 = number.trim().parse().map_err(|_| format!("invalid length '{}'", value))?;
+    let factor = match unit {
+        "" | "px" => 1.0,
+        "pt" => 96.0 / 72.0,
+        "pc" => 16.0,
+        "mm" => 96.0 / 25.4,
+        "cm" => 96.0 / 2.54,
+        "in" => 96.0,
+        "em" => 16.0,
+        _ => return Err(format!("unsupported unit in '{}'", value)),
+    };
+    Ok(number * factor)
+}
+
+fn parse_dasharray(value: &str) -> Result<Vec<f32>, String> {
+    if value == "none" {
+        return Ok(Vec::new());
+    }
+    let dash = value
+        .split([',', ' '])
+        .filter(|s| !s.is_empty())
+        .map(parse_length)
+        .collect::<Result<Vec<f32>, _>>()?;
+    // Todo ceros (o negativos) equivale a trazo continuo
+    if dash.iter().any(|d| *d < 0.0) || dash.iter().all(|d| *d == 0.0) {
+        return Ok(Vec::new());
+    }
+    Ok(dash)
+}
+
+/// Lista de puntos; si está mal formada se conservan los pares leídos hasta el error
+fn parse_points(value: &str) -> (Vec<(f32, f32)>, Option<String>) {
+    let mut numbers = Vec::new();
+    let mut error = None;
+    for token in value.split([',', ' ', '\t', '\n', '\r']).filter(|s| !s.is_empty()) {
+        match token.parse::<f32>() {
+            Ok(v) => numbers.push(v),
+            Err(_) => {
+                error = Some(format!("invalid number '{}'", token));
+                break;
+            }
+        }
+    }
+    if error.is_none() && numbers.len() % 2 == 1 {
+        error = Some("odd number of coordinates".into());
+    }
+    (numbers.chunks_exact(2).map(|c| (c[0], c[1])).collect(), error)
+}
+
+/// Elipse como cuatro cúbicas (constante kappa)
+fn ellipse_path(cx: f32, cy: f32, rx: f32, ry: f32) -> Path {
+    const KAPPA: f32 = 0.552_284_8;
+    let (kx, ky) = (rx * KAPPA, ry * KAPPA);
+    Path {
+        commands: vec![
+            PathCommand::MoveTo(cx + rx, cy),
+            PathCommand::CubicTo { c1x: cx + rx, c1y: cy + ky, c2x: cx + kx, c2y: cy + ry, x: cx, y: cy + ry },
+            PathCommand::CubicTo { c1x: cx - kx, c1y: cy + ry, c2x: cx - rx, c2y: cy + ky, x: cx - rx, y: cy },
+            PathCommand::CubicTo { c1x: cx - rx, c1y: cy - ky, c2x: cx - kx, c2y: cy - ry, x: cx, y: cy - ry },
+            PathCommand::CubicTo { c1x: cx + kx, c1y: cy - ry, c2x: cx + rx, c2y: cy - ky, x: cx + rx, y: cy },
+            PathCommand::Close,
+        ],
+    }
+}
+
+/// Al hornear la transformación en la geometría, el grosor del trazo se escala con ella
+fn baked_style(mut style: Style, ctm: &Affine) -> Style {
+    style.stroke_width *= ctm.det().abs().sqrt();
+    style
+}
+
+/// Afín 2D [a c e; b d f] (misma convención que `transform="matrix(a b c d e f)"`)
+#[derive(Clone, Copy, Debug)]
+struct Affine {
+    a: f32,
+    b: f32,
+    c: f32,
+    d: f32,
+    e: f32,
+    f: f32,
+}
+
+/// Rotación y escala de una afín sin sesgo, que es lo que admite `Transform`
+struct Placement {
+    rotation: f32,
+    scale_x: f32,
+    scale_y: f32,
+}
+
+impl Placement {
+    /// Transform de entidad con origen en el punto local (px, py)
+    fn at(&self, ctm: &Affine, px: f32, py: f32) -> Transform {
+        let (x, y) = ctm.apply(px, py);
+        Transform { x, y, rotation: self.rotation, scale_x: self.scale_x, scale_y: self.scale_y }
+    }
+}
+
+impl Affine {
+    const IDENTITY: Affine = Affine { a: 1.0, b: 0.0, c: 0.0, d: 1.0, e: 0.0, f: 0.0 };
+
+    fn translate(x: f32, y: f32) -> Self {
+        Affine { e: x, f: y, ..Self::IDENTITY }
+    }
+
+    /// `self` seguida de `local` (local se aplica primero a los puntos)
+    fn then(&self, local: &Affine) -> Affine {
+        Affine {
+            a: self.a * local.a + self.c * local.b,
+            b: self.b * local.a + self.d * local.b,
+            c: self.a * local.c + self.c * local.d,
+            d: self.b * local.c + self.d * local.d,
+            e: self.a * local.e + self.c * local.f + self.e,
+            f: self.b * local.e + self.d * local.f + self.f,
+        }
+    }
+
+    fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        (self.a * x + self.c * y + self.e, self.b * x + self.d * y + self.f)
+    }
+
+    fn det(&self) -> f32 {
+        self.a * self.d - self.b * self.c
+    }
+
+    /// Descompone en rotación * escala (sin sesgo); None si hay sesgo o es degenerada
+    fn decompose(&self) -> Option<Placement> {
+        let scale_x = self.a.hypot(self.b);
+        if scale_x <= f32::EPSILON {
+            return None;
+        }
+        let rotation = self.b.atan2(self.a);
+        let (sin, cos) = rotation.sin_cos();
+        // Segunda columna esperada: R * (0, sy)
+        let scale_y = -self.c * sin + self.d * cos;
+        let skew = (self.c + sin * scale_y).abs() + (self.d - cos * scale_y).abs();
+        if scale_y.abs() <= f32::EPSILON || skew > 1e-4 * scale_x.max(scale_y.abs()) {
+            return None;
+        }
+        Some(Placement { rotation, scale_x, scale_y })
+    }
+
+    /// Las afines conservan rectas y curvas de Bézier: basta transformar los puntos de control
+    fn apply_path(&self, path: &Path) -> Path {
+        let commands = path
+            .commands
+            .iter()
+            .map(|command| match *command {
+                PathCommand::MoveTo(x, y) => {
+                    let (x, y) = self.apply(x, y);
+                    PathCommand::MoveTo(x, y)
+                }
+                PathCommand::LineTo(x, y) => {
+                    let (x, y) = self.apply(x, y);
+                    PathCommand::LineTo(x, y)
+                }
+                PathCommand::QuadTo { cx, cy, x, y } => {
+                    let ((cx, cy), (x, y)) = (self.apply(cx, cy), self.apply(x, y));
+                    PathCommand::QuadTo { cx, cy, x, y }
+                }
+                PathCommand::CubicTo { c1x, c1y, c2x, c2y, x, y } => {
+                    let ((c1x, c1y), (c2x, c2y), (x, y)) = (self.apply(c1x, c1y), self.apply(c2x, c2y), self.apply(x, y));
+                    PathCommand::CubicTo { c1x, c1y, c2x, c2y, x, y }
+                }
+                PathCommand::Close => PathCommand::Close,
+            })
+            .collect();
+        Path { commands }
+    }
+}
+
+/// Lista de `transform`: matrix, translate, scale, rotate (con centro opcional), skewX y skewY
+fn parse_transform(list: &str) -> Result<Affine, String> {
+    let mut result = Affine::IDENTITY;
+    let mut rest = list.trim();
+    while !rest.is_empty() {
+        let open = rest.find('(').ok_or_else(|| format!("expected '(' in '{}'", rest))?;
+        // El cierre se busca tras la apertura: con ")(" no hay argumentos que leer
+        let close = rest[open..].find(')').map(|i| open + i).ok_or_else(|| format!("expected ')' in '{}'", rest))?;
+        let name = rest[..open].trim();
+        let args: Vec<f32> = rest[open + 1..close]
+            .split([',', ' ', '\t', '\n'])
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse::<f32>().map_err(|_| format!("invalid number '{}'", s)))
+            .collect::<Result<_, _>>()?;
+        let m = match (name, args.as_slice()) {
+            ("matrix", [a, b, c, d, e, f]) => Affine { a: *a, b: *b, c: *c, d: *d, e: *e, f: *f },
+            ("translate", [x]) => Affine::translate(*x, 0.0),
+            ("translate", [x, y]) => Affine::translate(*x, *y),
+            ("scale", [s]) => Affine { a: *s, d: *s, ..Affine::IDENTITY },
+            ("scale", [sx, sy]) => Affine { a: *sx, d: *sy, ..Affine::IDENTITY },
+            ("rotate", [deg]) => rotation(*deg),
+            ("rotate", [deg, cx, cy]) => Affine::translate(*cx, *cy).then(&rotation(*deg)).then(&Affine::translate(-cx, -cy)),
+            ("skewX", [deg]) => Affine { c: deg.to_radians().tan(), ..Affine::IDENTITY },
+            ("skewY", [deg]) => Affine { b: deg.to_radians().tan(), ..Affine::IDENTITY },
+            _ => return Err(format!("unsupported transform '{}' with {} arguments", name, args.len())),
+        };
+        result = result.then(&m);
+        rest = rest[close + 1..].trim_start_matches([',', ' ', '\t', '\n']);
+    }
+    Ok(result)
+}
+
+fn rotation(deg: f32) -> Affine {
+    let (sin, cos) = deg.to_radians().sin_cos();
+    Affine { a: cos, b: sin, c: -sin, d: cos, e: 0.0, f: 0.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    #[test]
+    fn transform_lists_compose_left_to_right() {
+        let m = parse_transform("translate(10, 20) scale(2)").unwrap();
+        assert_eq!(m.apply(1.0, 1.0), (12.0, 22.0));
+        let m = parse_transform("rotate(90 5 5)").unwrap();
+        let (x, y) = m.apply(10.0, 5.0);
+        assert!(close(x, 5.0) && close(y, 10.0));
+        let m = parse_transform("matrix(1 0 0 1 3 4),skewX(0)").unwrap();
+        assert_eq!(m.apply(0.0, 0.0), (3.0, 4.0));
+        assert_eq!(parse_transform("  ").unwrap().apply(7.0, 8.0), (7.0, 8.0));
+    }
+
+    #[test]
+    fn malformed_transforms_are_errors_not_panics() {
+        for list in [")(", ")", "(", "translate(1", "scale)(2", "translate(a b)", "rotate()", "shear(1)", "matrix(1 2 3)"] {
+            assert!(parse_transform(list).is_err(), "{list}");
+        }
+    }
+
+    #[test]
+    fn broken_transform_attribute_only_warns() {
+        let import = from_svg(r#"<svg xmlns="http://www.w3.org/2000/svg"><rect width="10" height="10" transform=")("/></svg>"#).unwrap();
+        assert_eq!(import.document.count(), 1);
+        assert!(!import.warnings.is_empty());
+    }
+
+    #[test]
+    fn colors_in_every_notation() {
+        assert_eq!(parse_color("#f00"), Some(Color(1.0, 0.0, 0.0, 1.0)));
+        assert_eq!(parse_color("#ff000080").map(|c| (c.0, c.3)), Some((1.0, 128.0 / 255.0)));
+        assert_eq!(parse_color("rgb(255, 0, 0)"), Some(Color(1.0, 0.0, 0.0, 1.0)));
+        assert_eq!(parse_color("rgba(100%, 0%, 0%, 0.5)"), Some(Color(1.0, 0.0, 0.0, 0.5)));
+        assert_eq!(parse_color(" Red "), Some(Color(1.0, 0.0, 0.0, 1.0)));
+        for value in ["", "#", "#ff", "#12345", "#ggg", "#ffé", "rgb(", "rgb(1, 2)", "rgb(a, b, c)", "rgba(1, 2, 3, x)", "nocolor"] {
+            assert_eq!(parse_color(value), None, "{value}");
+        }
+    }
+
+    #[test]
+    fn lengths_convert_absolute_units() {
+        assert_eq!(parse_length("12"), Ok(12.0));
+        assert_eq!(parse_length(" 3px "), Ok(3.0));
+        assert_eq!(parse_length("1in"), Ok(96.0));
+        assert_eq!(parse_length("1e2"), Ok(100.0));
+        assert!(close(parse_length("72pt").unwrap(), 96.0));
+        for value in ["", "px", "1vw", "abc", "1.2.3", "é", "1é", "--1"] {
+            assert!(parse_length(value).is_err(), "{value}");
+        }
+    }
+}
@@ -0,0 +1,9 @@
+//! Intercambio con SVG: exportación de documentos (y selecciones) a un SVG autónomo
+//! e importación de SVG a entidades del documento.
+
+mod export;
+mod import;
+mod path_data;
+
+pub use export::{path_data, selection_to_svg, to_svg, SvgExportOptions};
+pub use import::{from_svg, SvgImport, SvgImportError, SvgImportWarning};
@@ -0,0 +1,345 @@
+//! Parser del atributo `d` de `<path>`: todos los comandos (absolutos y relativos) se
+//! normalizan a `PathCommand` absolutos. H/V pasan a LineTo, S/T a curvas con el control
+//! reflejado y los arcos (A) se aproximan con cúbicas.
+
+use std::f64::consts::PI;
+
+use crate::model::{Path, PathCommand};
+
+/// Trazado resultante; `error` indica dónde se dejó de leer si `d` estaba mal formado
+/// (como en los navegadores, se conserva todo lo anterior al error).
+pub(crate) struct ParsedPath {
+    pub path: Path,
+    pub error: Option<String>,
+}
+
+pub(crate) fn parse_path_data(d: &str) -> ParsedPath {
+    let mut parser = Parser { src: d.as_bytes(), pos: 0 };
+    let mut out = Vec::new();
+    let error = parse_commands(&mut parser, &mut out).err();
+    ParsedPath { path: Path { commands: out }, error }
+}
+
+fn parse_commands(p: &mut Parser, out: &mut Vec<PathCommand>) -> Result<(), String> {
+    let (mut cx, mut cy) = (0.0f32, 0.0f32);
+    let (mut start_x, mut start_y) = (0.0f32, 0.0f32);
+    // Último punto de control de una cúbica/cuadrática, para reflejarlo en S/T
+    let mut last_cubic: Option<(f32, f32)> = None;
+    let mut last_quad: Option<(f32, f32)> = None;
+    let mut command: Option<u8> = None;
+
+    loop {
+        p.skip_separators();
+        let Some(next) = p.peek() else { return Ok(()) };
+        let cmd = if next.is_ascii_alphabetic() {
+            p.pos += 1;
+            next
+        } else {
+            // Parámetros repetidos: se repite el comando anterior (tras M, como L)
+            match command {
+                Some(b'M') => b'L',
+                Some(b'm') => b'l',
+                Some(c) if c != b'Z' && c != b'z' => c,
+                _ => return Err(format!("expected a command at byte {}", p.pos)),
+            }
+        };
+        if command.is_none() && !matches!(cmd, b'M' | b'm') {
+            return Err("path data must start with a moveto".into());
+        }
+        command = Some(cmd);
+        let relative = cmd.is_ascii_lowercase();
+        let (ox, oy) = if relative { (cx, cy) } else { (0.0, 0.0) };
+
+        let (mut next_cubic, mut next_quad) = (None, None);
+        match cmd.to_ascii_uppercase() {
+            b'M' => {
+                let (x, y) = (p.number()? + ox, p.number()? + oy);
+                out.push(PathCommand::MoveTo(x, y));
+                (cx, cy) = (x, y);
+                (start_x, start_y) = (x, y);
+            }
+            b'L' => {
+                let (x, y) = (p.number()? + ox, p.number()? + oy);
+                out.push(PathCommand::LineTo(x, y));
+                (cx, cy) = (x, y);
+            }
+            b'H' => {
+                cx = p.number()? + ox;
+                out.push(PathCommand::LineTo(cx, cy));
+            }
+            b'V' => {
+                cy = p.number()? + oy;
+                out.push(PathCommand::LineTo(cx, cy));
+            }
+            b'C' => {
+                let (c1x, c1y) = (p.number()? + ox, p.number()? + oy);
+                let (c2x, c2y) = (p.number()? + ox, p.number()? + oy);
+                let (x, y) = (p.number()? + ox, p.number()? + oy);
+                out.push(PathCommand::CubicTo { c1x, c1y, c2x, c2y, x, y });
+                next_cubic = Some((c2x, c2y));
+                (cx, cy) = (x, y);
+            }
+            b'S' => {
+                let (c1x, c1y) = reflect(last_cubic, cx, cy);
+                let (c2x, c2y) = (p.number()? + ox, p.number()? + oy);
+                let (x, y) = (p.number()? + ox, p.number()? + oy);
+                out.push(PathCommand::CubicTo { c1x, c1y, c2x, c2y, x, y });
+                next_cubic = Some((c2x, c2y));
+                (cx, cy) = (x, y);
+            }
+            b'Q' => {
+                let (qx, qy) = (p.number()? + ox, p.number()? + oy);
+                let (x, y) = (p.number()? + ox, p.number()? + oy);
+                out.push(PathCommand::QuadTo { cx: qx, cy: qy, x, y });
+                next_quad = Some((qx, qy));
+                (cx, cy) = (x, y);
+            }
+            b'T' => {
+                let (qx, qy) = reflect(last_quad, cx, cy);
+                let (x, y) = (p.number()? + ox, p.number()? + oy);
+                out.push(PathCommand::QuadTo { cx: qx, cy: qy, x, y });
+                next_quad = Some((qx, qy));
+                (cx, cy) = (x, y);
+            }
+            b'A' => {
+                let (rx, ry, angle) = (p.number()?, p.number()?, p.number()?);
+                let (large_arc, sweep) = (p.flag()?, p.flag()?);
+                let (x, y) = (p.number()? + ox, p.number()? + oy);
+                arc_to_cubics(out, (cx, cy), rx, ry, angle, large_arc, sweep, (x, y));
+                (cx, cy) = (x, y);
+            }
+            b'Z' => {
+                out.push(PathCommand::Close);
+                (cx, cy) = (start_x, start_y);
+            }
+            other => return Err(format!("unknown path command '{}'", other as char)),
+        }
+        last_cubic = next_cubic;
+        last_quad = next_quad;
+    }
+}
+
+/// Reflejo del último control respecto al punto actual (o el propio punto si no hay curva previa)
+fn reflect(control: Option<(f32, f32)>, cx: f32, cy: f32) -> (f32, f32) {
+    match control {
+        Some((x, y)) => (2.0 * cx - x, 2.0 * cy - y),
+        None => (cx, cy),
+    }
+}
+
+/// Arco elíptico en parametrización de extremos (SVG 1.1, apéndice F.6) como cúbicas de hasta 90°
+#[allow(clippy::too_many_arguments)]
+fn arc_to_cubics(
+    out: &mut Vec<PathCommand>,
+    from: (f32, f32),
+    rx: f32,
+    ry: f32,
+    angle_deg: f32,
+    large_arc: bool,
+    sweep: bool,
+    to: (f32, f32),
+) {
+    let (x1, y1) = (from.0 as f64, from.1 as f64);
+    let (x2, y2) = (to.0 as f64, to.1 as f64);
+    if x1 == x2 && y1 == y2 {
+        return;
+    }
+    let (mut rx, mut ry) = ((rx as f64).abs(), (ry as f64).abs());
+    if rx == 0.0 || ry == 0.0 {
+        out.push(PathCommand::LineTo(to.0, to.1));
+        return;
+    }
+    let phi = (angle_deg as f64).to_radians();
+    let (sin_phi, cos_phi) = phi.sin_cos();
+
+    // Punto inicial en el marco del arco
+    let dx = (x1 - x2) / 2.0;
+    let dy = (y1 - y2) / 2.0;
+    let x1p = cos_phi * dx + sin_phi * dy;
+    let y1p = -sin_phi * dx + cos_phi * dy;
+
+    // Radios demasiado pequeños: se agrandan lo justo para que el arco exista
+    let lambda = (x1p * x1p) / (rx * rx) + (y1p * y1p) / (ry * ry);
+    if lambda > 1.0 {
+        let s = lambda.sqrt();
+        rx *= s;
+        ry *= s;
+    }
+
+    let num = rx * rx * ry * ry - rx * rx * y1p * y1p - ry * ry * x1p * x1p;
+    let den = rx * rx * y1p * y1p + ry * ry * x1p * x1p;
+    let mut coef = (num / den).max(0.0).sqrt();
+    if large_arc == sweep {
+        coef = -coef;
+    }
+    let cxp = coef * rx * y1p / ry;
+    let cyp = -coef * ry * x1p / rx;
+    let center_x = cos_phi * cxp - sin_phi * cyp + (x1 + x2) / 2.0;
+    let center_y = sin_phi * cxp + cos_phi * cyp + (y1 + y2) / 2.0;
+
+    let angle_between = |ux: f64, uy: f64, vx: f64, vy: f64| {
+        let sign = if ux * vy - uy * vx < 0.0 { -1.0 } else { 1.0 };
+        let dot = (ux * vx + uy * vy) / ((ux * ux + uy * uy).sqrt() * (vx * vx + vy * vy).sqrt());
+        sign * dot.clamp(-1.0, 1.0).acos()
+    };
+    let ux = (x1p - cxp) / rx;
+    let uy = (y1p - cyp) / ry;
+    let vx = (-x1p - cxp) / rx;
+    let vy = (-y1p - cyp) / ry;
+    let theta1 = angle_between(1.0, 0.0, ux, uy);
+    let mut delta = angle_between(ux, uy, vx, vy);
+    if !sweep && delta > 0.0 {
+        delta -= 2.0 * PI;
+    } else if sweep && delta < 0.0 {
+        delta += 2.0 * PI;
+    }
+
+    let segments = (delta.abs() / (PI / 2.0)).ceil().max(1.0) as usize;
+    let step = delta / segments as f64;
+    let k = 4.0 / 3.0 * (step / 4.0).tan();
+    let point = |t: f64| {
+        let (sin_t, cos_t) = t.sin_cos();
+        (
+            center_x + rx * cos_phi * cos_t - ry * sin_phi * sin_t,
+            center_y + rx * sin_phi * cos_t + ry * cos_phi * sin_t,
+        )
+    };
+    let derivative = |t: f64| {
+        let (sin_t, cos_t) = t.sin_cos();
+        (-rx * cos_phi * sin_t - ry * sin_phi * cos_t, -rx * sin_phi * sin_t + ry * cos_phi * cos_t)
+    };
+
+    let mut t = theta1;
+    for i in 0..segments {
+        let t2 = t + step;
+        let (p1x, p1y) = point(t);
+        let (d1x, d1y) = derivative(t);
+        let (p2x, p2y) = if i + 1 == segments { (x2, y2) } else { point(t2) };
+        let (d2x, d2y) = derivative(t2);
+        out.push(PathCommand::CubicTo {
+            c1x: (p1x + k * d1x) as f32,
+            c1y: (p1y + k * d1y) as f32,
+            c2x: (p2x - k * d2x) as f32,
+            c2y: (p2y - k * d2y) as f32,
+            x: p2x as f32,
+            y: p2y as f32,
+        });
+        t = t2;
+    }
+}
+
+struct Parser<'a> {
+    src: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.src.get(self.pos).copied()
+    }
+
+    fn skip_separators(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r' | b',')) {
+            self.pos += 1;
+        }
+    }
+
+    /// Número SVG: admite signo, decimales sin parte entera y exponente ("-.5e2", "1.5.5" son dos números)
+    fn number(&mut self) -> Result<f32, String> {
+        self.skip_separators();
+        let start = self.pos;
+        if matches!(self.peek(), Some(b'+' | b'-')) {
+            self.pos += 1;
+        }
+        let mut digits = self.eat_digits();
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            digits += self.eat_digits();
+        }
+        if digits == 0 {
+            self.pos = start;
+            return Err(format!("expected a number at byte {}", start));
+        }
+        if matches!(self.peek(), Some(b'e' | b'E')) {
+            let mark = self.pos;
+            self.pos += 1;
+            if matches!(self.peek(), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+            if self.eat_digits() == 0 {
+                self.pos = mark;
+            }
+        }
+        let text = std::str::from_utf8(&self.src[start..self.pos]).unwrap_or_default();
+        text.parse().map_err(|_| format!("invalid number '{}'", text))
+    }
+
+    /// Flag de arco: un único carácter 0/1, que puede ir pegado al siguiente número ("a1 1 0 014 4")
+    fn flag(&mut self) -> Result<bool, String> {
+        self.skip_separators();
+        match self.peek() {
+            Some(b'0') => { self.pos += 1; Ok(false) }
+            Some(b'1') => { self.pos += 1; Ok(true) }
+            _ => Err(format!("expected an arc flag at byte {}", self.pos)),
+        }
+    }
+
+    fn eat_digits(&mut self) -> usize {
+        let start = self.pos;
+        while matches!(self.peek(), Some(b'0'..=b'9')) {
+            self.pos += 1;
+        }
+        self.pos - start
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_and_shorthand_commands_become_absolute() {
+        let parsed = parse_path_data("m10 10 h5 v5 l-5-5z M0,0 L1 1 2 2");
+        assert!(parsed.error.is_none());
+        assert_eq!(
+            parsed.path.commands,
+            vec![
+                PathCommand::MoveTo(10.0, 10.0),
+                PathCommand::LineTo(15.0, 10.0),
+                PathCommand::LineTo(15.0, 15.0),
+                PathCommand::LineTo(10.0, 10.0),
+                PathCommand::Close,
+                PathCommand::MoveTo(0.0, 0.0),
+                PathCommand::LineTo(1.0, 1.0),
+                PathCommand::LineTo(2.0, 2.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn smooth_curves_reflect_and_arcs_become_cubics() {
+        let parsed = parse_path_data("M0 0 C0 10 10 10 10 0 S20 -10 20 0 A5 5 0 0110 0");
+        assert!(parsed.error.is_none());
+        let commands = &parsed.path.commands;
+        assert_eq!(commands[2], PathCommand::CubicTo { c1x: 10.0, c1y: -10.0, c2x: 20.0, c2y: -10.0, x: 20.0, y: 0.0 });
+        // Semicírculo: dos cúbicas de 90° que acaban en el punto pedido
+        assert_eq!(commands.len(), 5);
+        assert!(matches!(commands[4], PathCommand::CubicTo { x, y, .. } if (x - 10.0).abs() < 1e-3 && y.abs() < 1e-3));
+    }
+
+    #[test]
+    fn malformed_data_keeps_what_was_read() {
+        let parsed = parse_path_data("M0 0 L10 10 L20");
+        assert_eq!(parsed.path.commands, vec![PathCommand::MoveTo(0.0, 0.0), PathCommand::LineTo(10.0, 10.0)]);
+        assert!(parsed.error.is_some());
+
+        for d in ["L1 1", "M", "M1", "M1 1 X2 2", "M1 1 A1 1 0 2 0 3 3", "M.e1 0", "M1 1 z 2", "Mé"] {
+            assert!(parse_path_data(d).error.is_some(), "{d}");
+        }
+        assert!(parse_path_data("").error.is_none());
+        assert!(parse_path_data(" ,, ").error.is_none());
+        // Arcos degenerados: radio cero como recta y mismo punto, nada
+        let parsed = parse_path_data("M0 0 A0 5 0 0 1 10 0 A5 5 0 0 1 10 0");
+        assert_eq!(parsed.path.commands, vec![PathCommand::MoveTo(0.0, 0.0), PathCommand::LineTo(10.0, 0.0)]);
+    }
+}
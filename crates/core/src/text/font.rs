@@ -0,0 +1,263 @@
+//! Fuentes cargadas en el núcleo y conformado de texto con rustybuzz.
+//!
+//! Con las mismas fuentes y el mismo texto, el resultado (avances, glifos y métricas) es idéntico
+//! en cualquier plataforma y sesión: es lo que hace que todos los renderers y las exportaciones
+//! compartan maquetación. El texto se conforma de izquierda a derecha.
+
+use std::fmt;
+use std::sync::Arc;
+
+use rustybuzz::ttf_parser::{self, name_id, GlyphId};
+use rustybuzz::{Direction, Face, UnicodeBuffer};
+use thiserror::Error;
+
+use super::{ShapedText, TextMeasure};
+use crate::model::{Path, PathCommand, TextMetrics, TextSpan};
+
+/// Peso por defecto (normal) cuando el tramo no indica ninguno
+const DEFAULT_WEIGHT: u16 = 400;
+
+#[derive(Debug, Error)]
+pub enum FontError {
+    #[error("Unsupported or corrupt font file")] InvalidFont,
+}
+
+/// Índice de una fuente dentro de su `FontBook`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FontId(pub usize);
+
+/// Glifo conformado, listo para pintar con `FontBook::glyph_path`
+#[derive(Clone, Copy, Debug)]
+pub struct Glyph {
+    pub font: FontId,
+    pub id: u16,
+    /// Carácter (offset en el trozo) con el que empieza su cluster
+    pub cluster: usize,
+    /// Origen del glifo: x respecto al inicio del trozo, y respecto a su línea base
+    /// (en una maquetación, respecto al borde superior del trozo)
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Clone)]
+struct LoadedFont {
+    data: Arc<[u8]>,
+    family: String,
+    weight: u16,
+    italic: bool,
+}
+
+impl fmt::Debug for LoadedFont {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoadedFont")
+            .field("family", &self.family)
+            .field("weight", &self.weight)
+            .field("italic", &self.italic)
+            .field("bytes", &self.data.len())
+            .finish()
+    }
+}
+
+/// Conjunto de fuentes TrueType/OpenType. Clonarlo es barato: los datos se comparten.
+#[derive(Clone, Debug, Default)]
+pub struct FontBook {
+    fonts: Vec<LoadedFont>,
+}
+
+impl FontBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Añade una fuente a partir del fichero completo (.ttf/.otf; en colecciones, la primera)
+    pub fn load(&mut self, data: Vec<u8>) -> Result<FontId, FontError> {
+        let face = Face::from_slice(&data, 0).ok_or(FontError::InvalidFont)?;
+        let family = family_name(&face).unwrap_or_default();
+        let weight = face.weight().to_number();
+        let italic = face.is_italic();
+        drop(face);
+        self.fonts.push(LoadedFont { data: data.into(), family, weight, italic });
+        Ok(FontId(self.fonts.len() - 1))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fonts.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.fonts.len()
+    }
+
+    pub fn family(&self, id: FontId) -> Option<&str> {
+        self.fonts.get(id.0).map(|f| f.family.as_str())
+    }
+
+    /// Fuente para una familia y un peso: la de esa familia (sin distinguir mayúsculas) con el peso
+    /// más cercano, prefiriendo la redonda a la cursiva. Si la familia no está, cualquiera de las cargadas.
+    pub fn select(&self, family: Option<&str>, weight: Option<u16>) -> Option<FontId> {
+        let in_family = |f: &LoadedFont| family.is_some_and(|name| f.family.eq_ignore_ascii_case(name));
+        let any_in_family = self.fonts.iter().any(in_family);
+        let weight = weight.unwrap_or(DEFAULT_WEIGHT);
+        self.fonts
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| !any_in_family || in_family(f))
+            .min_by_key(|(i, f)| (f.weight.abs_diff(weight), f.italic, *i))
+            .map(|(i, _)| FontId(i))
+    }
+
+    /// Conforma un tramo con la fuente que le corresponde; None si no hay ninguna cargada
+    pub fn shape(&self, span: &TextSpan) -> Option<ShapedText> {
+        let id = self.select(span.font_family.as_deref(), span.weight)?;
+        let face = Face::from_slice(&self.fonts[id.0].data, 0)?;
+        Some(shape_with(&face, id, span))
+    }
+
+    /// Contornos de unos glifos de tamaño `size`, cada uno en su posición, en un solo trazado
+    pub fn glyph_path(&self, glyphs: &[Glyph], size: f32) -> Path {
+        let mut out = PathOutline { commands: Vec::new(), scale: 0.0, x: 0.0, y: 0.0 };
+        let mut face: Option<(FontId, Face)> = None;
+        for glyph in glyphs {
+            if face.as_ref().is_none_or(|(id, _)| *id != glyph.font) {
+                face = self.fonts.get(glyph.font.0).and_then(|f| Face::from_slice(&f.data, 0)).map(|f| (glyph.font, f));
+            }
+            let Some((_, face)) = &face else { continue };
+            out.scale = size / face.units_per_em() as f32;
+            out.x = glyph.x;
+            out.y = glyph.y;
+            face.outline_glyph(GlyphId(glyph.id), &mut out);
+        }
+        Path { commands: out.commands }
+    }
+
+    /// Medidor para la maquetación: conforma con estas fuentes y, si no hay ninguna, usa `fallback`
+    pub fn measurer<F: TextMeasure>(&self, fallback: F) -> FontMeasure<'_, F> {
+        FontMeasure { book: self, faces: Vec::new(), fallback }
+    }
+}
+
+/// `TextMeasure` sobre un `FontBook` que mantiene las fuentes analizadas mientras dura la maquetación
+pub struct FontMeasure<'a, F> {
+    book: &'a FontBook,
+    faces: Vec<Option<Face<'a>>>,
+    fallback: F,
+}
+
+impl<F: TextMeasure> TextMeasure for FontMeasure<'_, F> {
+    fn shape(&mut self, span: &TextSpan) -> ShapedText {
+        let Some(id) = self.book.select(span.font_family.as_deref(), span.weight) else {
+            return self.fallback.shape(span);
+        };
+        if self.faces.is_empty() {
+            self.faces = self.book.fonts.iter().map(|f| Face::from_slice(&f.data, 0)).collect();
+        }
+        match &self.faces[id.0] {
+            Some(face) => shape_with(face, id, span),
+            None => self.fallback.shape(span),
+        }
+    }
+}
+
+fn family_name(face: &ttf_parser::Face) -> Option<String> {
+    // La familia tipográfica agrupa pesos que la familia "legacy" separa (p.ej. "X Light")
+    [name_id::TYPOGRAPHIC_FAMILY, name_id::FAMILY]
+        .iter()
+        .find_map(|id| face.names().into_iter().filter(|n| n.name_id == *id).find_map(|n| n.to_string()))
+}
+
+fn shape_with(face: &Face, font: FontId, span: &TextSpan) -> ShapedText {
+    let scale = span.size / face.units_per_em() as f32;
+    let metrics = |width| TextMetrics {
+        width,
+        ascent: face.ascender() as f32 * scale,
+        // El descent de la fuente es negativo; TextMetrics lo guarda como distancia
+        descent: -face.descender() as f32 * scale,
+        line_gap: face.line_gap() as f32 * scale,
+    };
+    let chars = span.text.chars().count();
+    if chars == 0 {
+        return ShapedText { metrics: metrics(0.0), carets: vec![0.0], glyphs: Vec::new() };
+    }
+
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str(&span.text);
+    buffer.set_direction(Direction::LeftToRight);
+    buffer.guess_segment_properties();
+    let output = rustybuzz::shape(face, &[], buffer);
+
+    // Clusters de rustybuzz en bytes → offsets en caracteres
+    let char_starts: Vec<usize> = span.text.char_indices().map(|(b, _)| b).collect();
+    let char_at_byte = |byte: u32| char_starts.partition_point(|b| *b <= byte as usize).saturating_sub(1);
+    let mut glyphs = Vec::with_capacity(output.len());
+    let mut advances = vec![0.0f32; chars];
+    let mut starts = vec![false; chars];
+    let mut pen = 0.0;
+    for (info, pos) in output.glyph_infos().iter().zip(output.glyph_positions()) {
+        let cluster = char_at_byte(info.cluster);
+        let advance = pos.x_advance as f32 * scale;
+        glyphs.push(Glyph {
+            font,
+            id: info.glyph_id as u16,
+            cluster,
+            x: pen + pos.x_offset as f32 * scale,
+            y: -pos.y_offset as f32 * scale,
+        });
+        advances[cluster] += advance;
+        starts[cluster] = true;
+        pen += advance;
+    }
+
+    // Dentro de un cluster de varios caracteres (p.ej. una ligadura) el cursor se reparte a partes iguales
+    let mut carets = vec![0.0; chars + 1];
+    let mut i = 0;
+    while i < chars {
+        let end = (i + 1..chars).find(|j| starts[*j]).unwrap_or(chars);
+        let n = (end - i) as f32;
+        for j in i..end {
+            carets[j + 1] = carets[i] + advances[i] * (j + 1 - i) as f32 / n;
+        }
+        i = end;
+    }
+    carets[chars] = pen;
+    ShapedText { metrics: metrics(pen), carets, glyphs }
+}
+
+/// Recoge el contorno de un glifo escalado y con el eje Y hacia abajo
+struct PathOutline {
+    commands: Vec<PathCommand>,
+    scale: f32,
+    x: f32,
+    y: f32,
+}
+
+impl PathOutline {
+    fn map(&self, x: f32, y: f32) -> (f32, f32) {
+        (self.x + x * self.scale, self.y - y * self.scale)
+    }
+}
+
+impl ttf_parser::OutlineBuilder for PathOutline {
+    fn move_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.map(x, y);
+        self.commands.push(PathCommand::MoveTo(x, y));
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.map(x, y);
+        self.commands.push(PathCommand::LineTo(x, y));
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let ((cx, cy), (x, y)) = (self.map(x1, y1), self.map(x, y));
+        self.commands.push(PathCommand::QuadTo { cx, cy, x, y });
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let ((c1x, c1y), (c2x, c2y), (x, y)) = (self.map(x1, y1), self.map(x2, y2), self.map(x, y));
+        self.commands.push(PathCommand::CubicTo { c1x, c1y, c2x, c2y, x, y });
+    }
+
+    fn close(&mut self) {
+        self.commands.push(PathCommand::Close);
+    }
+}
@@ -0,0 +1,443 @@
+//! Maquetación de bloques de texto (`TextBox`): reparto en líneas (ajuste al ancho en las
+//! oportunidades de corte de Unicode UAX #14 y saltos explícitos), alineación e interlineado.
+//!
+//! Las medidas vienen de un `TextMeasure`. Con fuentes cargadas en un `FontBook` el texto se conforma
+//! con rustybuzz y la maquetación es determinista: las mismas líneas, cursores y glifos en todos los
+//! renderers, sesiones y exportaciones, y se pinta con los contornos de los glifos. Sin fuentes se
+//! recurre a `RenderPort::measure_text` o a una estimación por tamaño de letra.
+//! La maquetación también da la geometría de edición: posición del cursor, offset bajo un punto
+//! y rectángulos de selección.
+
+mod font;
+
+pub use font::{FontBook, FontError, FontId, FontMeasure, Glyph};
+
+use crate::model::{BoundingBox, Rect, Shape, Style, TextAlign, TextBox, TextMetrics, TextSpan, Transform, Vec2};
+use crate::ports::{RenderError, RenderPort};
+use unicode_linebreak::{linebreaks, BreakOpportunity};
+
+/// Margen entre el borde de la forma y su etiqueta, en unidades locales
+pub const LABEL_PADDING: f32 = 8.0;
+/// Ancho mínimo de ajuste de una etiqueta, para formas estrechas o líneas verticales
+const LABEL_MIN_WIDTH: f32 = 40.0;
+
+/// Medidas estimadas a partir del tamaño de letra, para cuando no hay renderer (o no tiene fuente)
+pub fn approximate_metrics(span: &TextSpan) -> TextMetrics {
+    TextMetrics {
+        width: span.text.chars().count() as f32 * span.size * 0.6,
+        ascent: span.size * 0.8,
+        descent: span.size * 0.2,
+        line_gap: span.size * 0.1,
+    }
+}
+
+/// Medidor que pregunta al renderer y recurre a la estimación si falla
+pub fn measure_with(renderer: &mut dyn RenderPort) -> impl FnMut(&TextSpan) -> TextMetrics + '_ {
+    move |span| renderer.measure_text(span).unwrap_or_else(|_| approximate_metrics(span))
+}
+
+/// Trozo de texto de un solo tramo ya medido
+#[derive(Clone, Debug, Default)]
+pub struct ShapedText {
+    pub metrics: TextMetrics,
+    /// x de cada posición del cursor (un valor más que caracteres)
+    pub carets: Vec<f32>,
+    /// Glifos conformados; vacío si se midió sin fuente
+    pub glyphs: Vec<Glyph>,
+}
+
+/// Fuente de medidas de la maquetación
+pub trait TextMeasure {
+    fn shape(&mut self, span: &TextSpan) -> ShapedText;
+}
+
+/// Cualquier función de métricas sirve de medidor: mide cada prefijo para situar los cursores,
+/// con el mismo kerning con el que luego se pinta el trozo entero
+impl<F: FnMut(&TextSpan) -> TextMetrics + ?Sized> TextMeasure for F {
+    fn shape(&mut self, span: &TextSpan) -> ShapedText {
+        let mut carets = vec![0.0];
+        let mut prefix = String::new();
+        let mut metrics = self(&TextSpan { text: String::new(), ..span.clone() });
+        for c in span.text.chars() {
+            prefix.push(c);
+            metrics = self(&TextSpan { text: prefix.clone(), ..span.clone() });
+            carets.push(metrics.width);
+        }
+        ShapedText { metrics, carets, glyphs: Vec::new() }
+    }
+}
+
+/// Trozo de una línea con un único estilo, listo para `draw_text`
+#[derive(Clone, Debug)]
+pub struct TextRun {
+    /// Texto del trozo con el estilo de su tramo
+    pub span: TextSpan,
+    /// Offset del primer carácter en el bloque
+    pub start: usize,
+    /// Posición respecto al inicio de la línea
+    pub x: f32,
+    /// Borde superior del texto (línea base menos ascent), respecto al bloque
+    pub y: f32,
+    /// x de cada posición del cursor dentro del trozo, respecto a `x` (un valor más que caracteres)
+    pub carets: Vec<f32>,
+    /// Glifos respecto a (`x`, `y`) si se conformó con fuente
+    pub glyphs: Vec<Glyph>,
+}
+
+#[derive(Clone, Debug)]
+pub struct TextLine {
+    /// Offsets de inicio y fin; el fin no incluye el salto de línea
+    pub start: usize,
+    pub end: usize,
+    /// Desplazamiento por la alineación
+    pub x: f32,
+    pub y: f32,
+    /// Ancho sin los espacios finales
+    pub width: f32,
+    pub height: f32,
+    pub baseline: f32,
+    pub runs: Vec<TextRun>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TextLayout {
+    pub lines: Vec<TextLine>,
+    /// El ancho de ajuste si lo hay; si no, el de la línea más ancha
+    pub width: f32,
+    pub height: f32,
+}
+
+/// Palabra (con sus espacios finales) partida en trozos de un solo tramo
+struct Word {
+    runs: Vec<TextRun>,
+    width: f32,
+    trailing: f32,
+    ascent: f32,
+    descent: f32,
+    size: f32,
+}
+
+/// Reparte el bloque en líneas con `measure`
+pub fn layout(text: &TextBox, measure: &mut dyn TextMeasure) -> TextLayout {
+    layout_wrapped(text, text.wrap_width, measure)
+}
+
+/// Maqueta el bloque y guarda su tamaño en `extent`, que usan las cajas y el hit test
+pub fn update_extent(text: &mut TextBox, measure: &mut dyn TextMeasure) -> TextLayout {
+    let layout = layout(text, measure);
+    text.extent = Vec2(layout.width, layout.height);
+    layout
+}
+
+fn layout_wrapped(text: &TextBox, wrap: Option<f32>, measure: &mut dyn TextMeasure) -> TextLayout {
+    let chars: Vec<(char, usize)> = text
+        .spans
+        .iter()
+        .enumerate()
+        .flat_map(|(i, span)| span.text.chars().map(move |c| (c, i)))
+        .collect();
+    let size_at = |offset: usize| {
+        let span = chars.get(offset).or(chars.last()).map_or(0, |c| c.1);
+        text.spans.get(span).map_or(0.0, |s| s.size)
+    };
+
+    let mut lines = Vec::new();
+    let mut y = 0.0;
+    let mut start = 0;
+    // Cada párrafo (separado por '\n') empieza línea
+    loop {
+        let end = (start..chars.len()).find(|i| chars[*i].0 == '\n').unwrap_or(chars.len());
+        let mut words = split_words(text, &chars, start, end, measure);
+        if let Some(wrap) = wrap {
+            words = words.into_iter().flat_map(|w| break_word(w, wrap)).collect();
+        }
+
+        let mut line: Vec<Word> = Vec::new();
+        let mut line_start = start;
+        let mut x = 0.0;
+        for word in words {
+            let fits = wrap.is_none_or(|wrap| x + word.width - word.trailing <= wrap);
+            if !line.is_empty() && !fits {
+                let next = word.runs[0].start;
+                lines.push(finish_line(std::mem::take(&mut line), line_start, next, &mut y, text.line_height, size_at(line_start)));
+                line_start = next;
+                x = 0.0;
+            }
+            x += word.width;
+            line.push(word);
+        }
+        lines.push(finish_line(line, line_start, end, &mut y, text.line_height, size_at(line_start)));
+
+        if end >= chars.len() {
+            break;
+        }
+        start = end + 1;
+    }
+
+    let widest = lines.iter().fold(0.0f32, |m, l| m.max(l.width));
+    let width = wrap.unwrap_or(widest);
+    for line in &mut lines {
+        line.x = match text.align {
+            TextAlign::Left => 0.0,
+            TextAlign::Center => (width - line.width) / 2.0,
+            TextAlign::Right => width - line.width,
+        };
+    }
+    TextLayout { lines, width, height: y }
+}
+
+/// Palabras de `start..end`, de una oportunidad de corte a la siguiente (con sus blancos finales),
+/// en trozos por tramo
+fn split_words(
+    text: &TextBox,
+    chars: &[(char, usize)],
+    start: usize,
+    end: usize,
+    measure: &mut dyn TextMeasure,
+) -> Vec<Word> {
+    let paragraph: String = chars[start..end].iter().map(|c| c.0).collect();
+    let char_starts: Vec<usize> = paragraph.char_indices().map(|(b, _)| b).collect();
+    let breaks = linebreaks(&paragraph)
+        .filter(|(_, kind)| *kind == BreakOpportunity::Allowed)
+        .map(|(byte, _)| start + char_starts.partition_point(|b| *b < byte))
+        .chain(std::iter::once(end));
+
+    let mut words = Vec::new();
+    let mut i = start;
+    for j in breaks {
+        if j <= i {
+            continue;
+        }
+        let blank_start = (i..j).rev().find(|k| !chars[*k].0.is_whitespace()).map_or(i, |k| k + 1);
+
+        let mut word = Word { runs: Vec::new(), width: 0.0, trailing: 0.0, ascent: 0.0, descent: 0.0, size: 0.0 };
+        let mut k = i;
+        while k < j {
+            let span_index = chars[k].1;
+            let run_end = (k..j).find(|m| chars[*m].1 != span_index).unwrap_or(j);
+            let source = &text.spans[span_index];
+            let span = TextSpan { text: chars[k..run_end].iter().map(|c| c.0).collect(), ..source.clone() };
+            let ShapedText { metrics, carets, glyphs } = measure.shape(&span);
+            // Ancho de los blancos finales de este trozo
+            if run_end > blank_start {
+                let first_blank = blank_start.max(k) - k;
+                word.trailing += metrics.width - carets[first_blank];
+            }
+            word.ascent = word.ascent.max(metrics.ascent);
+            word.descent = word.descent.max(metrics.descent);
+            word.size = word.size.max(source.size);
+            word.runs.push(TextRun { span, start: k, x: word.width, y: 0.0, carets, glyphs });
+            word.width += metrics.width;
+            k = run_end;
+        }
+        words.push(word);
+        i = j;
+    }
+    words
+}
+
+/// Parte por caracteres una palabra más ancha que `wrap` (al menos un carácter por trozo).
+/// Los blancos finales nunca pasan a la línea siguiente.
+fn break_word(word: Word, wrap: f32) -> Vec<Word> {
+    if word.width - word.trailing <= wrap {
+        return vec![word];
+    }
+    let empty = || Word { runs: Vec::new(), width: 0.0, trailing: 0.0, ascent: word.ascent, descent: word.descent, size: word.size };
+    let push_piece = |current: &mut Word, run: &TextRun, from: usize, to: usize| {
+        let carets: Vec<f32> = run.carets[from..=to].iter().map(|c| c - run.carets[from]).collect();
+        let width = carets[carets.len() - 1];
+        let text = run.span.text.chars().skip(from).take(to - from).collect();
+        let glyphs = run
+            .glyphs
+            .iter()
+            .filter(|g| (from..to).contains(&g.cluster))
+            .map(|g| Glyph { cluster: g.cluster - from, x: g.x - run.carets[from], ..*g })
+            .collect();
+        current.runs.push(TextRun { span: TextSpan { text, ..run.span.clone() }, start: run.start + from, x: current.width, y: 0.0, carets, glyphs });
+        current.width += width;
+    };
+
+    let mut out = Vec::new();
+    let mut current = empty();
+    for run in &word.runs {
+        let chars: Vec<char> = run.span.text.chars().collect();
+        let mut from = 0;
+        for (c, ch) in chars.iter().enumerate() {
+            let pending = current.width + run.carets[c + 1] - run.carets[from];
+            let has_content = c > from || !current.runs.is_empty();
+            if pending > wrap && has_content && !ch.is_whitespace() {
+                if c > from {
+                    push_piece(&mut current, run, from, c);
+                }
+                out.push(std::mem::replace(&mut current, empty()));
+                from = c;
+            }
+        }
+        push_piece(&mut current, run, from, chars.len());
+    }
+    current.trailing = word.trailing.min(current.width);
+    out.push(current);
+    out
+}
+
+/// Coloca en vertical las palabras de una línea y las une en trozos con x relativa a la línea
+fn finish_line(words: Vec<Word>, start: usize, end: usize, y: &mut f32, line_height: f32, fallback_size: f32) -> TextLine {
+    let size = words.iter().fold(0.0f32, |m, w| m.max(w.size));
+    let size = if size > 0.0 { size } else { fallback_size };
+    let mut ascent = words.iter().fold(0.0f32, |m, w| m.max(w.ascent));
+    let mut descent = words.iter().fold(0.0f32, |m, w| m.max(w.descent));
+    if ascent + descent <= 0.0 {
+        // Línea vacía: proporciones habituales para que el cursor tenga altura
+        ascent = size * 0.8;
+        descent = size * 0.2;
+    }
+    let height = (size * line_height).max(ascent + descent);
+    let baseline = *y + (height - ascent - descent) / 2.0 + ascent;
+    let width = words.iter().map(|w| w.width).sum::<f32>() - words.last().map_or(0.0, |w| w.trailing);
+
+    let mut runs: Vec<TextRun> = Vec::new();
+    let mut x = 0.0;
+    for word in words {
+        for mut run in word.runs {
+            // Cada trozo se apoya en la línea base con su propio ascent
+            let run_ascent = run.span.size / size.max(f32::EPSILON) * ascent;
+            run.x += x;
+            run.y = baseline - run_ascent;
+            // Los glifos pasan de la línea base al borde superior del trozo
+            run.glyphs.iter_mut().for_each(|g| g.y += run_ascent);
+            // Palabras seguidas del mismo tramo se pintan como un solo trozo
+            match runs.last_mut() {
+                Some(prev) if same_style(&prev.span, &run.span) && prev.start + prev.carets.len() - 1 == run.start => {
+                    let (dx, len) = (run.x - prev.x, prev.carets.len() - 1);
+                    prev.span.text.push_str(&run.span.text);
+                    prev.carets.extend(run.carets[1..].iter().map(|c| c + dx));
+                    prev.glyphs.extend(run.glyphs.iter().map(|g| Glyph { cluster: g.cluster + len, x: g.x + dx, ..*g }));
+                }
+                _ => runs.push(run),
+            }
+        }
+        x += word.width;
+    }
+    let line = TextLine { start, end, x: 0.0, y: *y, width, height, baseline, runs };
+    *y += height;
+    line
+}
+
+fn same_style(a: &TextSpan, b: &TextSpan) -> bool {
+    a.color == b.color && a.size == b.size && a.font_family == b.font_family && a.weight == b.weight
+}
+
+impl TextLayout {
+    /// Línea que contiene el offset: en una frontera de ajuste, la siguiente
+    pub fn line_at(&self, offset: usize) -> usize {
+        self.lines.iter().rposition(|l| l.start <= offset).unwrap_or(0)
+    }
+
+    /// Cursor en el offset: (x, y superior, alto) en el espacio del bloque
+    pub fn caret(&self, offset: usize) -> (f32, f32, f32) {
+        let Some(line) = self.lines.get(self.line_at(offset)) else { return (0.0, 0.0, 0.0); };
+        (line.x + line_caret_x(line, offset), line.y, line.height)
+    }
+
+    /// Offset más cercano al punto (x, y) del espacio del bloque
+    pub fn offset_at(&self, x: f32, y: f32) -> usize {
+        let Some(index) = self.lines.iter().position(|l| y < l.y + l.height).or(self.lines.len().checked_sub(1)) else {
+            return 0;
+        };
+        let line = &self.lines[index];
+        // En un ajuste el final de la línea es el inicio de la siguiente, donde se dibujaría el cursor
+        let soft_wrap = self.lines.get(index + 1).is_some_and(|next| next.start == line.end);
+        let x = x - line.x;
+        let mut best = (line.start, f32::INFINITY);
+        for run in &line.runs {
+            for (i, caret) in run.carets.iter().enumerate() {
+                let d = (run.x + caret - x).abs();
+                let offset = run.start + i;
+                if d < best.1 && (offset < line.end || (offset == line.end && !soft_wrap)) {
+                    best = (offset, d);
+                }
+            }
+        }
+        best.0
+    }
+
+    /// Rectángulos (espacio del bloque) que cubren `start..end`, uno por línea
+    pub fn selection_rects(&self, start: usize, end: usize) -> Vec<Rect> {
+        let (start, end) = (start.min(end), start.max(end));
+        self.lines
+            .iter()
+            .filter_map(|line| {
+                let (a, b) = (start.max(line.start), end.min(line.end));
+                if a >= b {
+                    return None;
+                }
+                let (x0, x1) = (line_caret_x(line, a), line_caret_x(line, b));
+                Some(Rect { x: line.x + x0, y: line.y, w: x1 - x0, h: line.height })
+            })
+            .collect()
+    }
+}
+
+fn line_caret_x(line: &TextLine, offset: usize) -> f32 {
+    for run in &line.runs {
+        let len = run.carets.len() - 1;
+        if offset <= run.start + len {
+            return run.x + run.carets[offset.saturating_sub(run.start)];
+        }
+    }
+    line.runs.last().map_or(0.0, |r| r.x + r.carets[r.carets.len() - 1])
+}
+
+/// Caja local de la forma (sin transformar), en la que se centra su etiqueta
+pub fn label_frame(shape: &Shape) -> BoundingBox {
+    BoundingBox::local_from_shape(&Transform::default(), shape)
+}
+
+/// Etiqueta maquetada dentro de la forma: ajustada al ancho de su caja menos el margen y centrada en ella.
+/// Devuelve la maquetación y su origen en coordenadas locales de la entidad.
+pub fn label_layout(shape: &Shape, label: &TextBox, measure: &mut dyn TextMeasure) -> (TextLayout, (f32, f32)) {
+    let frame = label_frame(shape);
+    let wrap = (frame.width - 2.0 * LABEL_PADDING).max(LABEL_MIN_WIDTH);
+    let layout = layout_wrapped(label, Some(wrap), measure);
+    let (cx, cy) = frame.center();
+    let origin = (cx - wrap / 2.0, cy - layout.height / 2.0);
+    (layout, origin)
+}
+
+/// Pinta una maquetación con su origen en `origin` (coordenadas locales de `transform`).
+/// Los trozos conformados con `fonts` se pintan con los contornos de sus glifos, igual en todos
+/// los renderers; el resto con `RenderPort::draw_text`.
+pub fn draw_layout(
+    renderer: &mut dyn RenderPort,
+    fonts: &FontBook,
+    transform: &Transform,
+    layout: &TextLayout,
+    origin: (f32, f32),
+) -> Result<(), RenderError> {
+    for line in &layout.lines {
+        for run in &line.runs {
+            let (x, y) = transform.apply(origin.0 + line.x + run.x, origin.1 + run.y);
+            let at = Transform { x, y, ..*transform };
+            if run.glyphs.is_empty() {
+                renderer.draw_text(&at, &run.span)?;
+            } else {
+                let path = fonts.glyph_path(&run.glyphs, run.span.size);
+                let style = Style { fill: Some(run.span.color.into()), opacity: 1.0, ..Style::default() };
+                renderer.draw_shape(&at, &Shape::Path { path }, &style)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Maqueta con `fonts` (o, sin fuentes, con las medidas del propio renderer) y pinta el bloque
+pub fn draw_text_box(renderer: &mut dyn RenderPort, fonts: &FontBook, transform: &Transform, text: &TextBox) -> Result<(), RenderError> {
+    let layout = layout(text, &mut fonts.measurer(measure_with(renderer)));
+    draw_layout(renderer, fonts, transform, &layout, (0.0, 0.0))
+}
+
+/// Pinta la etiqueta de una entidad centrada en su forma
+pub fn draw_label(renderer: &mut dyn RenderPort, fonts: &FontBook, transform: &Transform, shape: &Shape, label: &TextBox) -> Result<(), RenderError> {
+    let (layout, origin) = label_layout(shape, label, &mut fonts.measurer(measure_with(renderer)));
+    draw_layout(renderer, fonts, transform, &layout, origin)
+}
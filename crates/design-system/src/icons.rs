@@ -36,6 +36,7 @@ pub enum IconType {
     Rectangle,
     Ellipse,
     Arrow,
+    Connector,
     Line,
     Text,
     Pen,
@@ -106,6 +107,11 @@ pub fn Icon(
                     <polyline points="7,7 17,7 17,17"/>
                 }.into_any(),
                 
+                IconType::Connector => view! {
+                    <path d="M4 18h6a2 2 0 0 0 2-2V8a2 2 0 0 1 2-2h6"/>
+                    <polyline points="17,3 20,6 17,9"/>
+                }.into_any(),
+                
                 IconType::Line => view! {
                     <line x1="7" y1="17" x2="17" y2="7"/>
                 }.into_any(),
@@ -0,0 +1,82 @@
+//! Cámara 2D del canvas infinito: desplazamiento (pan) y zoom.
+//!
+//! El documento vive en coordenadas de mundo; la entrada de puntero llega en CSS px
+//! relativos al canvas (pantalla). `screen = (world - origen) * zoom`.
+
+use bevy_ecs::prelude::Resource;
+use momentum_core::model::{BoundingBox, OrientedBoundingBox};
+
+/// Zoom mínimo (10%)
+pub const MIN_ZOOM: f32 = 0.1;
+/// Zoom máximo (3000%)
+pub const MAX_ZOOM: f32 = 30.0;
+/// Margen en CSS px al encuadrar contenido (zoom-to-fit / zoom-to-selection)
+pub const FIT_PADDING: f32 = 40.0;
+
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    /// Punto de mundo en la esquina superior izquierda del canvas
+    pub x: f32,
+    pub y: f32,
+    pub zoom: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self { x: 0.0, y: 0.0, zoom: 1.0 }
+    }
+}
+
+impl Camera {
+    pub fn screen_to_world(&self, sx: f32, sy: f32) -> (f32, f32) {
+        (self.x + sx / self.zoom, self.y + sy / self.zoom)
+    }
+
+    pub fn world_to_screen(&self, wx: f32, wy: f32) -> (f32, f32) {
+        ((wx - self.x) * self.zoom, (wy - self.y) * self.zoom)
+    }
+
+    /// Convierte una distancia en pantalla (delta de arrastre, radio de acierto) a mundo
+    pub fn screen_to_world_len(&self, len: f32) -> f32 {
+        len / self.zoom
+    }
+
+    /// Caja de mundo vista en pantalla (CSS px)
+    pub fn box_to_screen(&self, bounds: &OrientedBoundingBox) -> OrientedBoundingBox {
+        bounds.translated(-self.x, -self.y).scaled(self.zoom)
+    }
+
+    /// Desplaza la vista según un arrastre en pantalla: el contenido sigue al puntero
+    pub fn pan_by(&mut self, dx: f32, dy: f32) {
+        self.x -= dx / self.zoom;
+        self.y -= dy / self.zoom;
+    }
+
+    /// Multiplica el zoom manteniendo fijo el punto de mundo bajo (sx, sy)
+    pub fn zoom_at(&mut self, sx: f32, sy: f32, factor: f32) {
+        if !factor.is_finite() || factor <= 0.0 { return; }
+        let (wx, wy) = self.screen_to_world(sx, sy);
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.x = wx - sx / self.zoom;
+        self.y = wy - sy / self.zoom;
+    }
+
+    /// Encuadra `bounds` centrado en un viewport de (width, height) CSS px
+    pub fn fit(&mut self, bounds: &BoundingBox, width: f32, height: f32) {
+        let avail_w = (width - 2.0 * FIT_PADDING).max(1.0);
+        let avail_h = (height - 2.0 * FIT_PADDING).max(1.0);
+        // Cajas degeneradas (una línea horizontal, un punto) solo limitan en el otro eje
+        let zoom_w = if bounds.width > f32::EPSILON { avail_w / bounds.width } else { MAX_ZOOM };
+        let zoom_h = if bounds.height > f32::EPSILON { avail_h / bounds.height } else { MAX_ZOOM };
+        self.zoom = zoom_w.min(zoom_h).clamp(MIN_ZOOM, MAX_ZOOM);
+        let (cx, cy) = bounds.center();
+        self.x = cx - width / (2.0 * self.zoom);
+        self.y = cy - height / (2.0 * self.zoom);
+    }
+
+    /// Matriz afín [a, b, c, d, e, f] de mundo a píxeles físicos para `RenderPort::set_camera`
+    pub fn matrix(&self, dpr: f32) -> [f32; 6] {
+        let s = self.zoom * dpr;
+        [s, 0.0, 0.0, s, -self.x * s, -self.y * s]
+    }
+}
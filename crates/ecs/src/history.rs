@@ -0,0 +1,260 @@
+//! Historial de comandos reversibles para undo/redo.
+//!
+//! Cada mutación del documento se registra como un `Command` que sabe aplicarse
+//! y revertirse. Las interacciones de arrastre (mover, escalar, rotar) se registran
+//! una sola vez al terminar, así todo el gesto se deshace de una vez.
+
+use std::collections::{HashSet, VecDeque};
+
+use bevy_ecs::prelude::Resource;
+use momentum_core::model::{ArrowBindings, EntityId, Hitbox, Shape, Style, TextBox, Transform};
+use momentum_core::usecases::{Document, Entity};
+
+/// Profundidad por defecto del historial
+pub const DEFAULT_HISTORY_LIMIT: usize = 100;
+
+/// Copia completa de una entidad, con su posición en el orden de pintado
+#[derive(Debug, Clone)]
+pub struct EntitySnapshot {
+    pub id: EntityId,
+    pub index: usize,
+    pub transform: Transform,
+    pub style: Style,
+    pub shape: Shape,
+    pub hitbox: Option<Hitbox>,
+    pub label: Option<TextBox>,
+    pub bindings: ArrowBindings,
+}
+
+impl EntitySnapshot {
+    /// Captura la entidad `id` tal como está ahora en el documento
+    pub fn capture(doc: &Document, id: EntityId) -> Option<Self> {
+        Some(Self::of(doc.get(id)?, doc.z_index(id)?))
+    }
+
+    /// Captura varias entidades, en orden de pintado. `Document::z_index` recorre el documento,
+    /// así que los índices de todas se sacan en una sola pasada.
+    pub fn capture_all(doc: &Document, ids: impl IntoIterator<Item = EntityId>) -> Vec<Self> {
+        let wanted: HashSet<EntityId> = ids.into_iter().collect();
+        if wanted.is_empty() {
+            return Vec::new();
+        }
+        doc.iter()
+            .enumerate()
+            .filter(|(_, entity)| wanted.contains(&entity.id))
+            .map(|(index, entity)| Self::of(entity, index))
+            .collect()
+    }
+
+    /// Nueva captura de la misma entidad en su sitio, tras editarla sin cambiar el orden de pintado
+    pub fn recapture(&self, doc: &Document) -> Option<Self> {
+        Some(Self::of(doc.get(self.id)?, self.index))
+    }
+
+    fn of(entity: &Entity, index: usize) -> Self {
+        Self {
+            id: entity.id,
+            index,
+            transform: entity.transform,
+            style: entity.style.clone(),
+            shape: entity.shape.clone(),
+            hitbox: entity.hitbox.clone(),
+            label: entity.label.clone(),
+            bindings: entity.bindings,
+        }
+    }
+
+    fn entity(&self) -> Entity {
+        Entity {
+            id: self.id,
+            transform: self.transform,
+            style: self.style.clone(),
+            shape: self.shape.clone(),
+            hitbox: self.hitbox.clone(),
+            label: self.label.clone(),
+            bindings: self.bindings,
+        }
+    }
+
+    fn remove_from(&self, doc: &mut Document) {
+        doc.remove(self.id);
+    }
+}
+
+/// Mutación reversible del documento
+#[derive(Debug, Clone)]
+pub enum Command {
+    /// Entidades añadidas (deshacer las elimina)
+    Insert(Vec<EntitySnapshot>),
+    /// Entidades eliminadas (deshacer las restaura en su posición)
+    Remove(Vec<EntitySnapshot>),
+    /// Cambios de transformación: (entidad, antes, después)
+    SetTransforms(Vec<(EntityId, Transform, Transform)>),
+    /// Cambio del orden de pintado: orden completo antes y después
+    Reorder { before: Vec<EntityId>, after: Vec<EntityId> },
+    /// Otras versiones de entidades en su mismo sitio del orden de pintado: (antes, después)
+    Replace(Vec<(EntitySnapshot, EntitySnapshot)>),
+    /// Varios comandos como una sola entrada del historial
+    Batch(Vec<Command>),
+}
+
+impl Command {
+    /// Sustituye una entidad por otra versión suya en el mismo sitio (p.ej. tras editar su texto)
+    pub fn replace(before: EntitySnapshot, after: EntitySnapshot) -> Self {
+        Command::Replace(vec![(before, after)])
+    }
+
+    pub fn apply(&self, doc: &mut Document) {
+        match self {
+            // Insertar en orden ascendente de índice para restaurar posiciones exactas
+            Command::Insert(snapshots) => insert_all(doc, snapshots),
+            Command::Remove(snapshots) => snapshots.iter().for_each(|s| s.remove_from(doc)),
+            Command::SetTransforms(changes) => {
+                for (id, _before, after) in changes {
+                    set_transform(doc, *id, *after);
+                }
+            }
+            Command::Reorder { after, .. } => doc.reorder(after),
+            Command::Replace(changes) => changes.iter().for_each(|(_, after)| replace(doc, after)),
+            Command::Batch(commands) => commands.iter().for_each(|c| c.apply(doc)),
+        }
+    }
+
+    /// Entidades que aparecen, desaparecen o cambian de caja al aplicar o revertir el comando
+    pub fn entities(&self) -> Vec<EntityId> {
+        match self {
+            Command::Insert(snapshots) | Command::Remove(snapshots) => snapshots.iter().map(|s| s.id).collect(),
+            Command::SetTransforms(changes) => changes.iter().map(|(id, _, _)| *id).collect(),
+            Command::Replace(changes) => changes.iter().map(|(before, _)| before.id).collect(),
+            Command::Reorder { .. } => Vec::new(),
+            Command::Batch(commands) => commands.iter().flat_map(Command::entities).collect(),
+        }
+    }
+
+    pub fn revert(&self, doc: &mut Document) {
+        match self {
+            Command::Insert(snapshots) => snapshots.iter().for_each(|s| s.remove_from(doc)),
+            Command::Remove(snapshots) => insert_all(doc, snapshots),
+            Command::SetTransforms(changes) => {
+                for (id, before, _after) in changes {
+                    set_transform(doc, *id, *before);
+                }
+            }
+            Command::Reorder { before, .. } => doc.reorder(before),
+            Command::Replace(changes) => changes.iter().for_each(|(before, _)| replace(doc, before)),
+            Command::Batch(commands) => commands.iter().rev().for_each(|c| c.revert(doc)),
+        }
+    }
+}
+
+/// Restaura las entidades en sus posiciones exactas, en una sola pasada por el documento
+fn insert_all(doc: &mut Document, snapshots: &[EntitySnapshot]) {
+    doc.insert_many(snapshots.iter().map(|s| (s.index, s.entity())).collect());
+}
+
+/// Sobrescribe la entidad en su sitio; si ya no existe vuelve a su posición
+fn replace(doc: &mut Document, snapshot: &EntitySnapshot) {
+    if doc.contains(snapshot.id) {
+        doc.insert(snapshot.entity());
+    } else {
+        doc.insert_at(snapshot.index, snapshot.entity());
+    }
+}
+
+fn set_transform(doc: &mut Document, id: EntityId, transform: Transform) {
+    if let Some(entity) = doc.get_mut(id) {
+        entity.transform = transform;
+    }
+}
+
+/// Construye el comando de un gesto de transformación a partir de las transformaciones iniciales.
+/// Devuelve None si nada cambió (p.ej. un clic sin arrastre).
+pub fn transform_changes(doc: &Document, initial: &[(EntityId, Transform)]) -> Option<Command> {
+    let changes: Vec<_> = initial
+        .iter()
+        .filter_map(|(id, before)| {
+            let after = &doc.get(*id)?.transform;
+            let changed = before.x != after.x
+                || before.y != after.y
+                || before.rotation != after.rotation
+                || before.scale_x != after.scale_x
+                || before.scale_y != after.scale_y;
+            changed.then_some((*id, *before, *after))
+        })
+        .collect();
+    (!changes.is_empty()).then_some(Command::SetTransforms(changes))
+}
+
+/// Pilas de undo/redo con profundidad limitada
+#[derive(Resource, Debug)]
+pub struct History {
+    undo_stack: VecDeque<Command>,
+    redo_stack: Vec<Command>,
+    limit: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::with_limit(DEFAULT_HISTORY_LIMIT)
+    }
+}
+
+impl History {
+    pub fn with_limit(limit: usize) -> Self {
+        Self { undo_stack: VecDeque::new(), redo_stack: Vec::new(), limit: limit.max(1) }
+    }
+
+    /// Registra un comando ya aplicado; invalida la pila de redo
+    pub fn record(&mut self, command: Command) {
+        self.redo_stack.clear();
+        self.undo_stack.push_back(command);
+        while self.undo_stack.len() > self.limit {
+            self.undo_stack.pop_front();
+        }
+    }
+
+    /// Revierte el último comando y lo devuelve, o None si no había nada que deshacer
+    pub fn undo(&mut self, doc: &mut Document) -> Option<&Command> {
+        let command = self.undo_stack.pop_back()?;
+        command.revert(doc);
+        self.redo_stack.push(command);
+        self.redo_stack.last()
+    }
+
+    /// Vuelve a aplicar el último comando deshecho y lo devuelve, o None si no había ninguno
+    pub fn redo(&mut self, doc: &mut Document) -> Option<&Command> {
+        let command = self.redo_stack.pop()?;
+        command.apply(doc);
+        self.undo_stack.push_back(command);
+        self.undo_stack.back()
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Cambia la profundidad máxima, descartando las entradas más antiguas si sobran
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit.max(1);
+        while self.undo_stack.len() > self.limit {
+            self.undo_stack.pop_front();
+        }
+        if self.redo_stack.len() > self.limit {
+            let excess = self.redo_stack.len() - self.limit;
+            self.redo_stack.drain(..excess);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+}
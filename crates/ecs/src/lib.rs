@@ -1,11 +1,29 @@
 //! ECS standalone crate integrating bevy_ecs with momentum-core models.
 
+use std::collections::{HashMap, HashSet};
+use std::ops::DerefMut;
+
+use indexmap::IndexSet;
+
 use bevy_ecs::{prelude::*, schedule::Schedule};
-use momentum_core::usecases::Document;
-use momentum_core::model::{Style, Transform, Shape, Color, EntityId, Hitbox};
-use momentum_core::ports::RenderPort;
+use momentum_core::arrow;
+use momentum_core::freehand::{self, StrokeSample};
+use momentum_core::image::{self, ImageError};
+use momentum_core::sketch;
+use momentum_core::usecases::{Document, DocumentFormatError, Entity};
+use momentum_core::model::{Style, Transform, Shape, Color, EntityId, Hitbox, BoundingBox, OrientedBoundingBox, HandleType, rotate_around, FreehandStroke, PATH_FLATTEN_TOLERANCE, TextAlign, TextBox, TextMetrics, TextSpan, BlobId, ImageId, Rect, Arrow, ArrowBinding, ArrowBindings, ArrowHead, ArrowRouting, Paint, Sketch, SketchFill, CornerRadii};
+use momentum_core::ports::{RenderPort, StoragePort};
+use momentum_core::storage::MemoryStorage;
+use momentum_core::text::{self, FontBook, FontError, FontId, TextLayout, TextMeasure};
 use bevy_ecs::system::NonSendMut;
 
+pub mod camera;
+pub mod history;
+pub mod text_edit;
+use camera::Camera;
+use history::{Command, EntitySnapshot, History};
+use text_edit::{target_text, target_text_mut, TextEdit, TextEditState, TextKey, TextTarget};
+
 #[cfg(target_arch = "wasm32")]
 use js_sys;
 #[cfg(target_arch = "wasm32")]
@@ -34,6 +52,10 @@ pub struct AppState {
 #[derive(Resource, Default)]
 pub struct CoreDoc(pub Document);
 
+/// Fuentes cargadas en el lienzo: con alguna, la maquetación del texto es la del núcleo en todos los renderers
+#[derive(Resource, Default)]
+pub struct Fonts(pub FontBook);
+
 fn tick_system(mut state: ResMut<AppState>) {
     state.frames += 1;
 }
@@ -44,7 +66,7 @@ pub struct PointerDown {
     pub x: f32,
     pub y: f32,
     pub ctrl_key: bool, // Para multi-selección
-    pub shift_key: bool, // Para selección rango (futuro)
+    pub shift_key: bool, // Selección aditiva (marquee): no limpia la selección al pulsar en vacío
 }
 
 // Nuevos eventos para manipulación
@@ -75,23 +97,108 @@ pub struct ScaleStart {
 pub struct ScaleUpdate {
     pub dx: f32, // Delta X desde el inicio del escalado
     pub dy: f32, // Delta Y desde el inicio del escalado
+    pub shift_key: bool, // Mantener proporción
+    pub alt_key: bool, // Escalar desde el centro
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct ScaleEnd;
 
+// Eventos para rotación
+#[derive(Debug, Clone, Copy)]
+pub struct RotateStart {
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RotateUpdate {
+    pub dx: f32, // Delta X desde el inicio de la rotación
+    pub dy: f32, // Delta Y desde el inicio de la rotación
+    pub shift_key: bool, // Ajustar a incrementos de 15°
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RotateEnd;
+
+// Eventos para selección por rectángulo (marquee)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MarqueeMode {
+    /// Solo entidades completamente dentro del rectángulo
+    #[default]
+    Contain,
+    /// Cualquier entidad que toque el rectángulo
+    Intersect,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MarqueeStart {
+    pub x: f32,
+    pub y: f32,
+    pub shift_key: bool, // Añadir a la selección existente
+    pub mode: MarqueeMode,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MarqueeUpdate {
+    pub dx: f32, // Delta X desde el inicio del marquee
+    pub dy: f32, // Delta Y desde el inicio del marquee
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MarqueeEnd;
+
+// Eventos de edición sobre la selección
+#[derive(Debug, Clone, Copy)]
+pub struct DeleteSelection;
+
+#[derive(Debug, Clone, Copy)]
+pub struct DuplicateSelection {
+    pub dx: f32, // Desplazamiento de las copias en CSS px
+    pub dy: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZOrderOp {
+    /// Subir un nivel
+    BringForward,
+    /// Bajar un nivel
+    SendBackward,
+    /// Encima de todo
+    BringToFront,
+    /// Debajo de todo
+    SendToBack,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ChangeZOrder { pub op: ZOrderOp }
+
 #[derive(Resource, Default)]
 pub struct InputQueue {
     pub pointer_down: Vec<PointerDown>,
     pub create_rect: Vec<CreateRect>,
     pub create_ellipse: Vec<CreateEllipse>,
     pub create_line: Vec<CreateLine>,
+    pub create_arrow: Vec<CreateArrow>,
     pub move_start: Vec<MoveStart>,
     pub move_update: Vec<MoveUpdate>,
     pub move_end: Vec<MoveEnd>,
     pub scale_start: Vec<ScaleStart>,
     pub scale_update: Vec<ScaleUpdate>,
     pub scale_end: Vec<ScaleEnd>,
+    pub rotate_start: Vec<RotateStart>,
+    pub rotate_update: Vec<RotateUpdate>,
+    pub rotate_end: Vec<RotateEnd>,
+    pub marquee_start: Vec<MarqueeStart>,
+    pub marquee_update: Vec<MarqueeUpdate>,
+    pub marquee_end: Vec<MarqueeEnd>,
+    pub delete_selection: Vec<DeleteSelection>,
+    pub duplicate_selection: Vec<DuplicateSelection>,
+    pub z_order: Vec<ChangeZOrder>,
+    pub pen_start: Vec<PenStart>,
+    pub pen_move: Vec<PenMove>,
+    pub pen_end: Vec<PenEnd>,
+    pub text_edit: Vec<TextEdit>,
 }
 
 // Nuevos eventos para crear otras formas
@@ -101,10 +208,25 @@ pub struct CreateEllipse { pub x: f32, pub y: f32, pub rx: f32, pub ry: f32 }
 #[derive(Debug, Clone, Copy)]
 pub struct CreateLine { pub x1: f32, pub y1: f32, pub x2: f32, pub y2: f32 }
 
+/// Flecha por sus puntos de mundo: inicio, quiebres y fin (en un conector en codo, solo cuentan los extremos)
+#[derive(Debug, Clone)]
+pub struct CreateArrow { pub points: Vec<(f32, f32)>, pub routing: ArrowRouting }
+
+// Eventos del lápiz: muestras del puntero en CSS px (como PointerDown) con su presión (0..=1)
+#[derive(Debug, Clone, Copy)]
+pub struct PenStart { pub x: f32, pub y: f32, pub pressure: f32 }
+
+#[derive(Debug, Clone, Copy)]
+pub struct PenMove { pub x: f32, pub y: f32, pub pressure: f32 }
+
+#[derive(Debug, Clone, Copy)]
+pub struct PenEnd;
+
 // Recurso para gestionar selección
 #[derive(Resource, Default)]
 pub struct Selection {
-    pub selected: Vec<EntityId>,
+    // En orden de selección; un conjunto para que `is_selected` no recorra la lista
+    pub selected: IndexSet<EntityId>,
 }
 
 // Recurso para gestionar el estado de movimiento
@@ -121,6 +243,76 @@ pub struct ScaleState {
     pub handle_type: Option<momentum_core::model::HandleType>,
     pub initial_transforms: Vec<(EntityId, Transform)>, // Transformaciones iniciales
     pub initial_bounds: momentum_core::model::BoundingBox, // Bounding box inicial del grupo
+    pub initial_frame: OrientedBoundingBox, // Caja de la selección en la que se escala (orientada si es una sola forma)
+}
+
+// Recurso para gestionar el estado de rotación
+#[derive(Resource, Default)]
+pub struct RotateState {
+    pub is_rotating: bool,
+    pub center: (f32, f32), // Centro de rotación (centro de la selección al empezar)
+    pub start: (f32, f32), // Punto donde empezó el arrastre
+    pub initial_transforms: Vec<(EntityId, Transform)>, // Transformaciones iniciales
+}
+
+// Caja de mundo de cada entidad tras el último recálculo de flechas y entidades creadas, borradas o
+// cambiadas desde entonces, para recalcular solo las flechas afectadas
+#[derive(Resource, Default)]
+pub struct ArrowRoutes {
+    pub bounds: HashMap<EntityId, BoundingBox>,
+    pub dirty: HashSet<EntityId>,
+}
+
+impl ArrowRoutes {
+    /// Apunta entidades cuya caja puede haber cambiado para el próximo recálculo
+    pub fn touch(&mut self, ids: impl IntoIterator<Item = EntityId>) {
+        self.dirty.extend(ids);
+    }
+
+    /// Tras sustituir el documento entero: todo lo que había y todo lo que hay puede haber cambiado
+    fn touch_all(&mut self, doc: &Document) {
+        let ArrowRoutes { bounds, dirty } = self;
+        dirty.extend(bounds.keys().copied().chain(doc.ids()));
+    }
+
+    /// Vacía las entidades apuntadas, guarda sus cajas nuevas y devuelve las flechas que hay que
+    /// recolocar por ellas
+    fn affected_arrows(&mut self, doc: &Document) -> Vec<EntityId> {
+        let mut changed = HashSet::with_capacity(self.dirty.len());
+        let mut areas = Vec::new();
+        for id in self.dirty.drain() {
+            let now = doc.get(id).map(|e| BoundingBox::from_shape(&e.transform, &e.shape));
+            let before = match now {
+                Some(now) => self.bounds.insert(id, now),
+                None => self.bounds.remove(&id),
+            };
+            if before != now {
+                changed.insert(id);
+                areas.extend(now);
+                areas.extend(before);
+            }
+        }
+        if changed.is_empty() { return Vec::new(); }
+        arrow::affected_arrows(doc, &changed, &areas)
+    }
+}
+
+// Recurso con el trazo de lápiz en curso
+#[derive(Resource, Default)]
+pub struct PenState {
+    pub is_drawing: bool,
+    pub samples: Vec<StrokeSample>, // Muestras en coordenadas de mundo
+}
+
+// Recurso para gestionar el estado del marquee
+#[derive(Resource, Default)]
+pub struct MarqueeState {
+    pub is_active: bool,
+    pub mode: MarqueeMode,
+    pub additive: bool,
+    pub origin: (f32, f32), // Punto donde empezó el arrastre, en coordenadas de mundo
+    pub rect: BoundingBox, // Rectángulo actual en coordenadas de mundo (normalizado)
+    pub initial_selection: IndexSet<EntityId>, // Selección al empezar (se conserva si es aditivo)
 }
 
 impl Selection {
@@ -129,13 +321,11 @@ impl Selection {
     }
     
     pub fn select(&mut self, id: EntityId) {
-        if !self.is_selected(id) {
-            self.selected.push(id);
-        }
+        self.selected.insert(id);
     }
     
     pub fn deselect(&mut self, id: EntityId) {
-        self.selected.retain(|&x| x != id);
+        self.selected.shift_remove(&id);
     }
     
     pub fn clear(&mut self) {
@@ -162,12 +352,75 @@ fn hit_test_entity(click_x: f32, click_y: f32, entity_id: EntityId, transform: &
     }
 }
 
+/// Caja de la selección en coordenadas de mundo: orientada si hay una sola entidad (sigue su rotación),
+/// AABB combinado si hay varias
+fn selection_bounds(selection: &Selection, doc: &Document) -> Option<OrientedBoundingBox> {
+    let mut boxes = selection.selected.iter().filter_map(|selected_id| {
+        doc.get(*selected_id).map(|entity| (&entity.transform, &entity.shape))
+    });
+    let (first_transform, first_shape) = boxes.next()?;
+    let mut combined = BoundingBox::from_shape(first_transform, first_shape);
+    let mut count = 1;
+    for (transform, shape) in boxes {
+        combined = combined.union(&BoundingBox::from_shape(transform, shape));
+        count += 1;
+    }
+    if count == 1 {
+        Some(OrientedBoundingBox::from_shape(first_transform, first_shape))
+    } else {
+        Some(combined.into())
+    }
+}
+
+/// Tamaño visual de los handles en CSS px (se multiplica por DPR al dibujar)
+const HANDLE_SIZE: f32 = 10.0;
+/// Radio de acierto de los handles en CSS px, más grande que el visual para facilitar la interacción
+const HANDLE_HIT_RADIUS: f32 = 12.0;
+
+/// Resultado de un pick en un punto del canvas
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PickResult {
+    /// Handle de la selección bajo el punto (tiene prioridad sobre las entidades)
+    pub handle: Option<HandleType>,
+    /// Entidad más alta (la última dibujada) bajo el punto
+    pub entity: Option<EntityId>,
+    /// Todas las entidades bajo el punto, de arriba a abajo
+    pub hits: Vec<EntityId>,
+}
+
+/// Pick en coordenadas de pantalla (CSS px): handles de la selección primero, luego entidades de arriba a abajo
+fn pick(doc: &Document, selection: &Selection, camera: &Camera, x: f32, y: f32) -> PickResult {
+    // Los handles tienen tamaño fijo en pantalla; las entidades se prueban en mundo
+    let handle = selection_bounds(selection, doc).and_then(|bounds| {
+        camera
+            .box_to_screen(&bounds)
+            .generate_handles(HANDLE_SIZE)
+            .into_iter()
+            .find(|handle| {
+                let dx = x - (handle.x + handle.size / 2.0);
+                let dy = y - (handle.y + handle.size / 2.0);
+                (dx * dx + dy * dy).sqrt() <= HANDLE_HIT_RADIUS
+            })
+            .map(|handle| handle.handle_type)
+    });
+    
+    // Las entidades se dibujan en orden z: la última es la de arriba
+    let (x, y) = camera.screen_to_world(x, y);
+    let hits: Vec<EntityId> = doc
+        .iter()
+        .rev()
+        .filter(|entity| hit_test_entity(x, y, entity.id, &entity.transform, &entity.shape, doc))
+        .map(|entity| entity.id)
+        .collect();
+    
+    PickResult { handle, entity: hits.first().copied(), hits }
+}
+
 fn handle_pointer_down_system(
     mut queue: ResMut<InputQueue>,
     core: Res<CoreDoc>,
     mut selection: ResMut<Selection>,
-    _canvas_size: Res<CanvasSize>,
-    dpr: Res<CanvasDpr>,
+    camera: Res<Camera>,
 ) {
     if queue.pointer_down.is_empty() { return; }
     
@@ -175,135 +428,42 @@ fn handle_pointer_down_system(
     let events: Vec<_> = queue.pointer_down.drain(..).collect();
     
     for event in &events {
-        // Convertir coordenadas de CSS px a píxeles físicos para hit testing
-        let click_x = event.x * dpr.0;
-        let click_y = event.y * dpr.0;
-        
-        let mut handle_clicked = false;
+        let result = pick(&core.0, &selection, &camera, event.x, event.y);
         
-        // PRIORIDAD 1: Verificar si se hizo clic en un scale handle (solo si hay selección)
-        if !selection.selected.is_empty() {
-            // Calcular bounding box combinado de todas las entidades seleccionadas
-            let mut min_x = f32::INFINITY;
-            let mut max_x = f32::NEG_INFINITY;
-            let mut min_y = f32::INFINITY;
-            let mut max_y = f32::NEG_INFINITY;
-            
-            for selected_id in &selection.selected {
-                for (id, transform, _style, shape) in &core.0.entities {
-                    if id == selected_id {
-                        let bbox = momentum_core::model::BoundingBox::from_shape(transform, shape);
-                        min_x = min_x.min(bbox.x);
-                        max_x = max_x.max(bbox.x + bbox.width);
-                        min_y = min_y.min(bbox.y);
-                        max_y = max_y.max(bbox.y + bbox.height);
-                        break;
-                    }
-                }
-            }
-            
-            if min_x < f32::INFINITY {
-                // Crear bounding box combinado y convertir a píxeles físicos
-                let combined_bbox = momentum_core::model::BoundingBox {
-                    x: min_x * dpr.0,
-                    y: min_y * dpr.0,
-                    width: (max_x - min_x) * dpr.0,
-                    height: (max_y - min_y) * dpr.0,
-                };
-                
-                // Generar handles y verificar si se hizo clic en uno
-                let handle_size = 10.0 * dpr.0;
-                let handles = combined_bbox.generate_handles(handle_size);
-                
-                // Usar hitbox circular más grande para mejor interacción con handles
-                let hit_radius = (handle_size * 0.75).max(12.0 * dpr.0); // Más grande que visual
-                
-                for handle in handles {
-                    let handle_center_x = handle.x + handle.size / 2.0;
-                    let handle_center_y = handle.y + handle.size / 2.0;
-                    
-                    let dx = click_x - handle_center_x;
-                    let dy = click_y - handle_center_y;
-                    let distance = (dx * dx + dy * dy).sqrt();
-                    
-                    if distance <= hit_radius {
-                        // Se hizo clic en un handle - iniciar escalado
-                        log!("Clicked on scale handle: {:?} (hit radius: {})", handle.handle_type, hit_radius);
-                        queue.scale_start.push(ScaleStart {
-                            handle_type: handle.handle_type,
-                            x: event.x,
-                            y: event.y,
-                        });
-                        handle_clicked = true;
-                        break; // Solo procesar el primer handle encontrado
-                    }
-                }
+        // PRIORIDAD 1: handle de la selección
+        if let Some(handle_type) = result.handle {
+            if handle_type == HandleType::Rotate {
+                // Se hizo clic en el handle de rotación - iniciar rotación
+                log!("Clicked on rotate handle");
+                queue.rotate_start.push(RotateStart { x: event.x, y: event.y });
+            } else {
+                // Se hizo clic en un handle - iniciar escalado
+                log!("Clicked on scale handle: {:?}", handle_type);
+                queue.scale_start.push(ScaleStart { handle_type, x: event.x, y: event.y });
             }
+            continue;
         }
         
-        // PRIORIDAD 2: Hit test de entidades (solo si no se hizo clic en un handle)
-        if !handle_clicked {
-            let mut found_entity = None;
-            
-            for (id, transform, _style, shape) in &core.0.entities {
-                // Usar el nuevo sistema de hitbox con fallback al shape
-                let transform_physical = Transform {
-                    x: transform.x * dpr.0,
-                    y: transform.y * dpr.0,
-                    scale_x: transform.scale_x * dpr.0,
-                    scale_y: transform.scale_y * dpr.0,
-                    rotation: transform.rotation,
-                };
-                
-                let mut shape_physical = shape.clone();
-                match shape_physical {
-                    Shape::Rect { ref mut w, ref mut h } => {
-                        *w *= dpr.0;
-                        *h *= dpr.0;
-                    }
-                    Shape::Ellipse { ref mut rx, ref mut ry } => {
-                        *rx *= dpr.0;
-                        *ry *= dpr.0;
-                    }
-                    Shape::Line { ref mut x2, ref mut y2 } => {
-                        *x2 *= dpr.0;
-                        *y2 *= dpr.0;
-                    }
-                    Shape::Polygon { ref mut points } => {
-                        for (x, y) in points {
-                            *x *= dpr.0;
-                            *y *= dpr.0;
-                        }
-                    }
-                }
-                
-                if hit_test_entity(click_x, click_y, *id, &transform_physical, &shape_physical, &core.0) {
-                    found_entity = Some(*id);
-                    break; // Seleccionar la primera entidad encontrada
-                }
-            }
-            
-            // Actualizar selección con soporte para multi-selección
-            if let Some(entity_id) = found_entity {
-                log!("Hit detected on entity {}", entity_id.0);
-                if event.ctrl_key {
-                    // Ctrl/Cmd+click: toggle la selección de la entidad
-                    selection.toggle(entity_id);
-                    log!("Toggled entity {} selection", entity_id.0);
-                } else {
-                    // Click normal: selección única (reemplaza la anterior)
-                    selection.clear();
-                    selection.select(entity_id);
-                    log!("Selected entity {}", entity_id.0);
-                }
-                log!("Current selection: {:?}", selection.selected);
+        // PRIORIDAD 2: entidad más alta bajo el puntero
+        if let Some(entity_id) = result.entity {
+            log!("Hit detected on entity {}", entity_id.0);
+            if event.ctrl_key {
+                // Ctrl/Cmd+click: toggle la selección de la entidad
+                selection.toggle(entity_id);
+                log!("Toggled entity {} selection", entity_id.0);
             } else {
-                log!("No entity hit");
-                // Clic en espacio vacío - limpiar selección solo si no se mantiene Ctrl
-                if !event.ctrl_key {
-                    selection.clear();
-                    log!("Cleared selection");
-                }
+                // Click normal: selección única (reemplaza la anterior)
+                selection.clear();
+                selection.select(entity_id);
+                log!("Selected entity {}", entity_id.0);
+            }
+            log!("Current selection: {:?}", selection.selected);
+        } else {
+            log!("No entity hit");
+            // Clic en espacio vacío - limpiar selección salvo con Ctrl o Shift (marquee aditivo)
+            if !event.ctrl_key && !event.shift_key {
+                selection.clear();
+                log!("Cleared selection");
             }
         }
     }
@@ -316,42 +476,398 @@ pub struct CreateRect { pub x: f32, pub y: f32, pub w: f32, pub h: f32 }
 fn handle_create_rect_system(
     mut queue: ResMut<InputQueue>,
     mut core: ResMut<CoreDoc>,
+    mut history: ResMut<History>,
+    mut routes: ResMut<ArrowRoutes>,
 ) {
     if queue.create_rect.is_empty() { return; }
     for ev in queue.create_rect.drain(..) {
-        let _id = core.0.create_shape(
+        let id = core.0.create_shape(
             Transform { x: ev.x, y: ev.y, ..Default::default() },
-            Style { stroke: Some(Color(0.10, 0.12, 0.16, 1.0)), stroke_width: 2.0, opacity: 1.0, ..Default::default() },
-            Shape::Rect { w: ev.w, h: ev.h },
+            Style { stroke: Some(Color(0.10, 0.12, 0.16, 1.0).into()), stroke_width: 2.0, opacity: 1.0, ..Default::default() },
+            Shape::Rect { w: ev.w, h: ev.h, radii: CornerRadii::default() },
         );
+        routes.touch([id]);
+        if let Some(snapshot) = EntitySnapshot::capture(&core.0, id) {
+            history.record(Command::Insert(vec![snapshot]));
+        }
     }
 }
 
 fn handle_create_ellipse_system(
     mut queue: ResMut<InputQueue>,
     mut core: ResMut<CoreDoc>,
+    mut history: ResMut<History>,
+    mut routes: ResMut<ArrowRoutes>,
 ) {
     if queue.create_ellipse.is_empty() { return; }
     for ev in queue.create_ellipse.drain(..) {
-        let _id = core.0.create_shape(
+        let id = core.0.create_shape(
             Transform { x: ev.x, y: ev.y, ..Default::default() },
-            Style { stroke: Some(Color(0.10, 0.12, 0.16, 1.0)), stroke_width: 2.0, opacity: 1.0, ..Default::default() },
+            Style { stroke: Some(Color(0.10, 0.12, 0.16, 1.0).into()), stroke_width: 2.0, opacity: 1.0, ..Default::default() },
             Shape::Ellipse { rx: ev.rx, ry: ev.ry },
         );
+        routes.touch([id]);
+        if let Some(snapshot) = EntitySnapshot::capture(&core.0, id) {
+            history.record(Command::Insert(vec![snapshot]));
+        }
     }
 }
 
 fn handle_create_line_system(
     mut queue: ResMut<InputQueue>,
     mut core: ResMut<CoreDoc>,
+    mut history: ResMut<History>,
+    mut routes: ResMut<ArrowRoutes>,
 ) {
     if queue.create_line.is_empty() { return; }
     for ev in queue.create_line.drain(..) {
-        let _id = core.0.create_shape(
+        let id = core.0.create_shape(
             Transform { x: ev.x1, y: ev.y1, ..Default::default() },
-            Style { stroke: Some(Color(0.10, 0.12, 0.16, 1.0)), stroke_width: 2.0, opacity: 1.0, ..Default::default() },
+            Style { stroke: Some(Color(0.10, 0.12, 0.16, 1.0).into()), stroke_width: 2.0, opacity: 1.0, ..Default::default() },
             Shape::Line { x2: ev.x2 - ev.x1, y2: ev.y2 - ev.y1 },
         );
+        routes.touch([id]);
+        if let Some(snapshot) = EntitySnapshot::capture(&core.0, id) {
+            history.record(Command::Insert(vec![snapshot]));
+        }
+    }
+}
+
+/// Enganche para un extremo de flecha en (x, y) de mundo: la entidad enganchable más alta bajo el punto
+fn binding_at(doc: &Document, x: f32, y: f32) -> Option<ArrowBinding> {
+    doc.iter()
+        .rev()
+        .find(|e| arrow::is_bindable(&e.shape) && hit_test_entity(x, y, e.id, &e.transform, &e.shape, doc))
+        .map(|e| ArrowBinding { target: e.id, anchor: arrow::anchor_at(&e.transform, &e.shape, x, y) })
+}
+
+/// Enganches de una copia: los destinos también copiados pasan a sus copias y el resto se suelta
+fn remapped_bindings(bindings: ArrowBindings, copies: &HashMap<EntityId, EntityId>) -> ArrowBindings {
+    let remap = |b: Option<ArrowBinding>| b.and_then(|b| Some(ArrowBinding { target: *copies.get(&b.target)?, ..b }));
+    ArrowBindings { start: remap(bindings.start), end: remap(bindings.end) }
+}
+
+fn handle_create_arrow_system(
+    mut queue: ResMut<InputQueue>,
+    mut core: ResMut<CoreDoc>,
+    mut history: ResMut<History>,
+    mut routes: ResMut<ArrowRoutes>,
+) {
+    if queue.create_arrow.is_empty() { return; }
+    for ev in queue.create_arrow.drain(..) {
+        if ev.points.len() < 2 { continue; }
+        let ((x1, y1), (x2, y2)) = (ev.points[0], ev.points[ev.points.len() - 1]);
+        // Cada extremo se engancha a la forma sobre la que empieza o termina
+        let bindings = ArrowBindings { start: binding_at(&core.0, x1, y1), end: binding_at(&core.0, x2, y2) };
+        let points = ev.points.iter().map(|(x, y)| (x - x1, y - y1)).collect();
+        let arrow = Arrow { points, start_head: ArrowHead::None, end_head: ArrowHead::Triangle, routing: ev.routing };
+        let id = core.0.create_shape(
+            Transform { x: x1, y: y1, ..Default::default() },
+            Style { stroke: Some(Color(0.10, 0.12, 0.16, 1.0).into()), stroke_width: 2.0, opacity: 1.0, ..Default::default() },
+            Shape::Arrow { arrow },
+        );
+        if let Some(entity) = core.0.get_mut(id) {
+            entity.bindings = bindings;
+        }
+        // Los extremos enganchados quedan ya sobre el contorno de su destino (y el conector, trazado)
+        arrow::update_arrows(&mut core.0, &[id]);
+        routes.touch([id]);
+        if let Some(snapshot) = EntitySnapshot::capture(&core.0, id) {
+            history.record(Command::Insert(vec![snapshot]));
+        }
+    }
+}
+
+/// Recoloca las flechas enganchadas a entidades que se han movido, escalado o rotado, y vuelve a
+/// trazar los conectores en codo por los que pasa algo que ha cambiado. Solo se miran las entidades
+/// apuntadas en `ArrowRoutes` (comparando su caja con la anterior) y el documento solo se toma
+/// como mutable, y se marca cambiado, si alguna flecha se mueve.
+/// No se registra en el historial: deshacer el gesto devuelve los destinos y las flechas los siguen.
+fn update_arrow_bindings_system(mut core: ResMut<CoreDoc>, mut routes: ResMut<ArrowRoutes>) {
+    if routes.dirty.is_empty() { return; }
+    sync_arrows(&mut core, &mut routes);
+}
+
+fn sync_arrows(core: &mut impl DerefMut<Target = CoreDoc>, routes: &mut ArrowRoutes) {
+    let affected = routes.affected_arrows(&core.0);
+    if affected.is_empty() { return; }
+    let doc = &mut core.0;
+    // Las cajas de las flechas recolocadas quedan al día sin volver a mirarlas en el próximo frame
+    for id in arrow::update_arrows(doc, &affected) {
+        if let Some(e) = doc.get(id) {
+            routes.bounds.insert(id, BoundingBox::from_shape(&e.transform, &e.shape));
+        }
+    }
+}
+
+/// Grosor del lápiz (unidades de mundo) con la presión por defecto
+const PEN_WIDTH: f32 = 3.0;
+/// Desviación máxima al simplificar el trazo, en CSS px: con zoom se conserva más detalle
+const PEN_SIMPLIFY_TOLERANCE: f32 = 0.75;
+
+fn pen_style() -> Style {
+    Style { stroke: Some(Color(0.10, 0.12, 0.16, 1.0).into()), stroke_width: PEN_WIDTH, opacity: 1.0, ..Default::default() }
+}
+
+/// Trazo ajustado a las muestras, con el origen de la entidad en la primera
+fn pen_stroke(samples: &[StrokeSample], camera: &Camera) -> Option<(Transform, momentum_core::model::FreehandStroke)> {
+    let first = *samples.first()?;
+    let local: Vec<StrokeSample> = samples.iter().map(|s| StrokeSample { x: s.x - first.x, y: s.y - first.y, ..*s }).collect();
+    let stroke = freehand::fit_stroke(&local, PEN_WIDTH, camera.screen_to_world_len(PEN_SIMPLIFY_TOLERANCE))?;
+    Some((Transform { x: first.x, y: first.y, ..Default::default() }, stroke))
+}
+
+fn handle_pen_start_system(
+    mut queue: ResMut<InputQueue>,
+    mut pen: ResMut<PenState>,
+    camera: Res<Camera>,
+) {
+    if queue.pen_start.is_empty() { return; }
+    for ev in queue.pen_start.drain(..) {
+        let (x, y) = camera.screen_to_world(ev.x, ev.y);
+        pen.is_drawing = true;
+        pen.samples = vec![StrokeSample { x, y, pressure: ev.pressure }];
+    }
+}
+
+fn handle_pen_move_system(
+    mut queue: ResMut<InputQueue>,
+    mut pen: ResMut<PenState>,
+    camera: Res<Camera>,
+) {
+    if queue.pen_move.is_empty() { return; }
+    for ev in queue.pen_move.drain(..) {
+        if !pen.is_drawing { continue; }
+        let (x, y) = camera.screen_to_world(ev.x, ev.y);
+        pen.samples.push(StrokeSample { x, y, pressure: ev.pressure });
+    }
+}
+
+fn handle_pen_end_system(
+    mut queue: ResMut<InputQueue>,
+    mut pen: ResMut<PenState>,
+    camera: Res<Camera>,
+    mut core: ResMut<CoreDoc>,
+    mut history: ResMut<History>,
+    mut routes: ResMut<ArrowRoutes>,
+) {
+    if queue.pen_end.is_empty() { return; }
+    queue.pen_end.clear();
+    if !pen.is_drawing { return; }
+    pen.is_drawing = false;
+    let samples = std::mem::take(&mut pen.samples);
+    let Some((transform, stroke)) = pen_stroke(&samples, &camera) else { return; };
+    let id = core.0.create_shape(transform, pen_style(), Shape::Freehand { stroke });
+    log!("ECS: pen stroke {:?} from {} samples", id, samples.len());
+    routes.touch([id]);
+    if let Some(snapshot) = EntitySnapshot::capture(&core.0, id) {
+        history.record(Command::Insert(vec![snapshot]));
+    }
+}
+
+/// Tamaño de letra de los textos y etiquetas nuevos, en unidades de mundo
+const TEXT_SIZE: f32 = 20.0;
+
+fn text_span() -> TextSpan {
+    TextSpan { text: String::new(), color: Color(0.10, 0.12, 0.16, 1.0), size: TEXT_SIZE, font_family: None, weight: None }
+}
+
+/// El color va en los tramos; el estilo de la entidad solo aporta la opacidad
+fn text_style() -> Style {
+    Style { opacity: 1.0, ..Default::default() }
+}
+
+/// Medidas del renderer activo (estimadas si no hay), para que cursor y cajas coincidan con lo pintado
+fn renderer_measure(mut renderer: Option<&mut RendererBox>) -> impl FnMut(&TextSpan) -> TextMetrics + '_ {
+    move |span| match renderer.as_mut() {
+        Some(renderer) => renderer.0.measure_text(span).unwrap_or_else(|_| text::approximate_metrics(span)),
+        None => text::approximate_metrics(span),
+    }
+}
+
+/// Medidor del texto: conformado con las fuentes cargadas y, si no hay ninguna, las medidas del renderer
+fn text_measure<'a>(fonts: &'a FontBook, renderer: Option<&'a mut RendererBox>) -> impl TextMeasure + 'a {
+    fonts.measurer(renderer_measure(renderer))
+}
+
+/// Punto de mundo en coordenadas locales de la entidad (deshace traslación, rotación y escala)
+fn world_to_local(transform: &Transform, x: f32, y: f32) -> (f32, f32) {
+    let (x, y) = transform.unrotate_point(x, y);
+    ((x - transform.x) / transform.scale_x, (y - transform.y) / transform.scale_y)
+}
+
+/// Maquetación de `shown` como texto (o etiqueta) de la entidad y su origen en coordenadas locales
+fn edit_layout(
+    entity: &Entity,
+    target: TextTarget,
+    shown: &TextBox,
+    measure: &mut dyn TextMeasure,
+) -> (TextLayout, (f32, f32)) {
+    match target {
+        TextTarget::Text(_) => (text::layout(shown, measure), (0.0, 0.0)),
+        TextTarget::Label(_) => text::label_layout(&entity.shape, shown, measure),
+    }
+}
+
+/// Offset del texto del objetivo bajo el punto (x, y) CSS px
+fn text_offset_at(
+    doc: &Document,
+    target: TextTarget,
+    camera: &Camera,
+    x: f32,
+    y: f32,
+    measure: &mut dyn TextMeasure,
+) -> usize {
+    let (Some(entity), Some(current)) = (doc.get(target.id()), target_text(doc, target)) else { return 0; };
+    let (layout, origin) = edit_layout(entity, target, current, measure);
+    let (wx, wy) = camera.screen_to_world(x, y);
+    let (lx, ly) = world_to_local(&entity.transform, wx, wy);
+    layout.offset_at(lx - origin.0, ly - origin.1)
+}
+
+/// Empieza a editar el texto bajo (x, y) CSS px, la etiqueta de la forma bajo el punto o, con `create`, un texto nuevo
+fn begin_text_edit(
+    edit: &mut TextEditState,
+    doc: &mut Document,
+    camera: &Camera,
+    (x, y): (f32, f32),
+    create: bool,
+    measure: &mut dyn TextMeasure,
+) {
+    // Sin selección: los handles no cuentan, solo las entidades
+    let hit = pick(doc, &Selection::default(), camera, x, y).entity.and_then(|id| doc.get(id));
+    let target = match hit {
+        Some(entity) if matches!(entity.shape, Shape::Text { .. }) => TextTarget::Text(entity.id),
+        Some(entity) if !create => TextTarget::Label(entity.id),
+        _ if create => {
+            // La primera línea queda centrada en el clic
+            let mut text_box = TextBox::new(text_span());
+            text::update_extent(&mut text_box, measure);
+            let (wx, wy) = camera.screen_to_world(x, y);
+            let transform = Transform { x: wx, y: wy - text_box.extent.1 / 2.0, ..Default::default() };
+            let id = doc.create_shape(transform, text_style(), Shape::Text { text: text_box });
+            log!("ECS: new text {:?}", id);
+            *edit = TextEditState { target: Some(TextTarget::Text(id)), ..Default::default() };
+            return;
+        }
+        _ => return,
+    };
+
+    // Sin huecos la posición en el orden de pintado sale directamente del slot
+    doc.compact();
+    let before = EntitySnapshot::capture(doc, target.id());
+    if let (TextTarget::Label(id), Some(entity)) = (target, doc.get_mut(target.id())) {
+        if entity.label.is_none() {
+            log!("ECS: new label on {:?}", id);
+            entity.label = Some(TextBox { align: TextAlign::Center, ..TextBox::new(text_span()) });
+        }
+    }
+    let offset = text_offset_at(doc, target, camera, x, y, measure);
+    *edit = TextEditState { target: Some(target), caret: offset, anchor: offset, before, ..Default::default() };
+}
+
+/// Termina la edición: un texto vacío desaparece y una etiqueta vacía se quita.
+/// Lo editado entra en el historial como una sola entrada.
+fn end_text_edit(edit: &mut TextEditState, doc: &mut Document, history: &mut History) {
+    let Some(target) = edit.target.take() else { return; };
+    let before = edit.before.take();
+    let changed = std::mem::take(&mut edit.changed);
+    edit.preedit.clear();
+    let empty = target_text(doc, target).is_none_or(TextBox::is_empty);
+    match target {
+        TextTarget::Text(id) if empty => {
+            match before {
+                Some(before) => {
+                    let command = Command::Remove(vec![before]);
+                    command.apply(doc);
+                    history.record(command);
+                }
+                // Creado en esta edición y sin escribir nada: no deja rastro
+                None => {
+                    doc.remove(id);
+                }
+            }
+            return;
+        }
+        TextTarget::Label(id) if empty => {
+            if let Some(entity) = doc.get_mut(id) {
+                entity.label = None;
+            }
+        }
+        _ => {}
+    }
+    if !changed {
+        return;
+    }
+    // Misma posición que al empezar: la edición no cambia el orden de pintado
+    let after = match &before {
+        Some(before) => before.recapture(doc),
+        None => EntitySnapshot::capture(doc, target.id()),
+    };
+    match (before, after) {
+        (Some(before), Some(after)) => history.record(Command::replace(before, after)),
+        (None, Some(after)) => history.record(Command::Insert(vec![after])),
+        _ => {}
+    }
+}
+
+fn handle_text_edit_system(
+    mut queue: ResMut<InputQueue>,
+    mut edit: ResMut<TextEditState>,
+    mut core: ResMut<CoreDoc>,
+    mut selection: ResMut<Selection>,
+    mut history: ResMut<History>,
+    (camera, fonts, mut routes): (Res<Camera>, Res<Fonts>, ResMut<ArrowRoutes>),
+    mut renderer: Option<NonSendMut<RendererBox>>,
+) {
+    if queue.text_edit.is_empty() { return; }
+    let mut measure = text_measure(&fonts.0, renderer.as_deref_mut());
+    let events: Vec<TextEdit> = queue.text_edit.drain(..).collect();
+    for event in events {
+        let doc = &mut core.0;
+        // El texto que se edita (o se crea, o se borra al quedar vacío) puede cambiar de caja
+        routes.touch(edit.target.map(|target| target.id()));
+        match event {
+            TextEdit::Begin { x, y, create } => {
+                end_text_edit(&mut edit, doc, &mut history);
+                begin_text_edit(&mut edit, doc, &camera, (x, y), create, &mut measure);
+                routes.touch(edit.target.map(|target| target.id()));
+                if edit.is_editing() {
+                    selection.clear();
+                }
+                continue;
+            }
+            TextEdit::End => {
+                end_text_edit(&mut edit, doc, &mut history);
+                continue;
+            }
+            _ => {}
+        }
+        let Some(target) = edit.target else { continue; };
+        match event {
+            TextEdit::Pointer { x, y, extend } => {
+                let offset = text_offset_at(doc, target, &camera, x, y, &mut measure);
+                edit.move_to(offset, extend);
+            }
+            TextEdit::Insert(inserted) => {
+                if let Some(text_box) = target_text_mut(doc, target) {
+                    edit.insert(text_box, &inserted);
+                }
+            }
+            TextEdit::Preedit(preedit) => edit.preedit = preedit,
+            TextEdit::Key { key, shift } => {
+                let (Some(entity), Some(current)) = (doc.get(target.id()), target_text(doc, target)) else { continue; };
+                let (layout, _) = edit_layout(entity, target, current, &mut measure);
+                if let Some(text_box) = target_text_mut(doc, target) {
+                    edit.apply_key(text_box, &layout, key, shift);
+                }
+            }
+            TextEdit::Begin { .. } | TextEdit::End => {}
+        }
+        // El tamaño guardado sigue al texto para que cajas y hit test estén al día
+        if let (TextTarget::Text(_), Some(text_box)) = (target, target_text_mut(doc, target)) {
+            text::update_extent(text_box, &mut measure);
+        }
     }
 }
 
@@ -367,11 +883,8 @@ fn handle_move_start_system(
             // Capturar posiciones iniciales de todas las entidades seleccionadas
             move_state.initial_positions.clear();
             for selected_id in &selection.selected {
-                for (id, transform, _style, _shape) in &core.0.entities {
-                    if id == selected_id {
-                        move_state.initial_positions.push((*selected_id, *transform));
-                        break;
-                    }
+                if let Some(entity) = core.0.get(*selected_id) {
+                    move_state.initial_positions.push((*selected_id, entity.transform));
                 }
             }
             move_state.is_moving = true;
@@ -383,22 +896,21 @@ fn handle_move_update_system(
     mut queue: ResMut<InputQueue>,
     move_state: Res<MoveState>,
     mut core: ResMut<CoreDoc>,
-    dpr: Res<CanvasDpr>,
+    camera: Res<Camera>,
+    mut routes: ResMut<ArrowRoutes>,
 ) {
     if queue.move_update.is_empty() || !move_state.is_moving { return; }
+    routes.touch(move_state.initial_positions.iter().map(|(id, _)| *id));
     for ev in queue.move_update.drain(..) {
-        // Convertir delta de CSS px a píxeles físicos
-        let dx = ev.dx * dpr.0;
-        let dy = ev.dy * dpr.0;
+        // Convertir delta de pantalla a mundo
+        let dx = camera.screen_to_world_len(ev.dx);
+        let dy = camera.screen_to_world_len(ev.dy);
         
         // Actualizar posiciones de entidades seleccionadas
         for (move_id, initial_transform) in &move_state.initial_positions {
-            for (id, transform, _style, _shape) in &mut core.0.entities {
-                if id == move_id {
-                    transform.x = initial_transform.x + dx;
-                    transform.y = initial_transform.y + dy;
-                    break;
-                }
+            if let Some(entity) = core.0.get_mut(*move_id) {
+                entity.transform.x = initial_transform.x + dx;
+                entity.transform.y = initial_transform.y + dy;
             }
         }
     }
@@ -407,9 +919,15 @@ fn handle_move_update_system(
 fn handle_move_end_system(
     mut queue: ResMut<InputQueue>,
     mut move_state: ResMut<MoveState>,
+    mut history: ResMut<History>,
+    core: Res<CoreDoc>,
 ) {
     if queue.move_end.is_empty() { return; }
     for _ev in queue.move_end.drain(..) {
+        // Todo el arrastre queda como una sola entrada del historial
+        if let Some(command) = history::transform_changes(&core.0, &move_state.initial_positions) {
+            history.record(command);
+        }
         move_state.is_moving = false;
         move_state.initial_positions.clear();
     }
@@ -423,85 +941,125 @@ fn handle_scale_start_system(
 ) {
     if queue.scale_start.is_empty() { return; }
     for ev in queue.scale_start.drain(..) {
-        if !selection.selected.is_empty() {
-            // Capturar transformaciones iniciales y calcular bounding box del grupo
-            scale_state.initial_transforms.clear();
-            let mut min_x = f32::INFINITY;
-            let mut max_x = f32::NEG_INFINITY;
-            let mut min_y = f32::INFINITY;
-            let mut max_y = f32::NEG_INFINITY;
-            
-            for selected_id in &selection.selected {
-                for (id, transform, _style, shape) in &core.0.entities {
-                    if id == selected_id {
-                        scale_state.initial_transforms.push((*selected_id, *transform));
-                        let bbox = momentum_core::model::BoundingBox::from_shape(transform, shape);
-                        min_x = min_x.min(bbox.x);
-                        max_x = max_x.max(bbox.x + bbox.width);
-                        min_y = min_y.min(bbox.y);
-                        max_y = max_y.max(bbox.y + bbox.height);
-                        break;
-                    }
-                }
+        let Some(frame) = selection_bounds(&selection, &core.0) else { continue; };
+        
+        // Capturar transformaciones iniciales y la caja del grupo
+        scale_state.initial_transforms.clear();
+        for selected_id in &selection.selected {
+            if let Some(entity) = core.0.get(*selected_id) {
+                scale_state.initial_transforms.push((*selected_id, entity.transform));
             }
-            
-            scale_state.initial_bounds = momentum_core::model::BoundingBox {
-                x: min_x,
-                y: min_y,
-                width: max_x - min_x,
-                height: max_y - min_y,
-            };
-            scale_state.handle_type = Some(ev.handle_type);
-            scale_state.is_scaling = true;
         }
+        
+        scale_state.initial_bounds = frame.aabb();
+        scale_state.initial_frame = frame;
+        scale_state.handle_type = Some(ev.handle_type);
+        scale_state.is_scaling = true;
+    }
+}
+
+/// Factores de escala en el marco de la selección y ancla (en ese marco) para el arrastre de un handle.
+/// El ancla es el handle opuesto, o el centro con `from_center`; pasar del ancla da factores negativos (volteo).
+fn scale_factors(
+    frame: &OrientedBoundingBox,
+    handle_type: HandleType,
+    dx: f32,
+    dy: f32,
+    keep_aspect: bool,
+    from_center: bool,
+) -> (f32, f32, (f32, f32)) {
+    let local = frame.local;
+    let (u, v) = handle_type.box_position();
+    let (anchor_u, anchor_v) = if from_center { (0.5, 0.5) } else { (1.0 - u, 1.0 - v) };
+    let anchor = (local.x + anchor_u * local.width, local.y + anchor_v * local.height);
+    let handle = (local.x + u * local.width, local.y + v * local.height);
+    
+    // Delta del puntero expresado en el marco de la selección
+    let (local_dx, local_dy) = rotate_around(dx, dy, 0.0, 0.0, -frame.rotation);
+    
+    let factor = |start: f32, anchor: f32, delta: f32| {
+        let span = start - anchor;
+        if span.abs() < f32::EPSILON { return 1.0; }
+        let f = (span + delta) / span;
+        // No colapsar a tamaño cero: mínimo 1px, conservando el signo
+        let min = 1.0 / span.abs();
+        if f.abs() < min { min.copysign(f) } else { f }
+    };
+    
+    // Los handles de borde solo escalan un eje
+    let affects_x = u != 0.5;
+    let affects_y = v != 0.5;
+    let mut fx = if affects_x { factor(handle.0, anchor.0, local_dx) } else { 1.0 };
+    let mut fy = if affects_y { factor(handle.1, anchor.1, local_dy) } else { 1.0 };
+    
+    if keep_aspect {
+        match (affects_x, affects_y) {
+            (true, true) => {
+                let f = fx.abs().max(fy.abs());
+                fx = f.copysign(fx);
+                fy = f.copysign(fy);
+            }
+            (true, false) => fy = fx.abs(),
+            (false, true) => fx = fy.abs(),
+            (false, false) => {}
+        }
+    }
+    
+    (fx, fy, anchor)
+}
+
+/// Aplica la escala (fx, fy) con ancla en el marco de la selección a la transformación inicial de una entidad
+fn scale_transform(initial: &Transform, frame: &OrientedBoundingBox, anchor: (f32, f32), fx: f32, fy: f32) -> Transform {
+    // El origen de la entidad se escala respecto al ancla, así los miembros del grupo mantienen sus proporciones
+    let (px, py) = frame.to_local(initial.x, initial.y);
+    let (x, y) = rotate_around(
+        anchor.0 + (px - anchor.0) * fx,
+        anchor.1 + (py - anchor.1) * fy,
+        frame.pivot_x,
+        frame.pivot_y,
+        frame.rotation,
+    );
+    let mut transform = Transform { x, y, ..*initial };
+    
+    let relative = initial.rotation - frame.rotation;
+    if relative.sin().abs() < 1e-4 {
+        // Ejes alineados con el marco: la escala se aplica tal cual
+        transform.scale_x = initial.scale_x * fx;
+        transform.scale_y = initial.scale_y * fy;
+    } else {
+        // Entidad girada respecto al marco: aproximar (sin sesgo) con la imagen de sus ejes locales
+        let (sin, cos) = relative.sin_cos();
+        let axis_x = (fx * cos, fy * sin);
+        let axis_y = (-fx * sin, fy * cos);
+        let flip = if fx * fy < 0.0 { -1.0 } else { 1.0 };
+        transform.rotation = (frame.rotation + axis_x.1.atan2(axis_x.0)).rem_euclid(std::f32::consts::TAU);
+        transform.scale_x = initial.scale_x * axis_x.0.hypot(axis_x.1);
+        transform.scale_y = initial.scale_y * axis_y.0.hypot(axis_y.1) * flip;
     }
+    transform
 }
 
 fn handle_scale_update_system(
     mut queue: ResMut<InputQueue>,
     scale_state: Res<ScaleState>,
     mut core: ResMut<CoreDoc>,
+    camera: Res<Camera>,
+    mut routes: ResMut<ArrowRoutes>,
 ) {
     if queue.scale_update.is_empty() || !scale_state.is_scaling { return; }
+    routes.touch(scale_state.initial_transforms.iter().map(|(id, _)| *id));
     for ev in queue.scale_update.drain(..) {
         let Some(handle_type) = scale_state.handle_type else { continue; };
+        if handle_type == HandleType::Rotate { continue; }
         
-        // Calcular factor de escala basado en el handle y el delta
-        let (scale_x, scale_y) = match handle_type {
-            momentum_core::model::HandleType::TopLeft => {
-                let factor_x = 1.0 - ev.dx / scale_state.initial_bounds.width.max(1.0);
-                let factor_y = 1.0 - ev.dy / scale_state.initial_bounds.height.max(1.0);
-                (factor_x.max(0.1), factor_y.max(0.1))
-            },
-            momentum_core::model::HandleType::TopRight => {
-                let factor_x = 1.0 + ev.dx / scale_state.initial_bounds.width.max(1.0);
-                let factor_y = 1.0 - ev.dy / scale_state.initial_bounds.height.max(1.0);
-                (factor_x.max(0.1), factor_y.max(0.1))
-            },
-            momentum_core::model::HandleType::BottomLeft => {
-                let factor_x = 1.0 - ev.dx / scale_state.initial_bounds.width.max(1.0);
-                let factor_y = 1.0 + ev.dy / scale_state.initial_bounds.height.max(1.0);
-                (factor_x.max(0.1), factor_y.max(0.1))
-            },
-            momentum_core::model::HandleType::BottomRight => {
-                let factor_x = 1.0 + ev.dx / scale_state.initial_bounds.width.max(1.0);
-                let factor_y = 1.0 + ev.dy / scale_state.initial_bounds.height.max(1.0);
-                (factor_x.max(0.1), factor_y.max(0.1))
-            },
-            momentum_core::model::HandleType::Top => (1.0, (1.0 - ev.dy / scale_state.initial_bounds.height.max(1.0)).max(0.1)),
-            momentum_core::model::HandleType::Bottom => (1.0, (1.0 + ev.dy / scale_state.initial_bounds.height.max(1.0)).max(0.1)),
-            momentum_core::model::HandleType::Left => ((1.0 - ev.dx / scale_state.initial_bounds.width.max(1.0)).max(0.1), 1.0),
-            momentum_core::model::HandleType::Right => ((1.0 + ev.dx / scale_state.initial_bounds.width.max(1.0)).max(0.1), 1.0),
-        };
+        let frame = scale_state.initial_frame;
+        let (dx, dy) = (camera.screen_to_world_len(ev.dx), camera.screen_to_world_len(ev.dy));
+        let (fx, fy, anchor) = scale_factors(&frame, handle_type, dx, dy, ev.shift_key, ev.alt_key);
         
         // Aplicar escala a todas las entidades seleccionadas
         for (scale_id, initial_transform) in &scale_state.initial_transforms {
-            for (id, transform, _style, _shape) in &mut core.0.entities {
-                if id == scale_id {
-                    transform.scale_x = initial_transform.scale_x * scale_x;
-                    transform.scale_y = initial_transform.scale_y * scale_y;
-                    break;
-                }
+            if let Some(entity) = core.0.get_mut(*scale_id) {
+                entity.transform = scale_transform(initial_transform, &frame, anchor, fx, fy);
             }
         }
     }
@@ -510,46 +1068,347 @@ fn handle_scale_update_system(
 fn handle_scale_end_system(
     mut queue: ResMut<InputQueue>,
     mut scale_state: ResMut<ScaleState>,
+    mut history: ResMut<History>,
+    core: Res<CoreDoc>,
 ) {
     if queue.scale_end.is_empty() { return; }
     for _ev in queue.scale_end.drain(..) {
+        if let Some(command) = history::transform_changes(&core.0, &scale_state.initial_transforms) {
+            history.record(command);
+        }
         scale_state.is_scaling = false;
         scale_state.handle_type = None;
         scale_state.initial_transforms.clear();
     }
 }
 
-pub struct MomentumEcsApp {
-    world: World,
-    schedule: Schedule,
+/// Paso del ajuste de rotación con shift
+const ROTATION_SNAP: f32 = std::f32::consts::PI / 12.0; // 15°
+
+/// Redondea un ángulo al múltiplo de 15° más cercano
+fn snap_angle(angle: f32) -> f32 {
+    (angle / ROTATION_SNAP).round() * ROTATION_SNAP
 }
 
-impl Default for MomentumEcsApp {
-    fn default() -> Self {
-        let mut world = World::new();
-        world.insert_resource(AppState::default());
-        world.insert_resource(CoreDoc(Document::new()));
-        world.insert_resource(InputQueue::default());
-        world.insert_resource(Selection::default());
-        world.insert_resource(MoveState::default());
-        world.insert_resource(ScaleState::default());
-        world.insert_resource(CanvasSize::default());
-        world.insert_resource(CanvasDpr(1.0));
+fn handle_rotate_start_system(
+    mut queue: ResMut<InputQueue>,
+    mut rotate_state: ResMut<RotateState>,
+    selection: Res<Selection>,
+    core: Res<CoreDoc>,
+    camera: Res<Camera>,
+) {
+    if queue.rotate_start.is_empty() { return; }
+    for ev in queue.rotate_start.drain(..) {
+        let Some(bounds) = selection_bounds(&selection, &core.0) else { continue; };
+        
+        // Capturar transformaciones iniciales; se rota todo alrededor del centro del grupo
+        rotate_state.initial_transforms.clear();
+        for selected_id in &selection.selected {
+            if let Some(entity) = core.0.get(*selected_id) {
+                rotate_state.initial_transforms.push((*selected_id, entity.transform));
+            }
+        }
+        rotate_state.center = bounds.center();
+        rotate_state.start = camera.screen_to_world(ev.x, ev.y);
+        rotate_state.is_rotating = true;
+    }
+}
+
+fn handle_rotate_update_system(
+    mut queue: ResMut<InputQueue>,
+    rotate_state: Res<RotateState>,
+    mut core: ResMut<CoreDoc>,
+    camera: Res<Camera>,
+    mut routes: ResMut<ArrowRoutes>,
+) {
+    if queue.rotate_update.is_empty() || !rotate_state.is_rotating { return; }
+    routes.touch(rotate_state.initial_transforms.iter().map(|(id, _)| *id));
+    for ev in queue.rotate_update.drain(..) {
+        let (cx, cy) = rotate_state.center;
+        let (sx, sy) = rotate_state.start;
+        
+        // Ángulo barrido por el puntero alrededor del centro desde el inicio
+        let start_angle = (sy - cy).atan2(sx - cx);
+        let (dx, dy) = (camera.screen_to_world_len(ev.dx), camera.screen_to_world_len(ev.dy));
+        let current_angle = (sy + dy - cy).atan2(sx + dx - cx);
+        let mut delta = current_angle - start_angle;
+        
+        if ev.shift_key {
+            delta = match rotate_state.initial_transforms.as_slice() {
+                // Una sola forma: ajustar el ángulo absoluto resultante
+                [(_, initial)] => snap_angle(initial.rotation + delta) - initial.rotation,
+                // Grupo: ajustar el ángulo girado
+                _ => snap_angle(delta),
+            };
+        }
+        
+        // Rotar el origen de cada entidad alrededor del centro y acumular el ángulo
+        for (rotate_id, initial_transform) in &rotate_state.initial_transforms {
+            if let Some(entity) = core.0.get_mut(*rotate_id) {
+                let (x, y) = rotate_around(initial_transform.x, initial_transform.y, cx, cy, delta);
+                entity.transform.x = x;
+                entity.transform.y = y;
+                entity.transform.rotation = (initial_transform.rotation + delta).rem_euclid(std::f32::consts::TAU);
+            }
+        }
+    }
+}
+
+fn handle_rotate_end_system(
+    mut queue: ResMut<InputQueue>,
+    mut rotate_state: ResMut<RotateState>,
+    mut history: ResMut<History>,
+    core: Res<CoreDoc>,
+) {
+    if queue.rotate_end.is_empty() { return; }
+    for _ev in queue.rotate_end.drain(..) {
+        if let Some(command) = history::transform_changes(&core.0, &rotate_state.initial_transforms) {
+            history.record(command);
+        }
+        rotate_state.is_rotating = false;
+        rotate_state.initial_transforms.clear();
+    }
+}
+
+fn handle_marquee_start_system(
+    mut queue: ResMut<InputQueue>,
+    mut marquee: ResMut<MarqueeState>,
+    selection: Res<Selection>,
+    camera: Res<Camera>,
+) {
+    if queue.marquee_start.is_empty() { return; }
+    for ev in queue.marquee_start.drain(..) {
+        marquee.is_active = true;
+        marquee.mode = ev.mode;
+        marquee.additive = ev.shift_key;
+        let (x, y) = camera.screen_to_world(ev.x, ev.y);
+        marquee.origin = (x, y);
+        marquee.rect = BoundingBox { x, y, width: 0.0, height: 0.0 };
+        marquee.initial_selection = if ev.shift_key { selection.selected.clone() } else { IndexSet::new() };
+    }
+}
+
+fn handle_marquee_update_system(
+    mut queue: ResMut<InputQueue>,
+    mut marquee: ResMut<MarqueeState>,
+    mut selection: ResMut<Selection>,
+    core: Res<CoreDoc>,
+    camera: Res<Camera>,
+) {
+    if queue.marquee_update.is_empty() || !marquee.is_active { return; }
+    // Solo importa la última posición del frame; el delta es relativo al inicio
+    let Some(ev) = queue.marquee_update.pop() else { return; };
+    queue.marquee_update.clear();
+    
+    let (ox, oy) = marquee.origin;
+    let (dx, dy) = (camera.screen_to_world_len(ev.dx), camera.screen_to_world_len(ev.dy));
+    let rect = BoundingBox::from_corners(ox, oy, ox + dx, oy + dy);
+    marquee.rect = rect;
+    
+    // Recalcular la selección en vivo: selección inicial (si es aditivo) + entidades capturadas
+    selection.selected = marquee.initial_selection.clone();
+    for entity in core.0.iter() {
+        let captured = match marquee.mode {
+            MarqueeMode::Contain => rect.contains_box(&BoundingBox::from_shape(&entity.transform, &entity.shape)),
+            // Caja orientada de la entidad contra el rectángulo (aproximación para elipses)
+            MarqueeMode::Intersect => OrientedBoundingBox::from_shape(&entity.transform, &entity.shape).intersects_box(&rect),
+        };
+        if captured {
+            selection.select(entity.id);
+        }
+    }
+}
+
+fn handle_marquee_end_system(
+    mut queue: ResMut<InputQueue>,
+    mut marquee: ResMut<MarqueeState>,
+) {
+    if queue.marquee_end.is_empty() { return; }
+    for _ev in queue.marquee_end.drain(..) {
+        marquee.is_active = false;
+        marquee.initial_selection.clear();
+    }
+}
+
+fn handle_delete_selection_system(
+    mut queue: ResMut<InputQueue>,
+    mut selection: ResMut<Selection>,
+    mut core: ResMut<CoreDoc>,
+    mut history: ResMut<History>,
+    mut routes: ResMut<ArrowRoutes>,
+) {
+    if queue.delete_selection.is_empty() { return; }
+    for _ev in queue.delete_selection.drain(..) {
+        let snapshots = EntitySnapshot::capture_all(&core.0, selection.selected.iter().copied());
+        if snapshots.is_empty() { continue; }
+        let command = Command::Remove(snapshots);
+        command.apply(&mut core.0);
+        routes.touch(command.entities());
+        history.record(command);
+        selection.clear();
+    }
+}
+
+fn handle_duplicate_selection_system(
+    mut queue: ResMut<InputQueue>,
+    mut selection: ResMut<Selection>,
+    mut core: ResMut<CoreDoc>,
+    mut history: ResMut<History>,
+    camera: Res<Camera>,
+    mut routes: ResMut<ArrowRoutes>,
+) {
+    if queue.duplicate_selection.is_empty() { return; }
+    for ev in queue.duplicate_selection.drain(..) {
+        // Mismo desplazamiento en pantalla con cualquier zoom
+        let (dx, dy) = (camera.screen_to_world_len(ev.dx), camera.screen_to_world_len(ev.dy));
+        // Copiar en orden de pintado para que las copias conserven el apilado relativo
+        let originals: Vec<Entity> = core.0
+            .iter()
+            .filter(|entity| selection.is_selected(entity.id))
+            .cloned()
+            .collect();
+        if originals.is_empty() { continue; }
+        
+        let mut copies = HashMap::with_capacity(originals.len());
+        let mut ids = Vec::with_capacity(originals.len());
+        selection.clear();
+        for original in originals {
+            let mut transform = original.transform;
+            transform.x += dx;
+            transform.y += dy;
+            let id = core.0.create_shape(transform, original.style, original.shape);
+            if let Some(hitbox) = original.hitbox {
+                core.0.set_hitbox(id, hitbox);
+            }
+            if let Some(entity) = core.0.get_mut(id) {
+                entity.label = original.label;
+                entity.bindings = original.bindings;
+            }
+            copies.insert(original.id, id);
+            ids.push(id);
+            // Las copias quedan seleccionadas para poder moverlas a continuación
+            selection.select(id);
+        }
+        for id in &ids {
+            if let Some(entity) = core.0.get_mut(*id) {
+                entity.bindings = remapped_bindings(entity.bindings, &copies);
+            }
+        }
+        arrow::update_bindings(&mut core.0);
+        routes.touch(ids.iter().copied());
+        let snapshots = EntitySnapshot::capture_all(&core.0, ids);
+        history.record(Command::Insert(snapshots));
+    }
+}
+
+/// Nuevo orden de pintado (de abajo a arriba) tras aplicar `op` a las entidades seleccionadas.
+/// Las entidades seleccionadas conservan su orden relativo.
+fn reordered(order: &[EntityId], selection: &Selection, op: ZOrderOp) -> Vec<EntityId> {
+    let (selected, rest): (Vec<EntityId>, Vec<EntityId>) = order.iter().partition(|id| selection.is_selected(**id));
+    match op {
+        ZOrderOp::BringToFront => rest.into_iter().chain(selected).collect(),
+        ZOrderOp::SendToBack => selected.into_iter().chain(rest).collect(),
+        ZOrderOp::BringForward => {
+            // De arriba a abajo: cada seleccionada salta sobre la no seleccionada que tiene encima
+            let mut order = order.to_vec();
+            for i in (0..order.len().saturating_sub(1)).rev() {
+                if selection.is_selected(order[i]) && !selection.is_selected(order[i + 1]) {
+                    order.swap(i, i + 1);
+                }
+            }
+            order
+        }
+        ZOrderOp::SendBackward => {
+            let mut order = order.to_vec();
+            for i in 1..order.len() {
+                if selection.is_selected(order[i]) && !selection.is_selected(order[i - 1]) {
+                    order.swap(i, i - 1);
+                }
+            }
+            order
+        }
+    }
+}
+
+fn handle_z_order_system(
+    mut queue: ResMut<InputQueue>,
+    selection: Res<Selection>,
+    mut core: ResMut<CoreDoc>,
+    mut history: ResMut<History>,
+) {
+    if queue.z_order.is_empty() { return; }
+    for ev in queue.z_order.drain(..) {
+        if selection.selected.is_empty() { continue; }
+        let before: Vec<EntityId> = core.0.ids().collect();
+        let after = reordered(&before, &selection, ev.op);
+        if after == before { continue; }
+        let command = Command::Reorder { before, after };
+        command.apply(&mut core.0);
+        history.record(command);
+    }
+}
+
+pub struct MomentumEcsApp {
+    world: World,
+    schedule: Schedule,
+}
+
+impl Default for MomentumEcsApp {
+    fn default() -> Self {
+        let mut world = World::new();
+        world.insert_resource(AppState::default());
+        world.insert_resource(CoreDoc(Document::new()));
+        world.insert_resource(InputQueue::default());
+        world.insert_resource(Selection::default());
+        world.insert_resource(MoveState::default());
+        world.insert_resource(ScaleState::default());
+        world.insert_resource(RotateState::default());
+        world.insert_resource(MarqueeState::default());
+        world.insert_resource(PenState::default());
+        world.insert_resource(TextEditState::default());
+        world.insert_resource(Fonts::default());
+        world.insert_resource(ImageUploads::default());
+        world.insert_resource(ArrowRoutes::default());
+        world.insert_non_send_resource(StorageBox(Box::new(MemoryStorage::new())));
+        world.insert_resource(History::default());
+        world.insert_resource(CanvasSize::default());
+        world.insert_resource(CanvasDpr(1.0));
+        world.insert_resource(Camera::default());
 
         let mut schedule = Schedule::default();
+        // Start/update/end de movimiento, escalado, rotación y marquee pueden llegar en el mismo frame: procesarlos en orden
         schedule.add_systems((
             tick_system,
             handle_pointer_down_system,
             handle_create_rect_system,
             handle_create_ellipse_system,
             handle_create_line_system,
-            handle_move_start_system,
-            handle_move_update_system,
-            handle_move_end_system,
-            handle_scale_start_system,
-            handle_scale_update_system,
-            handle_scale_end_system,
-            render_system_with_selection_and_handles,
+            handle_create_arrow_system,
+            (handle_pen_start_system, handle_pen_move_system, handle_pen_end_system).chain(),
+            handle_text_edit_system.after(handle_pointer_down_system),
+            (handle_move_start_system, handle_move_update_system, handle_move_end_system)
+                .chain()
+                .after(handle_pointer_down_system),
+            (handle_scale_start_system, handle_scale_update_system, handle_scale_end_system)
+                .chain()
+                .after(handle_pointer_down_system),
+            (handle_rotate_start_system, handle_rotate_update_system, handle_rotate_end_system)
+                .chain()
+                .after(handle_pointer_down_system),
+            (handle_marquee_start_system, handle_marquee_update_system, handle_marquee_end_system)
+                .chain()
+                .after(handle_pointer_down_system),
+            // Las operaciones de edición actúan sobre la selección resultante del clic
+            (handle_duplicate_selection_system, handle_z_order_system, handle_delete_selection_system)
+                .chain()
+                .after(handle_pointer_down_system),
+            // Las flechas siguen a sus destinos antes de pintar el frame
+            (update_arrow_bindings_system, upload_images_system, render_system_with_selection_and_handles)
+                .chain()
+                .after(handle_create_arrow_system)
+                .after(handle_move_update_system)
+                .after(handle_scale_update_system)
+                .after(handle_rotate_update_system),
         ));
 
         Self { world, schedule }
@@ -571,84 +1430,27 @@ impl MomentumEcsApp {
     }
     
     pub fn send_pointer_down_with_modifiers(&mut self, x: f32, y: f32, ctrl_key: bool, shift_key: bool) -> PointerDownResult {
-        // Detectar handle click inmediatamente antes de añadir a queue
-        let handle_clicked = self.detect_handle_click(x, y);
+        // Resolver el pick inmediatamente (antes de añadir a la queue) para que la UI decida el modo
+        let result = self.pick(x, y);
         
         let mut q = self.world.resource_mut::<InputQueue>();
         q.pointer_down.push(PointerDown { x, y, ctrl_key, shift_key });
         
         PointerDownResult {
-            clicked_handle_type: handle_clicked,
-            entity_selected: false, // TODO: implementar detección de entidad si necesario
+            clicked_handle_type: result.handle.map(HandleType::to_u8),
+            entity_selected: result.handle.is_none() && result.entity.is_some(),
         }
     }
     
-    pub fn detect_handle_click(&mut self, x: f32, y: f32) -> Option<u8> {
+    /// Pick en coordenadas de pantalla (CSS px): handle de la selección, entidad más alta y todas las entidades bajo el punto
+    pub fn pick(&self, x: f32, y: f32) -> PickResult {
         let selection = self.world.resource::<Selection>();
         let core = self.world.resource::<CoreDoc>();
-        let dpr = self.world.resource::<CanvasDpr>();
-        
-        if selection.selected.is_empty() {
-            return None;
-        }
-        
-        // Convertir coordenadas de CSS px a píxeles físicos
-        let click_x = x * dpr.0;
-        let click_y = y * dpr.0;
-        
-        // Calcular bounding box combinado de todas las entidades seleccionadas
-        let mut min_x = f32::INFINITY;
-        let mut max_x = f32::NEG_INFINITY;
-        let mut min_y = f32::INFINITY;
-        let mut max_y = f32::NEG_INFINITY;
-        
-        for selected_id in &selection.selected {
-            for (id, transform, _style, shape) in &core.0.entities {
-                if id == selected_id {
-                    let bbox = momentum_core::model::BoundingBox::from_shape(transform, shape);
-                    min_x = min_x.min(bbox.x);
-                    max_x = max_x.max(bbox.x + bbox.width);
-                    min_y = min_y.min(bbox.y);
-                    max_y = max_y.max(bbox.y + bbox.height);
-                    break;
-                }
-            }
-        }
-        
-        if min_x >= f32::INFINITY {
-            return None;
-        }
-        
-        // Crear bounding box combinado y convertir a píxeles físicos
-        let combined_bbox = momentum_core::model::BoundingBox {
-            x: min_x * dpr.0,
-            y: min_y * dpr.0,
-            width: (max_x - min_x) * dpr.0,
-            height: (max_y - min_y) * dpr.0,
-        };
-        
-        // Generar handles y verificar si se hizo clic en uno
-        let handle_size = 10.0 * dpr.0;
-        let handles = combined_bbox.generate_handles(handle_size);
-        
-        // Usar hitbox circular más grande para mejor interacción (mismo que en handle_pointer_down_system)
-        let hit_radius = (handle_size * 0.75).max(12.0 * dpr.0);
-        
-        for handle in handles {
-            let handle_center_x = handle.x + handle.size / 2.0;
-            let handle_center_y = handle.y + handle.size / 2.0;
-            
-            let dx = click_x - handle_center_x;
-            let dy = click_y - handle_center_y;
-            let distance = (dx * dx + dy * dy).sqrt();
-            
-            if distance <= hit_radius {
-                log!("Detected handle click immediately: {:?} (hit radius: {})", handle.handle_type, hit_radius);
-                return Some(handle.handle_type.to_u8());
-            }
-        }
-        
-        None
+        pick(&core.0, selection, self.world.resource::<Camera>(), x, y)
+    }
+    
+    pub fn detect_handle_click(&mut self, x: f32, y: f32) -> Option<u8> {
+        self.pick(x, y).handle.map(HandleType::to_u8)
     }
     pub fn send_create_rect(&mut self, x: f32, y: f32, w: f32, h: f32) {
         let mut q = self.world.resource_mut::<InputQueue>();
@@ -665,6 +1467,127 @@ impl MomentumEcsApp {
         q.create_line.push(CreateLine { x1, y1, x2, y2 });
     }
     
+    /// Crea una flecha por sus puntos de mundo (inicio, quiebres y fin). Cada extremo que cae
+    /// sobre una forma queda enganchado a ella y la sigue cuando se mueve o se escala.
+    pub fn send_create_arrow(&mut self, points: Vec<(f32, f32)>) {
+        let mut q = self.world.resource_mut::<InputQueue>();
+        q.create_arrow.push(CreateArrow { points, routing: ArrowRouting::Straight });
+    }
+    
+    /// Crea un conector en codo entre dos puntos de mundo: tramos ortogonales que rodean las demás
+    /// entidades y se vuelven a trazar cuando algo se mueve cerca. Se engancha como `send_create_arrow`.
+    pub fn send_create_connector(&mut self, start: (f32, f32), end: (f32, f32)) {
+        let mut q = self.world.resource_mut::<InputQueue>();
+        q.create_arrow.push(CreateArrow { points: vec![start, end], routing: ArrowRouting::Elbow });
+    }
+    
+    /// Cambia las puntas de las flechas seleccionadas; devuelve false si no hay ninguna.
+    /// Se deshace en un solo paso.
+    pub fn set_arrow_heads(&mut self, start: ArrowHead, end: ArrowHead) -> bool {
+        self.edit_selection(|doc, id| {
+            let Some(Entity { shape: Shape::Arrow { arrow }, .. }) = doc.get_mut(id) else { return false; };
+            if (arrow.start_head, arrow.end_head) == (start, end) { return false; }
+            arrow.start_head = start;
+            arrow.end_head = end;
+            true
+        })
+    }
+    
+    /// Cambia el trazado de las flechas seleccionadas (recta o en codo); devuelve false si no hay
+    /// ninguna que cambie. Al pasar a recta se queda con los puntos del codo. Se deshace en un solo paso.
+    pub fn set_arrow_routing(&mut self, routing: ArrowRouting) -> bool {
+        self.edit_selection(|doc, id| {
+            let Some(Entity { shape: Shape::Arrow { arrow }, .. }) = doc.get_mut(id) else { return false; };
+            if arrow.routing == routing { return false; }
+            arrow.routing = routing;
+            arrow::update_arrows(doc, &[id]);
+            true
+        })
+    }
+    
+    /// Dibujo a mano alzada de la selección con la rugosidad y el relleno dados; `roughness <= 0`
+    /// vuelve a la geometría exacta. Cada entidad conserva su semilla (o estrena la de su id), así
+    /// que sus trazos no cambian entre frames ni sesiones. Devuelve si cambió algo (deshacible).
+    pub fn set_sketch(&mut self, roughness: f32, fill: SketchFill) -> bool {
+        self.edit_selection(|doc, id| {
+            let Some(entity) = doc.get_mut(id) else { return false; };
+            let sketch = (roughness > 0.0).then(|| Sketch {
+                roughness,
+                seed: entity.style.sketch.map_or_else(|| sketch::entity_seed(id), |s| s.seed),
+                fill,
+            });
+            if entity.style.sketch == sketch { return false; }
+            entity.style.sketch = sketch;
+            true
+        })
+    }
+    
+    /// Pintura de relleno de la selección (color, degradado, sombreado o imagen); `None` la quita.
+    /// Devuelve si cambió algo (deshacible en un solo paso).
+    pub fn set_fill_paint(&mut self, paint: Option<Paint>) -> bool {
+        self.set_paint(|style| &mut style.fill, paint)
+    }
+
+    /// Pintura del trazo de la selección; `None` lo quita. Devuelve si cambió algo (deshacible).
+    pub fn set_stroke_paint(&mut self, paint: Option<Paint>) -> bool {
+        self.set_paint(|style| &mut style.stroke, paint)
+    }
+
+    /// Radios de las esquinas de los rectángulos seleccionados (las demás formas no cambian).
+    /// Devuelve si cambió algo (deshacible en un solo paso).
+    pub fn set_corner_radii(&mut self, radii: CornerRadii) -> bool {
+        self.edit_selection(|doc, id| {
+            let Some(Entity { shape: Shape::Rect { radii: current, .. }, .. }) = doc.get_mut(id) else { return false; };
+            if *current == radii { return false; }
+            *current = radii;
+            true
+        })
+    }
+
+    fn set_paint(&mut self, slot: fn(&mut Style) -> &mut Option<Paint>, paint: Option<Paint>) -> bool {
+        self.edit_selection(|doc, id| {
+            let Some(entity) = doc.get_mut(id) else { return false; };
+            let current = slot(&mut entity.style);
+            if *current == paint { return false; }
+            *current = paint.clone();
+            true
+        })
+    }
+
+    /// Aplica `edit` a cada entidad seleccionada (devuelve si la cambió) y registra las que cambian
+    /// como un solo paso del historial. Devuelve si cambió alguna.
+    fn edit_selection(&mut self, mut edit: impl FnMut(&mut Document, EntityId) -> bool) -> bool {
+        let selected = self.world.resource::<Selection>().selected.clone();
+        let mut core = self.world.resource_mut::<CoreDoc>();
+        let mut changes = Vec::new();
+        for before in EntitySnapshot::capture_all(&core.0, selected) {
+            if !edit(&mut core.0, before.id) { continue; }
+            changes.extend(before.recapture(&core.0).map(|after| (before, after)));
+        }
+        if changes.is_empty() { return false; }
+        let command = Command::Replace(changes);
+        self.world.resource_mut::<ArrowRoutes>().touch(command.entities());
+        self.world.resource_mut::<History>().record(command);
+        true
+    }
+    
+    /// Empieza un trazo de lápiz en (x, y) CSS px; `pressure` en 0..=1
+    pub fn send_pen_start(&mut self, x: f32, y: f32, pressure: f32) {
+        let mut q = self.world.resource_mut::<InputQueue>();
+        q.pen_start.push(PenStart { x, y, pressure });
+    }
+    
+    pub fn send_pen_move(&mut self, x: f32, y: f32, pressure: f32) {
+        let mut q = self.world.resource_mut::<InputQueue>();
+        q.pen_move.push(PenMove { x, y, pressure });
+    }
+    
+    /// Termina el trazo: se suaviza, se simplifica y queda como una entidad (deshacible)
+    pub fn send_pen_end(&mut self) {
+        let mut q = self.world.resource_mut::<InputQueue>();
+        q.pen_end.push(PenEnd);
+    }
+    
     pub fn send_move_start(&mut self, x: f32, y: f32) {
         let mut q = self.world.resource_mut::<InputQueue>();
         q.move_start.push(MoveStart { x, y });
@@ -686,8 +1609,12 @@ impl MomentumEcsApp {
     }
     
     pub fn send_scale_update(&mut self, dx: f32, dy: f32) {
+        self.send_scale_update_with_modifiers(dx, dy, false, false);
+    }
+    
+    pub fn send_scale_update_with_modifiers(&mut self, dx: f32, dy: f32, shift_key: bool, alt_key: bool) {
         let mut q = self.world.resource_mut::<InputQueue>();
-        q.scale_update.push(ScaleUpdate { dx, dy });
+        q.scale_update.push(ScaleUpdate { dx, dy, shift_key, alt_key });
     }
     
     pub fn send_scale_end(&mut self) {
@@ -695,8 +1622,110 @@ impl MomentumEcsApp {
         q.scale_end.push(ScaleEnd);
     }
     
+    pub fn send_rotate_start(&mut self, x: f32, y: f32) {
+        let mut q = self.world.resource_mut::<InputQueue>();
+        q.rotate_start.push(RotateStart { x, y });
+    }
+    
+    pub fn send_rotate_update(&mut self, dx: f32, dy: f32, shift_key: bool) {
+        let mut q = self.world.resource_mut::<InputQueue>();
+        q.rotate_update.push(RotateUpdate { dx, dy, shift_key });
+    }
+    
+    pub fn send_rotate_end(&mut self) {
+        let mut q = self.world.resource_mut::<InputQueue>();
+        q.rotate_end.push(RotateEnd);
+    }
+    
+    pub fn send_marquee_start(&mut self, x: f32, y: f32, shift_key: bool, mode: MarqueeMode) {
+        let mut q = self.world.resource_mut::<InputQueue>();
+        q.marquee_start.push(MarqueeStart { x, y, shift_key, mode });
+    }
+    
+    pub fn send_marquee_update(&mut self, dx: f32, dy: f32) {
+        let mut q = self.world.resource_mut::<InputQueue>();
+        q.marquee_update.push(MarqueeUpdate { dx, dy });
+    }
+    
+    pub fn send_marquee_end(&mut self) {
+        let mut q = self.world.resource_mut::<InputQueue>();
+        q.marquee_end.push(MarqueeEnd);
+    }
+    
+    pub fn send_delete_selection(&mut self) {
+        let mut q = self.world.resource_mut::<InputQueue>();
+        q.delete_selection.push(DeleteSelection);
+    }
+    
+    /// Duplica la selección desplazando las copias (dx, dy) en CSS px; las copias pasan a estar seleccionadas
+    pub fn send_duplicate_selection(&mut self, dx: f32, dy: f32) {
+        let mut q = self.world.resource_mut::<InputQueue>();
+        q.duplicate_selection.push(DuplicateSelection { dx, dy });
+    }
+    
+    pub fn send_z_order(&mut self, op: ZOrderOp) {
+        let mut q = self.world.resource_mut::<InputQueue>();
+        q.z_order.push(ChangeZOrder { op });
+    }
+    
+    /// Deshace la última mutación del documento. Devuelve false si no había nada que deshacer.
+    pub fn undo(&mut self) -> bool {
+        let changed = self.world.resource_scope(|world, mut history: Mut<History>| {
+            let Some(command) = history.undo(&mut world.resource_mut::<CoreDoc>().0) else { return false; };
+            world.resource_mut::<ArrowRoutes>().touch(command.entities());
+            true
+        });
+        if changed {
+            self.sync_arrows();
+            self.prune_selection();
+        }
+        changed
+    }
+    
+    /// Rehace la última mutación deshecha. Devuelve false si no había nada que rehacer.
+    pub fn redo(&mut self) -> bool {
+        let changed = self.world.resource_scope(|world, mut history: Mut<History>| {
+            let Some(command) = history.redo(&mut world.resource_mut::<CoreDoc>().0) else { return false; };
+            world.resource_mut::<ArrowRoutes>().touch(command.entities());
+            true
+        });
+        if changed {
+            self.sync_arrows();
+            self.prune_selection();
+        }
+        changed
+    }
+    
+    pub fn can_undo(&self) -> bool {
+        self.world.resource::<History>().can_undo()
+    }
+    
+    pub fn can_redo(&self) -> bool {
+        self.world.resource::<History>().can_redo()
+    }
+    
+    /// Profundidad máxima del historial (mínimo 1)
+    pub fn set_history_limit(&mut self, limit: usize) {
+        self.world.resource_mut::<History>().set_limit(limit);
+    }
+    
+    /// Recoloca ya, sin esperar al próximo frame, las flechas afectadas por lo apuntado en `ArrowRoutes`
+    fn sync_arrows(&mut self) {
+        self.world.resource_scope(|world, mut routes: Mut<ArrowRoutes>| {
+            sync_arrows(&mut world.resource_mut::<CoreDoc>(), &mut routes);
+        });
+    }
+
+    /// Quitar de la selección las entidades que ya no existen (p.ej. tras deshacer una creación)
+    fn prune_selection(&mut self) {
+        self.world.resource_scope(|world, mut selection: Mut<Selection>| {
+            let doc = &world.resource::<CoreDoc>().0;
+            selection.selected.retain(|id| doc.contains(*id));
+        });
+    }
+    
     pub fn get_selected_entities(&self) -> Vec<EntityId> {
-        self.world.resource::<Selection>().selected.clone()
+        self.world.resource::<Selection>().selected.iter().copied().collect()
     }
     
     pub fn is_moving(&self) -> bool {
@@ -706,10 +1735,240 @@ impl MomentumEcsApp {
     pub fn is_scaling(&self) -> bool {
         self.world.resource::<ScaleState>().is_scaling
     }
+    
+    pub fn is_rotating(&self) -> bool {
+        self.world.resource::<RotateState>().is_rotating
+    }
+    
+    pub fn is_marquee_active(&self) -> bool {
+        self.world.resource::<MarqueeState>().is_active
+    }
+    pub fn is_pen_drawing(&self) -> bool {
+        self.world.resource::<PenState>().is_drawing
+    }
+    
+    /// Empieza a editar en (x, y) CSS px: el texto bajo el punto o, si no hay, la etiqueta de la forma
+    /// bajo el punto (doble clic). Con `create` (herramienta de texto) crea un texto si no hay ninguno.
+    pub fn send_text_begin(&mut self, x: f32, y: f32, create: bool) {
+        self.world.resource_mut::<InputQueue>().text_edit.push(TextEdit::Begin { x, y, create });
+    }
+    
+    /// Coloca el cursor en (x, y) CSS px dentro del texto en edición; con `extend` selecciona hasta ahí
+    pub fn send_text_pointer(&mut self, x: f32, y: f32, extend: bool) {
+        self.world.resource_mut::<InputQueue>().text_edit.push(TextEdit::Pointer { x, y, extend });
+    }
+    
+    pub fn send_text_insert(&mut self, text: &str) {
+        self.world.resource_mut::<InputQueue>().text_edit.push(TextEdit::Insert(text.to_string()));
+    }
+    
+    /// Composición del IME en curso (vacía al terminar o cancelar)
+    pub fn send_text_preedit(&mut self, text: &str) {
+        self.world.resource_mut::<InputQueue>().text_edit.push(TextEdit::Preedit(text.to_string()));
+    }
+    
+    pub fn send_text_key(&mut self, key: TextKey, shift: bool) {
+        self.world.resource_mut::<InputQueue>().text_edit.push(TextEdit::Key { key, shift });
+    }
+    
+    /// Termina la edición; el cambio se deshace en un solo paso
+    pub fn send_text_end(&mut self) {
+        self.world.resource_mut::<InputQueue>().text_edit.push(TextEdit::End);
+    }
+    
+    pub fn is_text_editing(&self) -> bool {
+        self.world.resource::<TextEditState>().is_editing()
+    }
+    
+    /// Entidad cuyo texto o etiqueta se está editando
+    pub fn text_edit_target(&self) -> Option<TextTarget> {
+        self.world.resource::<TextEditState>().target
+    }
+    
+    /// ¿Cae (x, y) CSS px dentro del texto en edición? (para decidir si un clic mueve el cursor o termina)
+    pub fn text_edit_hit(&self, x: f32, y: f32) -> bool {
+        self.text_edit_target().is_some_and(|target| self.pick(x, y).hits.contains(&target.id()))
+    }
+    
+    /// Cursor en pantalla (x, y superior y alto en CSS px), p.ej. para colocar la ventana del IME
+    pub fn text_caret_rect(&mut self) -> Option<(f32, f32, f32)> {
+        let edit = self.world.resource::<TextEditState>();
+        let target = edit.target?;
+        let doc = &self.world.resource::<CoreDoc>().0;
+        let shown = edit.displayed(target_text(doc, target)?);
+        let entity = doc.get(target.id())?.clone();
+        // Tras la composición en curso, que es donde el IME sigue escribiendo
+        let caret = edit.selection().0 + edit.preedit.chars().count();
+        let camera = *self.world.resource::<Camera>();
+        let fonts = self.world.resource::<Fonts>().0.clone();
+        let mut renderer = self.world.get_non_send_resource_mut::<RendererBox>();
+        let mut measure = text_measure(&fonts, renderer.as_deref_mut());
+        let (layout, origin) = edit_layout(&entity, target, &shown, &mut measure);
+        let (x, y, h) = layout.caret(caret);
+        let (wx, wy) = entity.transform.apply(origin.0 + x, origin.1 + y);
+        let (sx, sy) = camera.world_to_screen(wx, wy);
+        Some((sx, sy, h * entity.transform.scale_y.abs() * camera.zoom))
+    }
     pub fn document(&self) -> &Document { &self.world.resource::<CoreDoc>().0 }
+    
+    /// Sustituye el documento (p.ej. al importar). Limpia selección, interacciones en curso e historial.
+    pub fn replace_document(&mut self, doc: Document) {
+        self.world.resource_mut::<CoreDoc>().0 = doc;
+        self.world.resource_scope(|world, mut routes: Mut<ArrowRoutes>| routes.touch_all(&world.resource::<CoreDoc>().0));
+        self.world.resource_mut::<Selection>().clear();
+        self.world.insert_resource(InputQueue::default());
+        self.world.insert_resource(MoveState::default());
+        self.world.insert_resource(ScaleState::default());
+        self.world.insert_resource(RotateState::default());
+        self.world.insert_resource(MarqueeState::default());
+        self.world.insert_resource(PenState::default());
+        self.world.insert_resource(TextEditState::default());
+        self.world.resource_mut::<History>().clear();
+        self.remeasure_text();
+    }
+
+    /// Añade encima de todo las entidades de `doc` (p.ej. importadas de SVG) con ids nuevos,
+    /// conservando su orden, hitboxes, etiquetas y enganches entre ellas. Quedan seleccionadas y se
+    /// deshacen en un solo paso.
+    pub fn insert_entities(&mut self, doc: &Document) -> Vec<EntityId> {
+        let mut ids = Vec::with_capacity(doc.count());
+        let mut snapshots = Vec::with_capacity(doc.count());
+        self.world.resource_scope(|world, mut core: Mut<CoreDoc>| {
+            let mut selection = world.resource_mut::<Selection>();
+            selection.clear();
+            let mut copies = HashMap::with_capacity(doc.count());
+            for entity in doc.iter() {
+                let id = core.0.create_shape(entity.transform, entity.style.clone(), entity.shape.clone());
+                if let Some(hitbox) = &entity.hitbox {
+                    core.0.set_hitbox(id, hitbox.clone());
+                }
+                if let Some(copy) = core.0.get_mut(id) {
+                    copy.label = entity.label.clone();
+                    copy.bindings = entity.bindings;
+                }
+                copies.insert(entity.id, id);
+                selection.select(id);
+                ids.push(id);
+            }
+            for id in &ids {
+                if let Some(copy) = core.0.get_mut(*id) {
+                    copy.bindings = remapped_bindings(copy.bindings, &copies);
+                }
+            }
+            arrow::update_bindings(&mut core.0);
+            snapshots.extend(EntitySnapshot::capture_all(&core.0, ids.iter().copied()));
+        });
+        self.world.resource_mut::<ArrowRoutes>().touch(ids.iter().copied());
+        if !snapshots.is_empty() {
+            self.world.resource_mut::<History>().record(Command::Insert(snapshots));
+        }
+        ids
+    }
+
+    /// Importa una imagen PNG, JPEG o WebP centrada en (x, y) CSS px: guarda sus bytes como blob y crea
+    /// la entidad al tamaño de sus píxeles (limitado a `image::MAX_IMPORT_SIZE`). Queda seleccionada
+    /// y se deshace en un solo paso.
+    pub fn import_image(&mut self, data: &[u8], x: f32, y: f32) -> Result<EntityId, ImageError> {
+        let info = image::probe(data)?;
+        let blob = image::blob_id(data);
+        self.world.non_send_resource_mut::<StorageBox>().0.put_blob(blob, data)?;
+        // Si la misma imagen falló antes (p.ej. faltaba el blob), se vuelve a intentar subir
+        let mut uploads = self.world.resource_mut::<ImageUploads>();
+        if uploads.0.get(&ImageId::from(blob)) == Some(&false) {
+            uploads.0.remove(&ImageId::from(blob));
+        }
+
+        let (w, h) = info.import_size();
+        let (cx, cy) = self.world.resource::<Camera>().screen_to_world(x, y);
+        let transform = Transform { x: cx - w / 2.0, y: cy - h / 2.0, ..Default::default() };
+        let mut core = self.world.resource_mut::<CoreDoc>();
+        let id = core.0.create_shape(transform, Style { opacity: 1.0, ..Default::default() }, Shape::Image { blob, w, h });
+        let snapshot = EntitySnapshot::capture(&core.0, id);
+        self.world.resource_mut::<ArrowRoutes>().touch([id]);
+        let mut selection = self.world.resource_mut::<Selection>();
+        selection.clear();
+        selection.select(id);
+        if let Some(snapshot) = snapshot {
+            self.world.resource_mut::<History>().record(Command::Insert(vec![snapshot]));
+        }
+        log!("ECS: image {:?} imported as {:?} ({}x{} px)", info.format, id, info.width, info.height);
+        Ok(id)
+    }
+
+    /// Bytes de las imágenes del documento por blob, p.ej. para incrustarlas al exportar
+    pub fn image_blobs(&mut self) -> HashMap<BlobId, Vec<u8>> {
+        let blobs = self.world.resource::<CoreDoc>().0.image_blobs();
+        let mut storage = self.world.non_send_resource_mut::<StorageBox>();
+        blobs.into_iter().filter_map(|blob| Some((blob, storage.0.get_blob(blob).ok()??))).collect()
+    }
+
+    /// JSON del documento con sus imágenes incrustadas (ver `Document::to_json_with_images`)
+    pub fn document_json(&mut self) -> Result<String, DocumentFormatError> {
+        let images = self.image_blobs();
+        self.world.resource::<CoreDoc>().0.to_json_with_images(&images)
+    }
+
+    /// Abre un documento exportado con `document_json`: guarda sus imágenes en el almacenamiento
+    /// y sustituye el documento actual
+    pub fn load_document_json(&mut self, json: &str) -> Result<(), DocumentFormatError> {
+        let (doc, images) = Document::from_json_with_images(json)?;
+        let mut storage = self.world.non_send_resource_mut::<StorageBox>();
+        for (blob, data) in &images {
+            storage.0.put_blob(*blob, data)?;
+        }
+        // Como en `import_image`: las imágenes que fallaron por falta del blob se vuelven a subir
+        let mut uploads = self.world.resource_mut::<ImageUploads>();
+        for blob in images.keys() {
+            if uploads.0.get(&ImageId::from(*blob)) == Some(&false) {
+                uploads.0.remove(&ImageId::from(*blob));
+            }
+        }
+        self.replace_document(doc);
+        Ok(())
+    }
+
+    /// Sustituye el almacenamiento de blobs (p.ej. por uno persistente)
+    pub fn set_storage(&mut self, storage: Box<dyn StoragePort>) {
+        self.world.insert_non_send_resource(StorageBox(storage));
+        self.world.resource_mut::<ImageUploads>().0.clear();
+    }
+
     pub fn set_renderer(&mut self, renderer: Box<dyn RenderPort>) {
         // Guardar como recurso NonSend, ya que el renderer no es Send/Sync en WASM
         self.world.insert_non_send_resource(RendererBox(renderer));
+        // El nuevo renderer no tiene texturas: las imágenes se vuelven a subir en el próximo frame
+        self.world.resource_mut::<ImageUploads>().0.clear();
+        self.remeasure_text();
+    }
+
+    /// Carga una fuente (.ttf/.otf) para el texto del lienzo; los textos existentes se vuelven a medir con ella
+    pub fn load_font(&mut self, data: Vec<u8>) -> Result<FontId, FontError> {
+        let id = self.world.resource_mut::<Fonts>().0.load(data)?;
+        self.remeasure_text();
+        Ok(id)
+    }
+
+    pub fn fonts(&self) -> &FontBook { &self.world.resource::<Fonts>().0 }
+
+    /// Vuelve a medir los textos con las fuentes y el renderer actuales: otros dan otros tamaños
+    fn remeasure_text(&mut self) {
+        let fonts = self.world.resource::<Fonts>().0.clone();
+        let mut renderer = self.world.remove_non_send_resource::<RendererBox>();
+        // Sin fuentes ni renderer no hay medidas mejores que las guardadas
+        if fonts.is_empty() && renderer.is_none() { return; }
+        let mut measure = text_measure(&fonts, renderer.as_mut());
+        let mut measured = Vec::new();
+        for entity in self.world.resource_mut::<CoreDoc>().0.iter_mut() {
+            if let Shape::Text { text } = &mut entity.shape {
+                text::update_extent(text, &mut measure);
+                measured.push(entity.id);
+            }
+        }
+        drop(measure);
+        self.world.resource_mut::<ArrowRoutes>().touch(measured);
+        if let Some(renderer) = renderer {
+            self.world.insert_non_send_resource(renderer);
+        }
     }
     pub fn set_canvas_size(&mut self, w: u32, h: u32) {
         let mut sz = self.world.resource_mut::<CanvasSize>();
@@ -719,6 +1978,58 @@ impl MomentumEcsApp {
     pub fn set_canvas_dpr(&mut self, dpr: f32) {
         *self.world.resource_mut::<CanvasDpr>() = CanvasDpr(dpr.max(0.5));
     }
+    
+    pub fn camera(&self) -> Camera {
+        *self.world.resource::<Camera>()
+    }
+    
+    pub fn set_camera(&mut self, camera: Camera) {
+        let zoom = camera.zoom.clamp(camera::MIN_ZOOM, camera::MAX_ZOOM);
+        *self.world.resource_mut::<Camera>() = Camera { zoom, ..camera };
+    }
+    
+    /// Desplaza la vista por un arrastre de (dx, dy) CSS px
+    pub fn pan_by(&mut self, dx: f32, dy: f32) {
+        self.world.resource_mut::<Camera>().pan_by(dx, dy);
+    }
+    
+    /// Zoom alrededor del punto de pantalla (x, y), p.ej. el cursor en la rueda del ratón
+    pub fn zoom_at(&mut self, x: f32, y: f32, factor: f32) {
+        self.world.resource_mut::<Camera>().zoom_at(x, y, factor);
+    }
+    
+    pub fn screen_to_world(&self, x: f32, y: f32) -> (f32, f32) {
+        self.world.resource::<Camera>().screen_to_world(x, y)
+    }
+    
+    pub fn world_to_screen(&self, x: f32, y: f32) -> (f32, f32) {
+        self.world.resource::<Camera>().world_to_screen(x, y)
+    }
+    
+    /// Encuadra todo el documento. Devuelve false si está vacío.
+    pub fn zoom_to_fit(&mut self) -> bool {
+        let doc = &self.world.resource::<CoreDoc>().0;
+        let bounds = doc
+            .iter()
+            .map(|e| BoundingBox::from_shape(&e.transform, &e.shape))
+            .reduce(|a, b| a.union(&b));
+        self.fit_camera(bounds)
+    }
+    
+    /// Encuadra la selección. Devuelve false si no hay selección.
+    pub fn zoom_to_selection(&mut self) -> bool {
+        let bounds = selection_bounds(self.world.resource::<Selection>(), &self.world.resource::<CoreDoc>().0);
+        self.fit_camera(bounds.map(|b| b.aabb()))
+    }
+    
+    fn fit_camera(&mut self, bounds: Option<BoundingBox>) -> bool {
+        let Some(bounds) = bounds else { return false; };
+        // El viewport en CSS px: el tamaño del canvas está en píxeles físicos
+        let size = *self.world.resource::<CanvasSize>();
+        let dpr = self.world.resource::<CanvasDpr>().0;
+        self.world.resource_mut::<Camera>().fit(&bounds, size.w as f32 / dpr, size.h as f32 / dpr);
+        true
+    }
 }
 
 // ================= Render integration =================
@@ -731,66 +2042,199 @@ pub struct CanvasDpr(pub f32);
 /// Wrapper para almacenar un trait object no-Send en el mundo ECS
 pub struct RendererBox(pub Box<dyn RenderPort>);
 
+/// Almacenamiento de los blobs (bytes de las imágenes importadas); no-Send como el renderer
+pub struct StorageBox(pub Box<dyn StoragePort>);
+
+/// Texturas ya subidas al renderer actual, con si la subida fue bien. Se vacía al cambiar de renderer.
+#[derive(Resource, Default)]
+struct ImageUploads(HashMap<ImageId, bool>);
+
+/// Sube al renderer las imágenes del documento que aún no tiene, leyendo sus bytes del almacenamiento
+fn upload_images_system(
+    core: Res<CoreDoc>,
+    mut uploads: ResMut<ImageUploads>,
+    renderer: Option<NonSendMut<RendererBox>>,
+    storage: Option<NonSendMut<StorageBox>>,
+) {
+    let (Some(mut renderer), Some(mut storage)) = (renderer, storage) else { return; };
+    for entity in core.0.iter() {
+        let Shape::Image { blob, .. } = entity.shape else { continue; };
+        let id = ImageId::from(blob);
+        if uploads.0.contains_key(&id) { continue; }
+        // Sin blob o con un formato que el renderer no decodifica queda el marco de sustitución
+        let uploaded = match storage.0.get_blob(blob) {
+            Ok(Some(data)) => renderer.0.upload_image(id, &data).is_ok(),
+            _ => false,
+        };
+        if !uploaded {
+            log!("ECS: image {:?} could not be uploaded", blob);
+        }
+        uploads.0.insert(id, uploaded);
+    }
+}
+
+/// Marco de una imagen sin textura: aún decodificándose, en un formato que el renderer no admite o sin blob
+fn image_placeholder_style() -> Style {
+    Style {
+        fill: Some(Color(0.93, 0.94, 0.96, 1.0).into()),
+        stroke: Some(Color(0.62, 0.65, 0.70, 1.0).into()),
+        stroke_width: 1.0,
+        opacity: 1.0,
+        dash: vec![4.0, 4.0],
+        ..Default::default()
+    }
+}
+
+/// Despacha la forma al método del puerto que la sabe pintar: los trazados van por `draw_path`
+fn draw_entity_shape(
+    renderer: &mut dyn RenderPort,
+    fonts: &FontBook,
+    transform: &Transform,
+    shape: &Shape,
+    style: &Style,
+) -> Result<(), momentum_core::ports::RenderError> {
+    // A mano alzada: trazados generados en el núcleo con la semilla de la entidad
+    if let Some(geometry) = sketch::geometry(shape, style, transform) {
+        return sketch::draw_sketch(renderer, transform, &geometry, style);
+    }
+    match shape {
+        Shape::Path { path } => renderer.draw_path(transform, path, style),
+        // El lápiz se pinta rellenando su contorno con la tinta
+        Shape::Freehand { stroke } => {
+            renderer.draw_path(transform, &stroke.outline(PATH_FLATTEN_TOLERANCE), &FreehandStroke::ink_style(style))
+        }
+        // El texto se maqueta con las fuentes cargadas o, sin ellas, con las medidas del propio renderer
+        Shape::Text { text } => text::draw_text_box(renderer, fonts, transform, text),
+        // Cuerpo y puntas como trazados, iguales en todos los renderers
+        Shape::Arrow { arrow } => arrow::draw_arrow(renderer, transform, arrow, style),
+        Shape::Image { blob, w, h } => {
+            let frame = Shape::Rect { w: *w, h: *h, radii: CornerRadii::default() };
+            let tint = (style.opacity < 1.0).then_some(Color(1.0, 1.0, 1.0, style.opacity));
+            if renderer.draw_image(ImageId::from(*blob), Rect { x: 0.0, y: 0.0, w: *w, h: *h }, transform, tint).is_err() {
+                renderer.draw_shape(transform, &frame, &image_placeholder_style())?;
+            }
+            // La imagen hace de relleno: del estilo solo se pinta el trazo (p.ej. el de selección)
+            match style.stroke {
+                Some(_) => renderer.draw_shape(transform, &frame, &Style { fill: None, ..style.clone() }),
+                None => Ok(()),
+            }
+        }
+        _ => renderer.draw_shape(transform, shape, style),
+    }
+}
+
+/// Rectángulos de selección, cursor y subrayado del IME sobre el texto en edición
+fn draw_text_edit_overlay(
+    renderer: &mut dyn RenderPort,
+    fonts: &FontBook,
+    edit: &TextEditState,
+    target: TextTarget,
+    entity: &Entity,
+    shown: &TextBox,
+    screen_px: f32,
+) {
+    let (layout, origin) = edit_layout(entity, target, shown, &mut fonts.measurer(text::measure_with(renderer)));
+    let transform = entity.transform;
+    let scale = transform.scale_x.abs().max(f32::EPSILON);
+    // Rectángulo local (del bloque) pintado con el transform de la entidad
+    let mut fill_rect = |x: f32, y: f32, w: f32, h: f32, color: Color| {
+        let (wx, wy) = transform.apply(origin.0 + x, origin.1 + y);
+        let style = Style { fill: Some(color.into()), opacity: 1.0, ..Default::default() };
+        let _ = renderer.draw_shape(&Transform { x: wx, y: wy, ..transform }, &Shape::Rect { w, h, radii: CornerRadii::default() }, &style);
+    };
+    let (start, end) = edit.selection();
+    let preedit_len = edit.preedit.chars().count();
+    if preedit_len > 0 {
+        // La composición ocupa el lugar de la selección: se subraya y el cursor va tras ella
+        for rect in layout.selection_rects(start, start + preedit_len) {
+            fill_rect(rect.x, rect.y + rect.h - 2.0 * screen_px / scale, rect.w, screen_px / scale, Color(0.10, 0.12, 0.16, 1.0));
+        }
+    } else {
+        for rect in layout.selection_rects(start, end) {
+            fill_rect(rect.x, rect.y, rect.w, rect.h, Color(0.2, 0.45, 0.85, 0.25));
+        }
+    }
+    let (x, y, h) = layout.caret(if preedit_len > 0 { start + preedit_len } else { edit.caret });
+    fill_rect(x, y, 1.5 * screen_px / scale, h, Color(0.0, 0.4, 0.8, 1.0));
+}
+
 fn render_system_with_selection_and_handles(
     renderer: Option<NonSendMut<RendererBox>>, 
-    size: Res<CanvasSize>,
-    dpr: Res<CanvasDpr>,
+    (size, dpr, camera): (Res<CanvasSize>, Res<CanvasDpr>, Res<Camera>),
     core: Res<CoreDoc>,
     selection: Res<Selection>,
     move_state: Res<MoveState>,
-    _scale_state: Res<ScaleState>,
+    marquee: Res<MarqueeState>,
+    (pen, edit, fonts): (Res<PenState>, Res<TextEditState>, Res<Fonts>),
 ) {
     // Si no hay renderer (por ejemplo, WebGPU no disponible), omitir el render sin hacer panic.
     let Some(mut renderer) = renderer else { return; };
+    // La cámara lleva de mundo a píxeles físicos (incluye DPR); se fija antes de empezar el frame
+    let _ = renderer.0.set_camera(camera.matrix(dpr.0));
     // Comenzar frame con tamaño actual
     let _ = renderer.0.begin_frame(size.w, size.h);
     
-    // Dibujar cada entidad del documento
-    for (id, transform, style, shape) in &core.0.entities {
-        // Escalar coordenadas (CSS px) a píxeles físicos usando DPR
-        let mut t = *transform;
-        t.x *= dpr.0;
-        t.y *= dpr.0;
-        
-        let mut s_shape = shape.clone();
-        match s_shape {
-            Shape::Rect { ref mut w, ref mut h } => {
-                *w *= dpr.0;
-                *h *= dpr.0;
-            }
-            Shape::Ellipse { ref mut rx, ref mut ry } => {
-                *rx *= dpr.0;
-                *ry *= dpr.0;
-            }
-            Shape::Line { ref mut x2, ref mut y2 } => {
-                *x2 *= dpr.0;
-                *y2 *= dpr.0;
-            }
-            Shape::Polygon { ref mut points } => {
-                for (x, y) in points {
-                    *x *= dpr.0;
-                    *y *= dpr.0;
-                }
-            }
-        }
-        
-        let mut s = style.clone();
-        s.stroke_width *= dpr.0;
-        
-        // Escalar patrón de guiones por DPR
-        if !s.dash.is_empty() {
-            for d in &mut s.dash { *d *= dpr.0; }
-            s.dash_offset *= dpr.0;
+    // Grosores de la interfaz constantes en pantalla, independientes del zoom
+    let screen_px = camera.screen_to_world_len(1.0);
+    
+    // La entidad en edición se pinta con la composición del IME insertada en el cursor
+    let edited: Option<Entity> = edit.target.and_then(|target| {
+        let mut entity = core.0.get(target.id())?.clone();
+        let shown = edit.displayed(target_text(&core.0, target)?);
+        match (target, &mut entity.shape) {
+            (TextTarget::Text(_), Shape::Text { text }) => *text = shown,
+            _ => entity.label = Some(shown),
         }
-        
+        Some(entity)
+    });
+    
+    // Dibujar cada entidad del documento en coordenadas de mundo
+    for entity in core.0.iter() {
+        let entity = edited.as_ref().filter(|e| e.id == entity.id).unwrap_or(entity);
         // Modificar estilo si la entidad está seleccionada
-        if selection.is_selected(*id) {
+        if selection.is_selected(entity.id) {
             // Hacer el stroke más grueso y cambiar el color para indicar selección
-            s.stroke_width = (s.stroke_width).max(3.0 * dpr.0);
-            s.stroke = Some(Color(0.0, 0.4, 0.8, 1.0)); // Azul para selección
+            let mut s = entity.style.clone();
+            // Las puntas de flecha crecen con el grosor: una flecha seleccionada solo cambia de color
+            if !matches!(entity.shape, Shape::Arrow { .. }) {
+                s.stroke_width = s.stroke_width.max(3.0 * screen_px);
+            }
+            s.stroke = Some(Color(0.0, 0.4, 0.8, 1.0).into()); // Azul para selección
+            let _ = draw_entity_shape(renderer.0.as_mut(), &fonts.0, &entity.transform, &entity.shape, &s);
+        } else {
+            let _ = draw_entity_shape(renderer.0.as_mut(), &fonts.0, &entity.transform, &entity.shape, &entity.style);
+        }
+        if let Some(label) = &entity.label {
+            let _ = text::draw_label(renderer.0.as_mut(), &fonts.0, &entity.transform, &entity.shape, label);
         }
-        
-        let _ = renderer.0.draw_shape(&t, &s_shape, &s);
+    }
+    
+    // Selección, cursor y subrayado de la composición del texto en edición
+    if let (Some(target), Some(entity)) = (edit.target, &edited) {
+        if let Some(shown) = target_text(&core.0, target).map(|t| edit.displayed(t)) {
+            draw_text_edit_overlay(renderer.0.as_mut(), &fonts.0, &edit, target, entity, &shown, screen_px);
+        }
+    }
+    
+    // Trazo de lápiz en curso, ajustado igual que quedará al soltar
+    if pen.is_drawing {
+        if let Some((transform, stroke)) = pen_stroke(&pen.samples, &camera) {
+            let _ = draw_entity_shape(renderer.0.as_mut(), &fonts.0, &transform, &Shape::Freehand { stroke }, &pen_style());
+        }
+    }
+    
+    // Dibujar rectángulo de selección (marquee) translúcido
+    if marquee.is_active {
+        let t = Transform { x: marquee.rect.x, y: marquee.rect.y, ..Default::default() };
+        let shape = Shape::Rect { w: marquee.rect.width, h: marquee.rect.height, radii: CornerRadii::default() };
+        let style = Style {
+            fill: Some(Color(0.2, 0.45, 0.85, 0.08).into()),
+            stroke: Some(Color(0.2, 0.45, 0.85, 0.8).into()),
+            stroke_width: screen_px,
+            opacity: 1.0,
+            ..Default::default()
+        };
+        let _ = renderer.0.draw_shape(&t, &shape, &style);
     }
     
     // Dibujar scale handles para entidades seleccionadas (solo si no estamos en modo movimiento)
@@ -798,40 +2242,14 @@ fn render_system_with_selection_and_handles(
         // DEBUG: Log selection state
         log!("Selected entities: {:?}", selection.selected);
         
-        // Calcular bounding box combinado de todas las entidades seleccionadas
-        let mut min_x = f32::INFINITY;
-        let mut max_x = f32::NEG_INFINITY;
-        let mut min_y = f32::INFINITY;
-        let mut max_y = f32::NEG_INFINITY;
-        
-        for selected_id in &selection.selected {
-            for (id, transform, _style, shape) in &core.0.entities {
-                if id == selected_id {
-                    let bbox = momentum_core::model::BoundingBox::from_shape(transform, shape);
-                    log!("Entity {} bbox: {:?}", id.0, bbox);
-                    min_x = min_x.min(bbox.x);
-                    max_x = max_x.max(bbox.x + bbox.width);
-                    min_y = min_y.min(bbox.y);
-                    max_y = max_y.max(bbox.y + bbox.height);
-                    break;
-                }
-            }
-        }
-        
-        if min_x < f32::INFINITY {
-            // Crear bounding box combinado y convertir a píxeles físicos
-            let combined_bbox = momentum_core::model::BoundingBox {
-                x: min_x * dpr.0,
-                y: min_y * dpr.0,
-                width: (max_x - min_x) * dpr.0,
-                height: (max_y - min_y) * dpr.0,
-            };
-            
-            log!("Combined bbox (physical): {:?}", combined_bbox);
+        if let Some(bounds) = selection_bounds(&selection, &core.0) {
+            log!("Selection bounds: {:?}", bounds);
             
-            // Generar y dibujar handles
-            let handle_size = 10.0 * dpr.0; // 10px escalado por DPR (más grande como Excalidraw)
-            let handles = combined_bbox.generate_handles(handle_size);
+            // Los handles se dibujan en pantalla (píxeles físicos) con tamaño fijo, sea cual sea el zoom;
+            // siguen la rotación si hay una sola entidad
+            let _ = renderer.0.set_camera([1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+            let handle_size = HANDLE_SIZE * dpr.0; // Escalado por DPR (más grande como Excalidraw)
+            let handles = camera.box_to_screen(&bounds).scaled(dpr.0).generate_handles(handle_size);
             
             log!("Drawing {} handles", handles.len());
             
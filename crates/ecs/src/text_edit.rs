@@ -0,0 +1,166 @@
+//! Edición de texto en el lienzo: cursor, selección y composición del IME sobre el `TextBox`
+//! de una entidad de texto o de la etiqueta de una forma. Los cambios se ven en el documento
+//! al momento y se registran en el historial de una sola vez al terminar la edición.
+
+use bevy_ecs::prelude::Resource;
+use momentum_core::model::{EntityId, Shape, TextBox};
+use momentum_core::text::TextLayout;
+use momentum_core::usecases::Document;
+
+use crate::history::EntitySnapshot;
+
+/// Qué se está editando
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextTarget {
+    /// Entidad con `Shape::Text`
+    Text(EntityId),
+    /// Etiqueta de una forma
+    Label(EntityId),
+}
+
+impl TextTarget {
+    pub fn id(self) -> EntityId {
+        match self {
+            TextTarget::Text(id) | TextTarget::Label(id) => id,
+        }
+    }
+}
+
+/// Teclas de edición (las que no producen texto)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextKey { Left, Right, Up, Down, Home, End, Backspace, Delete, SelectAll }
+
+/// Eventos de edición de texto. Van en una sola cola para conservar el orden en que se teclean.
+#[derive(Debug, Clone)]
+pub enum TextEdit {
+    /// Empieza a editar en (x, y) CSS px el texto bajo el punto, o la etiqueta de la forma bajo el punto.
+    /// Con `create` (herramienta de texto) crea un texto nuevo si debajo no hay ninguno.
+    Begin { x: f32, y: f32, create: bool },
+    /// Clic o arrastre dentro del texto en edición: coloca el cursor; con `extend` amplía la selección
+    Pointer { x: f32, y: f32, extend: bool },
+    /// Texto confirmado (tecleado, pegado o fin de composición); sustituye la selección
+    Insert(String),
+    /// Composición del IME en curso: se muestra en el cursor sin formar parte del documento
+    Preedit(String),
+    Key { key: TextKey, shift: bool },
+    /// Termina la edición y la registra en el historial
+    End,
+}
+
+/// Edición en curso. `caret` y `anchor` son offsets en caracteres; la selección va de uno a otro.
+#[derive(Resource, Debug, Default)]
+pub struct TextEditState {
+    pub target: Option<TextTarget>,
+    pub caret: usize,
+    pub anchor: usize,
+    pub preedit: String,
+    /// Entidad al empezar; None si la creó esta edición
+    pub before: Option<EntitySnapshot>,
+    pub changed: bool,
+}
+
+impl TextEditState {
+    pub fn is_editing(&self) -> bool {
+        self.target.is_some()
+    }
+
+    /// Selección ordenada (inicio, fin)
+    pub fn selection(&self) -> (usize, usize) {
+        (self.caret.min(self.anchor), self.caret.max(self.anchor))
+    }
+
+    /// Cursor en `offset`; con `extend` se conserva el ancla y queda seleccionado lo recorrido
+    pub fn move_to(&mut self, offset: usize, extend: bool) {
+        self.caret = offset;
+        if !extend {
+            self.anchor = offset;
+        }
+    }
+
+    /// Sustituye la selección por `inserted`
+    pub fn insert(&mut self, text: &mut TextBox, inserted: &str) {
+        let inserted = inserted.replace("\r\n", "\n").replace('\r', "\n");
+        let (start, end) = self.selection();
+        text.delete(start, end);
+        text.insert(start, &inserted);
+        self.move_to(start + inserted.chars().count(), false);
+        self.preedit.clear();
+        self.changed = true;
+    }
+
+    /// Aplica una tecla de edición. `layout` es la maquetación actual, para subir y bajar de línea.
+    pub fn apply_key(&mut self, text: &mut TextBox, layout: &TextLayout, key: TextKey, shift: bool) {
+        let len = text.len();
+        let (start, end) = self.selection();
+        match key {
+            // Sin mayúsculas, una flecha con selección la colapsa hacia su lado
+            TextKey::Left if start != end && !shift => self.move_to(start, false),
+            TextKey::Right if start != end && !shift => self.move_to(end, false),
+            TextKey::Left => self.move_to(self.caret.saturating_sub(1), shift),
+            TextKey::Right => self.move_to((self.caret + 1).min(len), shift),
+            TextKey::Up | TextKey::Down => {
+                let line = layout.line_at(self.caret);
+                let (x, y, h) = layout.caret(self.caret);
+                let offset = match key {
+                    TextKey::Up if line == 0 => 0,
+                    TextKey::Down if line + 1 >= layout.lines.len() => len,
+                    TextKey::Up => layout.offset_at(x, y - h / 2.0),
+                    _ => layout.offset_at(x, y + h * 1.5),
+                };
+                self.move_to(offset, shift);
+            }
+            TextKey::Home | TextKey::End => {
+                let offset = layout.lines.get(layout.line_at(self.caret)).map_or(self.caret, |line| {
+                    if key == TextKey::Home { line.start } else { line.end }
+                });
+                self.move_to(offset, shift);
+            }
+            TextKey::Backspace | TextKey::Delete => {
+                let (from, to) = match key {
+                    _ if start != end => (start, end),
+                    TextKey::Backspace => (start.saturating_sub(1), start),
+                    _ => (start, (start + 1).min(len)),
+                };
+                if from < to {
+                    text.delete(from, to);
+                    self.changed = true;
+                }
+                self.move_to(from, false);
+            }
+            TextKey::SelectAll => {
+                self.anchor = 0;
+                self.caret = len;
+            }
+        }
+    }
+
+    /// Bloque tal como se ve: con la composición del IME en el lugar de la selección
+    pub fn displayed(&self, text: &TextBox) -> TextBox {
+        let mut shown = text.clone();
+        if !self.preedit.is_empty() {
+            let (start, end) = self.selection();
+            shown.delete(start, end);
+            shown.insert(start, &self.preedit);
+        }
+        shown
+    }
+}
+
+/// Bloque de texto del objetivo en el documento
+pub fn target_text(doc: &Document, target: TextTarget) -> Option<&TextBox> {
+    let entity = doc.get(target.id())?;
+    match (target, &entity.shape) {
+        (TextTarget::Text(_), Shape::Text { text }) => Some(text),
+        (TextTarget::Label(_), _) => entity.label.as_ref(),
+        _ => None,
+    }
+}
+
+pub fn target_text_mut(doc: &mut Document, target: TextTarget) -> Option<&mut TextBox> {
+    let entity = doc.get_mut(target.id())?;
+    match (target, &mut entity.shape) {
+        (TextTarget::Text(_), Shape::Text { text }) => Some(text),
+        (TextTarget::Label(_), _) => entity.label.as_mut(),
+        _ => None,
+    }
+}
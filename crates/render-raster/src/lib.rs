@@ -0,0 +1,443 @@
+//! Adaptador `RenderPort` por software (CPU) basado en tiny-skia.
+//!
+//! Rasteriza en un buffer RGBA en memoria, sin navegador ni GPU: sirve para exportar a PNG,
+//! generar miniaturas y comparar imágenes de referencia en CI. Sigue la semántica de
+//! `Canvas2DRenderer`: fondo blanco al empezar frame, cámara afín de mundo a píxeles físicos
+//! y colores con `alpha * style.opacity`.
+
+use std::collections::HashMap;
+
+use momentum_core::image::{self, ImageFormat};
+use momentum_core::{arrow, paint, sketch};
+use momentum_core::model::{
+    Color, FreehandStroke, GradientStop, ImageId, Paint, Path, PathCommand, Rect, ScaleHandle, Shape, StrokeCap, StrokeJoin, Style,
+    TextMetrics, TextSpan, Transform, PATH_FLATTEN_TOLERANCE,
+};
+use momentum_core::ports::{RenderError, RenderPort};
+use momentum_core::text::{self, FontBook};
+use momentum_core::usecases::Document;
+use tiny_skia::{
+    FillRule, FilterQuality, LineCap, LineJoin, LinearGradient, PathBuilder, Pattern, Pixmap, PixmapPaint, Point,
+    PremultipliedColorU8, RadialGradient, SpreadMode, Stroke, StrokeDash,
+};
+
+/// Color de los handles de escala (mismo azul que el renderer Canvas2D)
+const HANDLE_STROKE: Color = Color(0.098, 0.443, 0.761, 1.0);
+
+pub struct RasterRenderer {
+    pixmap: Pixmap,
+    /// Afín de mundo a píxeles; se aplica a todos los dibujos siguientes
+    camera: tiny_skia::Transform,
+    /// Color con el que `begin_frame` limpia el buffer (`None` = transparente)
+    background: Option<Color>,
+    images: HashMap<ImageId, Pixmap>,
+    fonts: FontBook,
+}
+
+impl RasterRenderer {
+    pub fn new(width: u32, height: u32) -> Result<Self, RenderError> {
+        let pixmap = Pixmap::new(width.max(1), height.max(1)).ok_or(RenderError::OutOfMemory)?;
+        Ok(Self {
+            pixmap,
+            camera: tiny_skia::Transform::identity(),
+            background: Some(Color(1.0, 1.0, 1.0, 1.0)),
+            images: HashMap::new(),
+            fonts: FontBook::new(),
+        })
+    }
+
+    /// Fondo de cada frame; `None` deja el buffer transparente (útil para exportar con alfa)
+    pub fn with_background(mut self, background: Option<Color>) -> Self {
+        self.background = background;
+        self
+    }
+
+    /// Añade una fuente TrueType/OpenType para `draw_text` y `measure_text`; cada tramo usa la de su
+    /// familia y peso. No hay fuentes del sistema: sin fuentes el texto devuelve `RenderError::TextShaping`.
+    pub fn load_font(&mut self, data: Vec<u8>) -> Result<(), RenderError> {
+        self.fonts.load(data).map_err(|_| RenderError::InvalidInput)?;
+        Ok(())
+    }
+
+    /// Usa las fuentes del lienzo, para pintar el texto con la misma maquetación
+    pub fn set_fonts(&mut self, fonts: FontBook) {
+        self.fonts = fonts;
+    }
+
+    pub fn fonts(&self) -> &FontBook {
+        &self.fonts
+    }
+
+    pub fn width(&self) -> u32 {
+        self.pixmap.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.pixmap.height()
+    }
+
+    /// Buffer interno (RGBA premultiplicado)
+    pub fn pixmap(&self) -> &Pixmap {
+        &self.pixmap
+    }
+
+    /// Píxel (x, y) en RGBA sin premultiplicar
+    pub fn pixel(&self, x: u32, y: u32) -> Option<[u8; 4]> {
+        let c = self.pixmap.pixel(x, y)?.demultiply();
+        Some([c.red(), c.green(), c.blue(), c.alpha()])
+    }
+
+    /// Copia del frame en RGBA sin premultiplicar, fila a fila
+    pub fn to_rgba(&self) -> Vec<u8> {
+        self.pixmap
+            .pixels()
+            .iter()
+            .flat_map(|p| {
+                let c = p.demultiply();
+                [c.red(), c.green(), c.blue(), c.alpha()]
+            })
+            .collect()
+    }
+
+    /// Codifica el frame actual como PNG
+    pub fn encode_png(&self) -> Result<Vec<u8>, RenderError> {
+        self.pixmap.encode_png().map_err(|e| RenderError::Other(e.to_string()))
+    }
+
+    /// Dibuja todas las entidades del documento en orden de pintado, con sus etiquetas (sin selección ni handles)
+    /// Las imágenes necesitan su textura subida antes, con `upload_image` bajo `ImageId::from(blob)`.
+    pub fn draw_document(&mut self, doc: &Document) -> Result<(), RenderError> {
+        let fonts = self.fonts.clone();
+        for entity in doc.iter() {
+            self.draw_shape(&entity.transform, &entity.shape, &entity.style)?;
+            if let Some(label) = &entity.label {
+                text::draw_label(self, &fonts, &entity.transform, &entity.shape, label)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Cámara * traslación * rotación * escala de la entidad (mismo orden que Canvas2D)
+    fn full_transform(&self, transform: &Transform) -> tiny_skia::Transform {
+        self.camera
+            .pre_translate(transform.x, transform.y)
+            .pre_rotate(transform.rotation.to_degrees())
+            .pre_scale(transform.scale_x, transform.scale_y)
+    }
+
+    fn fill_and_stroke(&mut self, path: &tiny_skia::Path, style: &Style, ts: tiny_skia::Transform, fill: bool) {
+        if fill {
+            if let Some(fill) = &style.fill {
+                let paint = skia_paint(fill, style.opacity, &self.images);
+                self.pixmap.fill_path(path, &paint, FillRule::Winding, ts, None);
+            }
+        }
+        if let Some(stroke) = &style.stroke {
+            let paint = skia_paint(stroke, style.opacity, &self.images);
+            self.pixmap.stroke_path(path, &paint, &to_stroke(style), ts, None);
+        }
+    }
+}
+
+impl RenderPort for RasterRenderer {
+    fn begin_frame(&mut self, width: u32, height: u32) -> Result<(), RenderError> {
+        let (width, height) = (width.max(1), height.max(1));
+        if self.pixmap.width() != width || self.pixmap.height() != height {
+            self.pixmap = Pixmap::new(width, height).ok_or(RenderError::OutOfMemory)?;
+        }
+        match self.background {
+            Some(color) => self.pixmap.fill(to_color(color, 1.0)),
+            None => self.pixmap.fill(tiny_skia::Color::TRANSPARENT),
+        }
+        Ok(())
+    }
+
+    fn end_frame(&mut self) -> Result<(), RenderError> {
+        Ok(())
+    }
+
+    fn set_camera(&mut self, transform_2d: [f32; 6]) -> Result<(), RenderError> {
+        let [a, b, c, d, e, f] = transform_2d;
+        self.camera = tiny_skia::Transform::from_row(a, b, c, d, e, f);
+        Ok(())
+    }
+
+    fn draw_shape(&mut self, transform: &Transform, shape: &Shape, style: &Style) -> Result<(), RenderError> {
+        if let Some(geometry) = sketch::geometry(shape, style, transform) {
+            return sketch::draw_sketch(self, transform, &geometry, style);
+        }
+        // Los rellenos sombreados se pintan como líneas sobre el contorno exacto
+        if let Some(outline) = paint::hatched_outline(shape, style, transform) {
+            return self.draw_path(transform, &outline, style);
+        }
+        // Las esquinas redondeadas, como trazado
+        if let Some(outline) = paint::rounded_outline(shape, transform) {
+            return self.draw_path(transform, &outline, style);
+        }
+        let ts = self.full_transform(transform);
+        let (path, fill) = match shape {
+            Shape::Path { path } => return self.draw_path(transform, path, style),
+            Shape::Freehand { stroke } => {
+                return self.draw_path(transform, &stroke.outline(PATH_FLATTEN_TOLERANCE), &FreehandStroke::ink_style(style))
+            }
+            Shape::Arrow { arrow } => return arrow::draw_arrow(self, transform, arrow, style),
+            Shape::Text { text } => {
+                let fonts = self.fonts.clone();
+                return text::draw_text_box(self, &fonts, transform, text);
+            }
+            // La textura se sube antes con `upload_image` bajo el id de su blob
+            Shape::Image { blob, w, h } => {
+                let tint = (style.opacity < 1.0).then_some(Color(1.0, 1.0, 1.0, style.opacity));
+                return self.draw_image(ImageId::from(*blob), Rect { x: 0.0, y: 0.0, w: *w, h: *h }, transform, tint);
+            }
+            Shape::Rect { w, h, .. } => (
+                tiny_skia::Rect::from_ltrb(w.min(0.0), h.min(0.0), w.max(0.0), h.max(0.0))
+                    .map(PathBuilder::from_rect),
+                true,
+            ),
+            Shape::Ellipse { rx, ry } => (
+                tiny_skia::Rect::from_ltrb(-rx.abs(), -ry.abs(), rx.abs(), ry.abs())
+                    .and_then(PathBuilder::from_oval),
+                true,
+            ),
+            Shape::Line { x2, y2 } => {
+                let mut pb = PathBuilder::new();
+                pb.move_to(0.0, 0.0);
+                pb.line_to(*x2, *y2);
+                // Las líneas solo admiten trazo
+                (pb.finish(), false)
+            }
+            Shape::Polygon { points } => {
+                let mut pb = PathBuilder::new();
+                if let Some((first, rest)) = points.split_first() {
+                    pb.move_to(first.0, first.1);
+                    for p in rest {
+                        pb.line_to(p.0, p.1);
+                    }
+                    pb.close();
+                }
+                (pb.finish(), true)
+            }
+        };
+        // Formas degeneradas (tamaño cero, polígono vacío): nada que dibujar
+        if let Some(path) = path {
+            self.fill_and_stroke(&path, style, ts, fill);
+        }
+        Ok(())
+    }
+
+    fn draw_path(&mut self, transform: &Transform, path: &Path, style: &Style) -> Result<(), RenderError> {
+        if let Some(result) = paint::draw_hatched(self, transform, path, style) {
+            return result;
+        }
+        if let Some(sk_path) = skia_path(path) {
+            let ts = self.full_transform(transform);
+            self.fill_and_stroke(&sk_path, style, ts, true);
+        }
+        Ok(())
+    }
+
+    fn draw_text(&mut self, transform: &Transform, span: &TextSpan) -> Result<(), RenderError> {
+        if span.text.is_empty() {
+            return Ok(());
+        }
+        let shaped = self.fonts.shape(span).ok_or(RenderError::TextShaping)?;
+        // Línea base "top" como Canvas2D: y = 0 es el borde superior del texto
+        let glyphs: Vec<_> = shaped.glyphs.iter().map(|g| text::Glyph { y: g.y + shaped.metrics.ascent, ..*g }).collect();
+        if let Some(path) = skia_path(&self.fonts.glyph_path(&glyphs, span.size)) {
+            let ts = self.full_transform(transform);
+            let paint = solid_paint(span.color, 1.0);
+            self.pixmap.fill_path(&path, &paint, FillRule::Winding, ts, None);
+        }
+        Ok(())
+    }
+
+    fn measure_text(&mut self, span: &TextSpan) -> Result<TextMetrics, RenderError> {
+        self.fonts.shape(span).map(|shaped| shaped.metrics).ok_or(RenderError::TextShaping)
+    }
+
+    /// `data` es una imagen PNG, JPEG o WebP codificada (los formatos que admite `image::probe`)
+    fn upload_image(&mut self, id: ImageId, data: &[u8]) -> Result<(), RenderError> {
+        let pixmap = decode_image(data).ok_or(RenderError::InvalidInput)?;
+        self.images.insert(id, pixmap);
+        Ok(())
+    }
+
+    fn draw_image(&mut self, id: ImageId, dest: Rect, transform: &Transform, tint: Option<Color>) -> Result<(), RenderError> {
+        let image = self.images.get(&id).ok_or(RenderError::InvalidInput)?;
+        if dest.w == 0.0 || dest.h == 0.0 {
+            return Ok(());
+        }
+        let tinted;
+        let source = match tint {
+            Some(tint) => {
+                tinted = tint_pixmap(image, tint);
+                &tinted
+            }
+            None => image,
+        };
+        let ts = self
+            .full_transform(transform)
+            .pre_translate(dest.x, dest.y)
+            .pre_scale(dest.w / source.width() as f32, dest.h / source.height() as f32);
+        let paint = PixmapPaint { quality: tiny_skia::FilterQuality::Bilinear, ..PixmapPaint::default() };
+        self.pixmap.draw_pixmap(0, 0, source.as_ref(), &paint, ts, None);
+        Ok(())
+    }
+
+    fn draw_scale_handle(&mut self, handle: &ScaleHandle) -> Result<(), RenderError> {
+        // Círculo blanco con borde azul, como en Canvas2D
+        let radius = handle.size / 2.0;
+        if let Some(circle) = PathBuilder::from_circle(handle.x + radius, handle.y + radius, radius) {
+            let style = Style {
+                fill: Some(Color(1.0, 1.0, 1.0, 1.0).into()),
+                stroke: Some(HANDLE_STROKE.into()),
+                stroke_width: 1.5,
+                opacity: 1.0,
+                ..Style::default()
+            };
+            let ts = self.camera;
+            self.fill_and_stroke(&circle, &style, ts, true);
+        }
+        Ok(())
+    }
+}
+
+/// Trazado de tiny-skia equivalente; None si no tiene segmentos
+fn skia_path(path: &Path) -> Option<tiny_skia::Path> {
+    let mut pb = PathBuilder::new();
+    for command in &path.commands {
+        match *command {
+            PathCommand::MoveTo(x, y) => pb.move_to(x, y),
+            PathCommand::LineTo(x, y) => pb.line_to(x, y),
+            PathCommand::QuadTo { cx, cy, x, y } => pb.quad_to(cx, cy, x, y),
+            PathCommand::CubicTo { c1x, c1y, c2x, c2y, x, y } => pb.cubic_to(c1x, c1y, c2x, c2y, x, y),
+            PathCommand::Close => pb.close(),
+        }
+    }
+    pb.finish()
+}
+
+fn to_color(Color(r, g, b, a): Color, opacity: f32) -> tiny_skia::Color {
+    tiny_skia::Color::from_rgba(r.clamp(0.0, 1.0), g.clamp(0.0, 1.0), b.clamp(0.0, 1.0), (a * opacity).clamp(0.0, 1.0))
+        .unwrap_or(tiny_skia::Color::TRANSPARENT)
+}
+
+fn solid_paint(color: Color, opacity: f32) -> tiny_skia::Paint<'static> {
+    let mut paint = tiny_skia::Paint::default();
+    paint.set_color(to_color(color, opacity));
+    paint.anti_alias = true;
+    paint
+}
+
+/// Pintura de tiny-skia en las coordenadas locales de la forma (`fill_path` y `stroke_path` le
+/// aplican la misma transform que al trazado). Los sombreados llegan ya como líneas, así que aquí
+/// son su color; un degradado sin paradas o sin radio y una imagen sin textura se pintan con
+/// `paint::color_at`, igual que en WebGPU.
+fn skia_paint<'a>(paint: &Paint, opacity: f32, images: &'a HashMap<ImageId, Pixmap>) -> tiny_skia::Paint<'a> {
+    let identity = tiny_skia::Transform::identity();
+    let shader = match paint {
+        Paint::LinearGradient { start, end, stops } => LinearGradient::new(
+            Point::from_xy(start.0, start.1),
+            Point::from_xy(end.0, end.1),
+            skia_stops(stops, opacity),
+            SpreadMode::Pad,
+            identity,
+        ),
+        Paint::RadialGradient { center, radius, stops } => {
+            let center = Point::from_xy(center.0, center.1);
+            RadialGradient::new(center, center, *radius, skia_stops(stops, opacity), SpreadMode::Pad, identity)
+        }
+        // Mosaicos de `w` x `h` desde el origen local
+        Paint::Image { blob, w, h } => images.get(&ImageId::from(*blob)).filter(|_| *w != 0.0 && *h != 0.0).map(|image| {
+            let scale = tiny_skia::Transform::from_scale(w / image.width() as f32, h / image.height() as f32);
+            Pattern::new(image.as_ref(), SpreadMode::Repeat, FilterQuality::Bilinear, opacity, scale)
+        }),
+        Paint::Solid(_) | Paint::Hatch { .. } => None,
+    };
+    match shader {
+        Some(shader) => tiny_skia::Paint { shader, anti_alias: true, ..tiny_skia::Paint::default() },
+        None => solid_paint(paint::color_at(paint, (0.0, 0.0)), opacity),
+    }
+}
+
+fn skia_stops(stops: &[GradientStop], opacity: f32) -> Vec<tiny_skia::GradientStop> {
+    stops.iter().map(|stop| tiny_skia::GradientStop::new(stop.offset, to_color(stop.color, opacity))).collect()
+}
+
+fn to_stroke(style: &Style) -> Stroke {
+    // Canvas2D repite las listas de guiones impares; tiny-skia exige un número par
+    let mut dash = style.dash.clone();
+    if dash.len() % 2 == 1 {
+        dash.extend_from_within(..);
+    }
+    Stroke {
+        width: style.stroke_width,
+        line_cap: match style.stroke_cap {
+            StrokeCap::Butt => LineCap::Butt,
+            StrokeCap::Square => LineCap::Square,
+            StrokeCap::Round => LineCap::Round,
+        },
+        line_join: match style.stroke_join {
+            StrokeJoin::Miter => LineJoin::Miter,
+            StrokeJoin::Bevel => LineJoin::Bevel,
+            StrokeJoin::Round => LineJoin::Round,
+        },
+        dash: StrokeDash::new(dash, style.dash_offset),
+        ..Stroke::default()
+    }
+}
+
+/// Multiplica cada píxel (premultiplicado) por el tinte
+/// Decodifica una imagen importada a un pixmap; None si está dañada o el formato no se admite
+fn decode_image(data: &[u8]) -> Option<Pixmap> {
+    match image::probe(data).ok()?.format {
+        ImageFormat::Png => Pixmap::decode_png(data).ok(),
+        ImageFormat::Jpeg => {
+            use zune_jpeg::zune_core::{colorspace::ColorSpace, options::DecoderOptions};
+            let options = DecoderOptions::default().jpeg_set_out_colorspace(ColorSpace::RGBA);
+            let mut decoder = zune_jpeg::JpegDecoder::new_with_options(data, options);
+            let rgba = decoder.decode().ok()?;
+            let (width, height) = decoder.dimensions()?;
+            pixmap_from_rgba(u32::try_from(width).ok()?, u32::try_from(height).ok()?, &rgba)
+        }
+        ImageFormat::Webp => {
+            let mut decoder = image_webp::WebPDecoder::new(std::io::Cursor::new(data)).ok()?;
+            let (width, height) = decoder.dimensions();
+            let mut pixels = vec![0; decoder.output_buffer_size()?];
+            decoder.read_image(&mut pixels).ok()?;
+            // Sin canal alfa, los píxeles vienen en RGB
+            let rgba = if decoder.has_alpha() {
+                pixels
+            } else {
+                pixels.chunks_exact(3).flat_map(|c| [c[0], c[1], c[2], 255]).collect()
+            };
+            pixmap_from_rgba(width, height, &rgba)
+        }
+    }
+}
+
+/// Pixmap (premultiplicado) a partir de píxeles RGBA sin premultiplicar
+fn pixmap_from_rgba(width: u32, height: u32, rgba: &[u8]) -> Option<Pixmap> {
+    let mut pixmap = Pixmap::new(width, height)?;
+    if rgba.len() != pixmap.pixels().len() * 4 {
+        return None;
+    }
+    for (p, c) in pixmap.pixels_mut().iter_mut().zip(rgba.chunks_exact(4)) {
+        *p = tiny_skia::ColorU8::from_rgba(c[0], c[1], c[2], c[3]).premultiply();
+    }
+    Some(pixmap)
+}
+
+fn tint_pixmap(image: &Pixmap, Color(r, g, b, a): Color) -> Pixmap {
+    let mut out = image.clone();
+    let scale = |c: u8, f: f32| (c as f32 * f.clamp(0.0, 1.0)).round() as u8;
+    for p in out.pixels_mut() {
+        let alpha = scale(p.alpha(), a);
+        let channels = (scale(p.red(), r * a), scale(p.green(), g * a), scale(p.blue(), b * a));
+        if let Some(tinted) = PremultipliedColorU8::from_rgba(channels.0.min(alpha), channels.1.min(alpha), channels.2.min(alpha), alpha) {
+            *p = tinted;
+        }
+    }
+    out
+}
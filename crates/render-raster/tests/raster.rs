@@ -0,0 +1,478 @@
+use momentum_core::arrow;
+use momentum_core::freehand::{fit_stroke, StrokeSample};
+use momentum_core::image::{self, ImageFormat};
+use momentum_core::model::{Arrow, BlobId, Color, CornerRadii, EntityId, GradientStop, Hitbox, ImageId, Paint, Path, PathCommand, Rect, Shape, Sketch, SketchFill, Style, TextBox, TextSpan, Transform};
+use momentum_core::sketch;
+use momentum_core::ports::{RenderError, RenderPort};
+use momentum_core::usecases::{Document, DocumentFormatError, Entity, DOCUMENT_SCHEMA_VERSION};
+use momentum_render_raster::RasterRenderer;
+use std::collections::HashMap;
+
+const RED: Color = Color(1.0, 0.0, 0.0, 1.0);
+const WHITE: [u8; 4] = [255, 255, 255, 255];
+
+fn filled(color: Color) -> Style {
+    Style { fill: Some(color.into()), opacity: 1.0, ..Style::default() }
+}
+
+fn at(x: f32, y: f32) -> Transform {
+    Transform { x, y, ..Transform::default() }
+}
+
+fn rect(w: f32, h: f32) -> Shape {
+    Shape::Rect { w, h, radii: CornerRadii::default() }
+}
+
+#[test]
+fn fills_rect_over_white_background() {
+    let mut r = RasterRenderer::new(64, 64).unwrap();
+    r.begin_frame(64, 64).unwrap();
+    r.draw_shape(&at(10.0, 10.0), &rect(20.0, 20.0), &filled(RED)).unwrap();
+    r.end_frame().unwrap();
+
+    assert_eq!(r.pixel(20, 20), Some([255, 0, 0, 255]));
+    assert_eq!(r.pixel(5, 5), Some(WHITE));
+    assert_eq!(r.pixel(40, 40), Some(WHITE));
+}
+
+#[test]
+fn camera_maps_world_to_pixels() {
+    let mut r = RasterRenderer::new(100, 100).unwrap();
+    // Zoom 2 con el origen de mundo desplazado a (5, 5)
+    r.set_camera([2.0, 0.0, 0.0, 2.0, -10.0, -10.0]).unwrap();
+    r.begin_frame(100, 100).unwrap();
+    r.draw_shape(&at(10.0, 10.0), &rect(10.0, 10.0), &filled(RED)).unwrap();
+
+    // El rectángulo de mundo (10..20) cae en píxeles 10..30
+    assert_eq!(r.pixel(12, 12), Some([255, 0, 0, 255]));
+    assert_eq!(r.pixel(28, 28), Some([255, 0, 0, 255]));
+    assert_eq!(r.pixel(32, 32), Some(WHITE));
+}
+
+#[test]
+fn dashed_stroke_leaves_gaps() {
+    let mut r = RasterRenderer::new(100, 20).unwrap();
+    r.begin_frame(100, 20).unwrap();
+    let style = Style { stroke: Some(RED.into()), stroke_width: 4.0, opacity: 1.0, dash: vec![10.0], ..Style::default() };
+    r.draw_shape(&at(0.0, 10.0), &Shape::Line { x2: 100.0, y2: 0.0 }, &style).unwrap();
+
+    assert_eq!(r.pixel(5, 10), Some([255, 0, 0, 255]));
+    assert_eq!(r.pixel(15, 10), Some(WHITE));
+    assert_eq!(r.pixel(25, 10), Some([255, 0, 0, 255]));
+}
+
+#[test]
+fn path_shape_fills_curved_outline() {
+    let mut r = RasterRenderer::new(64, 64).unwrap();
+    r.begin_frame(64, 64).unwrap();
+    // Semicírculo aproximado: base recta en y = 0 y arco hacia arriba hasta y ≈ -22
+    let path = Path {
+        commands: vec![
+            PathCommand::MoveTo(0.0, 0.0),
+            PathCommand::CubicTo { c1x: 0.0, c1y: -30.0, c2x: 40.0, c2y: -30.0, x: 40.0, y: 0.0 },
+            PathCommand::Close,
+        ],
+    };
+    r.draw_shape(&at(10.0, 40.0), &Shape::Path { path }, &filled(RED)).unwrap();
+
+    assert_eq!(r.pixel(30, 25), Some([255, 0, 0, 255]));
+    assert_eq!(r.pixel(12, 20), Some(WHITE));
+    assert_eq!(r.pixel(30, 45), Some(WHITE));
+}
+
+#[test]
+fn freehand_stroke_is_painted_with_stroke_color() {
+    let mut r = RasterRenderer::new(64, 32).unwrap();
+    r.begin_frame(64, 32).unwrap();
+    // Presión máxima: grosor 6 * 1.5 = 9 alrededor de y = 16
+    let samples: Vec<StrokeSample> = (0..=10).map(|i| StrokeSample { x: i as f32 * 5.0, y: 0.0, pressure: 1.0 }).collect();
+    let stroke = fit_stroke(&samples, 6.0, 0.5).unwrap();
+    let style = Style { stroke: Some(RED.into()), fill: None, stroke_width: 6.0, opacity: 1.0, ..Style::default() };
+    r.draw_shape(&at(5.0, 16.0), &Shape::Freehand { stroke }, &style).unwrap();
+
+    assert_eq!(r.pixel(30, 16), Some([255, 0, 0, 255]));
+    assert_eq!(r.pixel(30, 19), Some([255, 0, 0, 255]));
+    assert_eq!(r.pixel(30, 24), Some(WHITE));
+}
+
+#[test]
+fn arrow_draws_shaft_and_filled_head() {
+    let mut r = RasterRenderer::new(100, 40).unwrap();
+    r.begin_frame(100, 40).unwrap();
+    // Trazo 2: punta triangular de 12 de largo y 12 de ancho que termina en x = 90
+    let style = Style { stroke: Some(RED.into()), stroke_width: 2.0, opacity: 1.0, ..Style::default() };
+    r.draw_shape(&at(10.0, 20.0), &Shape::Arrow { arrow: Arrow::new((0.0, 0.0), (80.0, 0.0)) }, &style).unwrap();
+
+    assert_eq!(r.pixel(40, 20), Some([255, 0, 0, 255]));
+    assert_eq!(r.pixel(40, 22), Some(WHITE));
+    // La punta es más ancha que el cuerpo
+    assert_eq!(r.pixel(80, 22), Some([255, 0, 0, 255]));
+    assert_eq!(r.pixel(95, 20), Some(WHITE));
+}
+
+#[test]
+fn elbow_connector_routes_around_obstacles() {
+    let mut doc = Document::new();
+    // Obstáculo centrado por encima de la recta: el conector lo rodea por debajo, que es más corto
+    doc.create_shape(at(80.0, 10.0), filled(RED), rect(40.0, 60.0));
+    let id = doc.create_shape(at(10.0, 50.0), Style::default(), Shape::Arrow { arrow: Arrow::elbow((0.0, 0.0), (180.0, 0.0)) });
+    assert_eq!(arrow::update_bindings(&mut doc), vec![id]);
+
+    let mut r = RasterRenderer::new(200, 120).unwrap();
+    r.begin_frame(200, 120).unwrap();
+    let style = Style { stroke: Some(RED.into()), stroke_width: 2.0, opacity: 1.0, ..Style::default() };
+    let entity = doc.get(id).unwrap();
+    r.draw_shape(&entity.transform, &entity.shape, &style).unwrap();
+
+    // Baja desde el inicio, pasa a `ROUTE_MARGIN` del borde inferior del obstáculo y sube al final
+    assert_eq!(r.pixel(10, 70), Some([255, 0, 0, 255]));
+    assert_eq!(r.pixel(100, 90), Some([255, 0, 0, 255]));
+    assert_eq!(r.pixel(100, 50), Some(WHITE));
+    // Codo redondeado: la esquina exacta queda fuera del trazo
+    assert_eq!(r.pixel(9, 91), Some(WHITE));
+}
+
+#[test]
+fn png_round_trips_through_upload_image() {
+    let mut source = RasterRenderer::new(8, 8).unwrap().with_background(Some(RED));
+    source.begin_frame(8, 8).unwrap();
+    let png = source.encode_png().unwrap();
+
+    let mut r = RasterRenderer::new(32, 32).unwrap().with_background(None);
+    r.upload_image(ImageId(1), &png).unwrap();
+    r.begin_frame(32, 32).unwrap();
+    r.draw_image(ImageId(1), Rect { x: 0.0, y: 0.0, w: 16.0, h: 16.0 }, &Transform::default(), None).unwrap();
+
+    assert_eq!(r.pixel(8, 8), Some([255, 0, 0, 255]));
+    assert_eq!(r.pixel(24, 24), Some([0, 0, 0, 0]));
+    assert!(matches!(
+        r.draw_image(ImageId(2), Rect::default(), &Transform::default(), None),
+        Err(RenderError::InvalidInput)
+    ));
+}
+
+#[test]
+fn image_shape_draws_its_blob_texture() {
+    let mut source = RasterRenderer::new(4, 2).unwrap().with_background(Some(RED));
+    source.begin_frame(4, 2).unwrap();
+    let png = source.encode_png().unwrap();
+    let info = image::probe(&png).unwrap();
+    assert_eq!((info.format, info.width, info.height), (ImageFormat::Png, 4, 2));
+
+    let blob = image::blob_id(&png);
+    let mut doc = Document::new();
+    // Escalada al doble: ocupa 16x8 px
+    let transform = Transform { scale_x: 2.0, scale_y: 2.0, ..at(4.0, 4.0) };
+    doc.create_shape(transform, Style { opacity: 1.0, ..Style::default() }, Shape::Image { blob, w: 8.0, h: 4.0 });
+    let mut r = RasterRenderer::new(32, 32).unwrap();
+    r.begin_frame(32, 32).unwrap();
+    assert!(matches!(r.draw_document(&doc), Err(RenderError::InvalidInput)));
+
+    r.upload_image(ImageId::from(blob), &png).unwrap();
+    r.draw_document(&doc).unwrap();
+    assert_eq!(r.pixel(18, 10), Some([255, 0, 0, 255]));
+    assert_eq!(r.pixel(18, 14), Some(WHITE));
+}
+
+#[test]
+fn jpeg_and_webp_images_render_like_png() {
+    // 8x4 px: mitad izquierda roja, mitad derecha azul
+    let rgba: Vec<u8> = (0..32).flat_map(|i| if i % 8 < 4 { [255, 0, 0, 255] } else { [0, 0, 255, 255] }).collect();
+    let rgb: Vec<u8> = rgba.chunks_exact(4).flat_map(|c| [c[0], c[1], c[2]]).collect();
+    let mut jpeg = Vec::new();
+    jpeg_encoder::Encoder::new(&mut jpeg, 100).encode(&rgb, 8, 4, jpeg_encoder::ColorType::Rgb).unwrap();
+    let mut webp = Vec::new();
+    image_webp::WebPEncoder::new(&mut webp).encode(&rgba, 8, 4, image_webp::ColorType::Rgba8).unwrap();
+
+    for (data, format) in [(jpeg, ImageFormat::Jpeg), (webp, ImageFormat::Webp)] {
+        assert_eq!(image::probe(&data).unwrap().format, format);
+        let blob = image::blob_id(&data);
+        let mut doc = Document::new();
+        doc.create_shape(Transform { scale_x: 4.0, scale_y: 4.0, ..at(0.0, 0.0) }, Style { opacity: 1.0, ..Style::default() }, Shape::Image { blob, w: 8.0, h: 4.0 });
+
+        let mut r = RasterRenderer::new(32, 16).unwrap();
+        r.upload_image(ImageId::from(blob), &data).unwrap();
+        r.begin_frame(32, 16).unwrap();
+        r.draw_document(&doc).unwrap();
+        let [red, green, blue, _] = r.pixel(4, 8).unwrap();
+        assert!(red > 230 && green < 30 && blue < 30, "{format:?} {red} {green} {blue}");
+        let [red, green, blue, _] = r.pixel(28, 8).unwrap();
+        assert!(red < 30 && green < 30 && blue > 230, "{format:?} {red} {green} {blue}");
+    }
+
+    let mut r = RasterRenderer::new(4, 4).unwrap();
+    assert!(matches!(r.upload_image(ImageId(1), b"\xff\xd8\xff\xe0 not really a jpeg"), Err(RenderError::InvalidInput)));
+}
+
+#[test]
+fn text_without_font_is_an_error() {
+    let mut r = RasterRenderer::new(16, 16).unwrap();
+    let span = TextSpan { text: "Hola".into(), color: RED, size: 12.0, font_family: None, weight: None };
+    assert!(matches!(r.draw_text(&Transform::default(), &span), Err(RenderError::TextShaping)));
+}
+
+#[test]
+fn invalid_font_is_rejected() {
+    let mut r = RasterRenderer::new(16, 16).unwrap();
+    assert!(matches!(r.load_font(b"not a font".to_vec()), Err(RenderError::InvalidInput)));
+    assert!(r.fonts().is_empty());
+}
+
+#[test]
+fn document_draws_shape_labels() {
+    let mut doc = Document::new();
+    let id = doc.create_shape(at(4.0, 4.0), filled(RED), rect(20.0, 20.0));
+    let mut r = RasterRenderer::new(32, 32).unwrap();
+    r.begin_frame(32, 32).unwrap();
+    r.draw_document(&doc).unwrap();
+
+    // La etiqueta pasa por el texto del renderer: sin fuente cargada, falla tras pintar la forma
+    let span = TextSpan { text: "Hola".into(), color: RED, size: 12.0, font_family: None, weight: None };
+    doc.get_mut(id).unwrap().label = Some(TextBox::new(span));
+    assert!(matches!(r.draw_document(&doc), Err(RenderError::TextShaping)));
+    assert_eq!(r.pixel(14, 14), Some([255, 0, 0, 255]));
+}
+
+#[test]
+fn sketchy_rect_is_hachured_and_repeatable() {
+    let style = Style {
+        fill: Some(RED.into()),
+        stroke: Some(Color(0.0, 0.0, 0.0, 1.0).into()),
+        stroke_width: 2.0,
+        opacity: 1.0,
+        sketch: Some(Sketch { roughness: 1.0, seed: sketch::entity_seed(EntityId(7)), fill: SketchFill::Hachure }),
+        ..Style::default()
+    };
+    let render = || {
+        let mut r = RasterRenderer::new(100, 100).unwrap();
+        r.begin_frame(100, 100).unwrap();
+        r.draw_shape(&at(20.0, 20.0), &rect(60.0, 60.0), &style).unwrap();
+        (0..100u32).flat_map(|y| (0..100u32).map(move |x| (x, y))).map(|(x, y)| r.pixel(x, y).unwrap()).collect::<Vec<_>>()
+    };
+    let pixels = render();
+    assert_eq!(pixels, render());
+
+    // El interior queda sombreado: hay líneas rojas y huecos blancos entre ellas
+    let inside: Vec<[u8; 4]> = (30..70).flat_map(|y| (30..70).map(move |x| (x, y))).map(|(x, y)| pixels[y * 100 + x]).collect();
+    assert!(inside.iter().any(|p| p[0] > 200 && p[1] < 100));
+    assert!(inside.contains(&WHITE));
+    // El contorno tiembla poco: lejos de la forma no se pinta nada
+    assert_eq!(pixels[5 * 100 + 5], WHITE);
+    assert_eq!(pixels[95 * 100 + 95], WHITE);
+}
+
+#[test]
+fn linear_gradient_runs_from_start_to_end_in_local_space() {
+    let blue = Color(0.0, 0.0, 1.0, 1.0);
+    let paint = Paint::LinearGradient {
+        start: (0.0, 0.0),
+        end: (50.0, 0.0),
+        stops: vec![GradientStop { offset: 0.0, color: RED }, GradientStop { offset: 1.0, color: blue }],
+    };
+    let style = Style { fill: Some(paint), opacity: 1.0, ..Style::default() };
+    let mut r = RasterRenderer::new(120, 20).unwrap();
+    r.begin_frame(120, 20).unwrap();
+    // Con escala 2 el degradado ocupa 100 px
+    r.draw_shape(&Transform { x: 10.0, scale_x: 2.0, ..Transform::default() }, &rect(50.0, 10.0), &style).unwrap();
+
+    let [r0, _, b0, _] = r.pixel(11, 5).unwrap();
+    let [r1, _, b1, _] = r.pixel(108, 5).unwrap();
+    assert!(r0 > 240 && b0 < 15);
+    assert!(r1 < 15 && b1 > 240);
+    let [rm, _, bm, _] = r.pixel(60, 5).unwrap();
+    assert!((100..156).contains(&rm) && (100..156).contains(&bm));
+}
+
+#[test]
+fn hatch_fill_leaves_gaps_and_strokes_only_the_outline() {
+    let paint = Paint::Hatch { color: RED, gap: 8.0, angle: 0.0, width: 2.0, cross: false };
+    let style = Style { fill: Some(paint), opacity: 1.0, ..Style::default() };
+    let mut r = RasterRenderer::new(60, 60).unwrap();
+    r.begin_frame(60, 60).unwrap();
+    r.draw_shape(&at(10.0, 10.0), &rect(40.0, 40.0), &style).unwrap();
+
+    // Líneas horizontales cada 8 desde la mitad del hueco: y = 14, 22, ...
+    assert_eq!(r.pixel(30, 14), Some([255, 0, 0, 255]));
+    assert_eq!(r.pixel(30, 18), Some(WHITE));
+    // Recortadas al interior
+    assert_eq!(r.pixel(5, 14), Some(WHITE));
+
+    // Cruzado, los huecos quedan en cuadrícula
+    let cross = Paint::Hatch { color: RED, gap: 8.0, angle: 0.0, width: 2.0, cross: true };
+    r.begin_frame(60, 60).unwrap();
+    r.draw_shape(&at(10.0, 10.0), &rect(40.0, 40.0), &Style { fill: Some(cross), ..style }).unwrap();
+    assert_eq!(r.pixel(14, 18), Some([255, 0, 0, 255]));
+    assert_eq!(r.pixel(18, 18), Some(WHITE));
+}
+
+#[test]
+fn image_paint_tiles_the_uploaded_texture() {
+    // Mosaico de 8x1: mitad izquierda roja, derecha transparente
+    let mut tile = tiny_skia::Pixmap::new(8, 1).unwrap();
+    for p in &mut tile.pixels_mut()[..4] {
+        *p = tiny_skia::ColorU8::from_rgba(255, 0, 0, 255).premultiply();
+    }
+    let blob = BlobId(9);
+    let mut r = RasterRenderer::new(40, 10).unwrap();
+    r.upload_image(ImageId::from(blob), &tile.encode_png().unwrap()).unwrap();
+
+    // Cada mosaico mide 16 de ancho: rojo en 0..8, 16..24, ...
+    let style = Style { fill: Some(Paint::Image { blob, w: 16.0, h: 10.0 }), opacity: 1.0, ..Style::default() };
+    r.begin_frame(40, 10).unwrap();
+    r.draw_shape(&at(0.0, 0.0), &rect(40.0, 10.0), &style).unwrap();
+    assert_eq!(r.pixel(3, 5), Some([255, 0, 0, 255]));
+    assert_eq!(r.pixel(12, 5), Some(WHITE));
+    assert_eq!(r.pixel(20, 5), Some([255, 0, 0, 255]));
+}
+
+#[test]
+fn version_1_documents_still_load_and_newer_ones_are_refused() {
+    // Tal como lo exportaba la primera versión del formato: colores sueltos y rect sin radios
+    let v1 = r#"{"schema":"hodei-momentum/document","version":1,"document":{"entities":[[1,{"x":4.0,"y":4.0,"rotation":0.0,"scale_x":1.0,"scale_y":1.0},{"fill":[1.0,0.0,0.0,1.0],"stroke":null,"stroke_width":0.0,"opacity":1.0},{"Rect":{"w":10.0,"h":5.0}}]],"hitboxes":[],"next_id":2}}"#;
+    let doc = Document::from_json(v1).unwrap();
+    let entity = doc.get(EntityId(1)).unwrap();
+    assert_eq!(entity.style.fill, Some(Paint::Solid(RED)));
+    assert!(matches!(entity.shape, Shape::Rect { radii: CornerRadii::Uniform(r), .. } if r == 0.0));
+
+    // Al volver a guardarlo sale con la versión actual
+    let json = doc.to_json().unwrap();
+    assert!(json.contains(&format!(r#""version":{}"#, DOCUMENT_SCHEMA_VERSION)));
+    let newer = json.replace(&format!(r#""version":{}"#, DOCUMENT_SCHEMA_VERSION), &format!(r#""version":{}"#, DOCUMENT_SCHEMA_VERSION + 1));
+    assert!(matches!(Document::from_json(&newer), Err(DocumentFormatError::UnsupportedVersion { .. })));
+}
+
+#[test]
+fn stray_hitboxes_and_labels_are_ignored_on_load() {
+    // Como lo guardaba la versión sin cabecera, con un hitbox de una entidad que ya no existe
+    let json = r#"{"entities":[[1,{"x":0.0,"y":0.0,"rotation":0.0,"scale_x":1.0,"scale_y":1.0},{"fill":[1.0,0.0,0.0,1.0],"stroke":null,"stroke_width":0.0,"opacity":1.0},{"Rect":{"w":10.0,"h":5.0}}]],"hitboxes":[[1,{"FromShape":{"tolerance":2.0}}],[7,{"FromShape":{"tolerance":2.0}}]],"labels":[[9,{"spans":[]}]],"next_id":8}"#;
+    let mut doc = Document::from_json(json).unwrap();
+    assert_eq!(doc.count(), 1);
+    assert!(doc.get_hitbox(EntityId(1)).is_some());
+    assert!(doc.get(EntityId(1)).unwrap().label.is_none());
+    assert_eq!(doc.create_shape(at(0.0, 0.0), filled(RED), rect(1.0, 1.0)), EntityId(8));
+}
+
+#[test]
+fn insert_many_matches_inserting_one_by_one() {
+    let mut doc = Document::new();
+    let ids: Vec<EntityId> = (0..8).map(|i| doc.create_shape(at(i as f32, 0.0), filled(RED), rect(1.0, 1.0))).collect();
+    // Borrar unas cuantas (dejando huecos) y volver a ponerlas en su sitio
+    let removed: Vec<(usize, Entity)> = [6, 1, 3, 7]
+        .into_iter()
+        .map(|i| (doc.z_index(ids[i]).unwrap(), doc.get(ids[i]).unwrap().clone()))
+        .collect();
+    let mut one_by_one = doc.clone();
+    for (_, entity) in &removed {
+        doc.remove(entity.id);
+        one_by_one.remove(entity.id);
+    }
+    let mut sorted = removed.clone();
+    sorted.sort_by_key(|(z, _)| *z);
+    for (z, entity) in sorted {
+        one_by_one.insert_at(z, entity);
+    }
+    doc.insert_many(removed);
+    assert_eq!(doc.ids().collect::<Vec<_>>(), ids);
+    assert_eq!(doc.ids().collect::<Vec<_>>(), one_by_one.ids().collect::<Vec<_>>());
+    assert!(ids.iter().enumerate().all(|(z, id)| doc.z_index(*id) == Some(z)));
+
+    // Posiciones más allá del final quedan encima, en orden
+    let extra = Entity::new(EntityId(100), at(0.0, 0.0), filled(RED), rect(1.0, 1.0));
+    doc.insert_many(vec![(50, extra), (2, doc.get(ids[0]).unwrap().clone())]);
+    assert_eq!(doc.ids().nth(2), Some(ids[0]));
+    assert_eq!(doc.ids().last(), Some(EntityId(100)));
+    assert_eq!(doc.count(), 9);
+}
+
+#[test]
+fn document_json_carries_the_image_bytes() {
+    let mut source = RasterRenderer::new(4, 2).unwrap().with_background(Some(RED));
+    source.begin_frame(4, 2).unwrap();
+    let png = source.encode_png().unwrap();
+    let mut source = RasterRenderer::new(2, 2).unwrap();
+    source.begin_frame(2, 2).unwrap();
+    let photo = source.encode_png().unwrap();
+    let (blob, tile) = (image::blob_id(&png), image::blob_id(&photo));
+    let mut doc = Document::new();
+    doc.create_shape(at(0.0, 0.0), Style { opacity: 1.0, ..Style::default() }, Shape::Image { blob, w: 4.0, h: 2.0 });
+    let tiled = Style { fill: Some(Paint::Image { blob: tile, w: 4.0, h: 2.0 }), opacity: 1.0, ..Style::default() };
+    doc.create_shape(at(8.0, 0.0), tiled, rect(8.0, 8.0));
+    assert_eq!(doc.image_blobs(), vec![blob, tile]);
+
+    // Los blobs que el documento no usa no se guardan
+    let unused = b"not referenced".to_vec();
+    let images = HashMap::from([(blob, png.clone()), (tile, photo.clone()), (image::blob_id(&unused), unused)]);
+    let json = doc.to_json_with_images(&images).unwrap();
+    let (loaded, loaded_images) = Document::from_json_with_images(&json).unwrap();
+    assert_eq!(loaded.to_json().unwrap(), doc.to_json().unwrap());
+    assert_eq!(loaded_images, HashMap::from([(blob, png), (tile, photo)]));
+
+    // Sin imágenes (o con `from_json`) el documento carga igual
+    assert!(!doc.to_json().unwrap().contains("blobs"));
+    assert_eq!(Document::from_json(&json).unwrap().count(), 2);
+
+    // Un blob cuyo contenido no corresponde a su id es un error
+    let tampered = json.replacen(&format!("\"{}\":\"", blob.0), &format!("\"{}\":\"AAAA", blob.0), 1);
+    assert!(matches!(Document::from_json_with_images(&tampered), Err(DocumentFormatError::InvalidBlob(id)) if id == blob.0));
+}
+
+#[test]
+fn color_fills_keep_their_json_and_paints_round_trip() {
+    let mut doc = Document::new();
+    let solid = doc.create_shape(at(0.0, 0.0), filled(RED), rect(10.0, 10.0));
+    let hatch = Paint::Hatch { color: RED, gap: 4.0, angle: 0.5, width: 1.0, cross: true };
+    let hatched = doc.create_shape(at(0.0, 0.0), Style { stroke: Some(hatch.clone()), ..filled(RED) }, rect(10.0, 10.0));
+
+    // Un color sólido se guarda como antes de existir `Paint`, así que los documentos viejos cargan
+    let json = doc.to_json().unwrap();
+    assert!(json.contains(r#""fill":[1.0,0.0,0.0,1.0]"#));
+    let loaded = Document::from_json(&json).unwrap();
+    assert_eq!(loaded.get(solid).unwrap().style.fill, Some(Paint::Solid(RED)));
+    assert_eq!(loaded.get(hatched).unwrap().style.stroke, Some(hatch));
+}
+
+#[test]
+fn rounded_rect_leaves_its_corners_unpainted() {
+    let mut r = RasterRenderer::new(64, 64).unwrap();
+    r.begin_frame(64, 64).unwrap();
+    let rounded = Shape::Rect { w: 40.0, h: 40.0, radii: CornerRadii::Uniform(12.0) };
+    r.draw_shape(&at(10.0, 10.0), &rounded, &filled(RED)).unwrap();
+    assert_eq!(r.pixel(11, 11), Some(WHITE));
+    assert_eq!(r.pixel(48, 48), Some(WHITE));
+    assert_eq!(r.pixel(30, 11), Some([255, 0, 0, 255]));
+    assert_eq!(r.pixel(11, 30), Some([255, 0, 0, 255]));
+
+    // Una sola esquina redondeada (la inferior derecha): las otras tres siguen vivas
+    let mut r = RasterRenderer::new(64, 64).unwrap();
+    r.begin_frame(64, 64).unwrap();
+    let one = Shape::Rect { w: 40.0, h: 40.0, radii: CornerRadii::PerCorner([0.0, 0.0, 12.0, 0.0]) };
+    r.draw_shape(&at(10.0, 10.0), &one, &filled(RED)).unwrap();
+    assert_eq!(r.pixel(10, 10), Some([255, 0, 0, 255]));
+    assert_eq!(r.pixel(49, 10), Some([255, 0, 0, 255]));
+    assert_eq!(r.pixel(48, 48), Some(WHITE));
+}
+
+#[test]
+fn corner_radii_stay_circular_and_fit_under_non_uniform_scale() {
+    let rounded = Shape::Rect { w: 20.0, h: 20.0, radii: CornerRadii::Uniform(10.0) };
+    let stretched = Transform { scale_x: 2.0, ..Transform::default() };
+    // 40 x 20 en mundo con radio 10: en local, la mitad de ancho que de alto
+    let Shape::Rect { radii, .. } = &rounded else { unreachable!() };
+    assert_eq!(radii.resolve(20.0, 20.0, &stretched), [(5.0, 10.0); 4]);
+    // Si no caben, se reducen como en CSS: 40 x 20 con radio 30 se queda en 10
+    assert_eq!(CornerRadii::Uniform(30.0).resolve(40.0, 20.0, &Transform::default()), [(10.0, 10.0); 4]);
+
+    // El hit test sigue el arco circular en mundo, no el recuadro
+    let hitbox = Hitbox::from_shape(&rounded);
+    assert!(hitbox.hit_test(20.0, 10.0, &stretched, &rounded));
+    assert!(hitbox.hit_test(4.0, 4.0, &stretched, &rounded));
+    assert!(!hitbox.hit_test(1.0, 1.0, &stretched, &rounded));
+    assert!(!hitbox.hit_test(39.0, 19.0, &stretched, &rounded));
+    assert!(hitbox.hit_test(39.0, 10.0, &stretched, &rounded));
+
+    // El SVG lo lleva como <rect> con los radios locales
+    let mut doc = Document::new();
+    doc.create_shape(stretched, filled(RED), rounded);
+    let svg = momentum_core::svg::to_svg(&doc, &momentum_core::svg::SvgExportOptions::default());
+    assert!(svg.contains(r#"rx="5" ry="10""#), "{svg}");
+}
@@ -38,20 +38,91 @@ use leptos::wasm_bindgen::closure::Closure;
 #[cfg(target_arch = "wasm32")]
 use js_sys::{Function, Reflect};
 #[cfg(target_arch = "wasm32")]
-use web_sys::{console, HtmlCanvasElement, Element, Event, PointerEvent};
+use web_sys::{console, HtmlCanvasElement, HtmlTextAreaElement, Element, Event, KeyboardEvent, PointerEvent};
 
 #[cfg(target_arch = "wasm32")]
 fn event_to_canvas_css(ev: &leptos::ev::PointerEvent) -> Option<(f32, f32)> {
+    client_to_canvas_css(ev.client_x(), ev.client_y())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn client_to_canvas_css(client_x: i32, client_y: i32) -> Option<(f32, f32)> {
     use leptos::prelude::document;
     let doc = document();
     let elem = doc.get_element_by_id("main-canvas").and_then(|e| e.dyn_into::<HtmlCanvasElement>().ok())?;
     let rect = elem.get_bounding_client_rect();
     // Restar las coordenadas del canvas para obtener coordenadas relativas al canvas
-    let x = ev.client_x() as f64 - rect.left();
-    let y = ev.client_y() as f64 - rect.top();
+    let x = client_x as f64 - rect.left();
+    let y = client_y as f64 - rect.top();
     Some((x as f32, y as f32))
 }
 
+/// Convierte un punto del canvas (CSS px) a coordenadas de mundo según la cámara del ECS
+#[cfg(target_arch = "wasm32")]
+fn screen_to_world(x: f32, y: f32) -> (f32, f32) {
+    let global: JsValue = window().into();
+    let point = Reflect::get(&global, &JsValue::from_str("ecs_screen_to_world"))
+        .ok()
+        .and_then(|f| f.dyn_into::<Function>().ok())
+        .and_then(|f| f.call2(&JsValue::NULL, &JsValue::from_f64(x as f64), &JsValue::from_f64(y as f64)).ok());
+    let coord = |i: u32| point.as_ref().and_then(|p| Reflect::get_u32(p, i).ok()).and_then(|v| v.as_f64());
+    match (coord(0), coord(1)) {
+        (Some(wx), Some(wy)) => (wx as f32, wy as f32),
+        _ => (x, y),
+    }
+}
+
+/// Sensibilidad de la rueda: factor de zoom = exp(-deltaY * WHEEL_ZOOM_SPEED)
+#[cfg(target_arch = "wasm32")]
+const WHEEL_ZOOM_SPEED: f64 = 0.0015;
+
+/// Llama a una función global del bridge (`window.<name>()`) sin argumentos
+#[cfg(target_arch = "wasm32")]
+fn call_bridge(name: &str) {
+    call_bridge_with_args(name, &[]);
+}
+
+/// Llama a una función global del bridge (`window.<name>(...args)`)
+#[cfg(target_arch = "wasm32")]
+fn call_bridge_with_args(name: &str, args: &[JsValue]) {
+    call_bridge_value(name, args);
+}
+
+/// Como `call_bridge_with_args`, devolviendo el resultado (undefined si la función no existe)
+#[cfg(target_arch = "wasm32")]
+fn call_bridge_value(name: &str, args: &[JsValue]) -> JsValue {
+    let global: JsValue = window().into();
+    Reflect::get(&global, &JsValue::from_str(name))
+        .ok()
+        .and_then(|f| f.dyn_into::<Function>().ok())
+        .and_then(|f| f.apply(&JsValue::NULL, &args.iter().collect::<js_sys::Array>()).ok())
+        .unwrap_or(JsValue::UNDEFINED)
+}
+
+/// Campo oculto que recibe el tecleo y la composición del IME durante la edición de texto
+#[cfg(target_arch = "wasm32")]
+fn text_input() -> Option<HtmlTextAreaElement> {
+    use leptos::prelude::document;
+    document().get_element_by_id("text-input").and_then(|e| e.dyn_into::<HtmlTextAreaElement>().ok())
+}
+
+/// Espera antes de leer el cursor tras un evento de texto: el ECS lo procesa en su siguiente fotograma
+#[cfg(target_arch = "wasm32")]
+const TEXT_SYNC_DELAY_MS: i32 = 32;
+
+/// Desplazamiento de las copias al duplicar con Ctrl/Cmd+D (CSS px)
+#[cfg(target_arch = "wasm32")]
+const DUPLICATE_OFFSET: f64 = 10.0;
+
+/// El foco está en un campo de texto: los atajos de teclado no deben interceptarse
+#[cfg(target_arch = "wasm32")]
+fn is_editing_text(ev: &KeyboardEvent) -> bool {
+    ev.target()
+        .and_then(|t| t.dyn_into::<Element>().ok())
+        .map(|el| matches!(el.tag_name().as_str(), "INPUT" | "TEXTAREA") || el.has_attribute("contenteditable"))
+        .unwrap_or(false)
+}
+
 #[cfg(target_arch = "wasm32")]
 fn resize_canvas(canvas: &HtmlCanvasElement) {
     let win = window();
@@ -72,7 +143,7 @@ fn resize_canvas(canvas: &HtmlCanvasElement) {
 #[component]
 pub fn App() -> impl IntoView {
     #[derive(Clone, Copy, PartialEq, Eq)]
-    enum Tool { Select, Rect, Ellipse, Line }
+    enum Tool { Select, Rect, Ellipse, Arrow, Connector, Line, Pen, Text, Hand }
 
     #[derive(Clone, Debug)]
     enum PreviewShape {
@@ -83,6 +154,8 @@ pub fn App() -> impl IntoView {
 
     // Umbral mínimo de arrastre para considerar creación (evitar click simple -> rect diminuto)
     const DRAG_THRESHOLD: f32 = 4.0;
+    // Valor de HandleType::Rotate en el bridge (to_u8)
+    const ROTATE_HANDLE: u8 = 8;
     let (tool, set_tool) = signal(Tool::Select);
     // Estado local para drag-to-create (coords en CSS px relativos al canvas)
     let drag_start = std::rc::Rc::new(std::cell::Cell::new(None::<(f32, f32)>));
@@ -104,11 +177,68 @@ pub fn App() -> impl IntoView {
     let (is_scaling, set_is_scaling) = signal(false);
     let (scale_handle_type, set_scale_handle_type) = signal::<Option<u8>>(None);
     
+    // Estado de rotación (handle de rotación sobre la selección)
+    let (is_rotating, set_is_rotating) = signal(false);
+    
+    // Estado de marquee: pointer down en espacio vacío -> arrastrar dibuja un rectángulo de selección
+    let (marquee_candidate, set_marquee_candidate) = signal(false);
+    let (is_marquee, set_is_marquee) = signal(false);
+    
     // Estado del cursor para feedback visual
     let (cursor_state, set_cursor_state) = signal("default".to_string());
     
+    // Desplazamiento de la vista: herramienta mano o espacio mantenido + arrastre
+    let (space_held, set_space_held) = signal(false);
+    let (is_panning, set_is_panning) = signal(false);
+    let pan_last = std::rc::Rc::new(std::cell::Cell::new(None::<(f32, f32)>));
+    
+    // Trazo de lápiz en curso: las muestras van directas al ECS, que ajusta la curva al soltar
+    let (is_pen_drawing, set_is_pen_drawing) = signal(false);
+    
+    // Edición de texto: el ECS tiene el cursor y la selección; la UI solo lleva el campo oculto
+    // a la posición del cursor (para la ventana del IME) y le reenvía lo que se teclea
+    let (is_text_editing, set_is_text_editing) = signal(false);
+    let (is_text_selecting, set_is_text_selecting) = signal(false);
+    let (is_composing, set_is_composing) = signal(false);
+    let (text_caret, set_text_caret) = signal::<Option<(f32, f32, f32)>>(None);
+    let sync_text_input = move || {
+        let cb = Closure::once_into_js(move || {
+            let caret = call_bridge_value("ecs_text_caret_rect", &[]);
+            let field = |k: &str| Reflect::get(&caret, &JsValue::from_str(k)).ok().and_then(|v| v.as_f64()).map(|v| v as f32);
+            set_text_caret.set(match (field("x"), field("y"), field("h")) {
+                (Some(x), Some(y), Some(h)) => Some((x, y, h)),
+                _ => None,
+            });
+            // Tras el pointerdown el navegador quita el foco al campo: recuperarlo aquí
+            if is_text_editing.get_untracked() {
+                if let Some(input) = text_input() { let _ = input.focus(); }
+            }
+        });
+        let _ = window().set_timeout_with_callback_and_timeout_and_arguments_0(cb.unchecked_ref(), TEXT_SYNC_DELAY_MS);
+    };
+    let start_text_editing = move || {
+        set_is_text_editing.set(true);
+        if let Some(input) = text_input() {
+            input.set_value("");
+            let _ = input.focus();
+        }
+        sync_text_input();
+    };
+    let end_text_editing = move || {
+        call_bridge("ecs_text_end");
+        set_is_text_editing.set(false);
+        set_is_text_selecting.set(false);
+        set_is_composing.set(false);
+        set_text_caret.set(None);
+        if let Some(input) = text_input() {
+            input.set_value("");
+            let _ = input.blur();
+        }
+    };
+    
     // Handlers de puntero básicos
     let drag_start_down = drag_start.clone();
+    let pan_last_down = pan_last.clone();
     let on_pointer_down = move |ev: leptos::ev::PointerEvent| {
         let (x, y) = event_to_canvas_css(&ev).unwrap_or((ev.offset_x() as f32, ev.offset_y() as f32));
         console::log_1(&format!("pointerdown(canvas): css=({}, {})", x, y).into());
@@ -119,10 +249,47 @@ pub fn App() -> impl IntoView {
             }
         }
         
+        // Mano o espacio: arrastrar desplaza la vista, sin tocar la selección
+        if tool.get_untracked() == Tool::Hand || space_held.get_untracked() {
+            set_is_panning.set(true);
+            pan_last_down.set(Some((x, y)));
+            set_cursor_state.set("grabbing".to_string());
+            return;
+        }
+        
+        // Edición de texto en curso: un clic dentro coloca el cursor (arrastrar selecciona), fuera la termina
+        let (jx, jy) = (JsValue::from_f64(x as f64), JsValue::from_f64(y as f64));
+        if call_bridge_value("ecs_is_text_editing", &[]).as_bool().unwrap_or(false) {
+            if call_bridge_value("ecs_text_edit_hit", &[jx.clone(), jy.clone()]).as_bool().unwrap_or(false) {
+                set_is_text_selecting.set(true);
+                call_bridge_with_args("ecs_text_pointer", &[jx, jy, JsValue::from_bool(ev.shift_key())]);
+                sync_text_input();
+                return;
+            }
+            end_text_editing();
+        }
+        
+        // Texto: edita el texto bajo el puntero o crea uno nuevo
+        if tool.get_untracked() == Tool::Text {
+            call_bridge_with_args("ecs_text_begin", &[jx, jy, JsValue::from_bool(true)]);
+            start_text_editing();
+            return;
+        }
+        
+        // Lápiz: la presión viene del PointerEvent (0.5 con ratón, la real con lápiz digital)
+        if tool.get_untracked() == Tool::Pen {
+            set_is_pen_drawing.set(true);
+            call_bridge_with_args("ecs_pen_start", &[JsValue::from_f64(x as f64), JsValue::from_f64(y as f64), JsValue::from_f64(ev.pressure() as f64)]);
+            return;
+        }
+        
         // Si es herramienta Select, manejar selección y posible inicio de movimiento
         if tool.get_untracked() == Tool::Select {
             // Limpiar estado previo
             set_is_scaling.set(false);
+            set_is_rotating.set(false);
+            set_marquee_candidate.set(false);
+            set_is_marquee.set(false);
             set_scale_handle_type.set(None);
             set_is_dragging_selection.set(false);
             set_cursor_state.set("default".to_string());
@@ -141,6 +308,16 @@ pub fn App() -> impl IntoView {
                     
                     // Procesar respuesta inmediata
                     if let Ok(result_val) = func.apply(&JsValue::NULL, &args) {
+                        // Sin handle ni entidad bajo el puntero: un arrastre será un marquee
+                        let on_handle = Reflect::get(&result_val, &JsValue::from_str("clicked_handle_type"))
+                            .map(|v| !v.is_undefined() && !v.is_null())
+                            .unwrap_or(false);
+                        let on_entity = Reflect::get(&result_val, &JsValue::from_str("entity_selected"))
+                            .ok()
+                            .and_then(|v| v.as_bool())
+                            .unwrap_or(false);
+                        set_marquee_candidate.set(!on_handle && !on_entity);
+
                         // Extraer clicked_handle_type de la respuesta
                         if let Ok(clicked_handle) = Reflect::get(&result_val, &JsValue::from_str("clicked_handle_type")) {
                             if !clicked_handle.is_undefined() && !clicked_handle.is_null() {
@@ -159,6 +336,7 @@ pub fn App() -> impl IntoView {
                                         5 => "e-resize",  // Right
                                         6 => "s-resize",  // Bottom
                                         7 => "w-resize",  // Left
+                                        ROTATE_HANDLE => "grab",
                                         _ => "default",
                                     };
                                     set_cursor_state.set(cursor.to_string());
@@ -176,10 +354,33 @@ pub fn App() -> impl IntoView {
 
     // Clonado para usar dentro de on_pointer_move
     let drag_start_move = drag_start.clone();
+    let pan_last_move = pan_last.clone();
 
     let on_pointer_move = move |ev: leptos::ev::PointerEvent| {
         let (ex, ey) = event_to_canvas_css(&ev).unwrap_or((ev.offset_x() as f32, ev.offset_y() as f32));
         
+        // PANNING: desplazamiento incremental desde la última posición
+        if is_panning.get_untracked() {
+            if let Some((lx, ly)) = pan_last_move.get() {
+                call_bridge_with_args("ecs_pan_by", &[JsValue::from_f64((ex - lx) as f64), JsValue::from_f64((ey - ly) as f64)]);
+            }
+            pan_last_move.set(Some((ex, ey)));
+            return;
+        }
+        
+        // LÁPIZ: cada movimiento es una muestra del trazo
+        if is_pen_drawing.get_untracked() {
+            call_bridge_with_args("ecs_pen_move", &[JsValue::from_f64(ex as f64), JsValue::from_f64(ey as f64), JsValue::from_f64(ev.pressure() as f64)]);
+            return;
+        }
+        
+        // TEXTO: arrastrar dentro del texto en edición amplía la selección
+        if is_text_selecting.get_untracked() {
+            call_bridge_with_args("ecs_text_pointer", &[JsValue::from_f64(ex as f64), JsValue::from_f64(ey as f64), JsValue::from_bool(true)]);
+            sync_text_input();
+            return;
+        }
+        
         // HOVER DETECTION (cuando no se está arrastrando)
         if ev.buttons() == 0 && tool.get_untracked() == Tool::Select {
             // Detectar hover sobre handles (prioridad)
@@ -207,6 +408,7 @@ pub fn App() -> impl IntoView {
                                     5 => "e-resize",  // Right
                                     6 => "s-resize",  // Bottom
                                     7 => "w-resize",  // Left
+                                    ROTATE_HANDLE => "grab",
                                     _ => "default",
                                 };
                                 set_cursor_state.set(cursor.to_string());
@@ -251,8 +453,73 @@ pub fn App() -> impl IntoView {
                 if dx_abs > DRAG_THRESHOLD || dy_abs > DRAG_THRESHOLD {
                     match tool.get_untracked() {
                         Tool::Select => {
-                            // Verificar si estamos escalando (decidido inmediatamente en pointer_down)
-                            if let Some(handle_type) = scale_handle_type.get_untracked() {
+                            // Verificar si estamos rotando (handle de rotación pulsado en pointer_down)
+                            if scale_handle_type.get_untracked() == Some(ROTATE_HANDLE) {
+                                // MODO ROTACIÓN
+                                if !is_rotating.get_untracked() {
+                                    set_is_rotating.set(true);
+                                    set_cursor_state.set("grabbing".to_string());
+                                    let win = window();
+                                    let global: JsValue = win.into();
+                                    if let Ok(func_val) = Reflect::get(&global, &JsValue::from_str("ecs_rotate_start")) {
+                                        if let Ok(func) = func_val.dyn_into::<Function>() {
+                                            let args = js_sys::Array::new();
+                                            args.push(&JsValue::from_f64(sx as f64));
+                                            args.push(&JsValue::from_f64(sy as f64));
+                                            let _ = func.apply(&JsValue::NULL, &args);
+                                        }
+                                    }
+                                    console::log_1(&"UI: Started rotating mode".into());
+                                }
+                                
+                                // Enviar update de rotación con delta relativo al punto de inicio (shift = pasos de 15°)
+                                let dx = ex - sx;
+                                let dy = ey - sy;
+                                let win = window();
+                                let global: JsValue = win.into();
+                                if let Ok(func_val) = Reflect::get(&global, &JsValue::from_str("ecs_rotate_update")) {
+                                    if let Ok(func) = func_val.dyn_into::<Function>() {
+                                        let args = js_sys::Array::new();
+                                        args.push(&JsValue::from_f64(dx as f64));
+                                        args.push(&JsValue::from_f64(dy as f64));
+                                        args.push(&JsValue::from_bool(ev.shift_key()));
+                                        let _ = func.apply(&JsValue::NULL, &args);
+                                    }
+                                }
+                            } else if marquee_candidate.get_untracked() {
+                                // MODO MARQUEE (shift = añadir a la selección, alt = seleccionar por intersección)
+                                if !is_marquee.get_untracked() {
+                                    set_is_marquee.set(true);
+                                    set_cursor_state.set("crosshair".to_string());
+                                    let win = window();
+                                    let global: JsValue = win.into();
+                                    if let Ok(func_val) = Reflect::get(&global, &JsValue::from_str("ecs_marquee_start")) {
+                                        if let Ok(func) = func_val.dyn_into::<Function>() {
+                                            let mode = if ev.alt_key() { 1 } else { 0 };
+                                            let args = js_sys::Array::new();
+                                            args.push(&JsValue::from_f64(sx as f64));
+                                            args.push(&JsValue::from_f64(sy as f64));
+                                            args.push(&JsValue::from_bool(ev.shift_key()));
+                                            args.push(&JsValue::from_f64(mode as f64));
+                                            let _ = func.apply(&JsValue::NULL, &args);
+                                        }
+                                    }
+                                    console::log_1(&"UI: Started marquee mode".into());
+                                }
+                                
+                                let dx = ex - sx;
+                                let dy = ey - sy;
+                                let win = window();
+                                let global: JsValue = win.into();
+                                if let Ok(func_val) = Reflect::get(&global, &JsValue::from_str("ecs_marquee_update")) {
+                                    if let Ok(func) = func_val.dyn_into::<Function>() {
+                                        let args = js_sys::Array::new();
+                                        args.push(&JsValue::from_f64(dx as f64));
+                                        args.push(&JsValue::from_f64(dy as f64));
+                                        let _ = func.apply(&JsValue::NULL, &args);
+                                    }
+                                }
+                            } else if let Some(handle_type) = scale_handle_type.get_untracked() {
                                 // MODO ESCALADO
                                 if !is_scaling.get_untracked() {
                                     set_is_scaling.set(true);
@@ -271,15 +538,18 @@ pub fn App() -> impl IntoView {
                                 }
                                 
                                 // Enviar update de escalado con delta relativo al punto de inicio
+                                // (shift = mantener proporción, alt = escalar desde el centro)
                                 let dx = ex - sx;
                                 let dy = ey - sy;
                                 let win = window();
                                 let global: JsValue = win.into();
-                                if let Ok(func_val) = Reflect::get(&global, &JsValue::from_str("ecs_scale_update")) {
+                                if let Ok(func_val) = Reflect::get(&global, &JsValue::from_str("ecs_scale_update_with_modifiers")) {
                                     if let Ok(func) = func_val.dyn_into::<Function>() {
                                         let args = js_sys::Array::new();
                                         args.push(&JsValue::from_f64(dx as f64));
                                         args.push(&JsValue::from_f64(dy as f64));
+                                        args.push(&JsValue::from_bool(ev.shift_key()));
+                                        args.push(&JsValue::from_bool(ev.alt_key()));
                                         let _ = func.apply(&JsValue::NULL, &args);
                                     }
                                 }
@@ -335,9 +605,11 @@ pub fn App() -> impl IntoView {
                             let cy = y + ry;
                             set_drag_preview.set(Some(PreviewShape::Ellipse { cx, cy, rx, ry }));
                         }
-                        Tool::Line => {
+                        // La flecha y el conector se previsualizan como la recta entre sus extremos
+                        Tool::Line | Tool::Arrow | Tool::Connector => {
                             set_drag_preview.set(Some(PreviewShape::Line { x1: sx, y1: sy, x2: ex, y2: ey }));
                         }
+                        Tool::Pen | Tool::Text | Tool::Hand => {}
                     }
                 } else {
                     set_drag_preview.set(None);
@@ -347,13 +619,41 @@ pub fn App() -> impl IntoView {
     };
 
     let drag_start_up = drag_start.clone();
+    let pan_last_up = pan_last.clone();
     let on_pointer_up = move |ev: leptos::ev::PointerEvent| {
         let (ex, ey) = event_to_canvas_css(&ev).unwrap_or((ev.offset_x() as f32, ev.offset_y() as f32));
         console::log_1(&format!("pointerup(canvas): css=({}, {})", ex, ey).into());
+        if is_panning.get_untracked() {
+            set_is_panning.set(false);
+            pan_last_up.set(None);
+            let cursor = match tool.get_untracked() {
+                _ if space_held.get_untracked() => "grab",
+                Tool::Hand => "grab",
+                Tool::Pen => "crosshair",
+                Tool::Text => "text",
+                _ => "default",
+            };
+            set_cursor_state.set(cursor.to_string());
+            return;
+        }
+        if is_pen_drawing.get_untracked() {
+            set_is_pen_drawing.set(false);
+            call_bridge("ecs_pen_end");
+            return;
+        }
+        if is_text_selecting.get_untracked() {
+            set_is_text_selecting.set(false);
+            return;
+        }
         if let Some((sx, sy)) = drag_start_up.get() {
             let dx = (ex - sx).abs();
             let dy = (ey - sy).abs();
             if dx > DRAG_THRESHOLD || dy > DRAG_THRESHOLD {
+                // Las formas se crean en coordenadas de mundo (la previsualización sigue en pantalla)
+                let (sx, sy) = screen_to_world(sx, sy);
+                let (ex, ey) = screen_to_world(ex, ey);
+                let dx = (ex - sx).abs();
+                let dy = (ey - sy).abs();
                 let x = sx.min(ex);
                 let y = sy.min(ey);
                 let w = dx.max(1.0);
@@ -410,9 +710,66 @@ pub fn App() -> impl IntoView {
                             }
                         }
                     }
+                    Tool::Arrow => {
+                        let win = window();
+                        let global: JsValue = win.into();
+                        if let Ok(func_val) = Reflect::get(&global, &JsValue::from_str("ecs_create_arrow")) {
+                            if let Ok(func) = func_val.dyn_into::<Function>() {
+                                // Los extremos que caen sobre una forma se enganchan a ella en el ECS
+                                console::log_1(&format!("UI: calling ecs_create_arrow({}, {}, {}, {})", sx, sy, ex, ey).into());
+                                let points = js_sys::Array::new();
+                                for v in [sx, sy, ex, ey] {
+                                    points.push(&JsValue::from_f64(v as f64));
+                                }
+                                let _ = func.call1(&JsValue::NULL, &points);
+                            }
+                        }
+                    }
+                    Tool::Connector => {
+                        let win = window();
+                        let global: JsValue = win.into();
+                        if let Ok(func_val) = Reflect::get(&global, &JsValue::from_str("ecs_create_connector")) {
+                            if let Ok(func) = func_val.dyn_into::<Function>() {
+                                // El ECS traza los codos rodeando las demás formas
+                                console::log_1(&format!("UI: calling ecs_create_connector({}, {}, {}, {})", sx, sy, ex, ey).into());
+                                let args = js_sys::Array::new();
+                                for v in [sx, sy, ex, ey] {
+                                    args.push(&JsValue::from_f64(v as f64));
+                                }
+                                let _ = func.apply(&JsValue::NULL, &args);
+                            }
+                        }
+                    }
+                    Tool::Pen | Tool::Text | Tool::Hand => {}
                     Tool::Select => {
+                        // Finalizar marquee si estaba activo
+                        if is_marquee.get_untracked() {
+                            let win = window();
+                            let global: JsValue = win.into();
+                            if let Ok(func_val) = Reflect::get(&global, &JsValue::from_str("ecs_marquee_end")) {
+                                if let Ok(func) = func_val.dyn_into::<Function>() {
+                                    let _ = func.apply(&JsValue::NULL, &js_sys::Array::new());
+                                }
+                            }
+                            set_is_marquee.set(false);
+                            set_marquee_candidate.set(false);
+                            console::log_1(&"UI: Ended marquee mode".into());
+                        }
+                        // Finalizar rotación si estaba activa
+                        else if is_rotating.get_untracked() {
+                            let win = window();
+                            let global: JsValue = win.into();
+                            if let Ok(func_val) = Reflect::get(&global, &JsValue::from_str("ecs_rotate_end")) {
+                                if let Ok(func) = func_val.dyn_into::<Function>() {
+                                    let _ = func.apply(&JsValue::NULL, &js_sys::Array::new());
+                                }
+                            }
+                            set_is_rotating.set(false);
+                            set_scale_handle_type.set(None);
+                            console::log_1(&"UI: Ended rotating mode".into());
+                        }
                         // Finalizar escalado si estaba activo
-                        if is_scaling.get_untracked() {
+                        else if is_scaling.get_untracked() {
                             let win = window();
                             let global: JsValue = win.into();
                             if let Ok(func_val) = Reflect::get(&global, &JsValue::from_str("ecs_scale_end")) {
@@ -447,6 +804,94 @@ pub fn App() -> impl IntoView {
         set_drag_preview.set(None);
     };
 
+    // Doble clic sobre una forma: edita su texto, o su etiqueta si no es un texto
+    let on_double_click = move |ev: leptos::ev::MouseEvent| {
+        if tool.get_untracked() != Tool::Select || is_text_editing.get_untracked() { return; }
+        let Some((x, y)) = client_to_canvas_css(ev.client_x(), ev.client_y()) else { return; };
+        let (jx, jy) = (JsValue::from_f64(x as f64), JsValue::from_f64(y as f64));
+        if !call_bridge_value("ecs_detect_shape_hover", &[jx.clone(), jy.clone()]).as_bool().unwrap_or(false) { return; }
+        call_bridge_with_args("ecs_text_begin", &[jx, jy, JsValue::from_bool(false)]);
+        start_text_editing();
+    };
+
+    // Campo oculto: lo tecleado va al ECS y el campo se vacía; durante la composición del IME
+    // su contenido es el texto provisional
+    let on_text_input = move |_ev: Event| {
+        if is_composing.get_untracked() { return; }
+        let Some(input) = text_input() else { return; };
+        let value = input.value();
+        if value.is_empty() { return; }
+        input.set_value("");
+        call_bridge_with_args("ecs_text_insert", &[JsValue::from_str(&value)]);
+        sync_text_input();
+    };
+    let on_composition_update = move |_ev: leptos::ev::CompositionEvent| {
+        set_is_composing.set(true);
+        let value = text_input().map(|input| input.value()).unwrap_or_default();
+        call_bridge_with_args("ecs_text_preedit", &[JsValue::from_str(&value)]);
+        sync_text_input();
+    };
+    let on_composition_end = move |_ev: leptos::ev::CompositionEvent| {
+        set_is_composing.set(false);
+        let Some(input) = text_input() else { return; };
+        let value = input.value();
+        input.set_value("");
+        // Sin texto (composición cancelada) basta con quitar el provisional
+        if value.is_empty() {
+            call_bridge_with_args("ecs_text_preedit", &[JsValue::from_str("")]);
+        } else {
+            call_bridge_with_args("ecs_text_insert", &[JsValue::from_str(&value)]);
+        }
+        sync_text_input();
+    };
+    // Teclas de edición: Intro salto de línea, Escape o Ctrl/Cmd+Intro terminar, Ctrl/Cmd+A seleccionar todo
+    let on_text_keydown = move |ev: leptos::ev::KeyboardEvent| {
+        if ev.is_composing() || is_composing.get_untracked() { return; }
+        let modifier = ev.ctrl_key() || ev.meta_key();
+        match ev.key().as_str() {
+            "Escape" => end_text_editing(),
+            "Enter" if modifier => end_text_editing(),
+            "Enter" => {
+                call_bridge_with_args("ecs_text_insert", &[JsValue::from_str("\n")]);
+                sync_text_input();
+            }
+            "a" | "A" if modifier => {
+                call_bridge_with_args("ecs_text_key", &[JsValue::from_str("selectAll"), JsValue::from_bool(false)]);
+                sync_text_input();
+            }
+            // El deshacer nativo del campo no conoce el documento
+            "z" | "Z" | "y" | "Y" if modifier => {}
+            key => {
+                if !call_bridge_value("ecs_text_key", &[JsValue::from_str(key), JsValue::from_bool(ev.shift_key())]).as_bool().unwrap_or(false) {
+                    return;
+                }
+                sync_text_input();
+            }
+        }
+        ev.prevent_default();
+    };
+
+    // Rueda del ratón: zoom alrededor del cursor
+    let on_wheel = move |ev: leptos::ev::WheelEvent| {
+        ev.prevent_default();
+        let Some((x, y)) = client_to_canvas_css(ev.client_x(), ev.client_y()) else { return; };
+        // deltaMode 1 = líneas (Firefox): aproximar a píxeles
+        let delta = if ev.delta_mode() == 1 { ev.delta_y() * 16.0 } else { ev.delta_y() };
+        let factor = (-delta * WHEEL_ZOOM_SPEED).exp();
+        call_bridge_with_args("ecs_zoom_at", &[JsValue::from_f64(x as f64), JsValue::from_f64(y as f64), JsValue::from_f64(factor)]);
+    };
+
+    // Cursor base al cambiar de herramienta
+    Effect::new(move |_| {
+        let cursor = match tool.get() {
+            Tool::Hand => "grab",
+            Tool::Pen => "crosshair",
+            Tool::Text => "text",
+            _ => "default",
+        };
+        set_cursor_state.set(cursor.to_string());
+    });
+
     // Efecto para cambiar cursor dinámicamente (aplicar al canvas directamente)
     Effect::new(move |_| {
         let cursor = cursor_state.get();
@@ -519,7 +964,26 @@ pub fn App() -> impl IntoView {
                 let set_drag_preview_for_doc = set_drag_preview;
                 let tool_for_doc = tool;
                 let canvas_for_doc = canvas.clone();
+                let pan_last_for_doc = pan_last.clone();
                 let up_cb = Closure::wrap(Box::new(move |ev: Event| {
+                    // Un trazo de lápiz termina aunque se suelte fuera del canvas
+                    if is_pen_drawing.get_untracked() {
+                        set_is_pen_drawing.set(false);
+                        call_bridge("ecs_pen_end");
+                    }
+                    // Igual que el desplazamiento de la vista: si no, el siguiente hover seguiría desplazando
+                    if is_panning.get_untracked() {
+                        set_is_panning.set(false);
+                        pan_last_for_doc.set(None);
+                        let cursor = match tool_for_doc.get_untracked() {
+                            _ if space_held.get_untracked() => "grab",
+                            Tool::Hand => "grab",
+                            Tool::Pen => "crosshair",
+                            Tool::Text => "text",
+                            _ => "default",
+                        };
+                        set_cursor_state.set(cursor.to_string());
+                    }
                     // Intentar convertir a PointerEvent
                     if let Ok(pev) = ev.dyn_into::<PointerEvent>() {
                         if let Some((sx, sy)) = drag_start_for_doc.get() {
@@ -530,6 +994,10 @@ pub fn App() -> impl IntoView {
                             let dx = (ex - sx).abs();
                             let dy = (ey - sy).abs();
                             if dx > DRAG_THRESHOLD || dy > DRAG_THRESHOLD {
+                                let (sx, sy) = screen_to_world(sx, sy);
+                                let (ex, ey) = screen_to_world(ex, ey);
+                                let dx = (ex - sx).abs();
+                                let dy = (ey - sy).abs();
                                 let x = sx.min(ex);
                                 let y = sy.min(ey);
                                 let w = dx.max(1.0);
@@ -585,6 +1053,37 @@ pub fn App() -> impl IntoView {
                                             }
                                         }
                                     }
+                                    Tool::Arrow => {
+                                        let win = window();
+                                        let global: JsValue = win.into();
+                                        if let Ok(func_val) = Reflect::get(&global, &JsValue::from_str("ecs_create_arrow")) {
+                                            if let Ok(func) = func_val.dyn_into::<Function>() {
+                                                // Los extremos que caen sobre una forma se enganchan a ella en el ECS
+                                                console::log_1(&format!("DOC: ecs_create_arrow({}, {}, {}, {})", sx, sy, ex, ey).into());
+                                                let points = js_sys::Array::new();
+                                                for v in [sx, sy, ex, ey] {
+                                                    points.push(&JsValue::from_f64(v as f64));
+                                                }
+                                                let _ = func.call1(&JsValue::NULL, &points);
+                                            }
+                                        }
+                                    }
+                                    Tool::Connector => {
+                                        let win = window();
+                                        let global: JsValue = win.into();
+                                        if let Ok(func_val) = Reflect::get(&global, &JsValue::from_str("ecs_create_connector")) {
+                                            if let Ok(func) = func_val.dyn_into::<Function>() {
+                                                // El ECS traza los codos rodeando las demás formas
+                                                console::log_1(&format!("DOC: ecs_create_connector({}, {}, {}, {})", sx, sy, ex, ey).into());
+                                                let args = js_sys::Array::new();
+                                                for v in [sx, sy, ex, ey] {
+                                                    args.push(&JsValue::from_f64(v as f64));
+                                                }
+                                                let _ = func.apply(&JsValue::NULL, &args);
+                                            }
+                                        }
+                                    }
+                                    Tool::Pen | Tool::Text | Tool::Hand => {}
                                     Tool::Select => {
                                         // Finalizar movimiento si estaba activo
                                         if is_dragging_selection.get_untracked() {
@@ -608,6 +1107,107 @@ pub fn App() -> impl IntoView {
                 }) as Box<dyn FnMut(Event)>);
                 let _ = doc.add_event_listener_with_callback("pointerup", up_cb.as_ref().unchecked_ref());
                 up_cb.forget();
+
+                // Atajos de teclado: Ctrl/Cmd+Z deshacer, Ctrl/Cmd+Shift+Z o Ctrl/Cmd+Y rehacer,
+                // Supr/Retroceso borrar, Ctrl/Cmd+D duplicar, Ctrl/Cmd+]/[ subir/bajar (con Shift: al frente/al fondo),
+                // Espacio mantenido desplazar la vista, Shift+1 encuadrar todo, Shift+2 encuadrar selección, Ctrl/Cmd+0 zoom 100%,
+                // y una letra por herramienta, la de su pista en la barra (V, R, O o E, A, C, L, P, T, H)
+                let key_cb = Closure::wrap(Box::new(move |ev: Event| {
+                    let Ok(kev) = ev.dyn_into::<KeyboardEvent>() else { return; };
+                    if is_editing_text(&kev) { return; }
+                    if !(kev.ctrl_key() || kev.meta_key()) {
+                        match kev.code().as_str() {
+                            "Space" => {
+                                kev.prevent_default();
+                                if !space_held.get_untracked() {
+                                    set_space_held.set(true);
+                                    if !is_panning.get_untracked() { set_cursor_state.set("grab".to_string()); }
+                                }
+                            }
+                            "Digit1" if kev.shift_key() => {
+                                kev.prevent_default();
+                                call_bridge("ecs_zoom_to_fit");
+                            }
+                            "Digit2" if kev.shift_key() => {
+                                kev.prevent_default();
+                                call_bridge("ecs_zoom_to_selection");
+                            }
+                            _ if matches!(kev.key().as_str(), "Delete" | "Backspace") => {
+                                kev.prevent_default();
+                                call_bridge("ecs_delete_selection");
+                            }
+                            _ if !kev.shift_key() && !kev.alt_key() => {
+                                let shortcut = match kev.key().to_lowercase().as_str() {
+                                    "v" => Tool::Select,
+                                    "r" => Tool::Rect,
+                                    "o" | "e" => Tool::Ellipse,
+                                    "a" => Tool::Arrow,
+                                    "c" => Tool::Connector,
+                                    "l" => Tool::Line,
+                                    "p" => Tool::Pen,
+                                    "t" => Tool::Text,
+                                    "h" => Tool::Hand,
+                                    _ => return,
+                                };
+                                kev.prevent_default();
+                                set_tool.set(shortcut);
+                            }
+                            _ => {}
+                        }
+                        return;
+                    }
+                    if kev.code() == "Digit0" {
+                        kev.prevent_default();
+                        call_bridge("ecs_reset_camera");
+                        return;
+                    }
+                    // Los corchetes se leen por tecla física: con Shift `key()` cambia según el layout
+                    let z_order = match kev.code().as_str() {
+                        "BracketRight" if kev.shift_key() => Some(2),
+                        "BracketRight" => Some(0),
+                        "BracketLeft" if kev.shift_key() => Some(3),
+                        "BracketLeft" => Some(1),
+                        _ => None,
+                    };
+                    if let Some(op) = z_order {
+                        kev.prevent_default();
+                        call_bridge_with_args("ecs_z_order", &[JsValue::from_f64(op as f64)]);
+                        return;
+                    }
+                    let bridge_fn = match kev.key().to_lowercase().as_str() {
+                        "z" if kev.shift_key() => "ecs_redo",
+                        "z" => "ecs_undo",
+                        "y" => "ecs_redo",
+                        "d" => {
+                            kev.prevent_default();
+                            let offset = JsValue::from_f64(DUPLICATE_OFFSET);
+                            call_bridge_with_args("ecs_duplicate_selection", &[offset.clone(), offset]);
+                            return;
+                        }
+                        _ => return,
+                    };
+                    kev.prevent_default();
+                    call_bridge(bridge_fn);
+                }) as Box<dyn FnMut(Event)>);
+                let _ = doc.add_event_listener_with_callback("keydown", key_cb.as_ref().unchecked_ref());
+                key_cb.forget();
+
+                // Soltar espacio termina el modo de desplazamiento temporal
+                let key_up_cb = Closure::wrap(Box::new(move |ev: Event| {
+                    let Ok(kev) = ev.dyn_into::<KeyboardEvent>() else { return; };
+                    if kev.code() != "Space" { return; }
+                    set_space_held.set(false);
+                    if !is_panning.get_untracked() {
+                        match tool.get_untracked() {
+                            Tool::Hand => {}
+                            Tool::Pen => set_cursor_state.set("crosshair".to_string()),
+                            Tool::Text => set_cursor_state.set("text".to_string()),
+                            _ => set_cursor_state.set("default".to_string()),
+                        }
+                    }
+                }) as Box<dyn FnMut(Event)>);
+                let _ = doc.add_event_listener_with_callback("keyup", key_up_cb.as_ref().unchecked_ref());
+                key_up_cb.forget();
             }
         }
     });
@@ -653,6 +1253,22 @@ pub fn App() -> impl IntoView {
                     on:pointerdown=on_pointer_down
                     on:pointermove=on_pointer_move
                     on:pointerup=on_pointer_up
+                    on:dblclick=on_double_click
+                    on:wheel=on_wheel
+                />
+                
+                // Campo oculto de la edición de texto, sobre el cursor para que el IME abra ahí su ventana
+                <textarea
+                    id="text-input"
+                    class="text-input"
+                    style=move || match (is_text_editing.get(), text_caret.get()) {
+                        (true, Some((x, y, h))) => format!("left: {}px; top: {}px; height: {}px;", x, y, h),
+                        _ => "left: -9999px; top: 0px; height: 1px;".to_string(),
+                    }
+                    on:input=on_text_input
+                    on:compositionupdate=on_composition_update
+                    on:compositionend=on_composition_end
+                    on:keydown=on_text_keydown
                 />
                 
                 // Overlay de previsualización durante el arrastre
@@ -718,14 +1334,21 @@ pub fn App() -> impl IntoView {
                     />
                     <ToolbarButton 
                         icon=IconType::Ellipse
-                        tooltip="Elipse (O)"
+                        tooltip="Elipse (O o E)"
                         selected=Box::new(move || tool.get() == Tool::Ellipse)
                         on_click=Box::new(move || set_tool.set(Tool::Ellipse))
                     />
                     <ToolbarButton 
                         icon=IconType::Arrow
                         tooltip="Flecha (A)"
-                        disabled=true
+                        selected=Box::new(move || tool.get() == Tool::Arrow)
+                        on_click=Box::new(move || set_tool.set(Tool::Arrow))
+                    />
+                    <ToolbarButton 
+                        icon=IconType::Connector
+                        tooltip="Conector (C)"
+                        selected=Box::new(move || tool.get() == Tool::Connector)
+                        on_click=Box::new(move || set_tool.set(Tool::Connector))
                     />
                     <ToolbarButton 
                         icon=IconType::Line
@@ -736,12 +1359,14 @@ pub fn App() -> impl IntoView {
                     <ToolbarButton 
                         icon=IconType::Pen
                         tooltip="Lápiz (P)"
-                        disabled=true
+                        selected=Box::new(move || tool.get() == Tool::Pen)
+                        on_click=Box::new(move || set_tool.set(Tool::Pen))
                     />
                     <ToolbarButton 
                         icon=IconType::Text
                         tooltip="Texto (T)"
-                        disabled=true
+                        selected=Box::new(move || tool.get() == Tool::Text)
+                        on_click=Box::new(move || set_tool.set(Tool::Text))
                     />
                 </ToolbarGroup>
                 
@@ -755,8 +1380,9 @@ pub fn App() -> impl IntoView {
                     />
                     <ToolbarButton 
                         icon=IconType::Hand
-                        tooltip="Mano (H)"
-                        disabled=true
+                        tooltip="Mano (H, o Espacio + arrastrar)"
+                        selected=Box::new(move || tool.get() == Tool::Hand)
+                        on_click=Box::new(move || set_tool.set(Tool::Hand))
                     />
                 </ToolbarGroup>
                 
@@ -766,12 +1392,12 @@ pub fn App() -> impl IntoView {
                     <ToolbarButton 
                         icon=IconType::Undo
                         tooltip="Deshacer (Ctrl+Z)"
-                        disabled=true
+                        on_click=Box::new(move || call_bridge("ecs_undo"))
                     />
                     <ToolbarButton 
                         icon=IconType::Redo
                         tooltip="Rehacer (Ctrl+Y)"
-                        disabled=true
+                        on_click=Box::new(move || call_bridge("ecs_redo"))
                     />
                 </ToolbarGroup>
                 
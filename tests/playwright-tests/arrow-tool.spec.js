@@ -0,0 +1,80 @@
+const { test, expect } = require('@playwright/test');
+
+async function exportedDocument(page) {
+  return page.evaluate(() => JSON.parse(window.export_document_json()).document);
+}
+
+// Puntos de la flecha en mundo: sus puntos locales más la traslación de su transform
+function arrowWorldPoints(doc, id) {
+  const [, transform, , shape] = doc.entities.find(([eid]) => eid === id);
+  return shape.Arrow.arrow.points.map(([x, y]) => [x + transform.x, y + transform.y]);
+}
+
+test.describe('Arrow Tool', () => {
+  test.beforeEach(async ({ page }) => {
+    await page.goto('/hodei-draw/');
+    await page.waitForTimeout(2000);
+  });
+
+  async function createBoundArrow(page) {
+    await page.evaluate(() => {
+      window.ecs_create_rect(100, 200, 80, 60);
+      window.ecs_create_ellipse(400, 230, 40, 30);
+    });
+    await page.waitForTimeout(100);
+    await page.evaluate(() => window.ecs_create_arrow([140, 230, 400, 230]));
+    await page.waitForTimeout(200);
+    const order = await page.evaluate(() => window.ecs_get_entity_order());
+    return { rect: order[0], ellipse: order[1], arrow: order[2] };
+  }
+
+  test('should bind both ends to the shapes under them', async ({ page }) => {
+    const { rect, ellipse, arrow } = await createBoundArrow(page);
+    const doc = await exportedDocument(page);
+
+    const [, bindings] = doc.bindings.find(([id]) => id === arrow);
+    expect(bindings.start.target).toBe(rect);
+    expect(bindings.end.target).toBe(ellipse);
+
+    // Los extremos quedan en el borde de cada forma, separados un pequeño hueco
+    const [start, end] = arrowWorldPoints(doc, arrow);
+    expect(start[0]).toBeCloseTo(184, 0);
+    expect(end[0]).toBeCloseTo(356, 0);
+  });
+
+  test('should follow a bound shape when it moves and undo with it', async ({ page }) => {
+    const { arrow } = await createBoundArrow(page);
+    const before = arrowWorldPoints(await exportedDocument(page), arrow);
+
+    await page.evaluate(() => window.ecs_pointer_down(110, 210));
+    await page.waitForTimeout(100);
+    await page.evaluate(() => {
+      window.ecs_move_start(110, 210);
+      window.ecs_move_update(0, 100);
+      window.ecs_move_end();
+    });
+    await page.waitForTimeout(200);
+    const moved = arrowWorldPoints(await exportedDocument(page), arrow);
+    expect(moved[0][1]).toBeGreaterThan(before[0][1] + 30);
+
+    await page.evaluate(() => window.ecs_undo());
+    await page.waitForTimeout(200);
+    expect(arrowWorldPoints(await exportedDocument(page), arrow)).toEqual(before);
+  });
+
+  test('should change the heads of the selected arrow', async ({ page }) => {
+    const { arrow } = await createBoundArrow(page);
+    await page.evaluate(() => window.ecs_pointer_down(270, 230));
+    await page.waitForTimeout(100);
+    expect(await page.evaluate(() => window.ecs_set_arrow_heads('dot', 'open'))).toBe(true);
+
+    const doc = await exportedDocument(page);
+    const shape = doc.entities.find(([id]) => id === arrow)[3].Arrow.arrow;
+    expect([shape.start_head, shape.end_head]).toEqual(['Dot', 'Open']);
+
+    const error = await page.evaluate(() => {
+      try { window.ecs_set_arrow_heads('none', 'star'); return null; } catch (e) { return String(e.message || e); }
+    });
+    expect(error).toContain('Unknown arrow head');
+  });
+});
@@ -0,0 +1,66 @@
+const { test, expect } = require('@playwright/test');
+
+test.describe('Camera - Pan and Zoom', () => {
+  test.beforeEach(async ({ page }) => {
+    await page.goto('/hodei-draw/');
+    await page.waitForTimeout(2000);
+  });
+
+  test('should zoom around the cursor and keep picking consistent', async ({ page }) => {
+    await page.evaluate(() => window.ecs_create_rect(100, 100, 100, 100));
+    await page.waitForTimeout(100);
+
+    await page.evaluate(() => window.ecs_zoom_at(100, 100, 2));
+    const camera = await page.evaluate(() => window.ecs_get_camera());
+    expect(camera.zoom).toBeCloseTo(2);
+
+    // The point under the cursor stays fixed
+    const anchor = await page.evaluate(() => window.ecs_screen_to_world(100, 100));
+    expect(anchor[0]).toBeCloseTo(100);
+    expect(anchor[1]).toBeCloseTo(100);
+
+    // The rectangle now spans screen (100,100)-(300,300)
+    const inside = await page.evaluate(() => window.ecs_pick(280, 280));
+    expect(inside.entity).not.toBeNull();
+    const outside = await page.evaluate(() => window.ecs_pick(320, 320));
+    expect(outside.entity).toBeNull();
+  });
+
+  test('should pan the view and scale drag deltas by zoom', async ({ page }) => {
+    await page.evaluate(() => window.ecs_create_rect(100, 100, 100, 100));
+    await page.waitForTimeout(100);
+
+    await page.evaluate(() => window.ecs_pan_by(50, 0));
+    const panned = await page.evaluate(() => window.ecs_world_to_screen(100, 100));
+    expect(panned[0]).toBeCloseTo(150);
+    expect(panned[1]).toBeCloseTo(100);
+
+    // Handles follow the camera
+    await page.evaluate(() => window.ecs_pointer_down(200, 150));
+    await page.waitForTimeout(100);
+    const handle = await page.evaluate(() => window.ecs_detect_handle_hover(250, 200));
+    expect(handle).toBe(3);
+  });
+
+  test('should zoom to fit and to selection', async ({ page }) => {
+    await page.evaluate(() => window.ecs_create_rect(100, 100, 100, 100));
+    await page.waitForTimeout(100);
+    await page.evaluate(() => window.ecs_create_rect(1500, 1200, 100, 100));
+    await page.waitForTimeout(100);
+
+    const fitted = await page.evaluate(() => window.ecs_zoom_to_fit());
+    expect(fitted).toBe(true);
+    const fitCamera = await page.evaluate(() => window.ecs_get_camera());
+    expect(fitCamera.zoom).toBeLessThan(1);
+
+    // Nothing selected: zoom to selection is a no-op
+    expect(await page.evaluate(() => window.ecs_zoom_to_selection())).toBe(false);
+
+    await page.evaluate(() => window.ecs_reset_camera());
+    await page.evaluate(() => window.ecs_pointer_down(150, 150));
+    await page.waitForTimeout(100);
+    expect(await page.evaluate(() => window.ecs_zoom_to_selection())).toBe(true);
+    const selectionCamera = await page.evaluate(() => window.ecs_get_camera());
+    expect(selectionCamera.zoom).toBeGreaterThan(1);
+  });
+});
@@ -0,0 +1,81 @@
+const { test, expect } = require('@playwright/test');
+
+async function connectorPoints(page, id) {
+  const doc = await page.evaluate(() => JSON.parse(window.export_document_json()).document);
+  const [, transform, , shape] = doc.entities.find(([eid]) => eid === id);
+  return shape.Arrow.arrow.points.map(([x, y]) => [x + transform.x, y + transform.y]);
+}
+
+test.describe('Connector Routing', () => {
+  test.beforeEach(async ({ page }) => {
+    await page.goto('/hodei-draw/');
+    await page.waitForTimeout(2000);
+  });
+
+  // Dos cajas con un obstáculo alto entre ellas y un conector de centro a centro
+  async function createBlockedConnector(page) {
+    await page.evaluate(() => {
+      window.ecs_create_rect(100, 200, 80, 60);
+      window.ecs_create_rect(400, 200, 80, 60);
+      window.ecs_create_rect(250, 160, 60, 140);
+    });
+    await page.waitForTimeout(100);
+    await page.evaluate(() => window.ecs_create_connector(140, 230, 440, 230));
+    await page.waitForTimeout(200);
+    const order = await page.evaluate(() => window.ecs_get_entity_order());
+    return { obstacle: order[2], connector: order[3] };
+  }
+
+  test('should route orthogonally around the obstacle', async ({ page }) => {
+    const { connector } = await createBlockedConnector(page);
+    const points = await connectorPoints(page, connector);
+
+    expect(points.length).toBeGreaterThan(2);
+    // Solo tramos horizontales o verticales
+    for (let i = 1; i < points.length; i++) {
+      const [[x1, y1], [x2, y2]] = [points[i - 1], points[i]];
+      expect(Math.abs(x1 - x2) < 0.01 || Math.abs(y1 - y2) < 0.01).toBe(true);
+    }
+    // Ningún tramo horizontal cruza el obstáculo a su altura
+    const crossing = points.slice(1).some(([x2, y2], i) => {
+      const [x1, y1] = points[i];
+      return Math.abs(y1 - y2) < 0.01 && y1 > 160 && y1 < 300 && Math.min(x1, x2) < 310 && Math.max(x1, x2) > 250;
+    });
+    expect(crossing).toBe(false);
+  });
+
+  test('should straighten when the obstacle moves away and come back on undo', async ({ page }) => {
+    const { connector } = await createBlockedConnector(page);
+    const routed = await connectorPoints(page, connector);
+
+    await page.evaluate(() => window.ecs_pointer_down(280, 170));
+    await page.waitForTimeout(100);
+    await page.evaluate(() => {
+      window.ecs_move_start(280, 170);
+      window.ecs_move_update(0, 300);
+      window.ecs_move_end();
+    });
+    await page.waitForTimeout(200);
+    expect((await connectorPoints(page, connector)).length).toBe(2);
+
+    await page.evaluate(() => window.ecs_undo());
+    await page.waitForTimeout(200);
+    expect(await connectorPoints(page, connector)).toEqual(routed);
+  });
+
+  test('should switch the selected arrow between straight and elbow', async ({ page }) => {
+    await page.evaluate(() => window.ecs_create_arrow([100, 100, 300, 200]));
+    await page.waitForTimeout(200);
+    await page.evaluate(() => window.ecs_pointer_down(200, 150));
+    await page.waitForTimeout(100);
+
+    expect(await page.evaluate(() => window.ecs_set_arrow_routing('elbow'))).toBe(true);
+    const [id] = await page.evaluate(() => window.ecs_get_entity_order());
+    expect((await connectorPoints(page, id)).length).toBeGreaterThan(2);
+
+    const error = await page.evaluate(() => {
+      try { window.ecs_set_arrow_routing('curved'); return null; } catch (e) { return String(e.message || e); }
+    });
+    expect(error).toContain('Unknown arrow routing');
+  });
+});
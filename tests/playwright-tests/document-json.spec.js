@@ -0,0 +1,74 @@
+const { test, expect } = require('@playwright/test');
+
+test.describe('Document JSON - Versioned Export and Import', () => {
+  test.beforeEach(async ({ page }) => {
+    await page.goto('/hodei-draw/');
+    await page.waitForTimeout(2000);
+  });
+
+  test('should export a header with schema and version', async ({ page }) => {
+    await page.evaluate(() => window.ecs_create_rect(100, 100, 120, 80));
+    await page.waitForTimeout(100);
+
+    const parsed = await page.evaluate(() => JSON.parse(window.export_document_json()));
+    expect(parsed.schema).toBe('hodei-momentum/document');
+    expect(parsed.version).toBe(3);
+    expect(parsed.document.entities.length).toBe(1);
+  });
+
+  test('should round-trip every shape kind through import', async ({ page }) => {
+    await page.evaluate(() => {
+      window.ecs_create_rect(100, 100, 120, 80);
+      window.ecs_create_ellipse(400, 200, 60, 40);
+      window.ecs_create_line(50, 400, 300, 450);
+    });
+    await page.waitForTimeout(200);
+
+    const exported = await page.evaluate(() => window.export_document_json());
+    const order = await page.evaluate(() => window.ecs_get_entity_order());
+
+    // Vaciar el documento y volver a importar
+    await page.evaluate(() => {
+      window.import_document_json(JSON.stringify({
+        schema: 'hodei-momentum/document',
+        version: 1,
+        document: { entities: [] },
+      }));
+    });
+    await page.waitForTimeout(100);
+    expect(await page.evaluate(() => window.ecs_get_entity_order())).toEqual([]);
+
+    await page.evaluate((json) => window.import_document_json(json), exported);
+    await page.waitForTimeout(100);
+
+    expect(await page.evaluate(() => window.ecs_get_entity_order())).toEqual(order);
+    const reexported = await page.evaluate(() => window.export_document_json());
+    expect(JSON.parse(reexported)).toEqual(JSON.parse(exported));
+  });
+
+  test('should reject invalid JSON without touching the document', async ({ page }) => {
+    await page.evaluate(() => window.ecs_create_rect(100, 100, 120, 80));
+    await page.waitForTimeout(100);
+    const before = await page.evaluate(() => window.ecs_get_entity_order());
+
+    const error = await page.evaluate(() => {
+      try { window.import_document_json('{ not json'); return null; } catch (e) { return String(e.message || e); }
+    });
+    expect(error).not.toBeNull();
+    expect(await page.evaluate(() => window.ecs_get_entity_order())).toEqual(before);
+  });
+
+  test('should reject a document from a newer format version', async ({ page }) => {
+    const error = await page.evaluate(() => {
+      try {
+        window.import_document_json(JSON.stringify({
+          schema: 'hodei-momentum/document',
+          version: 99,
+          document: { entities: [] },
+        }));
+        return null;
+      } catch (e) { return String(e.message || e); }
+    });
+    expect(error).toContain('99');
+  });
+});
@@ -0,0 +1,70 @@
+const { test, expect } = require('@playwright/test');
+
+test.describe('Edit Operations - Delete, Duplicate and Z-order', () => {
+  test.beforeEach(async ({ page }) => {
+    await page.goto('/hodei-draw/');
+    await page.waitForTimeout(2000);
+  });
+
+  async function createThreeRects(page) {
+    for (const x of [100, 250, 400]) {
+      await page.evaluate((x) => window.ecs_create_rect(x, 100, 100, 100), x);
+      await page.waitForTimeout(100);
+    }
+    return page.evaluate(() => window.ecs_get_entity_order());
+  }
+
+  test('should delete the selection and restore it with undo', async ({ page }) => {
+    const initial = await createThreeRects(page);
+    await page.evaluate(() => window.ecs_pointer_down(300, 150));
+    await page.waitForTimeout(100);
+
+    await page.evaluate(() => window.ecs_delete_selection());
+    await page.waitForTimeout(100);
+    const afterDelete = await page.evaluate(() => window.ecs_get_entity_order());
+    expect(afterDelete).toEqual([initial[0], initial[2]]);
+
+    await page.evaluate(() => window.ecs_undo());
+    await page.waitForTimeout(100);
+    const afterUndo = await page.evaluate(() => window.ecs_get_entity_order());
+    expect(afterUndo).toEqual(initial);
+  });
+
+  test('should duplicate the selection with an offset and select the copy', async ({ page }) => {
+    const initial = await createThreeRects(page);
+    await page.evaluate(() => window.ecs_pointer_down(150, 150));
+    await page.waitForTimeout(100);
+
+    await page.evaluate(() => window.ecs_duplicate_selection(10, 10));
+    await page.waitForTimeout(100);
+    const order = await page.evaluate(() => window.ecs_get_entity_order());
+    expect(order.length).toBe(initial.length + 1);
+
+    // The copy is on top and selected, so its handles follow the offset
+    const handle = await page.evaluate(() => window.ecs_detect_handle_hover(210, 210));
+    expect(handle).toBe(3);
+  });
+
+  test('should change stacking order and undo it', async ({ page }) => {
+    const [a, b, c] = await createThreeRects(page);
+    await page.evaluate(() => window.ecs_pointer_down(150, 150));
+    await page.waitForTimeout(100);
+
+    // 0 = forward, 1 = backward, 2 = to front, 3 = to back
+    await page.evaluate(() => window.ecs_z_order(0));
+    await page.waitForTimeout(100);
+    expect(await page.evaluate(() => window.ecs_get_entity_order())).toEqual([b, a, c]);
+
+    await page.evaluate(() => window.ecs_z_order(2));
+    await page.waitForTimeout(100);
+    expect(await page.evaluate(() => window.ecs_get_entity_order())).toEqual([b, c, a]);
+
+    await page.evaluate(() => window.ecs_z_order(3));
+    await page.waitForTimeout(100);
+    expect(await page.evaluate(() => window.ecs_get_entity_order())).toEqual([a, b, c]);
+
+    await page.evaluate(() => window.ecs_undo());
+    await page.waitForTimeout(100);
+    expect(await page.evaluate(() => window.ecs_get_entity_order())).toEqual([b, c, a]);
+  });
+});
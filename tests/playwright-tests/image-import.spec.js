@@ -0,0 +1,77 @@
+const { test, expect } = require('@playwright/test');
+
+// PNG de w x h px de un solo color, codificado por el navegador
+async function solidPng(page, w, h, color) {
+  return page.evaluate(async ({ w, h, color }) => {
+    const canvas = document.createElement('canvas');
+    canvas.width = w;
+    canvas.height = h;
+    const ctx = canvas.getContext('2d');
+    ctx.fillStyle = color;
+    ctx.fillRect(0, 0, w, h);
+    const blob = await new Promise((resolve) => canvas.toBlob(resolve, 'image/png'));
+    return Array.from(new Uint8Array(await blob.arrayBuffer()));
+  }, { w, h, color });
+}
+
+async function importImage(page, bytes, x, y) {
+  const id = await page.evaluate(({ bytes, x, y }) => window.import_image(new Uint8Array(bytes), x, y), { bytes, x, y });
+  await page.waitForTimeout(200);
+  return id;
+}
+
+test.describe('Image Import', () => {
+  test.beforeEach(async ({ page }) => {
+    await page.goto('/hodei-draw/');
+    await page.waitForTimeout(2000);
+  });
+
+  test('should import a PNG as a selected image entity at its pixel size', async ({ page }) => {
+    const png = await solidPng(page, 120, 80, '#ff0000');
+    const id = await importImage(page, png, 300, 250);
+
+    expect(await page.evaluate(() => window.ecs_get_entity_order())).toEqual([id]);
+    const doc = await page.evaluate(() => JSON.parse(window.export_document_json()).document);
+    // Entidad serializada como [id, transform, style, shape]
+    const { w, h } = doc.entities[0][3].Image;
+    expect([w, h]).toEqual([120, 80]);
+
+    // Se puede seleccionar por cualquier punto de su superficie
+    expect(await page.evaluate(() => window.ecs_detect_shape_hover(340, 270))).toBe(true);
+    expect(await page.evaluate(() => window.ecs_detect_shape_hover(300, 300))).toBe(false);
+
+    // Pintada desde la textura: el centro es rojo
+    const pixel = await page.evaluate(() => {
+      const canvas = document.querySelector('canvas');
+      const dpr = canvas.width / canvas.clientWidth;
+      return Array.from(canvas.getContext('2d').getImageData(300 * dpr, 250 * dpr, 1, 1).data);
+    });
+    expect(pixel).toEqual([255, 0, 0, 255]);
+  });
+
+  test('should embed the image when exporting SVG', async ({ page }) => {
+    const png = await solidPng(page, 10, 10, '#00ff00');
+    await importImage(page, png, 200, 200);
+
+    const svg = await page.evaluate(() => window.export_svg(false, 0));
+    expect(svg).toContain('<image');
+    expect(svg).toContain('data:image/png;base64,');
+  });
+
+  test('should reject bytes that are not an image', async ({ page }) => {
+    const error = await page.evaluate(() => {
+      try { window.import_image(new TextEncoder().encode('not an image'), 100, 100); return null; } catch (e) { return String(e.message || e); }
+    });
+    expect(error).toContain('Unsupported image format');
+    expect(await page.evaluate(() => window.ecs_get_entity_order())).toEqual([]);
+  });
+
+  test('should undo the import in one step', async ({ page }) => {
+    const png = await solidPng(page, 20, 20, '#0000ff');
+    await importImage(page, png, 200, 200);
+
+    await page.evaluate(() => window.ecs_undo());
+    await page.waitForTimeout(100);
+    expect(await page.evaluate(() => window.ecs_get_entity_order())).toEqual([]);
+  });
+});
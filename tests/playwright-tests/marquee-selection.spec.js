@@ -0,0 +1,74 @@
+const { test, expect } = require('@playwright/test');
+
+test.describe('Marquee Selection - Rubber-band Box Selection', () => {
+  test.beforeEach(async ({ page }) => {
+    await page.goto('/hodei-draw/');
+    await page.waitForTimeout(2000);
+  });
+
+  async function createShapes(page) {
+    await page.evaluate(() => {
+      window.ecs_create_rect(100, 100, 50, 50);
+    });
+    await page.waitForTimeout(100);
+    await page.evaluate(() => {
+      window.ecs_create_rect(200, 100, 50, 50);
+    });
+    await page.waitForTimeout(100);
+  }
+
+  test('should select only fully contained shapes by default', async ({ page }) => {
+    await createShapes(page);
+
+    await page.evaluate(() => {
+      window.ecs_marquee_start(50, 50, false, 0);
+      window.ecs_marquee_update(170, 150);
+      window.ecs_marquee_end();
+    });
+    await page.waitForTimeout(100);
+
+    // Only the first rectangle is inside: its handles appear, the second one's don't
+    expect(await page.evaluate(() => window.ecs_detect_handle_hover(150, 150))).toBe(3);
+    expect(await page.evaluate(() => window.ecs_detect_handle_hover(250, 150))).toBeNull();
+  });
+
+  test('should select touching shapes in intersect mode and add with shift', async ({ page }) => {
+    await createShapes(page);
+
+    // Intersect mode: touching the second rectangle is enough
+    await page.evaluate(() => {
+      window.ecs_marquee_start(300, 300, false, 1);
+      window.ecs_marquee_update(-80, -180);
+      window.ecs_marquee_end();
+    });
+    await page.waitForTimeout(100);
+    expect(await page.evaluate(() => window.ecs_detect_handle_hover(250, 150))).toBe(3);
+
+    // Additive contain marquee around the first rectangle keeps the second selected
+    await page.evaluate(() => {
+      window.ecs_marquee_start(60, 60, true, 0);
+      window.ecs_marquee_update(120, 120);
+      window.ecs_marquee_end();
+    });
+    await page.waitForTimeout(100);
+
+    // Group selection: bounds span both rectangles (100..250 x 100..150)
+    expect(await page.evaluate(() => window.ecs_detect_handle_hover(100, 100))).toBe(0);
+    expect(await page.evaluate(() => window.ecs_detect_handle_hover(250, 150))).toBe(3);
+  });
+
+  test('should draw a marquee when dragging on empty canvas', async ({ page }) => {
+    await createShapes(page);
+    const canvas = page.locator('canvas');
+
+    await canvas.hover({ position: { x: 60, y: 60 } });
+    await page.mouse.down();
+    await page.mouse.move(300, 200, { steps: 5 });
+    await page.mouse.up();
+    await page.waitForTimeout(200);
+
+    // Both rectangles are inside the dragged area
+    expect(await page.evaluate(() => window.ecs_detect_handle_hover(100, 100))).toBe(0);
+    expect(await page.evaluate(() => window.ecs_detect_handle_hover(250, 150))).toBe(3);
+  });
+});
@@ -0,0 +1,70 @@
+const { test, expect } = require('@playwright/test');
+
+async function entityStyle(page, index) {
+  const doc = await page.evaluate(() => JSON.parse(window.export_document_json()).document);
+  return doc.entities[index][2];
+}
+
+const GRADIENT = {
+  LinearGradient: {
+    start: [0, 0],
+    end: [120, 0],
+    stops: [
+      { offset: 0, color: [1, 0, 0, 1] },
+      { offset: 1, color: [0, 0, 1, 1] },
+    ],
+  },
+};
+
+test.describe('Paint Fills', () => {
+  test.beforeEach(async ({ page }) => {
+    await page.goto('/hodei-draw/');
+    await page.waitForTimeout(2000);
+    await page.evaluate(() => window.ecs_create_rect(100, 100, 120, 80));
+    await page.waitForTimeout(200);
+    await page.evaluate(() => window.ecs_pointer_down(150, 150));
+    await page.waitForTimeout(100);
+  });
+
+  test('should fill the selection with a gradient and export it as an SVG gradient', async ({ page }) => {
+    expect(await page.evaluate((paint) => window.ecs_set_paint('fill', JSON.stringify(paint)), GRADIENT)).toBe(true);
+    // Repetirlo no cambia nada
+    expect(await page.evaluate((paint) => window.ecs_set_paint('fill', JSON.stringify(paint)), GRADIENT)).toBe(false);
+
+    expect((await entityStyle(page, 0)).fill).toEqual(GRADIENT);
+
+    const svg = await page.evaluate(() => window.export_svg(false, 0));
+    expect(svg).toContain('<linearGradient id="paint-1" gradientUnits="userSpaceOnUse"');
+    expect(svg).toContain('fill="url(#paint-1)"');
+  });
+
+  test('should keep solid colours as plain arrays and load them back', async ({ page }) => {
+    expect(await page.evaluate(() => window.ecs_set_paint('fill', '[0, 0.5, 0, 1]'))).toBe(true);
+    const json = await page.evaluate(() => window.export_document_json());
+    expect(JSON.parse(json).document.entities[0][2].fill).toEqual([0, 0.5, 0, 1]);
+
+    await page.evaluate((json) => window.import_document_json(json), json);
+    await page.waitForTimeout(100);
+    expect(await page.evaluate(() => window.export_svg(false, 0))).toContain('fill="#008000"');
+  });
+
+  test('should export hatch fills as straight lines under the outline', async ({ page }) => {
+    const hatch = { Hatch: { color: [0, 0, 0, 1], gap: 8, angle: 0.5, width: 1, cross: true } };
+    await page.evaluate((paint) => window.ecs_set_paint('fill', JSON.stringify(paint)), hatch);
+    await page.waitForTimeout(100);
+
+    const svg = await page.evaluate(() => window.export_svg(false, 0));
+    expect(svg).not.toContain('<rect');
+    expect(svg).toMatch(/<path d="M[^"]*L[^"]*"[^>]*stroke="#000000"/);
+
+    // Deshacer vuelve al relleno anterior
+    await page.evaluate(() => window.ecs_undo());
+    await page.waitForTimeout(100);
+    expect((await entityStyle(page, 0)).fill).toBeNull();
+  });
+
+  test('should reject invalid paints and targets', async ({ page }) => {
+    await expect(page.evaluate(() => window.ecs_set_paint('fill', '{"Plaid": {}}'))).rejects.toThrow('Invalid paint');
+    await expect(page.evaluate(() => window.ecs_set_paint('border', 'null'))).rejects.toThrow('Unknown paint target');
+  });
+});
@@ -0,0 +1,54 @@
+const { test, expect } = require('@playwright/test');
+
+// Muestras de una onda en CSS px con presión creciente
+async function drawWave(page) {
+  await page.evaluate(() => {
+    window.ecs_pen_start(100, 300, 0.2);
+    for (let i = 1; i <= 60; i++) {
+      const t = i / 60;
+      window.ecs_pen_move(100 + 400 * t, 300 + 60 * Math.sin(t * 2 * Math.PI), 0.2 + 0.6 * t);
+    }
+    window.ecs_pen_end();
+  });
+  await page.waitForTimeout(100);
+}
+
+test.describe('Pen Tool', () => {
+  test.beforeEach(async ({ page }) => {
+    await page.goto('/hodei-draw/');
+    await page.waitForTimeout(2000);
+  });
+
+  test('should turn a pen stroke into a single entity', async ({ page }) => {
+    await drawWave(page);
+
+    const order = await page.evaluate(() => window.ecs_get_entity_order());
+    expect(order.length).toBe(1);
+
+    // Hit test sobre la tinta (inicio del trazo) y fuera de ella
+    expect(await page.evaluate(() => window.ecs_detect_shape_hover(100, 300))).toBe(true);
+    expect(await page.evaluate(() => window.ecs_detect_shape_hover(300, 200))).toBe(false);
+  });
+
+  test('should store the smoothed stroke as a freehand shape', async ({ page }) => {
+    await drawWave(page);
+
+    const parsed = await page.evaluate(() => JSON.parse(window.export_document_json()));
+    // Entidad serializada como [id, transform, style, shape]
+    const shape = parsed.document.entities[0][3];
+    expect(shape.Freehand).toBeDefined();
+    // La simplificación deja menos nodos que muestras, con un grosor por nodo
+    const { path, widths } = shape.Freehand.stroke;
+    expect(path.commands.length).toBeLessThan(61);
+    expect(widths.length).toBe(path.commands.length);
+    expect(widths[widths.length - 1]).toBeGreaterThan(widths[0]);
+  });
+
+  test('should undo a stroke in one step', async ({ page }) => {
+    await drawWave(page);
+    await page.evaluate(() => window.ecs_undo());
+    await page.waitForTimeout(100);
+
+    expect(await page.evaluate(() => window.ecs_get_entity_order())).toEqual([]);
+  });
+});
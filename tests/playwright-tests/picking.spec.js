@@ -0,0 +1,56 @@
+const { test, expect } = require('@playwright/test');
+
+test.describe('Picking - Topmost-first Hit Testing', () => {
+  test.beforeEach(async ({ page }) => {
+    await page.goto('/hodei-draw/');
+    await page.waitForTimeout(2000);
+  });
+
+  test('should pick the topmost of overlapping shapes', async ({ page }) => {
+    await page.evaluate(() => {
+      window.ecs_create_rect(100, 100, 100, 100);
+    });
+    await page.waitForTimeout(100);
+    await page.evaluate(() => {
+      window.ecs_create_rect(150, 150, 100, 100);
+    });
+    await page.waitForTimeout(100);
+
+    const overlap = await page.evaluate(() => window.ecs_pick(175, 175));
+    expect(overlap.handle).toBeNull();
+    expect(overlap.hits.length).toBe(2);
+    // The second rectangle is drawn last, so it is on top
+    expect(overlap.entity).toBe(overlap.hits[0]);
+
+    const onlyBottom = await page.evaluate(() => window.ecs_pick(120, 120));
+    expect(onlyBottom.hits.length).toBe(1);
+    expect(onlyBottom.entity).toBe(overlap.hits[1]);
+
+    const empty = await page.evaluate(() => window.ecs_pick(400, 400));
+    expect(empty.entity).toBeNull();
+    expect(empty.hits).toEqual([]);
+  });
+
+  test('should select the topmost shape and report handles first', async ({ page }) => {
+    await page.evaluate(() => {
+      window.ecs_create_rect(100, 100, 100, 100);
+    });
+    await page.waitForTimeout(100);
+    await page.evaluate(() => {
+      window.ecs_create_rect(150, 150, 100, 100);
+    });
+    await page.waitForTimeout(100);
+
+    const down = await page.evaluate(() => {
+      const result = window.ecs_pointer_down_with_modifiers(175, 175, false, false);
+      return { handle: result.clicked_handle_type, selected: result.entity_selected };
+    });
+    expect(down.handle).toBeUndefined();
+    expect(down.selected).toBe(true);
+    await page.waitForTimeout(100);
+
+    // Bottom-right handle of the selected (top) rectangle
+    const onHandle = await page.evaluate(() => window.ecs_pick(250, 250));
+    expect(onHandle.handle).toBe(3);
+  });
+});
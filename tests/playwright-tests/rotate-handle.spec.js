@@ -0,0 +1,59 @@
+const { test, expect } = require('@playwright/test');
+
+test.describe('Rotate Handle - Rotation Interaction Testing', () => {
+  test.beforeEach(async ({ page }) => {
+    await page.goto('/hodei-draw/');
+    await page.waitForTimeout(2000);
+  });
+
+  test('should expose rotate handle above the selection', async ({ page }) => {
+    // Create and select rectangle
+    await page.evaluate(() => {
+      window.ecs_create_rect(200, 200, 150, 100);
+    });
+    await page.waitForTimeout(100);
+    await page.evaluate(() => {
+      window.ecs_pointer_down(275, 250);
+    });
+    await page.waitForTimeout(100);
+
+    // HandleType::Rotate = 8, 25px above top-center
+    const result = await page.evaluate(() => window.ecs_detect_handle_hover(275, 175));
+    expect(result).toBe(8);
+  });
+
+  test('should rotate selection and keep handles on the rotated shape', async ({ page }) => {
+    await page.evaluate(() => {
+      window.ecs_create_rect(200, 200, 150, 100);
+    });
+    await page.waitForTimeout(100);
+    await page.evaluate(() => {
+      window.ecs_pointer_down(275, 250);
+    });
+    await page.waitForTimeout(100);
+
+    // Rotate 90° clockwise around the center (275, 250) with shift snapping
+    const rotateSequence = await page.evaluate(() => {
+      try {
+        window.ecs_rotate_start(275, 175);
+        window.ecs_rotate_update(80, 70, true);
+        window.ecs_rotate_end();
+        return { success: true, error: null };
+      } catch (error) {
+        return { success: false, error: error.message };
+      }
+    });
+    expect(rotateSequence.success).toBe(true);
+    await page.waitForTimeout(200);
+
+    // After 90°, the former top-left corner (200, 200) lands at (325, 175)
+    const corner = await page.evaluate(() => window.ecs_detect_handle_hover(325, 175));
+    expect(corner).toBe(0);
+
+    // The rotated shape is hit where it is drawn, not where it was
+    const insideRotated = await page.evaluate(() => window.ecs_detect_shape_hover(275, 190));
+    expect(insideRotated).toBe(true);
+    const outsideRotated = await page.evaluate(() => window.ecs_detect_shape_hover(210, 250));
+    expect(outsideRotated).toBe(false);
+  });
+});
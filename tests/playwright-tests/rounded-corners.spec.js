@@ -0,0 +1,55 @@
+const { test, expect } = require('@playwright/test');
+
+async function entityShape(page, index) {
+  const doc = await page.evaluate(() => JSON.parse(window.export_document_json()).document);
+  return doc.entities[index][3];
+}
+
+test.describe('Rounded Corners', () => {
+  test.beforeEach(async ({ page }) => {
+    await page.goto('/hodei-draw/');
+    await page.waitForTimeout(2000);
+    await page.evaluate(() => window.ecs_create_rect(100, 100, 120, 80));
+    await page.waitForTimeout(200);
+    await page.evaluate(() => window.ecs_pointer_down(150, 150));
+    await page.waitForTimeout(100);
+  });
+
+  test('should round every corner and export a rounded rect', async ({ page }) => {
+    expect(await page.evaluate(() => window.ecs_set_corner_radii('16'))).toBe(true);
+    // Repetirlo no cambia nada
+    expect(await page.evaluate(() => window.ecs_set_corner_radii('16'))).toBe(false);
+
+    expect((await entityShape(page, 0)).Rect.radii).toBe(16);
+    const svg = await page.evaluate(() => window.export_svg(false, 0));
+    expect(svg).toContain('rx="16" ry="16"');
+  });
+
+  test('should round corners one by one and undo in one step', async ({ page }) => {
+    expect(await page.evaluate(() => window.ecs_set_corner_radii('[0, 20, 0, 8]'))).toBe(true);
+    expect((await entityShape(page, 0)).Rect.radii).toEqual([0, 20, 0, 8]);
+
+    // Radios distintos no caben en un <rect>: se exporta el contorno
+    const svg = await page.evaluate(() => window.export_svg(false, 0));
+    expect(svg).not.toContain('<rect');
+    expect(svg).toMatch(/<path d="M[^"]*C[^"]*Z"/);
+
+    await page.evaluate(() => window.ecs_undo());
+    await page.waitForTimeout(100);
+    expect((await entityShape(page, 0)).Rect.radii).toBe(0);
+  });
+
+  test('should not pick through a rounded-off corner', async ({ page }) => {
+    expect((await page.evaluate(() => window.ecs_pick(104, 104))).hits.length).toBe(1);
+    await page.evaluate(() => window.ecs_set_corner_radii('40'));
+    await page.waitForTimeout(100);
+
+    // La esquina superior izquierda ya no es parte del rectángulo; su interior, sí
+    expect((await page.evaluate(() => window.ecs_pick(104, 104))).hits).toEqual([]);
+    expect((await page.evaluate(() => window.ecs_pick(160, 140))).hits.length).toBe(1);
+  });
+
+  test('should reject invalid radii', async ({ page }) => {
+    await expect(page.evaluate(() => window.ecs_set_corner_radii('"round"'))).rejects.toThrow('Invalid corner radii');
+  });
+});
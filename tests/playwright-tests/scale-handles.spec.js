@@ -172,6 +172,40 @@ test.describe('Scale Handles - Complete Interaction Testing', () => {
     }
   });
 
+  test('should anchor scaling at the opposite handle and allow flipping', async ({ page }) => {
+    await page.evaluate(() => {
+      window.ecs_create_rect(200, 200, 150, 100);
+    });
+    await page.waitForTimeout(100);
+    await page.evaluate(() => {
+      window.ecs_pointer_down(275, 250);
+    });
+    await page.waitForTimeout(100);
+
+    // Drag TopLeft outwards: BottomRight (350, 300) must stay in place
+    await page.evaluate(() => {
+      window.ecs_scale_start(0, 200, 200);
+      window.ecs_scale_update(-30, -20);
+      window.ecs_scale_end();
+    });
+    await page.waitForTimeout(100);
+
+    expect(await page.evaluate(() => window.ecs_detect_handle_hover(170, 180))).toBe(0);
+    expect(await page.evaluate(() => window.ecs_detect_handle_hover(350, 300))).toBe(3);
+
+    // Drag Right past the anchor (left edge at 170) with shift: flips horizontally, keeps aspect
+    await page.evaluate(() => {
+      window.ecs_scale_start(5, 350, 240);
+      window.ecs_scale_update_with_modifiers(-270, 0, true, false);
+      window.ecs_scale_end();
+    });
+    await page.waitForTimeout(100);
+
+    // New box spans x 80..170 (width 90 = 0.5 * 180), height 0.5 * 120 = 60 around y 240
+    expect(await page.evaluate(() => window.ecs_detect_shape_hover(125, 240))).toBe(true);
+    expect(await page.evaluate(() => window.ecs_detect_shape_hover(250, 240))).toBe(false);
+  });
+
   test('should verify no conflicts between scaling and moving', async ({ page }) => {
     // Create and select rectangle
     await page.evaluate(() => {
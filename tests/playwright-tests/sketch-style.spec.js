@@ -0,0 +1,64 @@
+const { test, expect } = require('@playwright/test');
+
+async function entityStyle(page, index) {
+  const doc = await page.evaluate(() => JSON.parse(window.export_document_json()).document);
+  return doc.entities[index][2];
+}
+
+test.describe('Sketch Style', () => {
+  test.beforeEach(async ({ page }) => {
+    await page.goto('/hodei-draw/');
+    await page.waitForTimeout(2000);
+  });
+
+  test('should sketch the selection with a stable per-entity seed', async ({ page }) => {
+    await page.evaluate(() => {
+      window.ecs_create_rect(100, 100, 120, 80);
+      window.ecs_create_rect(300, 100, 120, 80);
+    });
+    await page.waitForTimeout(200);
+    await page.evaluate(() => window.ecs_pointer_down(150, 150));
+    await page.waitForTimeout(100);
+
+    expect(await page.evaluate(() => window.ecs_set_sketch(1, 'hachure'))).toBe(true);
+    // Repetirlo no cambia nada
+    expect(await page.evaluate(() => window.ecs_set_sketch(1, 'hachure'))).toBe(false);
+
+    const style = await entityStyle(page, 0);
+    expect(style.sketch.roughness).toBe(1);
+    expect(style.sketch.fill).toBe('Hachure');
+    expect((await entityStyle(page, 1)).sketch).toBeNull();
+
+    // Cambiar la rugosidad conserva la semilla
+    await page.evaluate(() => window.ecs_set_sketch(2, 'cross-hatch'));
+    expect((await entityStyle(page, 0)).sketch.seed).toBe(style.sketch.seed);
+
+    await expect(page.evaluate(() => window.ecs_set_sketch(1, 'zigzag'))).rejects.toThrow('Unknown sketch fill');
+  });
+
+  test('should export the same sketchy paths every time, across undo and reimport', async ({ page }) => {
+    await page.evaluate(() => window.ecs_create_rect(100, 100, 120, 80));
+    await page.waitForTimeout(200);
+    await page.evaluate(() => window.ecs_pointer_down(150, 150));
+    await page.waitForTimeout(100);
+    await page.evaluate(() => window.ecs_set_sketch(1, 'hachure'));
+    await page.waitForTimeout(100);
+
+    const first = await page.evaluate(() => window.export_svg(false, 0));
+    await page.waitForTimeout(100);
+    const second = await page.evaluate(() => window.export_svg(false, 0));
+    expect(first).toBe(second);
+    expect(first).not.toContain('<rect');
+    expect(first).toContain('<path');
+
+    // La semilla viaja con el documento: al reimportarlo se exporta igual
+    const json = await page.evaluate(() => window.export_document_json());
+    await page.evaluate(() => window.ecs_undo());
+    await page.waitForTimeout(100);
+    expect(await page.evaluate(() => window.export_svg(false, 0))).toContain('<rect');
+
+    await page.evaluate((json) => window.import_document_json(json), json);
+    await page.waitForTimeout(100);
+    expect(await page.evaluate(() => window.export_svg(false, 0))).toBe(first);
+  });
+});
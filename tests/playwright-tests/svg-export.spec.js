@@ -0,0 +1,45 @@
+const { test, expect } = require('@playwright/test');
+
+test.describe('SVG Export', () => {
+  test.beforeEach(async ({ page }) => {
+    await page.goto('/hodei-draw/');
+    await page.waitForTimeout(2000);
+  });
+
+  test('should export every shape with a viewBox fitted to the content', async ({ page }) => {
+    await page.evaluate(() => {
+      window.ecs_create_rect(100, 100, 120, 80);
+      window.ecs_create_ellipse(400, 200, 60, 40);
+      window.ecs_create_line(50, 400, 300, 450);
+    });
+    await page.waitForTimeout(200);
+
+    const svg = await page.evaluate(() => window.export_svg(false, 0));
+    expect(svg).toContain('<rect');
+    expect(svg).toContain('<ellipse');
+    expect(svg).toContain('<line');
+
+    const viewBox = await page.evaluate((svg) => {
+      const doc = new DOMParser().parseFromString(svg, 'image/svg+xml');
+      return doc.documentElement.getAttribute('viewBox').split(' ').map(Number);
+    }, svg);
+    const [x, y] = viewBox;
+    expect(x).toBeLessThanOrEqual(100);
+    expect(y).toBeLessThanOrEqual(100);
+    expect(x).toBeGreaterThan(40);
+  });
+
+  test('should export only the selection when requested', async ({ page }) => {
+    await page.evaluate(() => {
+      window.ecs_create_rect(100, 100, 120, 80);
+      window.ecs_create_ellipse(400, 200, 60, 40);
+    });
+    await page.waitForTimeout(200);
+    await page.evaluate(() => window.ecs_pointer_down(150, 150));
+    await page.waitForTimeout(100);
+
+    const svg = await page.evaluate(() => window.export_svg(true, 10));
+    expect(svg).toContain('<rect');
+    expect(svg).not.toContain('<ellipse');
+  });
+});
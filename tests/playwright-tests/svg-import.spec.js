@@ -0,0 +1,48 @@
+const { test, expect } = require('@playwright/test');
+
+const SVG = `<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 300 200">
+  <rect x="10" y="10" width="80" height="50" fill="#3366cc" stroke="black"/>
+  <g transform="translate(150 40) rotate(30)"><circle r="25" fill="orange"/></g>
+  <line x1="10" y1="190" x2="290" y2="150" stroke="purple"/>
+  <polygon points="200,100 260,120 230,170" fill="green"/>
+  <path d="M20 120 C 40 80, 80 80, 100 120 S 160 160, 180 120" fill="none" stroke="teal"/>
+  <text x="5" y="5">unsupported</text>
+</svg>`;
+
+test.describe('SVG Import', () => {
+  test.beforeEach(async ({ page }) => {
+    await page.goto('/hodei-draw/');
+    await page.waitForTimeout(2000);
+  });
+
+  test('should import supported elements and warn about the rest', async ({ page }) => {
+    const result = await page.evaluate((svg) => window.import_svg(svg), SVG);
+    await page.waitForTimeout(100);
+
+    expect(result.entities.length).toBe(5);
+    expect(result.warnings.some((w) => w.includes('<text>'))).toBe(true);
+
+    const order = await page.evaluate(() => window.ecs_get_entity_order());
+    expect(order).toEqual(result.entities);
+  });
+
+  test('should undo the whole import in one step', async ({ page }) => {
+    await page.evaluate(() => window.ecs_create_rect(400, 300, 50, 50));
+    await page.waitForTimeout(100);
+    const before = await page.evaluate(() => window.ecs_get_entity_order());
+
+    await page.evaluate((svg) => window.import_svg(svg), SVG);
+    await page.waitForTimeout(100);
+    await page.evaluate(() => window.ecs_undo());
+    await page.waitForTimeout(100);
+
+    expect(await page.evaluate(() => window.ecs_get_entity_order())).toEqual(before);
+  });
+
+  test('should reject malformed XML', async ({ page }) => {
+    const error = await page.evaluate(() => {
+      try { window.import_svg('<svg><rect></svg>'); return null; } catch (e) { return String(e.message || e); }
+    });
+    expect(error).not.toBeNull();
+  });
+});
@@ -0,0 +1,106 @@
+const { test, expect } = require('@playwright/test');
+
+// Crea un texto en (x, y) CSS px tecleando `content` y termina la edición
+async function typeText(page, x, y, content) {
+  await page.evaluate(({ x, y, content }) => {
+    window.ecs_text_begin(x, y, true);
+    window.ecs_text_insert(content);
+    window.ecs_text_end();
+  }, { x, y, content });
+  await page.waitForTimeout(100);
+}
+
+async function exportDocument(page) {
+  return page.evaluate(() => JSON.parse(window.export_document_json()).document);
+}
+
+test.describe('Text Tool', () => {
+  test.beforeEach(async ({ page }) => {
+    await page.goto('/hodei-draw/');
+    await page.waitForTimeout(2000);
+  });
+
+  test('should create a text entity with its measured extent', async ({ page }) => {
+    await typeText(page, 200, 200, 'Hola mundo');
+
+    const doc = await exportDocument(page);
+    expect(doc.entities.length).toBe(1);
+    // Entidad serializada como [id, transform, style, shape]
+    const { text } = doc.entities[0][3].Text;
+    expect(text.spans.map((s) => s.text).join('')).toBe('Hola mundo');
+    expect(text.extent[0]).toBeGreaterThan(0);
+    expect(text.extent[1]).toBeGreaterThan(0);
+
+    // El bloque se puede seleccionar como cualquier forma
+    expect(await page.evaluate(() => window.ecs_detect_shape_hover(205, 200))).toBe(true);
+  });
+
+  test('should edit with the caret keys and replace the selection', async ({ page }) => {
+    await page.evaluate(() => {
+      window.ecs_text_begin(200, 200, true);
+      window.ecs_text_insert('Hola mundo');
+      window.ecs_text_key('ArrowLeft', true);
+      window.ecs_text_key('ArrowLeft', true);
+      window.ecs_text_insert('\nxx');
+    });
+    await page.waitForTimeout(100);
+
+    expect(await page.evaluate(() => window.ecs_is_text_editing())).toBe(true);
+    const caret = await page.evaluate(() => window.ecs_text_caret_rect());
+    expect(caret.h).toBeGreaterThan(0);
+
+    await page.evaluate(() => window.ecs_text_end());
+    await page.waitForTimeout(100);
+    expect(await page.evaluate(() => window.ecs_is_text_editing())).toBe(false);
+
+    const doc = await exportDocument(page);
+    expect(doc.entities[0][3].Text.text.spans.map((s) => s.text).join('')).toBe('Hola mun\nxx');
+  });
+
+  test('should discard a new text left empty', async ({ page }) => {
+    await typeText(page, 200, 200, '');
+    expect(await page.evaluate(() => window.ecs_get_entity_order())).toEqual([]);
+  });
+
+  test('should add a label to a shape on double click', async ({ page }) => {
+    await page.evaluate(() => window.ecs_create_rect(100, 100, 200, 100));
+    await page.waitForTimeout(100);
+
+    await page.evaluate(() => {
+      window.ecs_text_begin(200, 150, false);
+      window.ecs_text_insert('Etiqueta');
+      window.ecs_text_end();
+    });
+    await page.waitForTimeout(100);
+
+    const doc = await exportDocument(page);
+    expect(doc.entities.length).toBe(1);
+    expect(doc.labels.length).toBe(1);
+    expect(doc.labels[0][0]).toBe(doc.entities[0][0]);
+    expect(doc.labels[0][1].spans[0].text).toBe('Etiqueta');
+  });
+
+  test('should undo a text edit in one step', async ({ page }) => {
+    await typeText(page, 200, 200, 'Hola');
+    // Segunda edición del mismo bloque: añadir al final
+    await page.evaluate(() => {
+      window.ecs_text_begin(205, 200, false);
+      window.ecs_text_key('End', false);
+      window.ecs_text_insert(' mundo');
+      window.ecs_text_end();
+    });
+    await page.waitForTimeout(100);
+
+    let doc = await exportDocument(page);
+    expect(doc.entities[0][3].Text.text.spans[0].text).toBe('Hola mundo');
+
+    await page.evaluate(() => window.ecs_undo());
+    await page.waitForTimeout(100);
+    doc = await exportDocument(page);
+    expect(doc.entities[0][3].Text.text.spans[0].text).toBe('Hola');
+
+    await page.evaluate(() => window.ecs_undo());
+    await page.waitForTimeout(100);
+    expect(await page.evaluate(() => window.ecs_get_entity_order())).toEqual([]);
+  });
+});
@@ -0,0 +1,72 @@
+const { test, expect } = require('@playwright/test');
+
+test.describe('Undo/Redo - Command History', () => {
+  test.beforeEach(async ({ page }) => {
+    await page.goto('/hodei-draw/');
+    await page.waitForTimeout(2000);
+  });
+
+  test('should undo and redo shape creation', async ({ page }) => {
+    await page.evaluate(() => {
+      window.ecs_create_rect(200, 200, 100, 100);
+    });
+    await page.waitForTimeout(100);
+    expect(await page.evaluate(() => window.ecs_detect_shape_hover(250, 250))).toBe(true);
+    expect(await page.evaluate(() => window.ecs_can_undo())).toBe(true);
+
+    expect(await page.evaluate(() => window.ecs_undo())).toBe(true);
+    expect(await page.evaluate(() => window.ecs_detect_shape_hover(250, 250))).toBe(false);
+    expect(await page.evaluate(() => window.ecs_can_redo())).toBe(true);
+
+    expect(await page.evaluate(() => window.ecs_redo())).toBe(true);
+    expect(await page.evaluate(() => window.ecs_detect_shape_hover(250, 250))).toBe(true);
+  });
+
+  test('should collapse a whole drag into a single history entry', async ({ page }) => {
+    await page.evaluate(() => {
+      window.ecs_create_rect(200, 200, 100, 100);
+    });
+    await page.waitForTimeout(100);
+    await page.evaluate(() => {
+      window.ecs_pointer_down(250, 250);
+    });
+    await page.waitForTimeout(100);
+
+    // Several updates, one drag
+    await page.evaluate(() => {
+      window.ecs_move_start(250, 250);
+      window.ecs_move_update(50, 0);
+      window.ecs_move_update(100, 0);
+      window.ecs_move_update(200, 0);
+      window.ecs_move_end();
+    });
+    await page.waitForTimeout(100);
+    expect(await page.evaluate(() => window.ecs_detect_shape_hover(250, 250))).toBe(false);
+
+    // One undo brings the shape back to where the drag started
+    await page.evaluate(() => window.ecs_undo());
+    expect(await page.evaluate(() => window.ecs_detect_shape_hover(250, 250))).toBe(true);
+    // The next undo removes the shape itself
+    await page.evaluate(() => window.ecs_undo());
+    expect(await page.evaluate(() => window.ecs_detect_shape_hover(250, 250))).toBe(false);
+    expect(await page.evaluate(() => window.ecs_can_undo())).toBe(false);
+  });
+
+  test('should respect the configured history limit', async ({ page }) => {
+    await page.evaluate(() => {
+      window.ecs_set_history_limit(2);
+    });
+    for (const x of [100, 200, 300]) {
+      await page.evaluate((x) => {
+        window.ecs_create_rect(x, 100, 50, 50);
+      }, x);
+      await page.waitForTimeout(100);
+    }
+
+    expect(await page.evaluate(() => window.ecs_undo())).toBe(true);
+    expect(await page.evaluate(() => window.ecs_undo())).toBe(true);
+    expect(await page.evaluate(() => window.ecs_undo())).toBe(false);
+    // The oldest creation fell off the history and stays on the canvas
+    expect(await page.evaluate(() => window.ecs_detect_shape_hover(125, 125))).toBe(true);
+  });
+});